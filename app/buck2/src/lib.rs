@@ -382,7 +382,7 @@ impl CommandKind {
                 buck2_client_ctx::eprintln!(
                     "WARNING: \"buck2 query\" is an alias for \"buck2 uquery\". Consider using \"buck2 cquery\" or \"buck2 uquery\" explicitly."
                 )?;
-                cmd.exec(matches, command_ctx)
+                cmd.exec_or_from_graph(matches, command_ctx)
             }
             CommandKind::Server(cmd) => cmd.exec(matches, command_ctx),
             CommandKind::Status(cmd) => cmd.exec(matches, command_ctx).into(),
@@ -392,7 +392,7 @@ impl CommandKind {
             CommandKind::Audit(cmd) => cmd.exec(matches, command_ctx),
             CommandKind::Starlark(cmd) => cmd.exec(matches, command_ctx),
             CommandKind::Run(cmd) => cmd.exec(matches, command_ctx),
-            CommandKind::Uquery(cmd) => cmd.exec(matches, command_ctx),
+            CommandKind::Uquery(cmd) => cmd.exec_or_from_graph(matches, command_ctx),
             CommandKind::Debug(cmd) => cmd.exec(matches, command_ctx),
             CommandKind::Docs(cmd) => cmd.exec(matches, command_ctx),
             CommandKind::Profile(cmd) => cmd.exec(matches, command_ctx),
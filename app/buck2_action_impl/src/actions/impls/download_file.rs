@@ -59,7 +59,8 @@ enum DownloadFileActionError {
 #[derive(Debug, Allocative)]
 pub(crate) struct UnregisteredDownloadFileAction {
     checksum: Checksum,
-    url: Arc<str>,
+    /// Mirrors, tried in order.
+    urls: Arc<[Arc<str>]>,
     vpnless_url: Option<Arc<str>>,
     is_executable: bool,
     is_deferrable: bool,
@@ -68,14 +69,14 @@ pub(crate) struct UnregisteredDownloadFileAction {
 impl UnregisteredDownloadFileAction {
     pub(crate) fn new(
         checksum: Checksum,
-        url: Arc<str>,
+        urls: Arc<[Arc<str>]>,
         vpnless_url: Option<Arc<str>>,
         is_executable: bool,
         is_deferrable: bool,
     ) -> Self {
         Self {
             checksum,
-            url,
+            urls,
             vpnless_url,
             is_executable,
             is_deferrable,
@@ -131,11 +132,10 @@ impl DownloadFileAction {
             .expect("a single artifact by construction")
     }
 
-    fn url(&self, client: &HttpClient) -> &Arc<str> {
-        if client.supports_vpnless() {
-            self.inner.vpnless_url.as_ref().unwrap_or(&self.inner.url)
-        } else {
-            &self.inner.url
+    fn urls(&self, client: &HttpClient) -> Arc<[Arc<str>]> {
+        match &self.inner.vpnless_url {
+            Some(vpnless_url) if client.supports_vpnless() => Arc::from(vec![vpnless_url.dupe()]),
+            _ => self.inner.urls.dupe(),
         }
     }
 
@@ -168,8 +168,20 @@ impl DownloadFileAction {
             None => return Ok(None),
         };
 
-        let url = self.url(client);
-        let head = http_head(client, url).await?;
+        // The first mirror that responds is assumed to have the same file as the others.
+        let urls = self.urls(client);
+        let mut head = None;
+        for url in urls.iter() {
+            match http_head(client, url).await {
+                Ok(response) => {
+                    head = Some((url, response));
+                    break;
+                }
+                Err(e) if url == urls.last().unwrap() => return Err(e),
+                Err(e) => tracing::warn!("Error querying `{}`: {:#}", url, e),
+            }
+        }
+        let (url, head) = head.context("No URL to download from")?;
 
         let content_length = head
             .headers()
@@ -273,7 +285,7 @@ impl IncrementalActionExecutable for DownloadFileAction {
         }
 
         let client = ctx.http_client();
-        let url = self.url(&client);
+        let urls = self.urls(&client);
 
         let (value, execution_kind) = {
             match self.declared_metadata(&client, ctx.digest_config()).await? {
@@ -286,7 +298,7 @@ impl IncrementalActionExecutable for DownloadFileAction {
                         .declare_http(
                             rel_path,
                             HttpDownloadInfo {
-                                url: url.dupe(),
+                                urls: urls.dupe(),
                                 checksum: self.inner.checksum.dupe(),
                                 metadata: metadata.dupe(),
                                 owner: ctx.target().owner().dupe(),
@@ -310,7 +322,7 @@ impl IncrementalActionExecutable for DownloadFileAction {
                         project_fs,
                         ctx.digest_config(),
                         &rel_path,
-                        url,
+                        &urls,
                         &self.inner.checksum,
                         self.inner.is_executable,
                     )
@@ -0,0 +1,301 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::borrow::Cow;
+use std::slice;
+use std::time::Instant;
+
+use allocative::Allocative;
+use anyhow::Context as _;
+use async_trait::async_trait;
+use buck2_artifact::artifact::build_artifact::BuildArtifact;
+use buck2_build_api::actions::execute::action_executor::ActionExecutionKind;
+use buck2_build_api::actions::execute::action_executor::ActionExecutionMetadata;
+use buck2_build_api::actions::execute::action_executor::ActionOutputs;
+use buck2_build_api::actions::Action;
+use buck2_build_api::actions::ActionExecutable;
+use buck2_build_api::actions::ActionExecutionCtx;
+use buck2_build_api::actions::IncrementalActionExecutable;
+use buck2_build_api::actions::UnregisteredAction;
+use buck2_build_api::artifact_groups::ArtifactGroup;
+use buck2_build_api::interpreter::rule_defs::cmd_args::value_as::ValueAsCommandLineLike;
+use buck2_build_api::interpreter::rule_defs::cmd_args::DefaultCommandLineContext;
+use buck2_core::category::Category;
+use buck2_core::fs::fs_util;
+use buck2_execute::artifact::artifact_dyn::ArtifactDyn;
+use buck2_execute::artifact::fs::ExecutorFs;
+use buck2_execute::execute::command_executor::ActionExecutionTimingData;
+use buck2_execute::materialize::materializer::WriteRequest;
+use dupe::Dupe;
+use gazebo::prelude::*;
+use indexmap::indexmap;
+use indexmap::IndexMap;
+use indexmap::IndexSet;
+use once_cell::sync::Lazy;
+use starlark::values::dict::DictRef;
+use starlark::values::OwnedFrozenValue;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+enum ExpandTemplateActionValidationError {
+    #[error("ExpandTemplateAction requires exactly one input, the template, got {0}")]
+    WrongNumberOfInputs(usize),
+    #[error("ExpandTemplateAction received no outputs")]
+    NoOutputs,
+    #[error("ExpandTemplateAction received more than one output")]
+    TooManyOutputs,
+    #[error("Only artifacts are supported as templates, got {0}")]
+    UnsupportedInput(ArtifactGroup),
+    #[error("Expected a dict of substitutions, got {0}")]
+    SubstitutionsNotDict(String),
+}
+
+#[derive(Allocative, Debug)]
+pub(crate) struct UnregisteredExpandTemplateAction {
+    pub(crate) is_executable: bool,
+}
+
+impl UnregisteredAction for UnregisteredExpandTemplateAction {
+    fn register(
+        self: Box<Self>,
+        inputs: IndexSet<ArtifactGroup>,
+        outputs: IndexSet<BuildArtifact>,
+        starlark_data: Option<OwnedFrozenValue>,
+    ) -> anyhow::Result<Box<dyn Action>> {
+        let substitutions = starlark_data.expect("module data to be present");
+
+        let action = ExpandTemplateAction::new(substitutions, inputs, outputs, *self)?;
+        Ok(Box::new(action))
+    }
+}
+
+#[derive(Debug, Allocative)]
+struct ExpandTemplateAction {
+    substitutions: OwnedFrozenValue, // Dict[str, str | cmd_args]
+    template: ArtifactGroup,
+    output: BuildArtifact,
+    inner: UnregisteredExpandTemplateAction,
+}
+
+impl ExpandTemplateAction {
+    fn new(
+        substitutions: OwnedFrozenValue,
+        inputs: IndexSet<ArtifactGroup>,
+        outputs: IndexSet<BuildArtifact>,
+        inner: UnregisteredExpandTemplateAction,
+    ) -> anyhow::Result<Self> {
+        let mut outputs = outputs.into_iter();
+
+        let output = match (outputs.next(), outputs.next()) {
+            (Some(o), None) => o,
+            (None, ..) => return Err(ExpandTemplateActionValidationError::NoOutputs.into()),
+            (Some(..), Some(..)) => {
+                return Err(ExpandTemplateActionValidationError::TooManyOutputs.into());
+            }
+        };
+
+        let template = match inputs.iter().into_singleton() {
+            Some(template @ (ArtifactGroup::Artifact(..) | ArtifactGroup::Promise(..))) => {
+                template.dupe()
+            }
+            Some(other) => {
+                return Err(
+                    ExpandTemplateActionValidationError::UnsupportedInput(other.dupe()).into(),
+                );
+            }
+            None => {
+                return Err(
+                    ExpandTemplateActionValidationError::WrongNumberOfInputs(inputs.len()).into(),
+                );
+            }
+        };
+
+        {
+            let dict = DictRef::from_value(substitutions.value()).ok_or_else(|| {
+                ExpandTemplateActionValidationError::SubstitutionsNotDict(
+                    substitutions.value().to_repr(),
+                )
+            })?;
+            for (k, v) in dict.iter() {
+                k.unpack_str().context("expecting string")?;
+                v.as_command_line_err()?;
+            }
+        }
+
+        Ok(ExpandTemplateAction {
+            substitutions,
+            template,
+            output,
+            inner,
+        })
+    }
+
+    /// The substitutions, in the order they are applied. Command lines are joined with spaces.
+    fn get_substitutions(&self, fs: &ExecutorFs) -> anyhow::Result<Vec<(String, String)>> {
+        let dict = DictRef::from_value(self.substitutions.value()).context("expecting dict")?;
+        let mut res = Vec::with_capacity(dict.len());
+        for (k, v) in dict.iter() {
+            let mut cli = Vec::<String>::new();
+            v.as_command_line_err()?
+                .add_to_command_line(&mut cli, &mut DefaultCommandLineContext::new(fs))?;
+            res.push((
+                k.unpack_str().context("expecting string")?.to_owned(),
+                cli.join(" "),
+            ));
+        }
+        Ok(res)
+    }
+}
+
+/// Applies each substitution in turn to the whole result of the previous ones, like Bazel. The
+/// template is substituted as bytes, so it doesn't need to be UTF-8.
+fn expand(template: Vec<u8>, substitutions: &[(String, String)]) -> Vec<u8> {
+    let mut res = template;
+    for (key, value) in substitutions {
+        if !key.is_empty() {
+            res = replace_bytes(&res, key.as_bytes(), value.as_bytes());
+        }
+    }
+    res
+}
+
+/// Replaces every non-overlapping occurrence of the non-empty `needle`, from left to right.
+fn replace_bytes(haystack: &[u8], needle: &[u8], replacement: &[u8]) -> Vec<u8> {
+    let mut res = Vec::with_capacity(haystack.len());
+    let mut rest = haystack;
+    while let Some(i) = rest.windows(needle.len()).position(|w| w == needle) {
+        res.extend_from_slice(&rest[..i]);
+        res.extend_from_slice(replacement);
+        rest = &rest[i + needle.len()..];
+    }
+    res.extend_from_slice(rest);
+    res
+}
+
+#[async_trait]
+impl Action for ExpandTemplateAction {
+    fn kind(&self) -> buck2_data::ActionKind {
+        buck2_data::ActionKind::ExpandTemplate
+    }
+
+    fn inputs(&self) -> anyhow::Result<Cow<'_, [ArtifactGroup]>> {
+        Ok(Cow::Borrowed(slice::from_ref(&self.template)))
+    }
+
+    fn outputs(&self) -> anyhow::Result<Cow<'_, [BuildArtifact]>> {
+        Ok(Cow::Borrowed(slice::from_ref(&self.output)))
+    }
+
+    fn as_executable(&self) -> ActionExecutable<'_> {
+        ActionExecutable::Incremental(self)
+    }
+
+    fn category(&self) -> &Category {
+        static EXPAND_TEMPLATE_CATEGORY: Lazy<Category> =
+            Lazy::new(|| Category::try_from("expand_template").unwrap());
+
+        &EXPAND_TEMPLATE_CATEGORY
+    }
+
+    fn identifier(&self) -> Option<&str> {
+        Some(self.output.get_path().path().as_str())
+    }
+
+    fn aquery_attributes(&self, fs: &ExecutorFs) -> IndexMap<String, String> {
+        indexmap! {
+            "substitutions".to_owned() => match self.get_substitutions(fs) {
+                Ok(v) => format!("{:?}", v),
+                Err(e) => format!("ERROR: constructing substitutions ({})", e)
+            },
+            "is_executable".to_owned() => self.inner.is_executable.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl IncrementalActionExecutable for ExpandTemplateAction {
+    async fn execute(
+        &self,
+        ctx: &mut dyn ActionExecutionCtx,
+    ) -> anyhow::Result<(ActionOutputs, ActionExecutionMetadata)> {
+        let (template, _value) = ctx
+            .artifact_values(&self.template)
+            .iter()
+            .into_singleton()
+            .context("Template did not dereference to exactly one artifact")?;
+
+        let fs = ctx.fs();
+        let template = template.resolve_path(fs)?;
+        ctx.materializer()
+            .ensure_materialized(vec![template.clone()])
+            .await?;
+
+        let mut execution_start = None;
+
+        let value = ctx
+            .materializer()
+            .declare_write(Box::new(|| {
+                execution_start = Some(Instant::now());
+                let template = fs_util::read(fs.fs().resolve(&template))?;
+                let substitutions = self.get_substitutions(&ctx.executor_fs())?;
+                Ok(vec![WriteRequest {
+                    path: fs.resolve_build(self.output.get_path()),
+                    content: expand(template, &substitutions),
+                    is_executable: self.inner.is_executable,
+                }])
+            }))
+            .await?
+            .into_iter()
+            .next()
+            .context("Write did not execute")?;
+
+        let wall_time = execution_start
+            .context("Action did not set execution_start")?
+            .elapsed();
+
+        Ok((
+            ActionOutputs::new(indexmap![self.output.get_path().dupe() => value]),
+            ActionExecutionMetadata {
+                execution_kind: ActionExecutionKind::Simple,
+                timing: ActionExecutionTimingData { wall_time },
+            },
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand() {
+        let substitutions = [
+            ("{NAME}".to_owned(), "foo {VERSION}".to_owned()),
+            ("{VERSION}".to_owned(), "1.0".to_owned()),
+            ("".to_owned(), "ignored".to_owned()),
+        ];
+        assert_eq!(
+            b"foo 1.0 is foo 1.0".as_slice(),
+            expand(b"{NAME} is {NAME}".to_vec(), &substitutions)
+        );
+        assert_eq!(
+            b"no match".as_slice(),
+            expand(b"no match".to_vec(), &substitutions)
+        );
+    }
+
+    #[test]
+    fn test_expand_non_utf8() {
+        let substitutions = [("{NAME}".to_owned(), "foo".to_owned())];
+        assert_eq!(
+            b"\xff\xfefoo\xff{NAME".as_slice(),
+            expand(b"\xff\xfe{NAME}\xff{NAME".to_vec(), &substitutions)
+        );
+    }
+}
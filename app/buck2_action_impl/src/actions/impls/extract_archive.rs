@@ -0,0 +1,734 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::borrow::Cow;
+use std::io::Read;
+use std::path::Component;
+use std::path::Path;
+use std::str::FromStr;
+
+use allocative::Allocative;
+use anyhow::Context as _;
+use async_trait::async_trait;
+use buck2_artifact::artifact::build_artifact::BuildArtifact;
+use buck2_build_api::actions::box_slice_set::BoxSliceSet;
+use buck2_build_api::actions::execute::action_executor::ActionExecutionKind;
+use buck2_build_api::actions::execute::action_executor::ActionExecutionMetadata;
+use buck2_build_api::actions::execute::action_executor::ActionOutputs;
+use buck2_build_api::actions::Action;
+use buck2_build_api::actions::ActionExecutable;
+use buck2_build_api::actions::ActionExecutionCtx;
+use buck2_build_api::actions::IncrementalActionExecutable;
+use buck2_build_api::actions::UnregisteredAction;
+use buck2_build_api::artifact_groups::ArtifactGroup;
+use buck2_common::file_ops::FileDigestConfig;
+use buck2_core::category::Category;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePathBuf;
+use buck2_execute::artifact::artifact_dyn::ArtifactDyn;
+use buck2_execute::artifact_value::ArtifactValue;
+use buck2_execute::directory::INTERNER;
+use buck2_execute::entry::build_entry_from_disk;
+use buck2_execute::execute::command_executor::ActionExecutionTimingData;
+use dupe::Dupe;
+use gazebo::prelude::*;
+use globset::Glob;
+use globset::GlobSet;
+use globset::GlobSetBuilder;
+use indexmap::IndexMap;
+use indexmap::IndexSet;
+use once_cell::sync::Lazy;
+use starlark::values::OwnedFrozenValue;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+enum ExtractArchiveActionError {
+    #[error("Exactly one input must be specified for an extract archive action, got {0}")]
+    WrongNumberOfInputs(usize),
+    #[error("Exactly one output must be specified for an extract archive action, got {0}")]
+    WrongNumberOfOutputs(usize),
+    #[error("Only artifact inputs are supported in extract archive actions, got {0}")]
+    UnsupportedInput(ArtifactGroup),
+    #[error("Unknown archive format `{0}`, expected one of `tar`, `tar.gz`, `tar.zst` or `zip`")]
+    UnknownFormat(String),
+    #[error(
+        "Cannot infer the archive format of `{0}` from its extension, pass `format` explicitly"
+    )]
+    CannotInferFormat(String),
+    #[error("Invalid glob `{0}`")]
+    InvalidGlob(String),
+    #[error("Archive entry `{0}` is not a valid relative path")]
+    InvalidEntryPath(String),
+    #[error(
+        "Archive entry `{0}` has an unsupported type (only files, directories and symlinks are supported)"
+    )]
+    UnsupportedEntry(String),
+    #[error(
+        "Archive entry `{0}` is a symlink to `{1}`, which is absolute, escapes the output, or uses `..` after a directory name"
+    )]
+    UnsafeSymlink(ForwardRelativePathBuf, String),
+    #[error("Archive entry `{0}` is inside `{1}`, which is a symlink")]
+    InsideSymlink(ForwardRelativePathBuf, ForwardRelativePathBuf),
+    #[error("No entry of the archive is under `strip_prefix` `{0}`")]
+    NothingUnderPrefix(ForwardRelativePathBuf),
+}
+
+#[derive(Debug, Clone, Copy, Dupe, PartialEq, Eq, Allocative)]
+pub(crate) enum ArchiveFormat {
+    Tar,
+    TarGz,
+    TarZst,
+    Zip,
+}
+
+impl FromStr for ArchiveFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "tar" => Ok(Self::Tar),
+            "tar.gz" | "tgz" => Ok(Self::TarGz),
+            "tar.zst" | "tzst" => Ok(Self::TarZst),
+            "zip" => Ok(Self::Zip),
+            _ => Err(ExtractArchiveActionError::UnknownFormat(s.to_owned()).into()),
+        }
+    }
+}
+
+impl ArchiveFormat {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Tar => "tar",
+            Self::TarGz => "tar.gz",
+            Self::TarZst => "tar.zst",
+            Self::Zip => "zip",
+        }
+    }
+
+    fn infer(path: &str) -> anyhow::Result<Self> {
+        const EXTENSIONS: &[(&str, ArchiveFormat)] = &[
+            (".tar", ArchiveFormat::Tar),
+            (".tar.gz", ArchiveFormat::TarGz),
+            (".tgz", ArchiveFormat::TarGz),
+            (".tar.zst", ArchiveFormat::TarZst),
+            (".tzst", ArchiveFormat::TarZst),
+            (".zip", ArchiveFormat::Zip),
+            (".jar", ArchiveFormat::Zip),
+        ];
+        EXTENSIONS
+            .iter()
+            .find(|(ext, _)| path.ends_with(ext))
+            .map(|(_, format)| *format)
+            .ok_or_else(|| ExtractArchiveActionError::CannotInferFormat(path.to_owned()).into())
+    }
+}
+
+/// Which entries of the archive to extract, and where.
+#[derive(Debug, Allocative)]
+pub(crate) struct EntryFilter {
+    strip_prefix: Option<ForwardRelativePathBuf>,
+    includes: Vec<String>,
+    excludes: Vec<String>,
+    #[allocative(skip)]
+    include_set: Option<GlobSet>,
+    #[allocative(skip)]
+    exclude_set: Option<GlobSet>,
+}
+
+impl EntryFilter {
+    pub(crate) fn new(
+        strip_prefix: Option<&str>,
+        includes: Vec<String>,
+        excludes: Vec<String>,
+    ) -> anyhow::Result<Self> {
+        let strip_prefix = strip_prefix
+            .map(|p| {
+                ForwardRelativePath::new_trim_trailing_slashes(p.trim_start_matches("./"))
+                    .map(|p| p.to_buf())
+            })
+            .transpose()
+            .context("Invalid `strip_prefix`")?;
+        let include_set = Self::glob_set(&includes)?;
+        let exclude_set = Self::glob_set(&excludes)?;
+        Ok(Self {
+            strip_prefix,
+            includes,
+            excludes,
+            include_set,
+            exclude_set,
+        })
+    }
+
+    fn glob_set(globs: &[String]) -> anyhow::Result<Option<GlobSet>> {
+        if globs.is_empty() {
+            return Ok(None);
+        }
+        let mut builder = GlobSetBuilder::new();
+        for glob in globs {
+            builder.add(
+                Glob::new(glob)
+                    .with_context(|| ExtractArchiveActionError::InvalidGlob(glob.clone()))?,
+            );
+        }
+        Ok(Some(builder.build()?))
+    }
+
+    /// Returns the path an archive entry is extracted to, relative to the output, or `None` if
+    /// it's not extracted. Fails for paths escaping the output.
+    fn output_path(&self, name: &str) -> anyhow::Result<Option<ForwardRelativePathBuf>> {
+        let mut trimmed = name;
+        while let Some(rest) = trimmed.strip_prefix("./") {
+            trimmed = rest;
+        }
+        if trimmed.is_empty() || trimmed == "." {
+            return Ok(None);
+        }
+        let path = ForwardRelativePath::new_trim_trailing_slashes(trimmed)
+            .map_err(|_| ExtractArchiveActionError::InvalidEntryPath(name.to_owned()))?;
+
+        let path = match &self.strip_prefix {
+            Some(prefix) => match path.strip_prefix_opt(prefix) {
+                Some(path) if !path.is_empty() => path,
+                _ => return Ok(None),
+            },
+            None => path,
+        };
+
+        if let Some(includes) = &self.include_set {
+            if !includes.is_match(path.as_str()) {
+                return Ok(None);
+            }
+        }
+        if let Some(excludes) = &self.exclude_set {
+            if excludes.is_match(path.as_str()) {
+                return Ok(None);
+            }
+        }
+        Ok(Some(path.to_buf()))
+    }
+}
+
+#[derive(Debug, Allocative)]
+pub(crate) struct UnregisteredExtractArchiveAction {
+    /// `None` to infer it from the extension of the archive.
+    format: Option<ArchiveFormat>,
+    filter: EntryFilter,
+}
+
+impl UnregisteredExtractArchiveAction {
+    pub(crate) fn new(format: Option<&str>, filter: EntryFilter) -> anyhow::Result<Self> {
+        Ok(Self {
+            format: format.map(ArchiveFormat::from_str).transpose()?,
+            filter,
+        })
+    }
+}
+
+impl UnregisteredAction for UnregisteredExtractArchiveAction {
+    fn register(
+        self: Box<Self>,
+        inputs: IndexSet<ArtifactGroup>,
+        outputs: IndexSet<BuildArtifact>,
+        _starlark_data: Option<OwnedFrozenValue>,
+    ) -> anyhow::Result<Box<dyn Action>> {
+        Ok(Box::new(ExtractArchiveAction::new(inputs, outputs, *self)?))
+    }
+}
+
+#[derive(Debug, Allocative)]
+struct ExtractArchiveAction {
+    inputs: BoxSliceSet<ArtifactGroup>,
+    outputs: BoxSliceSet<BuildArtifact>,
+    inner: UnregisteredExtractArchiveAction,
+}
+
+impl ExtractArchiveAction {
+    fn new(
+        inputs: IndexSet<ArtifactGroup>,
+        outputs: IndexSet<BuildArtifact>,
+        inner: UnregisteredExtractArchiveAction,
+    ) -> anyhow::Result<Self> {
+        match inputs.iter().into_singleton() {
+            Some(ArtifactGroup::Artifact(..) | ArtifactGroup::Promise(..)) => {}
+            Some(other) => {
+                return Err(ExtractArchiveActionError::UnsupportedInput(other.dupe()).into());
+            }
+            None => {
+                return Err(ExtractArchiveActionError::WrongNumberOfInputs(inputs.len()).into());
+            }
+        }
+        if outputs.len() != 1 {
+            return Err(ExtractArchiveActionError::WrongNumberOfOutputs(outputs.len()).into());
+        }
+        Ok(Self {
+            inputs: BoxSliceSet::from(inputs),
+            outputs: BoxSliceSet::from(outputs),
+            inner,
+        })
+    }
+
+    fn input(&self) -> &ArtifactGroup {
+        self.inputs
+            .iter()
+            .next()
+            .expect("a single input by construction")
+    }
+
+    fn output(&self) -> &BuildArtifact {
+        self.outputs
+            .iter()
+            .next()
+            .expect("a single artifact by construction")
+    }
+}
+
+#[async_trait]
+impl Action for ExtractArchiveAction {
+    fn kind(&self) -> buck2_data::ActionKind {
+        buck2_data::ActionKind::ExtractArchive
+    }
+
+    fn inputs(&self) -> anyhow::Result<Cow<'_, [ArtifactGroup]>> {
+        Ok(Cow::Borrowed(self.inputs.as_slice()))
+    }
+
+    fn outputs(&self) -> anyhow::Result<Cow<'_, [BuildArtifact]>> {
+        Ok(Cow::Borrowed(self.outputs.as_slice()))
+    }
+
+    fn as_executable(&self) -> ActionExecutable<'_> {
+        ActionExecutable::Incremental(self)
+    }
+
+    fn category(&self) -> &Category {
+        static EXTRACT_ARCHIVE_CATEGORY: Lazy<Category> =
+            Lazy::new(|| Category::try_from("extract_archive").unwrap());
+
+        &EXTRACT_ARCHIVE_CATEGORY
+    }
+
+    fn identifier(&self) -> Option<&str> {
+        Some(self.output().get_path().path().as_str())
+    }
+
+    fn aquery_attributes(
+        &self,
+        _fs: &buck2_execute::execute::request::ExecutorFs,
+    ) -> IndexMap<String, String> {
+        let filter = &self.inner.filter;
+        indexmap::indexmap! {
+            "format".to_owned() => self.inner.format.map_or("", |f| f.as_str()).to_owned(),
+            "strip_prefix".to_owned() => filter.strip_prefix.as_ref().map_or("", |p| p.as_str()).to_owned(),
+            "includes".to_owned() => format!("{:?}", filter.includes),
+            "excludes".to_owned() => format!("{:?}", filter.excludes),
+        }
+    }
+}
+
+#[async_trait]
+impl IncrementalActionExecutable for ExtractArchiveAction {
+    async fn execute(
+        &self,
+        ctx: &mut dyn ActionExecutionCtx,
+    ) -> anyhow::Result<(ActionOutputs, ActionExecutionMetadata)> {
+        let (input, _value) = ctx
+            .artifact_values(self.input())
+            .iter()
+            .into_singleton()
+            .context("Input did not dereference to exactly one artifact")?;
+
+        let artifact_fs = ctx.fs();
+        let src = input.resolve_path(artifact_fs)?;
+        let dest = artifact_fs.resolve_build(self.output().get_path());
+        let format = match self.inner.format {
+            Some(format) => format,
+            None => ArchiveFormat::infer(src.as_str())?,
+        };
+
+        ctx.materializer()
+            .ensure_materialized(vec![src.clone()])
+            .await?;
+        ctx.cleanup_outputs().await?;
+
+        let fs = ctx.fs().fs();
+        let src_abs = fs.resolve(&src);
+        let dest_abs = fs.resolve(&dest);
+        let digest_config = FileDigestConfig::build(ctx.digest_config().cas_digest_config());
+        let filter = &self.inner.filter;
+
+        let (entry, _hashing_time) = ctx
+            .blocking_executor()
+            .execute_io_inline(|| {
+                extract(format, &src_abs, &dest_abs, filter)
+                    .with_context(|| format!("Error extracting `{}`", src))?;
+                build_entry_from_disk(dest_abs.clone(), digest_config)
+            })
+            .await?;
+
+        let entry = entry
+            .with_context(|| format!("Extracting `{}` produced no output", src))?
+            .map_dir(|dir| {
+                dir.fingerprint(ctx.digest_config().as_directory_serializer())
+                    .shared(&*INTERNER)
+            });
+        let value = ArtifactValue::from(entry);
+
+        ctx.materializer()
+            .declare_existing(vec![(dest, value.dupe())])
+            .await?;
+
+        Ok((
+            ActionOutputs::from_single(self.output().get_path().dupe(), value),
+            ActionExecutionMetadata {
+                execution_kind: ActionExecutionKind::Simple,
+                timing: ActionExecutionTimingData::default(),
+            },
+        ))
+    }
+}
+
+fn extract(
+    format: ArchiveFormat,
+    src: &AbsNormPath,
+    dest: &AbsNormPath,
+    filter: &EntryFilter,
+) -> anyhow::Result<()> {
+    fs_util::create_dir_all(dest)?;
+    let mut out = Extractor {
+        dest,
+        filter,
+        extracted: 0,
+    };
+
+    let file = std::fs::File::open(src).with_context(|| format!("Error opening `{}`", src))?;
+    match format {
+        ArchiveFormat::Tar => out.tar(file)?,
+        ArchiveFormat::TarGz => out.tar(flate2::read::GzDecoder::new(file))?,
+        ArchiveFormat::TarZst => out.tar(zstd::stream::read::Decoder::new(file)?)?,
+        ArchiveFormat::Zip => out.zip(file)?,
+    }
+
+    if out.extracted == 0 {
+        if let Some(prefix) = &filter.strip_prefix {
+            return Err(ExtractArchiveActionError::NothingUnderPrefix(prefix.clone()).into());
+        }
+    }
+    Ok(())
+}
+
+struct Extractor<'a> {
+    dest: &'a AbsNormPath,
+    filter: &'a EntryFilter,
+    extracted: usize,
+}
+
+impl<'a> Extractor<'a> {
+    fn tar(&mut self, reader: impl Read) -> anyhow::Result<()> {
+        let mut archive = tar::Archive::new(reader);
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let name = path_to_str(&entry.path()?)?.to_owned();
+            let path = match self.dest_path(&name)? {
+                Some(path) => path,
+                None => continue,
+            };
+
+            let entry_type = entry.header().entry_type();
+            if entry_type.is_dir() {
+                self.dir(&path)?;
+            } else if entry_type.is_file() {
+                let executable = entry.header().mode()? & 0o111 != 0;
+                self.file(&path, &mut entry, executable)?;
+            } else if entry_type.is_symlink() {
+                let target = entry
+                    .link_name()?
+                    .with_context(|| ExtractArchiveActionError::UnsupportedEntry(name.clone()))?;
+                self.symlink(&path, &target)?;
+            } else if entry_type.is_pax_global_extensions() {
+                continue;
+            } else {
+                return Err(ExtractArchiveActionError::UnsupportedEntry(name).into());
+            }
+        }
+        Ok(())
+    }
+
+    fn zip(&mut self, file: std::fs::File) -> anyhow::Result<()> {
+        const S_IFMT: u32 = 0o170000;
+        const S_IFLNK: u32 = 0o120000;
+
+        let mut archive = zip::ZipArchive::new(file)?;
+        for i in 0..archive.len() {
+            let mut entry = archive.by_index(i)?;
+            let name = entry.name().to_owned();
+            let path = match self.dest_path(&name)? {
+                Some(path) => path,
+                None => continue,
+            };
+
+            let mode = entry.unix_mode().unwrap_or(0);
+            if entry.is_dir() {
+                self.dir(&path)?;
+            } else if mode & S_IFMT == S_IFLNK {
+                let mut target = String::new();
+                entry.read_to_string(&mut target)?;
+                self.symlink(&path, Path::new(&target))?;
+            } else {
+                self.file(&path, &mut entry, mode & 0o111 != 0)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn dest_path(&self, name: &str) -> anyhow::Result<Option<ForwardRelativePathBuf>> {
+        self.filter.output_path(name)
+    }
+
+    /// Returns the absolute path to write an entry to, after checking that writing it can't
+    /// escape the output through a symlink extracted earlier. Like `tar::Archive::unpack`, this
+    /// refuses entries under a symlink, and replaces a symlink at the entry path itself.
+    fn prepare(&self, path: &ForwardRelativePath) -> anyhow::Result<AbsNormPathBuf> {
+        let mut parent = path.parent();
+        while let Some(dir) = parent {
+            if dir.is_empty() {
+                break;
+            }
+            if let Some(metadata) = fs_util::symlink_metadata_if_exists(self.dest.join(dir))? {
+                if metadata.file_type().is_symlink() {
+                    return Err(ExtractArchiveActionError::InsideSymlink(
+                        path.to_buf(),
+                        dir.to_buf(),
+                    )
+                    .into());
+                }
+            }
+            parent = dir.parent();
+        }
+
+        let abs = self.dest.join(path);
+        if let Some(metadata) = fs_util::symlink_metadata_if_exists(&abs)? {
+            if metadata.file_type().is_symlink() {
+                fs_util::remove_file(&abs)?;
+            }
+        }
+        if let Some(parent) = abs.parent() {
+            fs_util::create_dir_all(parent)?;
+        }
+        Ok(abs)
+    }
+
+    fn dir(&mut self, path: &ForwardRelativePath) -> anyhow::Result<()> {
+        let path = self.prepare(path)?;
+        fs_util::create_dir_all(path)
+    }
+
+    fn file(
+        &mut self,
+        path: &ForwardRelativePath,
+        contents: &mut impl Read,
+        executable: bool,
+    ) -> anyhow::Result<()> {
+        let path = self.prepare(path)?;
+        let mut file = fs_util::create_file(&path)?;
+        std::io::copy(contents, &mut file).with_context(|| format!("Error writing `{}`", path))?;
+        drop(file);
+        if executable {
+            fs_util::set_executable(&path)?;
+        }
+        self.extracted += 1;
+        Ok(())
+    }
+
+    fn symlink(&mut self, path: &ForwardRelativePath, target: &Path) -> anyhow::Result<()> {
+        check_symlink_target(path, target)?;
+        let abs = self.prepare(path)?;
+        fs_util::symlink(target, abs)?;
+        self.extracted += 1;
+        Ok(())
+    }
+}
+
+/// Checks that a symlink at `path` (relative to the output) pointing to `target` stays inside the
+/// output. Only leading `..` are allowed: after a directory name, `..` would be resolved relative
+/// to wherever that directory (possibly itself a symlink) points.
+fn check_symlink_target(path: &ForwardRelativePath, target: &Path) -> anyhow::Result<()> {
+    let unsafe_symlink =
+        || ExtractArchiveActionError::UnsafeSymlink(path.to_buf(), target.display().to_string());
+    // Number of directories between the output and the symlink.
+    let mut depth = path.iter().count() - 1;
+    let mut descended = false;
+    for component in target.components() {
+        match component {
+            Component::CurDir => {}
+            Component::Normal(_) => descended = true,
+            Component::ParentDir if !descended => {
+                depth = depth.checked_sub(1).ok_or_else(unsafe_symlink)?;
+            }
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => {
+                return Err(unsafe_symlink().into());
+            }
+        }
+    }
+    Ok(())
+}
+
+fn path_to_str(path: &Path) -> anyhow::Result<&str> {
+    path.to_str()
+        .with_context(|| ExtractArchiveActionError::InvalidEntryPath(path.display().to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_infer_format() -> anyhow::Result<()> {
+        assert_eq!(ArchiveFormat::infer("a/sdk.tar.gz")?, ArchiveFormat::TarGz);
+        assert_eq!(ArchiveFormat::infer("a/sdk.tzst")?, ArchiveFormat::TarZst);
+        assert_eq!(ArchiveFormat::infer("a/sdk.zip")?, ArchiveFormat::Zip);
+        assert!(ArchiveFormat::infer("a/sdk.rar").is_err());
+        assert!(ArchiveFormat::from_str("rar").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_output_path() -> anyhow::Result<()> {
+        let filter = EntryFilter::new(
+            Some("sdk-1.0/"),
+            vec!["**/*.h".to_owned(), "bin/*".to_owned()],
+            vec!["**/internal/**".to_owned()],
+        )?;
+        let path = |name| filter.output_path(name).map(|p| p.map(|p| p.to_string()));
+
+        assert_eq!(
+            path("./sdk-1.0/include/a.h")?,
+            Some("include/a.h".to_owned())
+        );
+        assert_eq!(path("sdk-1.0/bin/tool")?, Some("bin/tool".to_owned()));
+        assert_eq!(path("sdk-1.0/")?, None);
+        assert_eq!(path("sdk-1.0/lib/a.so")?, None);
+        assert_eq!(path("sdk-1.0/include/internal/b.h")?, None);
+        assert_eq!(path("other/include/a.h")?, None);
+        assert!(path("sdk-1.0/../../etc/passwd").is_err());
+        assert!(path("/etc/passwd").is_err());
+        Ok(())
+    }
+
+    struct TestArchive {
+        _dir: tempfile::TempDir,
+        archive: AbsNormPathBuf,
+        out: AbsNormPathBuf,
+    }
+
+    impl TestArchive {
+        fn new(name: &str) -> anyhow::Result<Self> {
+            let dir = tempfile::tempdir()?;
+            let root = AbsNormPathBuf::try_from(dir.path().to_owned())?;
+            Ok(Self {
+                archive: root.join(ForwardRelativePath::new(name)?),
+                out: root.join(ForwardRelativePath::new("out")?),
+                _dir: dir,
+            })
+        }
+
+        fn tar(name: &str, symlinks: &[(&str, &str)], files: &[&str]) -> anyhow::Result<Self> {
+            let test = Self::new(name)?;
+            let mut builder = tar::Builder::new(fs_util::create_file(&test.archive)?);
+            for (path, target) in symlinks {
+                let mut header = tar::Header::new_gnu();
+                header.set_entry_type(tar::EntryType::Symlink);
+                header.set_size(0);
+                builder.append_link(&mut header, path, target)?;
+            }
+            for path in files {
+                let mut header = tar::Header::new_gnu();
+                header.set_size(2);
+                header.set_mode(0o644);
+                builder.append_data(&mut header, path, &b"hi"[..])?;
+            }
+            builder.finish()?;
+            Ok(test)
+        }
+
+        fn zip(name: &str, symlinks: &[(&str, &str)], files: &[&str]) -> anyhow::Result<Self> {
+            let test = Self::new(name)?;
+            let mut writer = zip::ZipWriter::new(fs_util::create_file(&test.archive)?);
+            let options = zip::write::FileOptions::default();
+            for (path, target) in symlinks {
+                writer.add_symlink(*path, *target, options)?;
+            }
+            for path in files {
+                writer.start_file(*path, options)?;
+                std::io::Write::write_all(&mut writer, b"hi")?;
+            }
+            writer.finish()?;
+            Ok(test)
+        }
+
+        fn extract(&self, format: ArchiveFormat) -> anyhow::Result<()> {
+            extract(
+                format,
+                &self.archive,
+                &self.out,
+                &EntryFilter::new(None, Vec::new(), Vec::new())?,
+            )
+        }
+    }
+
+    fn check_rejects_escaping_symlinks(
+        format: ArchiveFormat,
+        make: fn(&str, &[(&str, &str)], &[&str]) -> anyhow::Result<TestArchive>,
+        name: &str,
+    ) -> anyhow::Result<()> {
+        // Symlinks that stay inside the output are fine.
+        let ok = make(name, &[("a/b", "../c/d"), ("e", "./c")], &["c/d"])?;
+        ok.extract(format)?;
+        assert_eq!(
+            fs_util::read_link(ok.out.join(ForwardRelativePath::new("a/b")?))?,
+            Path::new("../c/d")
+        );
+
+        for target in ["/etc", "..", "../x", "a/../..", "c/../d"] {
+            let bad = make(name, &[("link", target)], &[])?;
+            let err = bad.extract(format).unwrap_err();
+            assert!(
+                format!("{:#}", err).contains("is a symlink to"),
+                "{}: {:#}",
+                target,
+                err
+            );
+        }
+
+        // Even a symlink pointing inside the output can't be written through.
+        let through = make(name, &[("a", "b")], &["a/passwd"])?;
+        let err = through.extract(format).unwrap_err();
+        assert!(
+            format!("{:#}", err).contains("which is a symlink"),
+            "{:#}",
+            err
+        );
+        assert!(!fs_util::try_exists(
+            through.out.join(ForwardRelativePath::new("b/passwd")?)
+        )?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_tar_rejects_escaping_symlinks() -> anyhow::Result<()> {
+        check_rejects_escaping_symlinks(ArchiveFormat::Tar, TestArchive::tar, "a.tar")
+    }
+
+    #[test]
+    fn test_zip_rejects_escaping_symlinks() -> anyhow::Result<()> {
+        check_rejects_escaping_symlinks(ArchiveFormat::Zip, TestArchive::zip, "a.zip")
+    }
+}
@@ -10,6 +10,8 @@
 pub(crate) mod cas_artifact;
 pub(crate) mod copy;
 pub(crate) mod download_file;
+pub(crate) mod expand_template;
+pub(crate) mod extract_archive;
 pub(crate) mod offline;
 pub mod run;
 pub(crate) mod symlinked_dir;
@@ -72,7 +72,7 @@ pub(crate) async fn declare_copy_from_offline_cache(
 }
 
 /// Declares a generic copy materialization from src to dest.
-async fn declare_copy_materialization(
+pub(crate) async fn declare_copy_materialization(
     ctx: &dyn ActionExecutionCtx,
     src: ProjectRelativePathBuf,
     dest: ProjectRelativePathBuf,
@@ -0,0 +1,364 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Support for `OutputPathsBehavior::ContentBased`.
+//!
+//! When an action's executor config uses it, an eligible run action executes with its outputs (and scratch / metadata paths)
+//! placed under a directory derived from the action's command line and inputs instead of its
+//! configuration. Two configurations that end up running the same command on the same inputs
+//! therefore produce the same request, and share a single action cache entry. The outputs are
+//! then copied to their usual location, so consumers are unaffected.
+//!
+//! Consumers still reference the configuration-specific paths, so this only dedupes actions whose
+//! inputs don't themselves depend on the configuration (e.g. actions that only read sources).
+//!
+//! Since equivalent actions share their content-based paths, only one of them runs at a time (see
+//! `lock_content_hash`). Outputs stored in the CAS are declared at their usual location directly,
+//! while outputs that only exist on local disk are copied there before the next equivalent action
+//! may clean up and reuse those paths.
+
+use std::sync::Arc;
+
+use anyhow::Context;
+use buck2_artifact::artifact::artifact_type::Artifact;
+use buck2_artifact::artifact::artifact_type::BaseArtifactKind;
+use buck2_artifact::artifact::build_artifact::BuildArtifact;
+use buck2_build_api::actions::execute::action_executor::ActionExecutionKind;
+use buck2_build_api::actions::execute::action_executor::ActionOutputs;
+use buck2_build_api::actions::impls::expanded_command_line::ExpandedCommandLine;
+use buck2_build_api::actions::ActionExecutionCtx;
+use buck2_build_api::artifact_groups::ArtifactGroupValues;
+use buck2_build_api::interpreter::rule_defs::cmd_args::CommandLineContext;
+use buck2_build_api::interpreter::rule_defs::cmd_args::CommandLineLocation;
+use buck2_build_api::interpreter::rule_defs::cmd_args::DefaultCommandLineContext;
+use buck2_core::directory::FingerprintedDirectory;
+use buck2_core::fs::artifact_path_resolver::ArtifactFs;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
+use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
+use buck2_execute::artifact::fs::ExecutorFs;
+use buck2_execute::digest_config::DigestConfig;
+use buck2_execute::directory::ActionDirectoryBuilder;
+use buck2_execute::execute::kind::CommandExecutionKind;
+use buck2_execute::execute::request::OutputType;
+use buck2_execute::execute::request::WorkerSpec;
+use buck2_execute::materialize::materializer::CasDownloadInfo;
+use dashmap::DashMap;
+use dupe::Dupe;
+use indexmap::IndexMap;
+use once_cell::sync::Lazy;
+use relative_path::RelativePathBuf;
+use sha1::Digest;
+use sha1::Sha1;
+use tokio::sync::Mutex;
+use tokio::sync::OwnedMutexGuard;
+
+use crate::actions::impls::offline::declare_copy_materialization;
+
+/// Used in place of the content hash when rendering the command line that the content hash is
+/// computed from, since that command line may reference the action's own outputs.
+pub(crate) const CONTENT_HASH_PLACEHOLDER: &str = "placeholder";
+
+/// A `CommandLineContext` that resolves the outputs of the action being run to their
+/// content-based location.
+pub(crate) struct ContentBasedPathsCommandLineContext<'a> {
+    inner: DefaultCommandLineContext<'a>,
+    outputs: &'a [BuildArtifact],
+    content_hash: Option<Arc<str>>,
+}
+
+impl<'a> ContentBasedPathsCommandLineContext<'a> {
+    /// With no `content_hash`, this behaves exactly like a `DefaultCommandLineContext`.
+    pub(crate) fn new(
+        fs: &'a ExecutorFs,
+        outputs: &'a [BuildArtifact],
+        content_hash: Option<Arc<str>>,
+    ) -> Self {
+        Self {
+            inner: DefaultCommandLineContext::new(fs),
+            outputs,
+            content_hash,
+        }
+    }
+}
+
+impl CommandLineContext for ContentBasedPathsCommandLineContext<'_> {
+    fn resolve_project_path(
+        &self,
+        path: ProjectRelativePathBuf,
+    ) -> anyhow::Result<CommandLineLocation> {
+        self.inner.resolve_project_path(path)
+    }
+
+    fn fs(&self) -> &ExecutorFs {
+        CommandLineContext::fs(&self.inner)
+    }
+
+    fn resolve_artifact(&self, artifact: &Artifact) -> anyhow::Result<CommandLineLocation> {
+        if let Some(content_hash) = &self.content_hash {
+            if let (BaseArtifactKind::Build(build), projected) = artifact.as_parts() {
+                if self
+                    .outputs
+                    .iter()
+                    .any(|o| o.get_path() == build.get_path())
+                {
+                    let path = self
+                        .fs()
+                        .fs()
+                        .resolve_build(&build.get_path().with_content_hash(content_hash.dupe()));
+                    let path = match projected {
+                        Some(projected) => path.join(projected),
+                        None => path,
+                    };
+                    return self.resolve_project_path(path);
+                }
+            }
+        }
+        self.inner.resolve_artifact(artifact)
+    }
+
+    fn next_macro_file_path(&mut self) -> anyhow::Result<RelativePathBuf> {
+        self.inner.next_macro_file_path()
+    }
+}
+
+/// Locks on the content hashes of actions currently running at content-based paths. Entries are
+/// removed once no action holds or waits for them.
+static CONTENT_HASHES: Lazy<DashMap<Arc<str>, Arc<Mutex<()>>>> = Lazy::new(DashMap::new);
+
+/// Keeps the content-based paths for a content hash for one action, see `lock_content_hash`.
+pub(crate) struct ContentHashGuard {
+    content_hash: Arc<str>,
+    guard: Option<OwnedMutexGuard<()>>,
+}
+
+impl Drop for ContentHashGuard {
+    fn drop(&mut self) {
+        drop(self.guard.take());
+        // Waiting actions hold a reference to the lock, so it is only referenced by the map if no
+        // other action uses it. The shard lock keeps new actions from taking it meanwhile.
+        CONTENT_HASHES.remove_if(&self.content_hash, |_, lock| Arc::strong_count(lock) == 1);
+    }
+}
+
+/// Waits until no other action runs at the content-based paths for `content_hash`, and returns a
+/// guard that keeps them for this action until it's dropped.
+pub(crate) async fn lock_content_hash(content_hash: &Arc<str>) -> ContentHashGuard {
+    let lock = CONTENT_HASHES
+        .entry(content_hash.dupe())
+        .or_default()
+        .dupe();
+    ContentHashGuard {
+        content_hash: content_hash.dupe(),
+        guard: Some(lock.lock_owned().await),
+    }
+}
+
+/// Computes the content hash of a run action from its command line (rendered with
+/// `CONTENT_HASH_PLACEHOLDER`), its inputs and its outputs.
+pub(crate) fn content_hash(
+    expanded: &ExpandedCommandLine,
+    worker: Option<&WorkerSpec>,
+    inputs: &[&ArtifactGroupValues],
+    outputs: &[BuildArtifact],
+    fs: &ArtifactFs,
+    digest_config: DigestConfig,
+) -> anyhow::Result<Arc<str>> {
+    let mut builder = ActionDirectoryBuilder::empty();
+    for &group in inputs {
+        group.add_to_directory(&mut builder, fs)?;
+    }
+    let inputs = builder.fingerprint(digest_config.as_directory_serializer());
+
+    Ok(hash_action(
+        expanded,
+        worker.map_or(&[][..], |w| w.exe.as_slice()),
+        outputs
+            .iter()
+            .map(|o| (o.get_path().path(), o.output_type())),
+        inputs.fingerprint().raw_digest().as_bytes(),
+    ))
+}
+
+/// Outputs are identified by their path relative to their owner, which doesn't include the
+/// configuration.
+fn hash_action<'a>(
+    expanded: &ExpandedCommandLine,
+    worker_exe: &[String],
+    outputs: impl IntoIterator<Item = (&'a ForwardRelativePath, OutputType)>,
+    inputs_digest: &[u8],
+) -> Arc<str> {
+    let mut hasher = Sha1::new();
+    let mut update_str = |s: &str| {
+        hasher.update(s.len().to_le_bytes());
+        hasher.update(s.as_bytes());
+    };
+    for exe in worker_exe {
+        update_str(exe);
+    }
+    for (path, output_type) in outputs {
+        update_str(path.as_str());
+        update_str(&format!("{:?}", output_type));
+    }
+    hasher.update(expanded.fingerprint().as_bytes());
+    hasher.update(inputs_digest);
+
+    // Truncated to keep paths short; this only needs to tell apart the actions of a single target.
+    Arc::from(hex::encode(&hasher.finalize()[..16]))
+}
+
+/// Returns how to download the outputs of an execution from the CAS, if it stored them there.
+fn cas_download_info(kind: &ActionExecutionKind) -> Option<CasDownloadInfo> {
+    match kind {
+        ActionExecutionKind::Command {
+            kind:
+                CommandExecutionKind::Remote { details, .. }
+                | CommandExecutionKind::ActionCache { details }
+                | CommandExecutionKind::RemoteDepFileCache { details },
+            ..
+        } => Some(CasDownloadInfo::new_declared(details.use_case)),
+        _ => None,
+    }
+}
+
+/// Declares the outputs of an action executed at content-based paths at their usual location, and
+/// returns them keyed by that location. Must be called while holding the `lock_content_hash` lock.
+///
+/// Outputs of a remote execution or cache hit are in the CAS and are declared as such, so they
+/// are only downloaded if needed. Outputs of a local execution only exist on local disk, and are
+/// copied.
+pub(crate) async fn declare_outputs_from_content_paths(
+    ctx: &mut dyn ActionExecutionCtx,
+    outputs: &[BuildArtifact],
+    content_hash: &Arc<str>,
+    executed: ActionOutputs,
+    execution_kind: &ActionExecutionKind,
+) -> anyhow::Result<ActionOutputs> {
+    let cas_download_info = cas_download_info(execution_kind);
+    let mut res = IndexMap::with_capacity(outputs.len());
+    let mut dests = Vec::with_capacity(outputs.len());
+    for output in outputs {
+        let content_path = output.get_path().with_content_hash(content_hash.dupe());
+        let value = executed
+            .get(&content_path)
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Internal error: missing content-based output `{}`",
+                    output.get_path()
+                )
+            })?
+            .dupe();
+        let dest = ctx.fs().resolve_build(output.get_path());
+        if cas_download_info.is_none() {
+            let src = ctx.fs().resolve_build(&content_path);
+            declare_copy_materialization(ctx, src, dest.clone(), value.dupe()).await?;
+        }
+        dests.push((dest, value.dupe()));
+        res.insert(output.get_path().dupe(), value);
+    }
+    match cas_download_info {
+        Some(info) => {
+            ctx.materializer()
+                .declare_cas_many(Arc::new(info), dests, ctx.cancellation_context())
+                .await?
+        }
+        None => {
+            // The next equivalent action may clean up the content-based paths as soon as the lock
+            // is released, so the copies can't wait until they're needed.
+            ctx.materializer()
+                .ensure_materialized(dests.into_iter().map(|(dest, _)| dest).collect())
+                .await
+                .context("Error copying outputs from content-based paths")?;
+        }
+    }
+    Ok(ActionOutputs::new(res))
+}
+
+#[cfg(test)]
+mod tests {
+    use buck2_artifact::artifact::artifact_type::testing::BuildArtifactTestingExt;
+    use buck2_artifact::deferred::id::DeferredId;
+    use buck2_core::configuration::data::ConfigurationData;
+    use buck2_core::fs::paths::forward_rel_path::ForwardRelativePathBuf;
+    use buck2_core::target::configured_target_label::ConfiguredTargetLabel;
+    use sorted_vector_map::SortedVectorMap;
+
+    use super::*;
+
+    fn command(args: &[&str]) -> ExpandedCommandLine {
+        ExpandedCommandLine {
+            exe: vec!["compiler".to_owned()],
+            args: args.iter().map(|a| (*a).to_owned()).collect(),
+            env: SortedVectorMap::new(),
+        }
+    }
+
+    fn output(cfg: ConfigurationData) -> BuildArtifact {
+        BuildArtifact::testing_new(
+            ConfiguredTargetLabel::testing_parse("cell//pkg:foo", cfg),
+            ForwardRelativePathBuf::unchecked_new("out/foo.o".to_owned()),
+            DeferredId::testing_new(0),
+        )
+    }
+
+    fn hash(expanded: &ExpandedCommandLine, output: &BuildArtifact, inputs: &[u8]) -> Arc<str> {
+        hash_action(
+            expanded,
+            &[],
+            [(output.get_path().path(), output.output_type())],
+            inputs,
+        )
+    }
+
+    #[test]
+    fn test_content_hash_is_stable_across_configurations() {
+        let cmd = command(&["-c", "foo.c"]);
+        let a = output(ConfigurationData::testing_new());
+        let b = output(ConfigurationData::unspecified());
+        assert_ne!(a.get_path(), b.get_path());
+        assert_eq!(hash(&cmd, &a, b"inputs"), hash(&cmd, &b, b"inputs"));
+    }
+
+    #[test]
+    fn test_content_hash_changes_with_inputs_and_command() {
+        let cmd = command(&["-c", "foo.c"]);
+        let out = output(ConfigurationData::testing_new());
+        let base = hash(&cmd, &out, b"inputs");
+
+        assert_ne!(base, hash(&cmd, &out, b"other inputs"));
+        assert_ne!(
+            base,
+            hash(&command(&["-O2", "-c", "foo.c"]), &out, b"inputs")
+        );
+        assert_ne!(
+            base,
+            hash_action(
+                &cmd,
+                &["worker".to_owned()],
+                [(out.get_path().path(), out.output_type())],
+                b"inputs",
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_lock_content_hash_removes_unused_entries() {
+        let content_hash: Arc<str> = Arc::from("test_lock_content_hash_removes_unused_entries");
+        let guard = lock_content_hash(&content_hash).await;
+        assert!(CONTENT_HASHES.contains_key(&content_hash));
+
+        let waiter = tokio::spawn({
+            let content_hash = content_hash.dupe();
+            async move { drop(lock_content_hash(&content_hash).await) }
+        });
+        tokio::task::yield_now().await;
+        drop(guard);
+        waiter.await.unwrap();
+        assert!(!CONTENT_HASHES.contains_key(&content_hash));
+    }
+}
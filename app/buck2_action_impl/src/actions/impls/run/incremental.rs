@@ -0,0 +1,183 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! The state directory that `incremental` actions keep between their executions. The state only
+//! exists on the local disk, so when such an action executes remotely, it runs as a plain action.
+
+use buck2_build_api::interpreter::rule_defs::cmd_args::CommandLineContext;
+use buck2_build_api::interpreter::rule_defs::cmd_args::DefaultCommandLineContext;
+use buck2_core::execution_types::executor_config::Executor;
+use buck2_core::execution_types::executor_config::RemoteEnabledExecutor;
+use buck2_core::fs::buck_out_path::BuckOutScratchPath;
+use buck2_execute::artifact::fs::ExecutorFs;
+use buck2_execute::execute::request::CommandExecutionInput;
+use buck2_execute::execute::request::ExecutorPreference;
+
+/// Environment variable with the path of the state directory, relative to the project root.
+pub(crate) const INCREMENTAL_STATE_ENV: &str = "BUCK_INCREMENTAL_STATE_PATH";
+
+/// The preference to execute an incremental action with if it runs incrementally, on the local
+/// executor, or `None` if it runs remotely as a plain action. On a hybrid executor, the action is
+/// kept local unless it prefers remote execution.
+pub(crate) fn incremental_executor_preference(
+    executor: &Executor,
+    preference: ExecutorPreference,
+) -> Option<ExecutorPreference> {
+    match executor {
+        Executor::Local(_)
+        | Executor::RemoteEnabled {
+            executor: RemoteEnabledExecutor::Local(_),
+            ..
+        } => Some(preference),
+        Executor::RemoteEnabled {
+            executor: RemoteEnabledExecutor::Remote(_),
+            ..
+        } => None,
+        Executor::RemoteEnabled {
+            executor: RemoteEnabledExecutor::Hybrid { .. },
+            ..
+        } => {
+            if preference.prefers_remote() {
+                None
+            } else {
+                preference.and(ExecutorPreference::LocalRequired).ok()
+            }
+        }
+    }
+}
+
+/// The input that provides the state directory `state` to an incremental action, and the
+/// environment variable that points the command at it.
+pub(crate) fn incremental_state_input(
+    executor_fs: &ExecutorFs,
+    state: BuckOutScratchPath,
+) -> anyhow::Result<(CommandExecutionInput, (String, String))> {
+    let path = executor_fs
+        .fs()
+        .buck_out_path_resolver()
+        .resolve_incremental_state(&state);
+    let env = DefaultCommandLineContext::new(executor_fs)
+        .resolve_project_path(path)?
+        .into_string();
+    Ok((
+        CommandExecutionInput::IncrementalStatePath(state),
+        (INCREMENTAL_STATE_ENV.to_owned(), env),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use buck2_core::base_deferred_key::BaseDeferredKey;
+    use buck2_core::category::Category;
+    use buck2_core::cells::cell_root_path::CellRootPathBuf;
+    use buck2_core::cells::name::CellName;
+    use buck2_core::cells::CellResolver;
+    use buck2_core::configuration::data::ConfigurationData;
+    use buck2_core::execution_types::executor_config::CacheUploadBehavior;
+    use buck2_core::execution_types::executor_config::HybridExecutionLevel;
+    use buck2_core::execution_types::executor_config::LocalExecutorOptions;
+    use buck2_core::execution_types::executor_config::PathSeparatorKind;
+    use buck2_core::execution_types::executor_config::RemoteExecutorOptions;
+    use buck2_core::execution_types::executor_config::RemoteExecutorUseCase;
+    use buck2_core::fs::artifact_path_resolver::ArtifactFs;
+    use buck2_core::fs::buck_out_path::BuckOutPathResolver;
+    use buck2_core::fs::project::ProjectRootTemp;
+    use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
+    use buck2_core::target::configured_target_label::ConfiguredTargetLabel;
+
+    use super::*;
+
+    fn remote_enabled(executor: RemoteEnabledExecutor) -> Executor {
+        Executor::RemoteEnabled {
+            executor,
+            re_properties: Default::default(),
+            re_use_case: RemoteExecutorUseCase::buck2_default(),
+            re_action_key: None,
+            cache_upload_behavior: CacheUploadBehavior::Disabled,
+            remote_cache_enabled: true,
+            remote_dep_file_cache_enabled: false,
+        }
+    }
+
+    #[test]
+    fn test_incremental_executor_preference() {
+        let local = Executor::Local(LocalExecutorOptions::default());
+        let remote = remote_enabled(RemoteEnabledExecutor::Remote(
+            RemoteExecutorOptions::default(),
+        ));
+        let hybrid = remote_enabled(RemoteEnabledExecutor::Hybrid {
+            local: LocalExecutorOptions::default(),
+            remote: RemoteExecutorOptions::default(),
+            level: HybridExecutionLevel::Limited,
+        });
+
+        assert!(matches!(
+            incremental_executor_preference(&local, ExecutorPreference::Default),
+            Some(ExecutorPreference::Default)
+        ));
+        assert!(matches!(
+            incremental_executor_preference(&local, ExecutorPreference::RemotePreferred),
+            Some(ExecutorPreference::RemotePreferred)
+        ));
+
+        // A remote-only executor runs the action as a plain, non-incremental action.
+        assert!(incremental_executor_preference(&remote, ExecutorPreference::Default).is_none());
+        assert!(
+            incremental_executor_preference(&remote, ExecutorPreference::LocalPreferred).is_none()
+        );
+
+        assert!(matches!(
+            incremental_executor_preference(&hybrid, ExecutorPreference::Default),
+            Some(ExecutorPreference::LocalRequired)
+        ));
+        assert!(
+            incremental_executor_preference(&hybrid, ExecutorPreference::RemotePreferred).is_none()
+        );
+    }
+
+    #[test]
+    fn test_incremental_state_env() -> anyhow::Result<()> {
+        let project_root = ProjectRootTemp::new()?;
+        let fs = ArtifactFs::new(
+            CellResolver::testing_with_name_and_path(
+                CellName::testing_new("cell"),
+                CellRootPathBuf::new(ProjectRelativePathBuf::unchecked_new("cell".to_owned())),
+            ),
+            BuckOutPathResolver::new(ProjectRelativePathBuf::unchecked_new(
+                "buck-out/v2".to_owned(),
+            )),
+            project_root.path().clone(),
+        );
+        let executor_fs = ExecutorFs::new(&fs, PathSeparatorKind::Unix);
+        let state = BuckOutScratchPath::new(
+            BaseDeferredKey::TargetLabel(ConfiguredTargetLabel::testing_parse(
+                "cell//pkg:foo",
+                ConfigurationData::testing_new(),
+            )),
+            &Category::try_from("compile").unwrap(),
+            Some("foo.o"),
+        )?;
+
+        let (input, (var, value)) = incremental_state_input(&executor_fs, state.clone())?;
+
+        assert!(matches!(
+            input,
+            CommandExecutionInput::IncrementalStatePath(path) if path == state
+        ));
+        assert_eq!(INCREMENTAL_STATE_ENV, var);
+        assert_eq!(
+            fs.buck_out_path_resolver()
+                .resolve_incremental_state(&state)
+                .as_str(),
+            value
+        );
+        assert!(value.starts_with("buck-out/v2/incremental/"), "{}", value);
+        Ok(())
+    }
+}
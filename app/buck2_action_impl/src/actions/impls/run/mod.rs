@@ -10,6 +10,8 @@
 use std::borrow::Cow;
 use std::fmt::Display;
 use std::ops::ControlFlow;
+use std::sync::Arc;
+use std::time::Duration;
 
 use allocative::Allocative;
 use anyhow::Context;
@@ -35,6 +37,7 @@ use buck2_build_api::interpreter::rule_defs::cmd_args::DefaultCommandLineContext
 use buck2_build_api::interpreter::rule_defs::cmd_args::SimpleCommandLineArtifactVisitor;
 use buck2_build_api::interpreter::rule_defs::provider::builtin::worker_info::WorkerInfo;
 use buck2_core::category::Category;
+use buck2_core::execution_types::executor_config::OutputPathsBehavior;
 use buck2_core::fs::buck_out_path::BuckOutPath;
 use buck2_core::fs::paths::forward_rel_path::ForwardRelativePathBuf;
 use buck2_events::dispatch::span_async;
@@ -53,6 +56,7 @@ use buck2_execute::execute::request::WorkerSpec;
 use buck2_execute::execute::result::CommandExecutionResult;
 use derive_more::Display;
 use dupe::Dupe;
+use dupe::OptionDupedExt;
 use gazebo::prelude::*;
 use host_sharing::HostSharingRequirements;
 use host_sharing::WeightClass;
@@ -79,14 +83,23 @@ use starlark::values::ValueOf;
 use thiserror::Error;
 
 use self::dep_files::DepFileBundle;
+use crate::actions::impls::run::content_based_paths::content_hash;
+use crate::actions::impls::run::content_based_paths::declare_outputs_from_content_paths;
+use crate::actions::impls::run::content_based_paths::lock_content_hash;
+use crate::actions::impls::run::content_based_paths::ContentBasedPathsCommandLineContext;
+use crate::actions::impls::run::content_based_paths::CONTENT_HASH_PLACEHOLDER;
 use crate::actions::impls::run::dep_files::make_dep_file_bundle;
 use crate::actions::impls::run::dep_files::populate_dep_files;
 use crate::actions::impls::run::dep_files::DepFilesCommandLineVisitor;
 use crate::actions::impls::run::dep_files::RunActionDepFiles;
+use crate::actions::impls::run::incremental::incremental_executor_preference;
+use crate::actions::impls::run::incremental::incremental_state_input;
 use crate::actions::impls::run::metadata::metadata_content;
 
 pub(crate) mod audit_dep_files;
+mod content_based_paths;
 pub mod dep_files;
+mod incremental;
 mod metadata;
 
 #[derive(Debug, Error)]
@@ -164,10 +177,15 @@ pub(crate) struct UnregisteredRunAction {
     pub(crate) dep_files: RunActionDepFiles,
     pub(crate) metadata_param: Option<MetadataParameter>,
     pub(crate) no_outputs_cleanup: bool,
+    /// Keeps the outputs and a state directory of the previous local execution. When the action
+    /// executes remotely, it runs as a plain action instead.
+    pub(crate) incremental: bool,
     pub(crate) allow_cache_upload: bool,
     pub(crate) allow_dep_file_cache_upload: bool,
     pub(crate) force_full_hybrid_if_capable: bool,
     pub(crate) unique_input_inodes: bool,
+    /// Falls back to the default timeout for the category if unset.
+    pub(crate) timeout: Option<Duration>,
 }
 
 impl UnregisteredAction for UnregisteredRunAction {
@@ -273,13 +291,19 @@ impl RunAction {
         })
     }
 
-    /// Get the command line expansion for this RunAction.
+    /// Get the command line expansion for this RunAction. With a `content_hash`, this action's
+    /// outputs are rendered at their content-based location.
     fn expand_command_line_and_worker(
         &self,
         fs: &ExecutorFs,
+        content_hash: Option<&Arc<str>>,
         artifact_visitor: &mut impl CommandLineArtifactVisitor,
     ) -> anyhow::Result<(ExpandedCommandLine, Option<WorkerSpec>)> {
-        let mut ctx = DefaultCommandLineContext::new(fs);
+        let mut ctx = ContentBasedPathsCommandLineContext::new(
+            fs,
+            self.outputs.as_slice(),
+            content_hash.duped(),
+        );
         let values = Self::unpack(&self.starlark_values)?;
 
         let mut exe_rendered = Vec::<String>::new();
@@ -314,7 +338,11 @@ impl RunAction {
             .into_iter()
             .map(|(k, v)| {
                 let mut env = String::new();
-                let mut ctx = DefaultCommandLineContext::new(fs);
+                let mut ctx = ContentBasedPathsCommandLineContext::new(
+                    fs,
+                    self.outputs.as_slice(),
+                    content_hash.duped(),
+                );
                 v.add_to_command_line(
                     &mut SpaceSeparatedCommandLineBuilder::wrap_string(&mut env),
                     &mut ctx,
@@ -362,12 +390,17 @@ impl RunAction {
         &self,
         visitor: &mut impl RunActionVisitor,
         ctx: &mut dyn ActionExecutionCtx,
+        content_based_paths: bool,
+        incremental: bool,
     ) -> anyhow::Result<PreparedRunAction> {
         let executor_fs = ctx.executor_fs();
         let fs = executor_fs.fs();
 
+        // The content hash can only be computed once we know the command line, so when it is
+        // used, the command line is first rendered with a placeholder in its place.
+        let placeholder = content_based_paths.then(|| Arc::from(CONTENT_HASH_PLACEHOLDER));
         let (expanded, worker) =
-            self.expand_command_line_and_worker(&ctx.executor_fs(), visitor)?;
+            self.expand_command_line_and_worker(&executor_fs, placeholder.as_ref(), visitor)?;
 
         // TODO (@torozco): At this point, might as well just receive the list already. Finding
         // those things in a HashMap is just not very useful.
@@ -376,6 +409,25 @@ impl RunAction {
             .map(|group| ctx.artifact_values(group))
             .collect();
 
+        let (expanded, worker, content_hash) = if content_based_paths {
+            let content_hash = content_hash(
+                &expanded,
+                worker.as_ref(),
+                &artifact_inputs,
+                self.outputs.as_slice(),
+                fs,
+                ctx.digest_config(),
+            )?;
+            let (expanded, worker) = self.expand_command_line_and_worker(
+                &executor_fs,
+                Some(&content_hash),
+                &mut SimpleCommandLineArtifactVisitor::new(),
+            )?;
+            (expanded, worker, Some(content_hash))
+        } else {
+            (expanded, worker, None)
+        };
+
         let mut inputs: Vec<CommandExecutionInput> =
             artifact_inputs[..].map(|&i| CommandExecutionInput::Artifact(Box::new(i.dupe())));
 
@@ -389,6 +441,10 @@ impl RunAction {
 
         if let Some(metadata_param) = &self.inner.metadata_param {
             let path = BuckOutPath::new(ctx.target().owner().dupe(), metadata_param.path.clone());
+            let path = match &content_hash {
+                Some(content_hash) => path.with_content_hash(content_hash.dupe()),
+                None => path,
+            };
             let env = cli_ctx
                 .resolve_project_path(fs.buck_out_path_resolver().resolve_gen(&path))?
                 .into_string();
@@ -402,6 +458,10 @@ impl RunAction {
         }
 
         let scratch = ctx.target().scratch_path();
+        let scratch = match &content_hash {
+            Some(content_hash) => scratch.with_content_hash(content_hash.dupe()),
+            None => scratch,
+        };
         let scratch_path = fs.buck_out_path_resolver().resolve_scratch(&scratch);
         extra_env.push((
             "BUCK_SCRATCH_PATH".to_owned(),
@@ -409,12 +469,21 @@ impl RunAction {
         ));
         inputs.push(CommandExecutionInput::ScratchPath(scratch));
 
+        if incremental {
+            let (input, env) = incremental_state_input(&executor_fs, ctx.target().scratch_path())?;
+            inputs.push(input);
+            extra_env.push(env);
+        }
+
         let paths = CommandExecutionPaths::new(
             inputs,
             self.outputs
                 .iter()
                 .map(|b| CommandExecutionOutput::BuildArtifact {
-                    path: b.get_path().dupe(),
+                    path: match &content_hash {
+                        Some(content_hash) => b.get_path().with_content_hash(content_hash.dupe()),
+                        None => b.get_path().dupe(),
+                    },
                     output_type: b.output_type(),
                 })
                 .collect(),
@@ -427,6 +496,7 @@ impl RunAction {
             extra_env,
             paths,
             worker,
+            content_hash,
         })
     }
 
@@ -494,6 +564,8 @@ pub(crate) struct PreparedRunAction {
     extra_env: Vec<(String, String)>,
     paths: CommandExecutionPaths,
     worker: Option<WorkerSpec>,
+    /// Set when outputs are placed at content-based paths.
+    content_hash: Option<Arc<str>>,
 }
 
 impl PreparedRunAction {
@@ -503,6 +575,7 @@ impl PreparedRunAction {
             extra_env,
             paths,
             worker,
+            content_hash: _,
         } = self;
 
         for (k, v) in extra_env {
@@ -601,8 +674,13 @@ impl Action for RunAction {
                 Some(x) => x.to_string(),
             },
             "no_outputs_cleanup".to_owned() => self.inner.no_outputs_cleanup.to_string(),
+            "incremental".to_owned() => self.inner.incremental.to_string(),
             "allow_cache_upload".to_owned() => self.inner.allow_cache_upload.to_string(),
             "allow_dep_file_cache_upload".to_owned() => self.inner.allow_dep_file_cache_upload.to_string(),
+            "timeout".to_owned() => match self.inner.timeout {
+                None => "None".to_owned(),
+                Some(x) => x.as_secs().to_string(),
+            },
         }
     }
 }
@@ -615,17 +693,45 @@ impl IncrementalActionExecutable for RunAction {
     ) -> anyhow::Result<(ActionOutputs, ActionExecutionMetadata)> {
         let knobs = ctx.run_action_knobs();
         let process_dep_files = !self.inner.dep_files.labels.is_empty() || knobs.hash_all_commands;
+        let (incremental, executor_preference) = if self.inner.incremental {
+            match incremental_executor_preference(
+                &ctx.target().execution_config().executor,
+                self.inner.executor_preference,
+            ) {
+                Some(preference) => (true, preference),
+                None => (false, self.inner.executor_preference),
+            }
+        } else {
+            (false, self.inner.executor_preference)
+        };
+        // Dep files and incremental state are keyed by the usual output paths, so those actions
+        // keep them. Anon targets and BXL don't have a configuration to take out of the path.
+        let content_based_paths = ctx
+            .target()
+            .execution_config()
+            .options
+            .output_paths_behavior
+            == OutputPathsBehavior::ContentBased
+            && !process_dep_files
+            && !incremental
+            && ctx.target().owner().unpack_target_label().is_some();
         let (prepared_run_action, dep_file_visitor) = if !process_dep_files {
             (
-                self.prepare(&mut SimpleCommandLineArtifactVisitor::new(), ctx)?,
+                self.prepare(
+                    &mut SimpleCommandLineArtifactVisitor::new(),
+                    ctx,
+                    content_based_paths,
+                    incremental,
+                )?,
                 None,
             )
         } else {
             let mut visitor = DepFilesCommandLineVisitor::new(&self.inner.dep_files);
-            let prepared = self.prepare(&mut visitor, ctx)?;
+            let prepared = self.prepare(&mut visitor, ctx, false, incremental)?;
             (prepared, Some(visitor))
         };
         let cmdline_digest = prepared_run_action.expanded.fingerprint();
+        let content_hash = prepared_run_action.content_hash.dupe();
 
         // Run actions are assumed to be shared
         let host_sharing_requirements = HostSharingRequirements::Shared(self.inner.weight);
@@ -633,13 +739,23 @@ impl IncrementalActionExecutable for RunAction {
         let req = prepared_run_action
             .into_command_execution_request()
             .with_prefetch_lossy_stderr(true)
-            .with_executor_preference(self.inner.executor_preference)
+            .with_executor_preference(executor_preference)
             .with_host_sharing_requirements(host_sharing_requirements)
             .with_low_pass_filter(self.inner.low_pass_filter)
-            .with_outputs_cleanup(!self.inner.no_outputs_cleanup)
+            .with_outputs_cleanup(!(self.inner.no_outputs_cleanup || incremental))
             .with_local_environment_inheritance(EnvironmentInheritance::local_command_exclusions())
             .with_force_full_hybrid_if_capable(self.inner.force_full_hybrid_if_capable)
             .with_unique_input_inodes(self.inner.unique_input_inodes);
+        let timeout = self.inner.timeout.or_else(|| {
+            knobs
+                .default_timeouts
+                .get(self.category().as_str())
+                .copied()
+        });
+        let req = match timeout {
+            Some(timeout) => req.with_timeout(timeout),
+            None => req,
+        };
 
         let (mut dep_file_bundle, req) = if let Some(visitor) = dep_file_visitor {
             let bundle = make_dep_file_bundle(ctx, visitor, cmdline_digest, req.paths())?;
@@ -665,6 +781,13 @@ impl IncrementalActionExecutable for RunAction {
             false
         };
 
+        // Equivalent actions from other configurations run into the same content-based paths, so
+        // only one of them may run at a time.
+        let _content_hash_lock = match &content_hash {
+            Some(content_hash) => Some(lock_content_hash(content_hash).await),
+            None => None,
+        };
+
         // Prepare the action, check the action cache, fully check the local dep file cache if needed, then execute the command
         let prepared_action = ctx.prepare_action(&req)?;
         let manager = ctx.command_execution_manager();
@@ -734,6 +857,19 @@ impl IncrementalActionExecutable for RunAction {
             self.inner.allow_cache_upload,
             self.inner.allow_dep_file_cache_upload,
         )?;
+        let outputs = match &content_hash {
+            Some(content_hash) => {
+                declare_outputs_from_content_paths(
+                    ctx,
+                    self.outputs.as_slice(),
+                    content_hash,
+                    outputs,
+                    &metadata.execution_kind,
+                )
+                .await?
+            }
+            None => outputs,
+        };
 
         if let Some(dep_file_bundle) = dep_file_bundle {
             populate_dep_files(ctx, dep_file_bundle, &outputs).await?;
@@ -9,6 +9,7 @@
 
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::Context;
 use buck2_artifact::artifact::artifact_type::OutputArtifact;
@@ -58,6 +59,7 @@ use sha1::Sha1;
 use starlark::environment::MethodsBuilder;
 use starlark::eval::Evaluator;
 use starlark::starlark_module;
+use starlark::values::dict::AllocDict;
 use starlark::values::dict::DictOf;
 use starlark::values::function::FUNCTION_TYPE;
 use starlark::values::none::NoneOr;
@@ -80,6 +82,9 @@ use crate::actions::impls::cas_artifact::UnregisteredCasArtifactAction;
 use crate::actions::impls::copy::CopyMode;
 use crate::actions::impls::copy::UnregisteredCopyAction;
 use crate::actions::impls::download_file::UnregisteredDownloadFileAction;
+use crate::actions::impls::expand_template::UnregisteredExpandTemplateAction;
+use crate::actions::impls::extract_archive::EntryFilter;
+use crate::actions::impls::extract_archive::UnregisteredExtractArchiveAction;
 use crate::actions::impls::run::dep_files::RunActionDepFiles;
 use crate::actions::impls::run::new_executor_preference;
 use crate::actions::impls::run::MetadataParameter;
@@ -94,6 +99,8 @@ use crate::actions::impls::write_macros::UnregisteredWriteMacrosToFileAction;
 enum DownloadFileError {
     #[error("Must pass in at least one checksum (e.g. `sha1 = ...`)")]
     MissingChecksum,
+    #[error("Must pass in at least one URL")]
+    NoUrls,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -122,6 +129,8 @@ enum RunActionError {
     InvalidWeight(i32),
     #[error("`weight` and `weight_percentage` cannot both be passed")]
     DuplicateWeightsSpecified,
+    #[error("`timeout` must be a positive number of seconds, got `{0}`")]
+    InvalidTimeout(i32),
     #[error("`dep_files` value with key `{}` has an invalid count of associated outputs. Expected 1, got {}.", .key, .count)]
     InvalidDepFileOutputs { key: String, count: usize },
     #[error("`dep_files` with keys `{}` and {} are using the same tag", .first, .second)]
@@ -140,6 +149,14 @@ enum RunActionError {
     ArtifactVisitRecursionLimitExceeded,
 }
 
+#[derive(Debug, thiserror::Error)]
+enum ExpandTemplateError {
+    #[error(
+        "Substitution for `{0}` contains arguments that write to files, which are not supported in templates"
+    )]
+    ArgAttrsNotSupported(String),
+}
+
 #[derive(Debug, thiserror::Error)]
 enum WriteActionError {
     #[error(
@@ -478,6 +495,52 @@ fn analysis_actions_methods_actions(builder: &mut MethodsBuilder) {
         }
     }
 
+    /// Returns an `artifact` whose contents are those of the `template` artifact, with every
+    /// occurrence of each key of `substitutions` replaced by its value. Substitutions are applied
+    /// in order, each to the result of the previous ones, as in Bazel. The template is substituted
+    /// as bytes, so it doesn't need to be UTF-8.
+    ///
+    /// * `substitutions`: values are strings or `cmd_args`, which are rendered as space separated
+    ///   arguments with artifacts resolved to their paths
+    /// * `is_executable` (optional): indicates whether the resulting file should be marked with executable permissions
+    fn expand_template<'v>(
+        this: &AnalysisActions<'v>,
+        template: ValueAsArtifactLike<'v>,
+        output: OutputArtifactArg<'v>,
+        substitutions: Option<ValueOf<'v, SmallMap<&'v str, Value<'v>>>>,
+        #[starlark(default = false)] is_executable: bool,
+        eval: &mut Evaluator<'v, '_>,
+    ) -> anyhow::Result<ValueTyped<'v, StarlarkDeclaredArtifact>> {
+        let substitutions = match substitutions {
+            None => eval.heap().alloc(AllocDict::EMPTY),
+            Some(substitutions) => {
+                for (key, value) in &substitutions.typed {
+                    if value.as_command_line_err()?.contains_arg_attr() {
+                        return Err(
+                            ExpandTemplateError::ArgAttrsNotSupported((*key).to_owned()).into()
+                        );
+                    }
+                }
+                substitutions.value
+            }
+        };
+
+        let template = template.0;
+        let artifact = template.get_artifact_group()?;
+        let mut this = this.state();
+        let (declaration, output_artifact) =
+            this.get_or_declare_output(eval, output, OutputType::File)?;
+
+        this.register_action(
+            indexset![artifact],
+            indexset![output_artifact],
+            UnregisteredExpandTemplateAction { is_executable },
+            Some(substitutions),
+        )?;
+
+        Ok(declaration.into_declared_artifact(AssociatedArtifacts::new()))
+    }
+
     /// Copies the source `artifact` to the destination (which can be a string representing a filename or an output `artifact`) and returns the output `artifact`.
     /// The copy works for files or directories.
     fn copy_file<'v>(
@@ -565,12 +628,56 @@ fn analysis_actions_methods_actions(builder: &mut MethodsBuilder) {
         create_dir_tree(eval, this, output, srcs, true)
     }
 
+    /// Returns an `artifact` which is a directory containing the contents of the archive `src`.
+    ///
+    /// * `format`: one of `tar`, `tar.gz`, `tar.zst` or `zip`, inferred from the extension of `src` if not given
+    /// * `strip_prefix`: only entries under this directory of the archive are extracted, relative to it
+    /// * `includes` and `excludes`: globs matched against entry paths (after `strip_prefix`) to select what is extracted
+    ///
+    /// Extraction happens within Buck2 rather than by running a command, and its result is cached like any other action.
+    /// Symlinks in the archive must point inside the output, and entries can't be extracted through them.
+    fn extract_archive<'v>(
+        this: &AnalysisActions<'v>,
+        #[starlark(require = pos)] output: OutputArtifactArg<'v>,
+        #[starlark(require = pos)] src: ValueAsArtifactLike<'v>,
+        #[starlark(require = named, default = NoneOr::None)] format: NoneOr<&str>,
+        #[starlark(require = named, default = NoneOr::None)] strip_prefix: NoneOr<&str>,
+        #[starlark(require = named, default = Vec::new())] includes: Vec<String>,
+        #[starlark(require = named, default = Vec::new())] excludes: Vec<String>,
+        eval: &mut Evaluator<'v, '_>,
+    ) -> anyhow::Result<ValueTyped<'v, StarlarkDeclaredArtifact>> {
+        let filter = EntryFilter::new(strip_prefix.into_option(), includes, excludes)?;
+        let action = UnregisteredExtractArchiveAction::new(format.into_option(), filter)?;
+
+        let src = src.0;
+        let artifact = src.get_artifact_group()?;
+        let associated_artifacts = src.get_associated_artifacts();
+        let mut this = this.state();
+        let (declaration, output_artifact) =
+            this.get_or_declare_output(eval, output, OutputType::Directory)?;
+
+        this.register_action(
+            indexset![artifact],
+            indexset![output_artifact],
+            action,
+            None,
+        )?;
+
+        Ok(declaration.into_declared_artifact(
+            associated_artifacts
+                .duped()
+                .unwrap_or_else(AssociatedArtifacts::new),
+        ))
+    }
+
     /// Runs a command
     ///
     /// * `arguments`: must be of type `cmd_args`, or a type convertible to such (such as a list of strings and artifacts) and must contain at least one `.as_output()` artifact
     /// * `category`: category and identifier - when used together, identify the action in Buck2's event stream, and must be unique for a given target
     /// * `weight`: used to note how heavy the command is and will typically be set to a higher value to indicate that less such commands should be run in parallel (if running locally)
+    /// * `timeout`: number of seconds after which the command is killed and the action fails with a timeout, wherever it runs. If unset, the default for the category from the `[action_timeouts]` buckconfig section applies, if any
     /// * `no_outputs_cleanup`: if this flag is set then Buck2 won't clean the outputs of a previous build that might be present on a disk; in which case, command from arguments should be responsible for the cleanup (that is useful, for example, when an action is supporting incremental mode and its outputs are based on result from a previous build)
+    /// * `incremental`: if this flag is set and the action runs locally, it keeps the outputs of its previous execution (as with `no_outputs_cleanup`) and gets a state directory that is preserved between executions, exposed via the environment variable `BUCK_INCREMENTAL_STATE_PATH`. On a hybrid executor, such actions run locally unless they prefer remote execution. When the action runs remotely, it runs as a plain action, without a state directory. The action is still cached based on its inputs alone, so the command must be able to start from scratch, with an empty state directory and no previous outputs
     /// * `metadata_env_var` and `meadata_path` should be used together: both set or both unset
     ///     * `metadata_path`: defines a path relative to the result directory for a file with action metadata, which will be created right before the command will be run.
     ///     * Metadata contains the path relative to the Buck2 project root and hash digest for every action input (this excludes symlinks as they could be resolved by a user script if needed). The resolved path relative to the Buck2 project for the metadata file will be passed to command from arguments, via the environment variable, with its name set by `metadata_env_var`
@@ -616,6 +723,7 @@ fn analysis_actions_methods_actions(builder: &mut MethodsBuilder) {
         #[starlark(require = named)] metadata_path: Option<String>,
         // TODO(scottcao): Refactor `no_outputs_cleanup` to `outputs_cleanup`
         #[starlark(require = named, default = false)] no_outputs_cleanup: bool,
+        #[starlark(require = named, default = false)] incremental: bool,
         #[starlark(require = named, default = false)] allow_cache_upload: bool,
         #[starlark(require = named, default = false)] allow_dep_file_cache_upload: bool,
         #[starlark(require = named, default = false)] force_full_hybrid_if_capable: bool,
@@ -623,6 +731,7 @@ fn analysis_actions_methods_actions(builder: &mut MethodsBuilder) {
             Either<ValueOf<'v, &'v WorkerRunInfo<'v>>, ValueOf<'v, &'v RunInfo<'v>>>,
         >,
         #[starlark(require = named, default = false)] unique_input_inodes: bool,
+        #[starlark(require = named)] timeout: Option<i32>,
         eval: &mut Evaluator<'v, '_>,
     ) -> anyhow::Result<NoneType> {
         struct RunCommandArtifactVisitor {
@@ -764,6 +873,14 @@ fn analysis_actions_methods_actions(builder: &mut MethodsBuilder) {
         let category = Category::try_from(category)?;
         let identifier = identifier.into_option();
 
+        let timeout = match timeout {
+            Some(timeout) if timeout <= 0 => {
+                return Err(RunActionError::InvalidTimeout(timeout).into());
+            }
+            Some(timeout) => Some(Duration::from_secs(timeout as u64)),
+            None => None,
+        };
+
         let metadata_param = match (metadata_env_var, metadata_path) {
             (Some(env_var), Some(path)) => {
                 let path: ForwardRelativePathBuf = path.try_into()?;
@@ -797,10 +914,12 @@ fn analysis_actions_methods_actions(builder: &mut MethodsBuilder) {
             dep_files: dep_files_configuration,
             metadata_param,
             no_outputs_cleanup,
+            incremental,
             allow_cache_upload,
             allow_dep_file_cache_upload,
             force_full_hybrid_if_capable,
             unique_input_inodes,
+            timeout,
         };
         this.state().register_action(
             artifacts.inputs,
@@ -812,13 +931,14 @@ fn analysis_actions_methods_actions(builder: &mut MethodsBuilder) {
     }
 
     /// Downloads a URL to an output (filename as string or output artifact).
+    /// `url` can also be a list of mirrors, which are tried in order until one succeeds.
     /// The file at the URL must have the given sha1 or the command will fail.
     /// The optional parameter is_executable indicates whether the resulting file should be marked with executable permissions.
     /// (Meta-internal) The optional parameter vpnless_url indicates a url from which this resource can be downloaded off VPN; this has the same restrictions as `url` above.
     fn download_file<'v>(
         this: &AnalysisActions<'v>,
         #[starlark(require = pos)] output: OutputArtifactArg<'v>,
-        #[starlark(require = pos)] url: &str,
+        #[starlark(require = pos)] url: Either<&str, Vec<&str>>,
         #[starlark(require = named, default = NoneOr::None)] vpnless_url: NoneOr<&str>,
         #[starlark(require = named, default = NoneOr::None)] sha1: NoneOr<&str>,
         #[starlark(require = named, default = NoneOr::None)] sha256: NoneOr<&str>,
@@ -840,12 +960,20 @@ fn analysis_actions_methods_actions(builder: &mut MethodsBuilder) {
             (None, None) => return Err(DownloadFileError::MissingChecksum.into()),
         };
 
+        let urls: Arc<[Arc<str>]> = match url {
+            Either::Left(url) => Arc::from(vec![Arc::from(url)]),
+            Either::Right(urls) if urls.is_empty() => {
+                return Err(DownloadFileError::NoUrls.into());
+            }
+            Either::Right(urls) => urls.into_iter().map(Arc::from).collect(),
+        };
+
         this.register_action(
             IndexSet::new(),
             indexset![output_artifact],
             UnregisteredDownloadFileAction::new(
                 checksum,
-                Arc::from(url),
+                urls,
                 vpnless_url.into_option().map(Arc::from),
                 is_executable,
                 is_deferrable,
@@ -223,3 +223,99 @@ fn declare_output_require_bound() -> anyhow::Result<()> {
         ),
     })
 }
+
+#[test]
+fn run_incremental_prefer_remote() -> anyhow::Result<()> {
+    let content = indoc!(
+        r#"
+         def test(c):
+             a = c.actions.declare_output("a")
+             c.actions.run([a.as_output()], category = "test_category", incremental = True, prefer_remote = True)
+         "#
+    );
+
+    run_ctx_test(content, |ret| {
+        ret.unwrap();
+        Ok(())
+    })
+}
+
+#[test]
+fn run_timeout() -> anyhow::Result<()> {
+    let content = indoc!(
+        r#"
+         def test(c):
+             a = c.actions.declare_output("a")
+             c.actions.run([a.as_output()], category = "test_category", timeout = 30)
+             return "ok"
+         "#
+    );
+    run_ctx_test(content, |ret| {
+        assert_eq!("ok", ret.unwrap().unpack_str().unwrap());
+        Ok(())
+    })?;
+
+    for timeout in ["0", "-5"] {
+        let content = format!(
+            indoc!(
+                r#"
+                 def test(c):
+                     a = c.actions.declare_output("a")
+                     c.actions.run([a.as_output()], category = "test_category", timeout = {})
+                 "#
+            ),
+            timeout
+        );
+        let expect = format!(
+            "`timeout` must be a positive number of seconds, got `{}`",
+            timeout
+        );
+        run_ctx_test(&content, |ret| match ret {
+            Err(e) if e.to_string().contains(&expect) => Ok(()),
+            _ => panic!(
+                "Expected a specific failure containing `{}`, got {:?}",
+                expect, ret
+            ),
+        })?;
+    }
+    Ok(())
+}
+
+#[test]
+fn expand_template() -> anyhow::Result<()> {
+    let content = indoc!(
+        r#"
+         def test(c):
+             template = c.actions.write("template.txt", "@DEP@ is not @MISSING@")
+             dep = c.actions.write("dep.txt", "dep")
+             out = c.actions.expand_template(
+                 template,
+                 "out.txt",
+                 substitutions = {"@DEP@": cmd_args(dep), "@NOT_IN_TEMPLATE@": "unused"},
+             )
+             no_substitutions = c.actions.expand_template(template, "no_substitutions.txt")
+             return (out.short_path, no_substitutions.short_path)
+         "#
+    );
+    run_ctx_test(content, |ret| {
+        let a = <(&str, &str)>::unpack_value(ret.unwrap()).unwrap();
+        assert_eq!(("out.txt", "no_substitutions.txt"), a);
+        Ok(())
+    })?;
+
+    let content = indoc!(
+        r#"
+         def test(c):
+             template = c.actions.write("template.txt", "")
+             c.actions.expand_template(template, "out.txt", substitutions = {1: "one"})
+         "#
+    );
+    let expect = "substitutions";
+    run_ctx_test(content, |ret| match ret {
+        Err(e) if e.to_string().contains(expect) => Ok(()),
+        _ => panic!(
+            "Expected a specific failure containing `{}`, got {:?}",
+            expect, ret
+        ),
+    })
+}
@@ -11,5 +11,6 @@ pub mod arg;
 pub(crate) mod configuration_dep;
 pub mod dep;
 pub(crate) mod query;
+pub mod record;
 pub(crate) mod source;
 pub(crate) mod split_transition_dep;
@@ -0,0 +1,102 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use buck2_interpreter::types::configured_providers_label::StarlarkConfiguredProvidersLabel;
+use buck2_interpreter::types::target_label::StarlarkTargetLabel;
+use buck2_node::attrs::attr_type::record::RecordAttrType;
+use buck2_node::attrs::attr_type::AttrType;
+use buck2_node::attrs::attr_type::AttrTypeInner;
+use gazebo::prelude::SliceExt;
+use starlark::environment::Module;
+use starlark::typing::Ty;
+use starlark::values::record::FrozenRecordType;
+use starlark::values::record::Record;
+use starlark::values::record::RecordType;
+use starlark::values::type_repr::StarlarkTypeRepr;
+use starlark::values::FrozenHeap;
+use starlark::values::Heap;
+use starlark::values::OwnedFrozenValue;
+use starlark::values::Value;
+
+/// The name of the record types that values of `attrs.record` attributes are provided as.
+pub const RECORD_TYPE_NAME: &str = "attrs.record";
+
+/// Returns the record type that resolved values of the attribute are instances of. It is built
+/// once per attribute declaration, so that values with the same fields compare equal.
+fn resolved_record_type(attr: &RecordAttrType) -> anyhow::Result<&OwnedFrozenValue> {
+    attr.starlark_type(|| {
+        let fields = attr
+            .fields
+            .try_map(|(name, field)| anyhow::Ok((name.clone(), resolved_ty(field.coercer())?)))?;
+        let module = Module::new();
+        let typ = RecordType::new_named(RECORD_TYPE_NAME, fields, module.heap())?;
+        module.set("record", module.heap().alloc(typ));
+        module.freeze()?.get("record")
+    })
+}
+
+/// The type of the values an attribute resolves to, or `Ty::any()` where the values depend on
+/// the analysis, like dependencies or sources.
+fn resolved_ty(attr: &AttrType) -> anyhow::Result<Ty> {
+    Ok(match &*attr.0 {
+        AttrTypeInner::Bool(_) => Ty::bool(),
+        AttrTypeInner::Int(_) => Ty::int(),
+        AttrTypeInner::String(_) | AttrTypeInner::Enum(_) => Ty::string(),
+        AttrTypeInner::List(t) => Ty::list(resolved_ty(&t.inner)?),
+        AttrTypeInner::Dict(t) => Ty::dict(resolved_ty(&t.key)?, resolved_ty(&t.value)?),
+        AttrTypeInner::Option(t) => Ty::union2(resolved_ty(&t.inner)?, Ty::none()),
+        AttrTypeInner::OneOf(t) => Ty::unions(t.xs.try_map(resolved_ty)?),
+        AttrTypeInner::Record(t) => resolved_record_type(t)?
+            .value()
+            .downcast_ref::<FrozenRecordType>()
+            .expect("record attribute types are frozen record types")
+            .instance_ty(),
+        AttrTypeInner::Label(_) => StarlarkConfiguredProvidersLabel::starlark_type_repr(),
+        AttrTypeInner::PluginDep(_) => StarlarkTargetLabel::starlark_type_repr(),
+        AttrTypeInner::Visibility(_) | AttrTypeInner::WithinView(_) => Ty::list(Ty::string()),
+        AttrTypeInner::Any(_)
+        | AttrTypeInner::Arg(_)
+        | AttrTypeInner::ConfigurationDep(_)
+        | AttrTypeInner::ConfiguredDep(_)
+        | AttrTypeInner::Dep(_)
+        | AttrTypeInner::Tuple(_)
+        | AttrTypeInner::Query(_)
+        | AttrTypeInner::Source(_)
+        | AttrTypeInner::SplitTransitionDep(_)
+        | AttrTypeInner::Metadata(_) => Ty::any(),
+    })
+}
+
+/// Allocates a resolved value of a record attribute, given the resolved values of its fields in
+/// order. The record type is owned by `frozen_heap`'s module.
+pub fn alloc_record<'v>(
+    frozen_heap: &'v FrozenHeap,
+    heap: &'v Heap,
+    attr: &RecordAttrType,
+    values: Vec<Value<'v>>,
+) -> anyhow::Result<Value<'v>> {
+    let typ = resolved_record_type(attr)?.owned_value(frozen_heap);
+    Ok(heap.alloc(Record::new(typ, values)?))
+}
+
+/// Allocates an unresolved value of a record attribute, as inspected from BXL. Fields may hold
+/// labels or selects rather than the resolved values, so they accept any value, and the record
+/// type is allocated along with the value.
+pub fn alloc_unresolved_record<'v>(
+    heap: &'v Heap,
+    fields: Vec<(&str, Value<'v>)>,
+) -> anyhow::Result<Value<'v>> {
+    let (names, values): (Vec<_>, Vec<_>) = fields.into_iter().unzip();
+    let typ = heap.alloc(RecordType::new_named(
+        RECORD_TYPE_NAME,
+        names.into_iter().map(|name| (name.to_owned(), Ty::any())),
+        heap,
+    )?);
+    Ok(heap.alloc(Record::new(typ, values)?))
+}
@@ -31,6 +31,7 @@ use starlark::values::dict::Dict;
 use starlark::values::list::AllocList;
 use starlark::values::list::ListRef;
 use starlark::values::none::NoneType;
+use starlark::values::record::Record;
 use starlark::values::tuple::AllocTuple;
 use starlark::values::FrozenValue;
 use starlark::values::Heap;
@@ -43,6 +44,8 @@ use crate::attrs::resolve::attr_type::configuration_dep::ConfigurationDepAttrTyp
 use crate::attrs::resolve::attr_type::dep::DepAttrTypeExt;
 use crate::attrs::resolve::attr_type::dep::ExplicitConfiguredDepAttrTypeExt;
 use crate::attrs::resolve::attr_type::query::ConfiguredQueryAttrExt;
+use crate::attrs::resolve::attr_type::record::alloc_record;
+use crate::attrs::resolve::attr_type::record::alloc_unresolved_record;
 use crate::attrs::resolve::attr_type::source::SourceAttrTypeExt;
 use crate::attrs::resolve::attr_type::split_transition_dep::SplitTransitionDepAttrTypeExt;
 use crate::attrs::resolve::ctx::AttrResolutionContext;
@@ -121,6 +124,18 @@ impl ConfiguredAttrExt for ConfiguredAttr {
                 }
                 Ok(ctx.heap().alloc(Dict::new(res)))
             }
+            ConfiguredAttr::Record(record) => {
+                let mut values = Vec::with_capacity(record.len());
+                for (_, v) in record.iter() {
+                    values.push(v.resolve_single(pkg.dupe(), ctx)?);
+                }
+                alloc_record(
+                    ctx.starlark_module().frozen_heap(),
+                    ctx.heap(),
+                    &record.typ,
+                    values,
+                )
+            }
             ConfiguredAttr::None => Ok(Value::new_none()),
             ConfiguredAttr::OneOf(box l, _) => l.resolve_single(pkg, ctx),
             a @ (ConfiguredAttr::Visibility(_) | ConfiguredAttr::WithinView(_)) => {
@@ -165,6 +180,7 @@ impl ConfiguredAttrExt for ConfiguredAttr {
             ConfiguredAttr::List(_) => Ok(starlark::values::list::ListRef::TYPE),
             ConfiguredAttr::Tuple(_) => Ok(starlark::values::tuple::TupleRef::TYPE),
             ConfiguredAttr::Dict(_) => Ok(Dict::TYPE),
+            ConfiguredAttr::Record(_) => Ok(Record::TYPE),
             ConfiguredAttr::None => Ok(NoneType::TYPE),
             ConfiguredAttr::OneOf(box l, _) => l.starlark_type(),
             ConfiguredAttr::Visibility(..) => Ok(ListRef::TYPE),
@@ -217,6 +233,10 @@ impl ConfiguredAttrExt for ConfiguredAttr {
 
                 heap.alloc(Dict::new(res))
             }
+            ConfiguredAttr::Record(record) => alloc_unresolved_record(
+                heap,
+                record.try_map(|(k, v)| anyhow::Ok((k.as_str(), v.to_value(pkg.dupe(), heap)?)))?,
+            )?,
             ConfiguredAttr::None => Value::new_none(),
             ConfiguredAttr::OneOf(box l, _) => l.to_value(pkg, heap)?,
             ConfiguredAttr::Visibility(VisibilitySpecification(specs))
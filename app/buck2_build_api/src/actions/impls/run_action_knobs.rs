@@ -7,11 +7,15 @@
  * of this source tree.
  */
 
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
 use dice::UserComputationData;
 use dupe::Dupe;
 
 /// Knobs controlling how RunAction works.
-#[derive(Copy, Clone, Dupe, Default)]
+#[derive(Clone, Dupe, Default)]
 pub struct RunActionKnobs {
     /// Process dep files as they are generated.
     pub eager_dep_files: bool,
@@ -24,6 +28,10 @@ pub struct RunActionKnobs {
     /// for network actions (download_file, cas_artifact). Used to support offline
     /// builds.
     pub use_network_action_output_cache: bool,
+
+    /// Timeouts for run actions that don't set one, by category (from the `[action_timeouts]`
+    /// buckconfig section).
+    pub default_timeouts: Arc<HashMap<String, Duration>>,
 }
 
 pub trait HasRunActionKnobs {
@@ -38,9 +46,9 @@ impl HasRunActionKnobs for UserComputationData {
     }
 
     fn get_run_action_knobs(&self) -> RunActionKnobs {
-        *self
-            .data
+        self.data
             .get::<RunActionKnobs>()
             .expect("RunActionKnobs should be set")
+            .dupe()
     }
 }
@@ -54,6 +54,7 @@ use crate::interpreter::rule_defs::provider::builtin::run_info::FrozenRunInfo;
 use crate::interpreter::rule_defs::provider::test_provider::TestProvider;
 
 mod graph_size;
+mod validations;
 
 /// The types of provider to build on the configured providers label
 #[derive(Debug, Clone, Dupe, Allocative)]
@@ -62,6 +63,7 @@ pub enum BuildProviderType {
     DefaultOther,
     Run,
     Test,
+    Validation,
 }
 
 #[derive(Clone, Debug, Allocative)]
@@ -199,7 +201,7 @@ pub async fn build_configured_label<'a>(
 
     let artifact_fs = ctx.get_artifact_fs().await?;
 
-    let (outputs, run_args) = {
+    let (mut outputs, run_args) = {
         // A couple of these objects aren't Send and so scope them here so async transform doesn't get concerned.
         let providers = match ctx.get_providers(providers_label.as_ref()).await? {
             MaybeCompatible::Incompatible(reason) => {
@@ -300,6 +302,16 @@ pub async fn build_configured_label<'a>(
         ));
     }
 
+    if providers_to_build.validations {
+        // Validations aren't top level outputs: they are built in parallel with those, but are
+        // not reported to build signals as they aren't on the critical path of the target.
+        for validation in
+            validations::get_transitive_validations(ctx, providers_label.target()).await?
+        {
+            outputs.push((validation, BuildProviderType::Validation));
+        }
+    }
+
     let outputs = outputs
         .into_iter()
         .enumerate()
@@ -374,6 +386,8 @@ pub struct ProvidersToBuild {
     pub default_other: bool,
     pub run: bool,
     pub tests: bool,
+    /// `ValidationInfo` of the target and its transitive dependencies.
+    pub validations: bool,
 }
 
 impl Debug for ProviderArtifacts {
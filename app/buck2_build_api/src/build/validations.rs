@@ -0,0 +1,137 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use allocative::Allocative;
+use async_trait::async_trait;
+use buck2_common::result::SharedResult;
+use buck2_core::configuration::compatibility::MaybeCompatible;
+use buck2_core::provider::label::ConfiguredProvidersLabel;
+use buck2_core::target::configured_target_label::ConfiguredTargetLabel;
+use buck2_node::nodes::configured_frontend::ConfiguredTargetNodeCalculation;
+use buck2_node::nodes::unconfigured::RuleKind;
+use dice::CancellationContext;
+use dice::DiceComputations;
+use dice::Key;
+use dupe::Dupe;
+use futures::future;
+use indexmap::IndexSet;
+
+use crate::analysis::calculation::RuleAnalysisCalculation;
+use crate::artifact_groups::ArtifactGroup;
+use crate::interpreter::rule_defs::provider::builtin::validation_info::FrozenValidationInfo;
+
+/// The validations of a target and of its target deps, shared with the deps rather than
+/// flattened, so that each target only adds its own validations.
+#[derive(Allocative)]
+struct TransitiveValidations {
+    validations: Vec<ArtifactGroup>,
+    deps: Vec<Arc<TransitiveValidations>>,
+}
+
+#[derive(
+    Clone,
+    Dupe,
+    derive_more::Display,
+    Debug,
+    Eq,
+    Hash,
+    PartialEq,
+    Allocative
+)]
+struct TransitiveValidationsKey(ConfiguredTargetLabel);
+
+#[async_trait]
+impl Key for TransitiveValidationsKey {
+    type Value = SharedResult<Arc<TransitiveValidations>>;
+
+    async fn compute(
+        &self,
+        ctx: &mut DiceComputations,
+        _cancellation: &CancellationContext,
+    ) -> Self::Value {
+        let ctx = &*ctx;
+        let node = match ctx.get_configured_target_node(&self.0).await? {
+            MaybeCompatible::Compatible(node) => node,
+            MaybeCompatible::Incompatible(..) => {
+                return Ok(Arc::new(TransitiveValidations {
+                    validations: Vec::new(),
+                    deps: Vec::new(),
+                }));
+            }
+        };
+
+        // Exec and toolchain deps only run as part of building the target, their validations
+        // don't apply to it.
+        let deps = node
+            .target_deps()
+            .filter(|dep| dep.rule_kind() != RuleKind::Toolchain)
+            .map(|dep| TransitiveValidationsKey(dep.label().dupe()));
+        let label = ConfiguredProvidersLabel::default_for(self.0.dupe());
+        let (providers, deps) = future::try_join(
+            async { ctx.get_providers(&label).await },
+            future::try_join_all(
+                deps.map(|key| async move { anyhow::Ok(ctx.compute(&key).await??) }),
+            ),
+        )
+        .await?;
+
+        let mut validations = Vec::new();
+        if let MaybeCompatible::Compatible(providers) = providers {
+            if let Some(info) = providers
+                .provider_collection()
+                .builtin_provider::<FrozenValidationInfo>()
+            {
+                info.for_each_validation(&mut |v| {
+                    validations.push(v);
+                    Ok(())
+                })?;
+            }
+        }
+        Ok(Arc::new(TransitiveValidations { validations, deps }))
+    }
+
+    fn equality(x: &Self::Value, y: &Self::Value) -> bool {
+        match (x, y) {
+            (Ok(x), Ok(y)) => {
+                x.validations == y.validations
+                    && x.deps.len() == y.deps.len()
+                    && x.deps.iter().zip(&y.deps).all(|(x, y)| Arc::ptr_eq(x, y))
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Returns the `ValidationInfo` validations of a target and all of its transitive target deps.
+///
+/// The validations are collected from the graph directly rather than propagated through
+/// providers, so that rules don't need to forward them and nothing depends on them.
+pub(crate) async fn get_transitive_validations(
+    ctx: &DiceComputations,
+    target: &ConfiguredTargetLabel,
+) -> anyhow::Result<Vec<ArtifactGroup>> {
+    let root = ctx
+        .compute(&TransitiveValidationsKey(target.dupe()))
+        .await??;
+
+    let mut queue = vec![&root];
+    let mut visited = HashSet::new();
+    let mut validations = IndexSet::new();
+    while let Some(item) = queue.pop() {
+        if !visited.insert(Arc::as_ptr(item)) {
+            continue;
+        }
+        validations.extend(item.validations.iter().cloned());
+        queue.extend(&item.deps);
+    }
+    Ok(validations.into_iter().collect())
+}
@@ -11,11 +11,13 @@ use std::sync::Arc;
 
 use buck2_common::dice::cells::SetCellResolver;
 use buck2_common::dice::data::SetIoProvider;
+use buck2_common::dice::persistence::RegisterPersistentKeys;
 use buck2_common::io::IoProvider;
 use buck2_common::legacy_configs::dice::SetLegacyConfigs;
 use buck2_common::legacy_configs::LegacyBuckConfig;
 use buck2_execute::digest_config::DigestConfig;
 use buck2_execute::digest_config::SetDigestConfig;
+use buck2_node::nodes::persistence::REGISTER_PERSISTENT_TARGET_KEYS;
 use dice::DetectCycles;
 use dice::Dice;
 use dice::WhichDice;
@@ -56,6 +58,8 @@ pub async fn configure_dice_for_buck(
     };
     dice.set_io_provider(io);
     dice.set_digest_config(digest_config);
+    dice.register_persistent_keys(digest_config.cas_digest_config());
+    (REGISTER_PERSISTENT_TARGET_KEYS.get()?)(&mut dice);
 
     let dice = dice.build(detect_cycles);
     let mut dice_ctx = dice.updater();
@@ -81,7 +81,8 @@ pub fn register_command_executor_config(builder: &mut GlobalsBuilder) {
     /// * `allow_cache_uploads`: Whether to upload local actions to the RE cache
     /// * `max_cache_upload_mebibytes`: Maximum size to upload in cache uploads
     /// * `experimental_low_pass_filter`: Whether to use the experimental low pass filter
-    /// * `remote_output_paths`: How to express output paths to RE
+    /// * `remote_output_paths`: How to express output paths to RE, or `content_based` to place the
+    /// outputs of run actions under paths derived from their command line and inputs
     #[starlark(as_type = StarlarkCommandExecutorConfig)]
     fn CommandExecutorConfig<'v>(
         #[starlark(require = named)] local_enabled: bool,
@@ -96,6 +96,16 @@ pub struct ExternalRunnerTestInfoGen<V> {
     /// should be ignored when executing tests even if those are passed as required from test runner.
     #[provider(field_type = DictType<String, Option<StarlarkConfiguredProvidersLabel>>)]
     local_resources: V,
+
+    /// Whether the outcome of this test only depends on its inputs, in which case a passing
+    /// result can be served from the action cache.
+    #[provider(field_type = bool)]
+    cacheable: V,
+
+    /// Number of parallel executions the test runner should split the test cases of this test
+    /// across.
+    #[provider(field_type = i32)]
+    shards: V,
 }
 
 // NOTE: All the methods here unwrap because we validate at freeze time.
@@ -134,6 +144,17 @@ impl FrozenExternalRunnerTestInfo {
             .unwrap_or_else(buck2_core::is_open_source)
     }
 
+    pub fn cacheable(&self) -> bool {
+        NoneOr::<bool>::unpack_value(self.cacheable.to_value())
+            .unwrap()
+            .into_option()
+            .unwrap_or(false)
+    }
+
+    pub fn shards(&self) -> u32 {
+        unpack_opt_shards(self.shards.to_value()).unwrap()
+    }
+
     pub fn default_executor(&self) -> Option<&StarlarkCommandExecutorConfig> {
         unpack_opt_executor(self.default_executor.to_value()).unwrap()
     }
@@ -395,6 +416,20 @@ fn unpack_opt_executor<'v>(
     Ok(Some(executor))
 }
 
+fn unpack_opt_shards(shards: Value<'_>) -> anyhow::Result<u32> {
+    match NoneOr::<i32>::unpack_value(shards)
+        .context("`shards` must be an int if provided")?
+        .into_option()
+    {
+        None => Ok(1),
+        Some(shards) if shards >= 1 => Ok(shards as u32),
+        Some(shards) => Err(anyhow::anyhow!(
+            "`shards` must be at least 1, got `{}`",
+            shards
+        )),
+    }
+}
+
 fn check_all<I, T>(it: I) -> anyhow::Result<()>
 where
     I: IntoIterator<Item = anyhow::Result<T>>,
@@ -428,6 +463,9 @@ where
         .context("`use_project_relative_paths` must be a bool if provided")?;
     NoneOr::<bool>::unpack_value(info.run_from_project_root.to_value())
         .context("`run_from_project_root` must be a bool if provided")?;
+    NoneOr::<bool>::unpack_value(info.cacheable.to_value())
+        .context("`cacheable` must be a bool if provided")?;
+    unpack_opt_shards(info.shards.to_value())?;
     unpack_opt_executor(info.default_executor.to_value()).context("Invalid `default_executor`")?;
     info.test_type
         .to_value()
@@ -450,6 +488,8 @@ fn external_runner_test_info_creator(globals: &mut GlobalsBuilder) {
         #[starlark(default = NoneType)] default_executor: Value<'v>,
         #[starlark(default = NoneType)] executor_overrides: Value<'v>,
         #[starlark(default = NoneType)] local_resources: Value<'v>,
+        #[starlark(default = NoneType)] cacheable: Value<'v>,
+        #[starlark(default = NoneType)] shards: Value<'v>,
     ) -> anyhow::Result<ExternalRunnerTestInfo<'v>> {
         let res = ExternalRunnerTestInfo {
             test_type: r#type,
@@ -462,6 +502,8 @@ fn external_runner_test_info_creator(globals: &mut GlobalsBuilder) {
             default_executor,
             executor_overrides,
             local_resources,
+            cacheable,
+            shards,
         };
         validate_external_runner_test_info(&res)?;
         Ok(res)
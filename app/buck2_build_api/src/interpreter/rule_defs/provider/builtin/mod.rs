@@ -22,5 +22,6 @@ pub mod platform_info;
 pub mod run_info;
 pub mod template_placeholder_info;
 pub(crate) mod ty;
+pub mod validation_info;
 pub mod worker_info;
 pub mod worker_run_info;
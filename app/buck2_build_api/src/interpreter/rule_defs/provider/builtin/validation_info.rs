@@ -0,0 +1,103 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use allocative::Allocative;
+use buck2_build_api_derive::internal_provider;
+use starlark::any::ProvidesStaticType;
+use starlark::coerce::Coerce;
+use starlark::environment::GlobalsBuilder;
+use starlark::values::list::ListRef;
+use starlark::values::Freeze;
+use starlark::values::Trace;
+use starlark::values::UnpackValue;
+use starlark::values::Value;
+use starlark::values::ValueLike;
+use thiserror::Error;
+
+use crate::artifact_groups::ArtifactGroup;
+use crate::interpreter::rule_defs::artifact::StarlarkArtifact;
+use crate::interpreter::rule_defs::artifact::ValueAsArtifactLike;
+
+#[derive(Debug, Error)]
+enum ValidationInfoError {
+    #[error("Expected `validations` to be a list of artifacts, got `{0}` (type `{1}`)")]
+    ExpectedArtifact(String, String),
+}
+
+/// A provider for outputs that check a target (e.g. lints or type checks) without being needed
+/// to build it.
+///
+/// `buck2 build` builds the validations of the requested targets and all of their transitive
+/// dependencies alongside their outputs, and fails if any of them fail. Nothing that depends on
+/// the target waits for its validations, so they stay off the critical path.
+///
+/// Fields:
+///  - validations: A list of artifacts to build.
+#[internal_provider(validation_info_creator)]
+#[derive(Clone, Debug, Trace, Coerce, Freeze, ProvidesStaticType, Allocative)]
+#[freeze(validator = validate_validation_info, bounds = "V: ValueLike<'freeze>")]
+#[repr(C)]
+pub struct ValidationInfoGen<V> {
+    #[provider(field_type = Vec<StarlarkArtifact>)]
+    validations: V,
+}
+
+impl<'v, V: ValueLike<'v>> ValidationInfoGen<V> {
+    fn validations_iter(&self) -> impl Iterator<Item = anyhow::Result<ValueAsArtifactLike<'v>>> {
+        ListRef::from_value(self.validations.to_value())
+            .expect("validated at construction")
+            .iter()
+            .map(|v| {
+                ValueAsArtifactLike::unpack_value(v).ok_or_else(|| {
+                    ValidationInfoError::ExpectedArtifact(v.to_repr(), v.get_type().to_owned())
+                        .into()
+                })
+            })
+    }
+
+    pub fn for_each_validation(
+        &self,
+        processor: &mut dyn FnMut(ArtifactGroup) -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        for artifact in self.validations_iter() {
+            processor(ArtifactGroup::Artifact(artifact?.0.get_bound_artifact()?))?;
+        }
+        Ok(())
+    }
+}
+
+fn validate_validation_info<'v, V>(info: &ValidationInfoGen<V>) -> anyhow::Result<()>
+where
+    V: ValueLike<'v>,
+{
+    let validations = info.validations.to_value();
+    if ListRef::from_value(validations).is_none() {
+        return Err(ValidationInfoError::ExpectedArtifact(
+            validations.to_repr(),
+            validations.get_type().to_owned(),
+        )
+        .into());
+    }
+    for artifact in info.validations_iter() {
+        artifact?;
+    }
+    Ok(())
+}
+
+#[starlark_module]
+fn validation_info_creator(globals: &mut GlobalsBuilder) {
+    #[starlark(as_type = FrozenValidationInfo)]
+    fn ValidationInfo<'v>(
+        #[starlark(require = named)] validations: Value<'v>,
+    ) -> anyhow::Result<ValidationInfo<'v>> {
+        let info = ValidationInfo { validations };
+        validate_validation_info(&info)?;
+        Ok(info)
+    }
+}
@@ -7,6 +7,8 @@
  * of this source tree.
  */
 
+use std::sync::Arc;
+
 use buck2_analysis::attrs::resolve::configured_attr::ConfiguredAttrExt;
 use buck2_build_api::interpreter::rule_defs::cmd_args::value_as::ValueAsCommandLineLike;
 use buck2_build_api::interpreter::rule_defs::cmd_args::DefaultCommandLineContext;
@@ -31,7 +33,9 @@ use buck2_interpreter_for_build::attrs::coerce::testing::coercion_ctx;
 use buck2_interpreter_for_build::attrs::coerce::testing::coercion_ctx_listing;
 use buck2_interpreter_for_build::attrs::coerce::testing::to_value;
 use buck2_interpreter_for_build::interpreter::selector::register_select;
+use buck2_node::attrs::attr::Attribute;
 use buck2_node::attrs::attr_type::AttrType;
+use buck2_node::attrs::coerced_attr::CoercedAttr;
 use buck2_node::attrs::coerced_deps_collector::CoercedDepsCollector;
 use buck2_node::attrs::configurable::AttrIsConfigurable;
 use buck2_node::attrs::configuration_context::AttrConfigurationContext;
@@ -291,6 +295,94 @@ fn test_dict() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_record() -> anyhow::Result<()> {
+    let env = Module::new();
+    let globals = GlobalsBuilder::standard().with(register_select).build();
+    let attr = AttrType::record(vec![
+        (
+            "name".to_owned(),
+            Attribute::new(None, "", AttrType::string()),
+        ),
+        (
+            "count".to_owned(),
+            Attribute::new(Some(Arc::new(CoercedAttr::Int(1))), "", AttrType::int()),
+        ),
+        (
+            "tags".to_owned(),
+            Attribute::new(None, "", AttrType::list(AttrType::string())),
+        ),
+    ]);
+
+    let value = to_value(
+        &env,
+        &globals,
+        indoc!(
+            r#"
+                {
+                    "name": "a" + select({"DEFAULT": "b"}),
+                    "tags": select({"//some:config": ["x"], "DEFAULT": ["y"]}),
+                }
+                "#
+        ),
+    );
+    let coerced = attr.coerce(AttrIsConfigurable::Yes, &coercion_ctx(), value)?;
+    assert_eq!(
+        r#"{"name": "a"+select("DEFAULT"="b"),"count": 1,"tags": select("root//some:config"=["x"],"DEFAULT"=["y"])}"#,
+        coerced.as_display_no_ctx().to_string()
+    );
+
+    let configured = coerced.configure(&attr, &configuration_ctx())?;
+    assert_eq!(
+        r#"{"name": "ab","count": 1,"tags": ["y"]}"#,
+        configured.as_display_no_ctx().to_string()
+    );
+
+    let ctx = resolution_ctx(&env);
+    let resolved = configured.resolve_single(PackageLabel::testing(), &ctx)?;
+    assert_eq!("record", resolved.get_type());
+    assert_eq!(
+        r#"record[attrs.record](name="ab", count=1, tags=["y"])"#,
+        resolved.to_string()
+    );
+    assert_eq!(
+        "ab",
+        resolved
+            .get_attr("name", env.heap())?
+            .unwrap()
+            .unpack_str()
+            .unwrap()
+    );
+
+    // Values of the same attribute share its record type, so equal fields compare equal.
+    let other = configured.resolve_single(PackageLabel::testing(), &ctx)?;
+    assert!(resolved.equals(other)?);
+    let value = to_value(&env, &globals, r#"{"name": "b", "tags": ["y"]}"#);
+    let different = attr
+        .coerce(AttrIsConfigurable::Yes, &coercion_ctx(), value)?
+        .configure(&attr, &configuration_ctx())?
+        .resolve_single(PackageLabel::testing(), &ctx)?;
+    assert!(!resolved.equals(different)?);
+
+    let value = to_value(&env, &globals, r#"{"name": "a", "tags": [], "size": 2}"#);
+    let err = attr
+        .coerce(AttrIsConfigurable::Yes, &coercion_ctx(), value)
+        .unwrap_err();
+    assert!(
+        err.to_string().contains("Unknown record field `size`"),
+        "{:#}",
+        err
+    );
+
+    let value = to_value(&env, &globals, r#"{"name": "a"}"#);
+    let err = attr
+        .coerce(AttrIsConfigurable::Yes, &coercion_ctx(), value)
+        .unwrap_err();
+    assert!(format!("{:#}", err).contains("`tags`"), "{:#}", err);
+
+    Ok(())
+}
+
 #[test]
 fn test_one_of() -> anyhow::Result<()> {
     let heap = Heap::new();
@@ -15,4 +15,5 @@ mod install_info;
 mod local_resource_info;
 mod run_info;
 mod tests;
+mod validation_info;
 mod worker_info;
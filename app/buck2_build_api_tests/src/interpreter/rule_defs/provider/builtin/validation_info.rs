@@ -0,0 +1,57 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use buck2_build_api::interpreter::rule_defs::provider::collection::tester::collection_creator;
+use buck2_build_api::interpreter::rule_defs::register_rule_defs;
+use buck2_common::result::SharedResult;
+use buck2_interpreter_for_build::interpreter::testing::expect_error;
+use buck2_interpreter_for_build::interpreter::testing::Tester;
+use indoc::indoc;
+
+use crate::interpreter::rule_defs::artifact::testing::artifactory;
+
+fn tester() -> Tester {
+    let mut tester = Tester::new().unwrap();
+    tester.additional_globals(collection_creator);
+    tester.additional_globals(artifactory);
+    tester.additional_globals(register_rule_defs);
+    tester
+}
+
+#[test]
+fn validation_info_works_as_provider_key() -> SharedResult<()> {
+    let content = indoc!(
+        r#"
+             lint = bound_artifact("//:dep1", "lint.txt")
+             c = create_collection([DefaultInfo(), ValidationInfo(validations = [lint])])
+             def test():
+                 assert_eq(True, contains_provider(c, ValidationInfo))
+                 assert_eq([lint], c[ValidationInfo].validations)
+             "#
+    );
+    let mut tester = tester();
+    tester.run_starlark_bzl_test(content)
+}
+
+#[test]
+fn validation_info_requires_artifacts() -> SharedResult<()> {
+    let mut tester = tester();
+    let test = indoc!(
+        r#"
+            def test():
+                ValidationInfo(validations = ["lint.txt"])
+            "#
+    );
+    expect_error(
+        tester.run_starlark_bzl_test(test),
+        test,
+        "Expected `validations` to be a list of artifacts",
+    );
+    Ok(())
+}
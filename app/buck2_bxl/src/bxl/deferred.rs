@@ -82,6 +82,7 @@ mod tests {
             },
             Arc::new(OrderedMap::new()),
             None,
+            None,
         );
 
         let mut deferred = DeferredRegistry::new(BaseKey::Base(BaseDeferredKey::BxlLabel(
@@ -101,15 +101,12 @@ pub(crate) async fn eval(
                     let cell_resolver = ctx.get_cell_resolver().await?;
 
                     let bxl_cell = cell_resolver
-                        .get(key.label().bxl_path.cell())
-                        .with_context(|| {
-                            format!("Cell does not exist: `{}`", key.label().bxl_path.cell())
-                        })?
+                        .get(key.cell())
+                        .with_context(|| format!("Cell does not exist: `{}`", key.cell()))?
                         .dupe();
 
-                    let target_alias_resolver = ctx
-                        .target_alias_resolver_for_cell(key.label().bxl_path.cell())
-                        .await?;
+                    let target_alias_resolver =
+                        ctx.target_alias_resolver_for_cell(key.cell()).await?;
 
                     let project_fs = ctx.global_data().get_io_provider().project_root().dupe();
                     let artifact_fs = ctx.get_artifact_fs().await?;
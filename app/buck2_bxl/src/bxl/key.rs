@@ -17,6 +17,7 @@ use allocative::Allocative;
 use anyhow::Context;
 use buck2_build_api::bxl::types::BxlFunctionLabel;
 use buck2_core::base_deferred_key::BaseDeferredKeyDyn;
+use buck2_core::cells::name::CellName;
 use buck2_core::execution_types::execution::ExecutionPlatformResolution;
 use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
 use buck2_core::fs::project_rel_path::ProjectRelativePath;
@@ -51,11 +52,13 @@ impl BxlKey {
         spec: BxlFunctionLabel,
         bxl_args: Arc<OrderedMap<String, CliArgValue>>,
         global_target_platform: Option<TargetLabel>,
+        fixture: Option<CellName>,
     ) -> Self {
         Self(Arc::new(BxlKeyData {
             spec,
             bxl_args,
             global_target_platform,
+            fixture,
         }))
     }
 
@@ -92,6 +95,14 @@ impl BxlKey {
     pub(crate) fn global_target_platform(&self) -> &Option<TargetLabel> {
         &self.0.global_target_platform
     }
+
+    /// The cell that unqualified targets and literals are resolved against: the fixture cell
+    /// when running as a test against a fixture, otherwise the cell of the bxl file.
+    pub(crate) fn cell(&self) -> CellName {
+        self.0
+            .fixture
+            .unwrap_or_else(|| self.0.spec.bxl_path.cell())
+    }
 }
 
 #[derive(
@@ -110,6 +121,8 @@ struct BxlKeyData {
     spec: BxlFunctionLabel,
     bxl_args: Arc<OrderedMap<String, CliArgValue>>,
     global_target_platform: Option<TargetLabel>,
+    /// Set by `buck2 bxl --test --fixture`.
+    fixture: Option<CellName>,
 }
 
 impl BxlKeyData {
@@ -176,6 +189,9 @@ impl BaseDeferredKeyDyn for BxlDynamicKeyData {
         let output_hash = {
             let mut hasher = DefaultHasher::new();
             self.key.bxl_args.hash(&mut hasher);
+            if let Some(fixture) = &self.key.fixture {
+                fixture.hash(&mut hasher);
+            }
             let output_hash = hasher.finish();
             format!("{:x}", output_hash)
         };
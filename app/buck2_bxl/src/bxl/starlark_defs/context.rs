@@ -487,9 +487,8 @@ pub(crate) async fn eval_bxl_for_dynamic_output<'v>(
         toolchains: dynamic_key.0.toolchains.clone(),
     };
     let global_target_platform = key.global_target_platform().dupe();
-    let label = key.label();
     let cell_resolver = dice_ctx.get_cell_resolver().await?;
-    let cell = label.bxl_path.cell();
+    let cell = key.cell();
     let bxl_cell = cell_resolver
         .get(cell)
         .with_context(|| format!("Cell does not exist: `{}`", cell))?
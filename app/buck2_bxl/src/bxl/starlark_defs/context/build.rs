@@ -221,6 +221,7 @@ pub(crate) fn build<'v>(
                                         default_other: true,
                                         run: true,
                                         tests: true,
+                                        validations: false,
                                     }, // TODO support skipping/configuring?
                                     BuildConfiguredLabelOptions {
                                         skippable: false,
@@ -113,6 +113,8 @@ pub(crate) enum BxlFilesystemError {
     PackageMismatch(PackageLabel, CellPath),
     #[error("Expected a single target hint, not an iterable: `{0}`")]
     MultipleTargetHintsNotSupported(String),
+    #[error("File `{0}` does not contain valid JSON")]
+    InvalidJson(CellPath),
 }
 
 impl<'v> BxlFilesystem<'v> {
@@ -135,6 +137,21 @@ impl<'v> BxlFilesystem<'v> {
     }
 }
 
+impl<'v> BxlFilesystem<'v> {
+    /// Reads the contents of a file through DICE, so that the calling bxl function is
+    /// invalidated whenever the file changes.
+    fn read_tracked(&'v self, expr: FileExpr<'v>) -> anyhow::Result<(CellPath, String)> {
+        self.ctx.async_ctx.borrow_mut().via(|dice| {
+            async {
+                let path = expr.get(dice, self.cell()?).await?;
+                let contents = <dyn FileOps>::read_file(&dice.file_ops(), path.as_ref()).await?;
+                Ok((path, contents))
+            }
+            .boxed_local()
+        })
+    }
+}
+
 #[async_recursion]
 async fn try_exists<'v>(file_ops: &DiceFileOps<'v>, path: CellPathRef<'v>) -> anyhow::Result<bool> {
     match file_ops.read_path_metadata_if_exists(path).await? {
@@ -235,6 +252,46 @@ fn fs_operations(builder: &mut MethodsBuilder) {
         Ok(std::path::Path::is_file(this.resolve(expr)?.as_ref()))
     }
 
+    /// Returns the contents of the given file as a string. Errors if the file does not exist.
+    /// The file is read through Buck's cached filesystem, so the result of the bxl function is
+    /// invalidated when the file changes.
+    /// The input is a either a literal, a source artifact (via `[StarlarkArtifact]`), or a `[StarlarkFileNode]`.
+    ///
+    /// Sample usage:
+    /// ```text
+    /// def _impl_read(ctx):
+    ///     ctx.output.print(ctx.fs.read("bin/README.md"))
+    /// ```
+    fn read<'v>(
+        this: &'v BxlFilesystem<'v>,
+        expr: FileExpr<'v>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<StringValue<'v>> {
+        let (_, contents) = this.read_tracked(expr)?;
+        Ok(heap.alloc_str(&contents))
+    }
+
+    /// Reads the given file and decodes its contents as JSON. Errors if the file does not exist
+    /// or is not valid JSON. Like `read`, the file is tracked for invalidation.
+    /// The input is a either a literal, a source artifact (via `[StarlarkArtifact]`), or a `[StarlarkFileNode]`.
+    ///
+    /// Sample usage:
+    /// ```text
+    /// def _impl_read_json(ctx):
+    ///     config = ctx.fs.read_json("tools/config.json")
+    ///     ctx.output.print(config["version"])
+    /// ```
+    fn read_json<'v>(
+        this: &'v BxlFilesystem<'v>,
+        expr: FileExpr<'v>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<Value<'v>> {
+        let (path, contents) = this.read_tracked(expr)?;
+        let json: serde_json::Value = serde_json::from_str(&contents)
+            .map_err(|e| anyhow::Error::new(e).context(BxlFilesystemError::InvalidJson(path)))?;
+        Ok(heap.alloc(json))
+    }
+
     /// Returns the relative path to the project root, given the file expression.
     ///
     /// Sample usage:
@@ -11,6 +11,7 @@ use std::fmt::Display;
 use std::fmt::Formatter;
 
 use allocative::Allocative;
+use buck2_analysis::attrs::resolve::attr_type::record::alloc_unresolved_record;
 use buck2_artifact::artifact::artifact_type::Artifact;
 use buck2_artifact::artifact::source_artifact::SourceArtifact;
 use buck2_build_api::interpreter::rule_defs::artifact::StarlarkArtifact;
@@ -43,6 +44,7 @@ use starlark::values::dict::Dict;
 use starlark::values::list::AllocList;
 use starlark::values::list::ListRef;
 use starlark::values::none::NoneType;
+use starlark::values::record::Record;
 use starlark::values::starlark_value;
 use starlark::values::tuple::AllocTuple;
 use starlark::values::FrozenValue;
@@ -140,6 +142,7 @@ impl CoercedAttrExt for CoercedAttr {
             CoercedAttr::List(_) => Ok(starlark::values::list::ListRef::TYPE),
             CoercedAttr::Tuple(_) => Ok(starlark::values::tuple::TupleRef::TYPE),
             CoercedAttr::Dict(_) => Ok(Dict::TYPE),
+            CoercedAttr::Record(_) => Ok(Record::TYPE),
             CoercedAttr::None => Ok(NoneType::TYPE),
             CoercedAttr::OneOf(l, _) => l.as_ref().starlark_type(),
             CoercedAttr::Visibility(..) => Ok(ListRef::TYPE),
@@ -191,6 +194,10 @@ impl CoercedAttrExt for CoercedAttr {
 
                 heap.alloc(Dict::new(res))
             }
+            CoercedAttr::Record(record) => alloc_unresolved_record(
+                heap,
+                record.try_map(|(k, v)| anyhow::Ok((k.as_str(), v.to_value(pkg.dupe(), heap)?)))?,
+            )?,
             CoercedAttr::None => Value::new_none(),
             CoercedAttr::OneOf(l, _) => l.as_ref().to_value(pkg, heap)?,
             CoercedAttr::Visibility(VisibilitySpecification(specs))
@@ -67,12 +67,17 @@ use crate::bxl::eval::BxlResolvedCliArgs;
 use crate::bxl::eval::CliResolutionCtx;
 use crate::bxl::key::BxlKey;
 use crate::bxl::starlark_defs::functions::BxlErrorWithoutStacktrace;
+use crate::test_command::bxl_test;
+use crate::watch_command::bxl_watch_command;
 
 pub(crate) async fn bxl_command(
     ctx: &dyn ServerCommandContextTrait,
     partial_result_dispatcher: PartialResultDispatcher<buck2_cli_proto::StdoutBytes>,
     req: BxlRequest,
 ) -> anyhow::Result<BxlResponse> {
+    if req.watch {
+        return bxl_watch_command(ctx, partial_result_dispatcher, req).await;
+    }
     run_server_command(BxlServerCommand { req }, ctx, partial_result_dispatcher).await
 }
 
@@ -114,6 +119,11 @@ impl ServerCommandTemplate for BxlServerCommand {
 
     fn is_success(&self, response: &Self::Response) -> bool {
         response.error_messages.is_empty()
+            && response
+                .test_statuses
+                .as_ref()
+                .and_then(|statuses| statuses.failed.as_ref())
+                .map_or(true, |failed| failed.count == 0)
     }
 }
 
@@ -123,6 +133,10 @@ async fn bxl(
     mut ctx: DiceTransaction,
     request: &BxlRequest,
 ) -> anyhow::Result<buck2_cli_proto::BxlResponse> {
+    if request.test {
+        return bxl_test(server_ctx, stdout, ctx, request).await;
+    }
+
     let cwd = server_ctx.working_dir();
     let cell_resolver = ctx.get_cell_resolver().await?;
     let bxl_label = parse_bxl_label_from_cli(cwd, &request.bxl_label, &cell_resolver)?;
@@ -140,6 +154,7 @@ async fn bxl(
                 return Ok(BxlResponse {
                     project_root,
                     error_messages: Vec::new(),
+                    test_statuses: None,
                 });
             }
         };
@@ -149,7 +164,7 @@ async fn bxl(
             .with_context(|| "Invalid final_artifact_materializations")
             .unwrap();
 
-    let bxl_key = BxlKey::new(bxl_label.clone(), bxl_args, global_target_platform);
+    let bxl_key = BxlKey::new(bxl_label.clone(), bxl_args, global_target_platform, None);
 
     let ctx = &ctx;
 
@@ -202,6 +217,7 @@ async fn bxl(
     Ok(BxlResponse {
         project_root,
         error_messages,
+        test_statuses: None,
     })
 }
 
@@ -232,7 +248,7 @@ pub(crate) async fn get_bxl_cli_args(
     resolve_cli_args(bxl_label, &cli_ctx, bxl_args, &frozen_callable).await
 }
 
-async fn copy_output<W: Write>(
+pub(crate) async fn copy_output<W: Write>(
     mut output: W,
     dice: &DiceComputations,
     output_loc: &BuckOutPath,
@@ -258,7 +274,7 @@ async fn copy_output<W: Write>(
     Ok(())
 }
 
-async fn ensure_artifacts(
+pub(crate) async fn ensure_artifacts(
     ctx: &DiceComputations,
     materialization_ctx: &MaterializationContext,
     bxl_result: &buck2_build_api::bxl::result::BxlResult,
@@ -350,6 +366,22 @@ pub(crate) fn parse_bxl_label_from_cli(
     bxl_label: &str,
     cell_resolver: &CellResolver,
 ) -> anyhow::Result<BxlFunctionLabel> {
+    let (bxl_path, bxl_fn) = bxl_label
+        .rsplit_once(':')
+        .ok_or_else(|| BxlLabelError::Format(bxl_label.to_owned()))?;
+
+    Ok(BxlFunctionLabel {
+        bxl_path: parse_bxl_path_from_cli(cwd, bxl_path, cell_resolver)?,
+        name: bxl_fn.to_owned(),
+    })
+}
+
+/// Parse the bxl file path (without the function name) out of cli pattern
+pub(crate) fn parse_bxl_path_from_cli(
+    cwd: &ProjectRelativePath,
+    bxl_path: &str,
+    cell_resolver: &CellResolver,
+) -> anyhow::Result<BxlFilePath> {
     let current_cell = cell_resolver.get_cell_path(cwd)?;
 
     // Targets with cell aliases should be resolved against the cell mapping
@@ -359,10 +391,6 @@ pub(crate) fn parse_bxl_label_from_cli(
         .unwrap()
         .cell_alias_resolver();
 
-    let (bxl_path, bxl_fn) = bxl_label
-        .rsplit_once(':')
-        .ok_or_else(|| BxlLabelError::Format(bxl_label.to_owned()))?;
-
     let opts: ParseImportOptions = ParseImportOptions {
         allow_missing_at_symbol: true,
         relative_import_option: RelativeImports::Allow {
@@ -384,8 +412,5 @@ pub(crate) fn parse_bxl_label_from_cli(
         )?;
     }
 
-    Ok(BxlFunctionLabel {
-        bxl_path: BxlFilePath::new(import_path)?,
-        name: bxl_fn.to_owned(),
-    })
+    BxlFilePath::new(import_path)
 }
@@ -21,6 +21,8 @@ pub(crate) mod bxl;
 pub(crate) mod command;
 mod commands;
 pub(crate) mod profile_command;
+pub(crate) mod test_command;
+pub(crate) mod watch_command;
 
 pub fn init_late_bindings() {
     static ONCE: Once = Once::new();
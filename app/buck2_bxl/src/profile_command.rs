@@ -108,7 +108,7 @@ impl ServerCommandTemplate for BxlProfileServerCommand {
                                 .await?;
 
                         let bxl_key =
-                            BxlKey::new(bxl_label.clone(), bxl_args, global_target_platform);
+                            BxlKey::new(bxl_label.clone(), bxl_args, global_target_platform, None);
 
                         server_ctx
                             .cancellation_context()
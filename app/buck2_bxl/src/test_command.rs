@@ -0,0 +1,366 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! `buck2 bxl --test`: runs the `test_*` functions of a bxl file, optionally against a fixture
+//! project checked in as a cell (`--fixture <cell>`).
+//!
+//! Each test function is evaluated like a regular bxl function invoked without cli args. With a
+//! fixture, unqualified targets and literals are resolved against the fixture cell instead of the
+//! cell of the bxl file. A test
+//! passes if its evaluation (and any artifacts it ensured) succeeds. Results are reported as
+//! regular `TestResult` events, so they show up in the console and event log the same way as
+//! `buck2 test` results do.
+
+use std::io::Write;
+use std::sync::Arc;
+use std::time::Instant;
+
+use buck2_build_api::build::ConvertMaterializationContext;
+use buck2_build_api::bxl::calculation::BxlComputeResult;
+use buck2_build_api::bxl::types::BxlFunctionLabel;
+use buck2_cli_proto::build_request::Materializations;
+use buck2_cli_proto::test_response::TestStatuses;
+use buck2_cli_proto::BxlRequest;
+use buck2_cli_proto::BxlResponse;
+use buck2_cli_proto::CounterWithExamples;
+use buck2_cli_proto::HasClientContext;
+use buck2_common::dice::cells::HasCellResolver;
+use buck2_core::cells::name::CellName;
+use buck2_core::cells::CellResolver;
+use buck2_core::configuration::data::ConfigurationData;
+use buck2_core::fs::project_rel_path::ProjectRelativePath;
+use buck2_core::target::label::TargetLabel;
+use buck2_data::ToProtoMessage;
+use buck2_events::dispatch::get_dispatcher;
+use buck2_interpreter::file_loader::LoadedModule;
+use buck2_interpreter::load_module::InterpreterCalculation;
+use buck2_interpreter::paths::bxl::BxlFilePath;
+use buck2_interpreter::paths::module::StarlarkModulePath;
+use buck2_server_ctx::ctx::ServerCommandContextTrait;
+use buck2_server_ctx::pattern::target_platform_from_client_context;
+use dice::DiceTransaction;
+use dupe::Dupe;
+use itertools::Itertools;
+
+use crate::bxl::calculation::eval_bxl;
+use crate::bxl::eval::BxlResolvedCliArgs;
+use crate::bxl::key::BxlKey;
+use crate::bxl::starlark_defs::bxl_function::FrozenBxlFunction;
+use crate::command::copy_output;
+use crate::command::ensure_artifacts;
+use crate::command::get_bxl_cli_args;
+use crate::command::parse_bxl_path_from_cli;
+
+/// Prefix of the bxl functions that are picked up as tests.
+const TEST_FUNCTION_PREFIX: &str = "test_";
+
+const MAX_EXAMPLE_VALUES: u64 = 10;
+
+#[derive(Debug, thiserror::Error)]
+enum BxlTestError {
+    #[error(
+        "No bxl functions starting with `{}` found in `{0}`",
+        TEST_FUNCTION_PREFIX
+    )]
+    NoTests(BxlFilePath),
+    #[error("bxl test `{0}` must not require any cli args")]
+    UnexpectedHelp(String),
+}
+
+pub(crate) async fn bxl_test(
+    server_ctx: &dyn ServerCommandContextTrait,
+    mut stdout: impl Write,
+    mut ctx: DiceTransaction,
+    request: &BxlRequest,
+) -> anyhow::Result<BxlResponse> {
+    let cwd = server_ctx.working_dir();
+    let cell_resolver = ctx.get_cell_resolver().await?;
+    let (bxl_path, test_name) =
+        parse_bxl_test_label_from_cli(cwd, &request.bxl_label, &cell_resolver)?;
+    let fixture = request
+        .fixture
+        .as_deref()
+        .map(|alias| resolve_fixture_cell(cwd, alias, &cell_resolver))
+        .transpose()?;
+    let project_root = server_ctx.project_root().to_string();
+
+    let client_ctx = request.client_context()?;
+    let global_target_platform =
+        target_platform_from_client_context(client_ctx, server_ctx, &mut ctx).await?;
+
+    let bxl_module = ctx
+        .get_loaded_module(StarlarkModulePath::BxlFile(&bxl_path))
+        .await?;
+    let test_names = match test_name {
+        Some(name) => vec![name],
+        None => collect_test_names(&bxl_module),
+    };
+    if test_names.is_empty() {
+        return Err(BxlTestError::NoTests(bxl_path).into());
+    }
+
+    let mut statuses = TestStatuses {
+        passed: Some(new_counter()),
+        skipped: Some(new_counter()),
+        failed: Some(new_counter()),
+        fatals: Some(new_counter()),
+        listing_success: Some(new_counter()),
+        listing_failed: Some(new_counter()),
+        flaky: Some(new_counter()),
+    };
+
+    let dispatcher = get_dispatcher();
+    for name in test_names {
+        let label = BxlFunctionLabel {
+            bxl_path: bxl_path.clone(),
+            name,
+        };
+
+        let start = Instant::now();
+        let mut output = Vec::new();
+        let mut error_output = Vec::new();
+        let outcome = run_one_test(
+            cwd,
+            &ctx,
+            &cell_resolver,
+            &label,
+            global_target_platform.dupe(),
+            fixture,
+            &mut output,
+            &mut error_output,
+        )
+        .await;
+        let duration = start.elapsed();
+
+        stdout.write_all(&output)?;
+
+        let mut details = String::from_utf8_lossy(&error_output).into_owned();
+        let (status, counter) = match outcome {
+            Ok(()) => (buck2_data::TestStatus::Pass, &mut statuses.passed),
+            Err(e) => {
+                details.push_str(&format!("{:#}", e));
+                (buck2_data::TestStatus::Fail, &mut statuses.failed)
+            }
+        };
+        add_to_counter(counter, &label.to_string());
+
+        dispatcher.instant_event(buck2_data::TestResult {
+            name: label.to_string(),
+            status: status as i32,
+            msg: None,
+            duration: duration.try_into().ok(),
+            details,
+            target_label: Some(test_target_label(&label)),
+            stdout: String::from_utf8_lossy(&output).into_owned(),
+            stderr: String::from_utf8_lossy(&error_output).into_owned(),
+        });
+    }
+
+    Ok(BxlResponse {
+        project_root,
+        error_messages: Vec::new(),
+        test_statuses: Some(statuses),
+    })
+}
+
+async fn run_one_test(
+    cwd: &ProjectRelativePath,
+    ctx: &DiceTransaction,
+    cell_resolver: &CellResolver,
+    label: &BxlFunctionLabel,
+    global_target_platform: Option<TargetLabel>,
+    fixture: Option<CellName>,
+    output: &mut Vec<u8>,
+    error_output: &mut Vec<u8>,
+) -> anyhow::Result<()> {
+    let bxl_args = match get_bxl_cli_args(cwd, ctx, label, &Vec::new(), cell_resolver).await? {
+        BxlResolvedCliArgs::Resolved(bxl_args) => Arc::new(bxl_args),
+        BxlResolvedCliArgs::Help => {
+            return Err(BxlTestError::UnexpectedHelp(label.to_string()).into());
+        }
+    };
+
+    let bxl_key = BxlKey::new(label.clone(), bxl_args, global_target_platform, fixture);
+    let BxlComputeResult {
+        bxl_result,
+        materializations,
+    } = eval_bxl(ctx, bxl_key).await?;
+
+    let materialization_context = ConvertMaterializationContext::with_existing_map(
+        Materializations::Skip,
+        &Arc::new((*materializations).clone()),
+    );
+    let build_result = ensure_artifacts(ctx, &materialization_context, &bxl_result).await;
+
+    copy_output(&mut *output, ctx, bxl_result.get_output_loc()).await?;
+    copy_output(&mut *error_output, ctx, bxl_result.get_error_loc()).await?;
+
+    match build_result {
+        Ok(()) => Ok(()),
+        Err(errors) => Err(anyhow::anyhow!(
+            "{}",
+            errors
+                .iter()
+                .map(|e| format!("{:#}", e))
+                .unique()
+                .join("\n")
+        )),
+    }
+}
+
+/// All exported bxl functions whose name starts with `test_`, sorted by name.
+fn collect_test_names(bxl_module: &LoadedModule) -> Vec<String> {
+    bxl_module
+        .env()
+        .names()
+        .map(|name| name.as_str().to_owned())
+        .filter(|name| name.starts_with(TEST_FUNCTION_PREFIX))
+        .filter(|name| {
+            bxl_module
+                .env()
+                .get(name)
+                .map_or(false, |v| v.downcast::<FrozenBxlFunction>().is_ok())
+        })
+        .sorted()
+        .collect()
+}
+
+/// In test mode, the label is either a bxl file (`<cell>//path/to/file.bxl`), in which case all
+/// test functions in it are run, or a single bxl function (`<cell>//path/to/file.bxl:test_foo`).
+fn parse_bxl_test_label_from_cli(
+    cwd: &ProjectRelativePath,
+    bxl_label: &str,
+    cell_resolver: &CellResolver,
+) -> anyhow::Result<(BxlFilePath, Option<String>)> {
+    match bxl_label.rsplit_once(':') {
+        Some((bxl_path, name)) => Ok((
+            parse_bxl_path_from_cli(cwd, bxl_path, cell_resolver)?,
+            Some(name.to_owned()),
+        )),
+        None => Ok((
+            parse_bxl_path_from_cli(cwd, bxl_label, cell_resolver)?,
+            None,
+        )),
+    }
+}
+
+/// Resolves the `--fixture` cell alias against the cell of the working directory, like the cell
+/// aliases in the bxl label.
+fn resolve_fixture_cell(
+    cwd: &ProjectRelativePath,
+    alias: &str,
+    cell_resolver: &CellResolver,
+) -> anyhow::Result<CellName> {
+    let current_cell = cell_resolver.get_cell_path(cwd)?;
+    cell_resolver
+        .get(current_cell.cell())?
+        .cell_alias_resolver()
+        .resolve(alias)
+}
+
+/// Bxl functions are not configured, so the test is reported under the bxl file as the package and
+/// the function as the target name, in the unspecified configuration that bxl uses by default.
+fn test_target_label(label: &BxlFunctionLabel) -> buck2_data::ConfiguredTargetLabel {
+    buck2_data::ConfiguredTargetLabel {
+        label: Some(buck2_data::TargetLabel {
+            package: label.bxl_path.to_string(),
+            name: label.name.clone(),
+        }),
+        configuration: Some(ConfigurationData::unspecified().as_proto()),
+        execution_configuration: None,
+    }
+}
+
+fn new_counter() -> CounterWithExamples {
+    CounterWithExamples {
+        count: 0,
+        max: MAX_EXAMPLE_VALUES,
+        example_tests: Vec::new(),
+    }
+}
+
+fn add_to_counter(counter: &mut Option<CounterWithExamples>, name: &str) {
+    let counter = counter.get_or_insert_with(new_counter);
+    counter.count += 1;
+    if counter.count <= counter.max {
+        counter.example_tests.push(name.to_owned());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use buck2_core::cells::alias::NonEmptyCellAlias;
+    use buck2_core::cells::cell_root_path::CellRootPathBuf;
+    use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
+
+    use super::*;
+
+    fn cell_resolver() -> CellResolver {
+        let root = CellName::testing_new("root");
+        let fixture = CellName::testing_new("fixture");
+        CellResolver::testing_with_names_and_paths_with_alias(&[
+            (
+                root,
+                CellRootPathBuf::new(ProjectRelativePathBuf::unchecked_new("".to_owned())),
+                HashMap::from([(NonEmptyCellAlias::testing_new("fix"), fixture)]),
+            ),
+            (
+                fixture,
+                CellRootPathBuf::new(ProjectRelativePathBuf::unchecked_new(
+                    "scripts/fixtures/project".to_owned(),
+                )),
+                HashMap::new(),
+            ),
+        ])
+    }
+
+    #[test]
+    fn test_resolve_fixture_cell() -> anyhow::Result<()> {
+        let cell_resolver = cell_resolver();
+        let cwd = ProjectRelativePath::new("scripts")?;
+
+        assert_eq!(
+            CellName::testing_new("fixture"),
+            resolve_fixture_cell(cwd, "fix", &cell_resolver)?
+        );
+        assert!(resolve_fixture_cell(cwd, "missing", &cell_resolver).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_test_target_label() -> anyhow::Result<()> {
+        let cell_resolver = cell_resolver();
+        let cwd = ProjectRelativePath::new("scripts")?;
+
+        let (bxl_path, name) = parse_bxl_test_label_from_cli(
+            cwd,
+            "//scripts/my_script_test.bxl:test_finds_lib",
+            &cell_resolver,
+        )?;
+        let label = BxlFunctionLabel {
+            bxl_path,
+            name: name.unwrap(),
+        };
+
+        let target_label = test_target_label(&label);
+        let expected = buck2_data::TargetLabel {
+            package: "root//scripts/my_script_test.bxl".to_owned(),
+            name: "test_finds_lib".to_owned(),
+        };
+        assert_eq!(Some(expected), target_label.label);
+        // The test report can only display configured labels with a configuration.
+        assert_eq!(
+            Some(ConfigurationData::unspecified().as_proto()),
+            target_label.configuration
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,312 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! `buck2 bxl --watch`: keeps re-evaluating a bxl function until the client disconnects.
+//!
+//! On every tick we take a fresh DICE transaction, which syncs the file watcher like any other
+//! command does. If the transaction is at a different DICE version than the one the previous
+//! evaluation used, because of our own sync or one done by a concurrent command, the bxl key is
+//! requested again. That is cheap unless one of its inputs was invalidated. A JSON line is emitted
+//! whenever the output differs from the last one we emitted.
+
+use std::io::Write;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use buck2_build_api::build::ConvertMaterializationContext;
+use buck2_build_api::bxl::calculation::BxlComputeResult;
+use buck2_cli_proto::build_request::Materializations;
+use buck2_cli_proto::BxlRequest;
+use buck2_cli_proto::BxlResponse;
+use buck2_cli_proto::HasClientContext;
+use buck2_common::dice::cells::HasCellResolver;
+use buck2_events::dispatch::span_async;
+use buck2_server_ctx::command_end::command_end;
+use buck2_server_ctx::ctx::ServerCommandContextTrait;
+use buck2_server_ctx::ctx::ServerCommandDiceContext;
+use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
+use buck2_server_ctx::pattern::target_platform_from_client_context;
+use dice::DiceEquality;
+use dice::DiceTransaction;
+use itertools::Itertools;
+use tokio::time::MissedTickBehavior;
+
+use crate::bxl::calculation::eval_bxl;
+use crate::bxl::eval::BxlResolvedCliArgs;
+use crate::bxl::key::BxlKey;
+use crate::command::copy_output;
+use crate::command::ensure_artifacts;
+use crate::command::get_bxl_cli_args;
+use crate::command::parse_bxl_label_from_cli;
+
+/// Version of the JSON lines emitted in watch mode. Bump when making incompatible changes.
+const WATCH_OUTPUT_VERSION: u32 = 1;
+
+/// How often we take a new DICE transaction to check for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The result of evaluating the bxl function once.
+enum Evaluation {
+    /// `--help` was passed to the function, and its help was printed.
+    Help,
+    /// The JSON line to report, and what the function wrote to stderr.
+    Output(serde_json::Value, Vec<u8>),
+}
+
+pub(crate) async fn bxl_watch_command(
+    server_ctx: &dyn ServerCommandContextTrait,
+    mut partial_result_dispatcher: PartialResultDispatcher<buck2_cli_proto::StdoutBytes>,
+    req: BxlRequest,
+) -> anyhow::Result<BxlResponse> {
+    let start_event = buck2_data::CommandStart {
+        metadata: server_ctx.request_metadata().await?,
+        data: Some(
+            buck2_data::BxlCommandStart {
+                bxl_label: req.bxl_label.clone(),
+            }
+            .into(),
+        ),
+    };
+    span_async(start_event, async {
+        let result = watch(server_ctx, &mut partial_result_dispatcher, &req).await;
+        let end_event = command_end(
+            &result,
+            buck2_data::BxlCommandEnd {
+                bxl_label: req.bxl_label.clone(),
+            },
+        );
+        (result, end_event)
+    })
+    .await
+}
+
+/// Runs until the client disconnects (which cancels this future) or an internal error occurs.
+/// Errors from the bxl function itself are reported in the output and don't stop watching.
+async fn watch(
+    server_ctx: &dyn ServerCommandContextTrait,
+    partial_result_dispatcher: &mut PartialResultDispatcher<buck2_cli_proto::StdoutBytes>,
+    req: &BxlRequest,
+) -> anyhow::Result<BxlResponse> {
+    let evaluator = DiceWatchEvaluator { server_ctx, req };
+    watch_loop(&evaluator, POLL_INTERVAL, |line, stderr| {
+        server_ctx.stderr()?.write_all(stderr)?;
+        let mut stdout = partial_result_dispatcher.as_writer();
+        serde_json::to_writer(&mut stdout, line)?;
+        stdout.write_all(b"\n")?;
+        stdout.flush()?;
+        Ok(())
+    })
+    .await?;
+
+    // The loop only returns when the function printed its help.
+    Ok(BxlResponse {
+        project_root: server_ctx.project_root().to_string(),
+        error_messages: Vec::new(),
+        test_statuses: None,
+    })
+}
+
+/// Evaluates the bxl function for the watch loop.
+#[async_trait]
+trait WatchEvaluator: Sync {
+    type Version: Copy + PartialEq + Send;
+
+    /// Takes a fresh transaction, and evaluates the bxl function in it unless the transaction is
+    /// at version `unchanged`, in which case the previous evaluation still holds.
+    async fn eval_if_changed(
+        &self,
+        unchanged: Option<Self::Version>,
+    ) -> anyhow::Result<Option<(Self::Version, Evaluation)>>;
+}
+
+struct DiceWatchEvaluator<'a> {
+    server_ctx: &'a dyn ServerCommandContextTrait,
+    req: &'a BxlRequest,
+}
+
+#[async_trait]
+impl WatchEvaluator for DiceWatchEvaluator<'_> {
+    type Version = DiceEquality;
+
+    async fn eval_if_changed(
+        &self,
+        unchanged: Option<DiceEquality>,
+    ) -> anyhow::Result<Option<(DiceEquality, Evaluation)>> {
+        let req = self.req;
+        self.server_ctx
+            .with_dice_ctx(|server_ctx, ctx| async move {
+                let version = ctx.equality_token();
+                if unchanged == Some(version) {
+                    return Ok(None);
+                }
+                Ok(Some((version, eval(server_ctx, ctx, req).await?)))
+            })
+            .await
+    }
+}
+
+/// Re-evaluates on every tick of `poll_interval` when the version changed, and calls `emit` with
+/// the JSON line and stderr of every evaluation whose output differs from the last emitted one.
+/// Only returns if the function printed its help, or on error.
+async fn watch_loop<E: WatchEvaluator>(
+    evaluator: &E,
+    poll_interval: Duration,
+    mut emit: impl FnMut(&serde_json::Value, &[u8]) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let mut ticker = tokio::time::interval(poll_interval);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    let mut version = None;
+    let mut last: Option<serde_json::Value> = None;
+    loop {
+        ticker.tick().await;
+        let (new_version, evaluation) = match evaluator.eval_if_changed(version).await? {
+            Some(evaluated) => evaluated,
+            None => continue,
+        };
+        version = Some(new_version);
+
+        match evaluation {
+            // The arguments don't change between evaluations, so this can only happen the first
+            // time around: report the help once, like a regular `buck2 bxl` would.
+            Evaluation::Help => return Ok(()),
+            Evaluation::Output(line, stderr) => {
+                if last.as_ref() != Some(&line) {
+                    emit(&line, &stderr)?;
+                    last = Some(line);
+                }
+            }
+        }
+    }
+}
+
+/// Evaluates the bxl function in a fresh transaction.
+async fn eval(
+    server_ctx: &dyn ServerCommandContextTrait,
+    mut ctx: DiceTransaction,
+    req: &BxlRequest,
+) -> anyhow::Result<Evaluation> {
+    let cwd = server_ctx.working_dir();
+    let cell_resolver = ctx.get_cell_resolver().await?;
+    let client_ctx = req.client_context()?;
+    let global_target_platform =
+        target_platform_from_client_context(client_ctx, server_ctx, &mut ctx).await?;
+
+    let evaluated: anyhow::Result<Option<BxlComputeResult>> = try {
+        let bxl_label = parse_bxl_label_from_cli(cwd, &req.bxl_label, &cell_resolver)?;
+        match get_bxl_cli_args(cwd, &ctx, &bxl_label, &req.bxl_args, &cell_resolver).await? {
+            BxlResolvedCliArgs::Resolved(bxl_args) => {
+                let bxl_key =
+                    BxlKey::new(bxl_label, Arc::new(bxl_args), global_target_platform, None);
+                Some(eval_bxl(&ctx, bxl_key).await?)
+            }
+            BxlResolvedCliArgs::Help => None,
+        }
+    };
+
+    let BxlComputeResult {
+        bxl_result,
+        materializations,
+    } = match evaluated {
+        Ok(Some(result)) => result,
+        Ok(None) => return Ok(Evaluation::Help),
+        Err(e) => {
+            let line = watch_output_line(req, "", &[format!("{:#}", e)]);
+            return Ok(Evaluation::Output(line, Vec::new()));
+        }
+    };
+
+    let final_artifact_materializations =
+        Materializations::from_i32(req.final_artifact_materializations)
+            .unwrap_or(Materializations::Default);
+    let materialization_context = ConvertMaterializationContext::with_existing_map(
+        final_artifact_materializations,
+        &Arc::new((*materializations).clone()),
+    );
+    let error_messages = match ensure_artifacts(&ctx, &materialization_context, &bxl_result).await {
+        Ok(()) => Vec::new(),
+        Err(errors) => errors.iter().map(|e| format!("{:#}", e)).unique().collect(),
+    };
+
+    let mut output = Vec::new();
+    copy_output(&mut output, &ctx, bxl_result.get_output_loc()).await?;
+    let mut stderr = Vec::new();
+    copy_output(&mut stderr, &ctx, bxl_result.get_error_loc()).await?;
+
+    let line = watch_output_line(req, &String::from_utf8_lossy(&output), &error_messages);
+    Ok(Evaluation::Output(line, stderr))
+}
+
+fn watch_output_line(req: &BxlRequest, output: &str, errors: &[String]) -> serde_json::Value {
+    serde_json::json!({
+        "version": WATCH_OUTPUT_VERSION,
+        "bxl_label": req.bxl_label,
+        "output": output,
+        "errors": errors,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    /// Replays a fixed sequence of transaction versions. The output of the bxl function is the
+    /// content of a file, which changes at some versions.
+    struct FakeEvaluator {
+        versions: Mutex<Vec<u32>>,
+        evaluated: Mutex<Vec<u32>>,
+    }
+
+    #[async_trait]
+    impl WatchEvaluator for FakeEvaluator {
+        type Version = u32;
+
+        async fn eval_if_changed(
+            &self,
+            unchanged: Option<u32>,
+        ) -> anyhow::Result<Option<(u32, Evaluation)>> {
+            let mut versions = self.versions.lock().unwrap();
+            if versions.is_empty() {
+                return Ok(Some((u32::MAX, Evaluation::Help)));
+            }
+            let version = versions.remove(0);
+            if unchanged == Some(version) {
+                return Ok(None);
+            }
+            self.evaluated.lock().unwrap().push(version);
+            let content = if version < 3 { "old" } else { "new" };
+            Ok(Some((
+                version,
+                Evaluation::Output(serde_json::json!({ "output": content }), Vec::new()),
+            )))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_watch_loop_reevaluates_on_file_change() -> anyhow::Result<()> {
+        // Version 2 doesn't touch the file, version 3 changes it, version 4 is unrelated.
+        let evaluator = FakeEvaluator {
+            versions: Mutex::new(vec![1, 1, 2, 2, 3, 3, 4]),
+            evaluated: Mutex::new(Vec::new()),
+        };
+        let mut emitted = Vec::new();
+        watch_loop(&evaluator, Duration::from_millis(1), |line, _stderr| {
+            emitted.push(line["output"].as_str().unwrap().to_owned());
+            Ok(())
+        })
+        .await?;
+
+        assert_eq!(vec![1, 2, 3, 4], *evaluator.evaluated.lock().unwrap());
+        assert_eq!(vec!["old", "new"], emitted);
+        Ok(())
+    }
+}
@@ -160,6 +160,21 @@ pub struct BuildCommand {
     )]
     skip_test_info: bool,
 
+    #[allow(unused)]
+    #[clap(
+        long,
+        group = "validation-info",
+        help = "Build validations of targets and their dependencies (this is the default)"
+    )]
+    build_validation_info: bool,
+
+    #[clap(
+        long,
+        group = "validation-info",
+        help = "Do not build validations of targets and their dependencies (this is not the default)"
+    )]
+    skip_validation_info: bool,
+
     #[clap(
         long = "out",
         help = "Copy the output of the built target to this path (`-` to stdout)"
@@ -191,6 +206,13 @@ impl BuildCommand {
         }
         build_providers::Action::Skip
     }
+
+    fn validation_info(&self) -> build_providers::Action {
+        if self.skip_validation_info {
+            return build_providers::Action::Skip;
+        }
+        build_providers::Action::BuildIfAvailable
+    }
 }
 
 #[derive(Debug, Clone, Dupe, clap::ArgEnum)]
@@ -249,6 +271,7 @@ impl StreamingCommand for BuildCommand {
                         default_info: self.default_info() as i32,
                         run_info: self.run_info() as i32,
                         test_info: self.test_info() as i32,
+                        validation_info: self.validation_info() as i32,
                     }),
                     response_options: Some(ResponseOptions {
                         return_outputs: self.show_output
@@ -8,7 +8,9 @@
  */
 
 use async_trait::async_trait;
+use buck2_cli_proto::test_response::TestStatuses;
 use buck2_cli_proto::BxlRequest;
+use buck2_cli_proto::BxlResponse;
 use buck2_client_ctx::client_ctx::ClientCommandContext;
 use buck2_client_ctx::command_outcome::CommandOutcome;
 use buck2_client_ctx::common::CommonBuildConfigurationOptions;
@@ -19,12 +21,18 @@ use buck2_client_ctx::common::CommonDaemonCommandOptions;
 use buck2_client_ctx::daemon::client::BuckdClientConnector;
 use buck2_client_ctx::daemon::client::StdoutPartialResultHandler;
 use buck2_client_ctx::exit_result::ExitResult;
+use buck2_client_ctx::final_console::FinalConsole;
 use buck2_client_ctx::path_arg::PathArg;
+use buck2_client_ctx::stdio::eprint_line;
 use buck2_client_ctx::streaming::StreamingCommand;
+use buck2_client_ctx::subscribers::superconsole::test::TestCounterColumn;
+use superconsole::Line;
+use superconsole::Span;
 
 use crate::commands::build::print_build_result;
 use crate::commands::build::FinalArtifactMaterializations;
 use crate::commands::build::MaterializationsToProto;
+use crate::commands::test::print_error_counter;
 
 #[derive(Debug, clap::Parser)]
 #[clap(name = "bxl", about = "Run BXL scripts")]
@@ -32,6 +40,21 @@ pub struct BxlCommand {
     #[clap(flatten)]
     bxl_opts: BxlCommandOptions,
 
+    /// Run the bxl functions whose names start with `test_` and report them as test results.
+    /// The label is then a bxl file (`<cell>//path/file.bxl`), or a single test function in it.
+    #[clap(long, conflicts_with = "watch")]
+    test: bool,
+
+    /// With `--test`, the cell alias of a fixture project. Unqualified targets and literals in the
+    /// tests are resolved against that cell's root instead of the bxl file's cell.
+    #[clap(long, requires = "test", value_name = "CELL")]
+    fixture: Option<String>,
+
+    /// Keep running, and re-run the bxl function whenever one of its inputs changes. Each result
+    /// is printed to stdout as a single JSON line. Stop with Ctrl-C.
+    #[clap(long)]
+    watch: bool,
+
     #[clap(flatten)]
     common_ops: CommonCommandOptions,
 }
@@ -72,6 +95,21 @@ pub struct BxlCommandOptions {
     pub user_event_log: Option<PathArg>,
 }
 
+fn print_bxl_test_statuses(console: &FinalConsole, statuses: &TestStatuses) -> anyhow::Result<()> {
+    let mut line = Line::default();
+    line.push(Span::new_unstyled_lossy("Tests finished: "));
+    for column in [TestCounterColumn::PASS, TestCounterColumn::FAIL] {
+        line.push(column.to_span_from_test_statuses(statuses)?);
+        line.push(Span::new_unstyled_lossy(". "));
+    }
+    eprint_line(&line)?;
+
+    if let Some(failed) = &statuses.failed {
+        print_error_counter(console, failed, "TESTS FAILED", "✗")?;
+    }
+    Ok(())
+}
+
 #[async_trait]
 impl StreamingCommand for BxlCommand {
     const COMMAND_NAME: &'static str = "bxl";
@@ -94,6 +132,9 @@ impl StreamingCommand for BxlCommand {
                     final_artifact_materializations: self.bxl_opts.materializations.to_proto()
                         as i32,
                     print_stacktrace: ctx.verbosity.print_success_stderr(),
+                    test: self.test,
+                    watch: self.watch,
+                    fixture: self.fixture,
                 },
                 ctx.stdin()
                     .console_interaction_stream(&self.common_ops.console_opts),
@@ -101,12 +142,27 @@ impl StreamingCommand for BxlCommand {
             )
             .await;
         let success = match &result {
-            Ok(CommandOutcome::Success(response)) => response.error_messages.is_empty(),
+            Ok(CommandOutcome::Success(response)) => {
+                response.error_messages.is_empty()
+                    && response
+                        .test_statuses
+                        .as_ref()
+                        .and_then(|statuses| statuses.failed.as_ref())
+                        .map_or(true, |failed| failed.count == 0)
+            }
             _ => false,
         };
 
         let console = self.common_ops.console_opts.final_console();
 
+        if let Ok(CommandOutcome::Success(BxlResponse {
+            test_statuses: Some(statuses),
+            ..
+        })) = &result
+        {
+            print_bxl_test_statuses(&console, statuses)?;
+        }
+
         if success {
             console.print_success("BXL SUCCEEDED")?;
         } else {
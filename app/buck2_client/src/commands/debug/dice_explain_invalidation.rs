@@ -0,0 +1,65 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use async_trait::async_trait;
+use buck2_cli_proto::UnstableDiceExplainInvalidationRequest;
+use buck2_client_ctx::client_ctx::ClientCommandContext;
+use buck2_client_ctx::common::CommonBuildConfigurationOptions;
+use buck2_client_ctx::common::CommonConsoleOptions;
+use buck2_client_ctx::common::CommonDaemonCommandOptions;
+use buck2_client_ctx::daemon::client::BuckdClientConnector;
+use buck2_client_ctx::exit_result::ExitResult;
+use buck2_client_ctx::streaming::StreamingCommand;
+
+#[derive(Debug, clap::Parser)]
+pub struct DiceExplainInvalidationCommand {
+    /// Explain keys whose description contains this string, for example a target label such as
+    /// `cell//foo:bar`, or a key type such as `AnalysisKey`.
+    #[clap(value_name = "KEY")]
+    query: String,
+}
+
+#[async_trait]
+impl StreamingCommand for DiceExplainInvalidationCommand {
+    const COMMAND_NAME: &'static str = "dice_explain_invalidation";
+
+    fn existing_only() -> bool {
+        true
+    }
+
+    async fn exec_impl(
+        self,
+        buckd: &mut BuckdClientConnector,
+        _matches: &clap::ArgMatches,
+        _ctx: &mut ClientCommandContext<'_>,
+    ) -> ExitResult {
+        let res = buckd
+            .with_flushing()
+            .unstable_dice_explain_invalidation(UnstableDiceExplainInvalidationRequest {
+                query: self.query,
+            })
+            .await?;
+
+        buck2_client_ctx::println!("{}", res.explanation.trim_end())?;
+
+        ExitResult::success()
+    }
+
+    fn console_opts(&self) -> &CommonConsoleOptions {
+        CommonConsoleOptions::none_ref()
+    }
+
+    fn event_log_opts(&self) -> &CommonDaemonCommandOptions {
+        CommonDaemonCommandOptions::default_ref()
+    }
+
+    fn common_opts(&self) -> &CommonBuildConfigurationOptions {
+        CommonBuildConfigurationOptions::default_ref()
+    }
+}
@@ -44,6 +44,10 @@ use crate::commands::query::common::CommonQueryOptions;
 ///
 /// `buck2 cquery //java/com/example/app:amazing --output-all-attributes`
 ///
+/// Show which `select()` branches were taken for the `deps` of a target
+///
+/// `buck2 cquery //java/com/example/app:amazing --output-attribute deps --show-select-provenance`
+///
 /// List the deps of a target (special characters in a target will require quotes):
 ///
 /// `buck2 cquery 'deps("//java/com/example/app:amazing+more")'`
@@ -74,6 +78,12 @@ pub struct CqueryCommand {
     )]
     show_providers: bool,
 
+    /// For each attribute (restricted to those selected by `--output-attribute`, if given), show
+    /// which `select()` branch was taken and which `config_setting` constraints matched, along
+    /// with the configuration and the transitions applied to the target's dependencies.
+    #[clap(long)]
+    show_select_provenance: bool,
+
     #[allow(rustdoc::bare_urls)]
     /// Enable deprecated `owner()` function behavior.
     ///
@@ -125,6 +135,7 @@ impl StreamingCommand for CqueryCommand {
                     output_attributes,
                     target_universe: self.target_universe,
                     show_providers: self.show_providers,
+                    show_select_provenance: self.show_select_provenance,
                     unstable_output_format,
                     correct_owner,
                 },
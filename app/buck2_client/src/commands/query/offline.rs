@@ -0,0 +1,77 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! `buck2 uquery --from-graph`: evaluates a query against a target graph snapshot written by
+//! `buck2 targets --export-graph`, without a daemon.
+
+use std::fs::File;
+use std::io::BufReader;
+
+use anyhow::Context;
+use buck2_cli_proto::QueryOutputFormat;
+use buck2_core::fs::paths::abs_path::AbsPath;
+use buck2_query::query::graph_snapshot::GraphSnapshot;
+use buck2_query::query::graph_snapshot::SnapshotQueryEnvironment;
+use buck2_query::query::graph_snapshot::PRINT_SNAPSHOT_QUERY_RESULT;
+use buck2_query::query::syntax::simple::eval::evaluator::QueryEvaluator;
+use buck2_query::query::syntax::simple::eval::multi_query::process_multi_query;
+use buck2_query::query::syntax::simple::eval::values::QueryEvaluationResult;
+use buck2_query::query::syntax::simple::functions::DefaultQueryFunctionsModule;
+use buck2_query_parser::placeholder::QUERY_PERCENT_S_PLACEHOLDER;
+
+#[derive(Debug, thiserror::Error)]
+enum OfflineQueryError {
+    #[error("Query args supplied without any `%s` placeholder in the query")]
+    ArgsWithoutPlaceholder,
+}
+
+pub(crate) async fn uquery_from_graph(
+    graph: &AbsPath,
+    query: &str,
+    query_args: &[String],
+    output_format: QueryOutputFormat,
+    output_attributes: &[String],
+) -> anyhow::Result<()> {
+    let file = File::open(graph)
+        .with_context(|| format!("Error opening target graph snapshot `{}`", graph.display()))?;
+    let snapshot = GraphSnapshot::read(BufReader::new(file))
+        .with_context(|| format!("Error reading target graph snapshot `{}`", graph.display()))?;
+
+    let env = SnapshotQueryEnvironment::new(&snapshot);
+    let functions = DefaultQueryFunctionsModule::new();
+    let result = if query.contains(QUERY_PERCENT_S_PLACEHOLDER) {
+        QueryEvaluationResult::Multiple(
+            process_multi_query(query, query_args, |input, query| {
+                let evaluator = QueryEvaluator::new(&env, &functions);
+                async move { (input, evaluator.eval_query(&query).await) }
+            })
+            .await,
+        )
+    } else if !query_args.is_empty() {
+        return Err(OfflineQueryError::ArgsWithoutPlaceholder.into());
+    } else {
+        QueryEvaluationResult::Single(
+            QueryEvaluator::new(&env, &functions)
+                .eval_query(query)
+                .await?,
+        )
+    };
+
+    // Printed like the daemon prints `uquery` results.
+    let mut output = Vec::new();
+    (PRINT_SNAPSHOT_QUERY_RESULT.get()?)(
+        &mut output,
+        result,
+        output_format as i32,
+        output_attributes,
+        snapshot.cell_resolver(),
+    )
+    .await?;
+    buck2_client_ctx::stdio::print_bytes(&output)
+}
@@ -18,9 +18,12 @@ use buck2_client_ctx::common::CommonDaemonCommandOptions;
 use buck2_client_ctx::daemon::client::BuckdClientConnector;
 use buck2_client_ctx::daemon::client::StdoutPartialResultHandler;
 use buck2_client_ctx::exit_result::ExitResult;
+use buck2_client_ctx::path_arg::PathArg;
+use buck2_client_ctx::streaming::BuckSubcommand;
 use buck2_client_ctx::streaming::StreamingCommand;
 
 use crate::commands::query::common::CommonQueryOptions;
+use crate::commands::query::offline::uquery_from_graph;
 
 /// Perform queries on the unconfigured target graph.
 ///
@@ -51,6 +54,13 @@ use crate::commands::query::common::CommonQueryOptions;
 /// `1 + select({"//:a": 1, "DEFAULT": 2})` will be encoded as:
 ///
 /// `{"__type": "concat", "items": [1, {"__type": "selector", "entries": {"//:a": 1, "DEFAULT": 2}}]}`
+///
+/// Offline queries:
+///
+/// A target graph snapshot written by `buck2 targets //... --export-graph graph.jsonl` can be
+/// queried later without a daemon or a checkout:
+///
+/// `buck2 uquery --from-graph graph.jsonl 'rdeps(//..., //lib:base)'`
 #[derive(Debug, clap::Parser)]
 #[clap(name = "uquery")]
 pub struct UqueryCommand {
@@ -59,6 +69,36 @@ pub struct UqueryCommand {
 
     #[clap(flatten)]
     query_common: CommonQueryOptions,
+
+    /// Evaluate the query against a target graph snapshot written by
+    /// `buck2 targets --export-graph` instead of the current project. No daemon is started.
+    #[clap(long, value_name = "PATH")]
+    from_graph: Option<PathArg>,
+}
+
+impl UqueryCommand {
+    /// Runs the command, evaluating the query offline if `--from-graph` is passed.
+    pub fn exec_or_from_graph(
+        self,
+        matches: &clap::ArgMatches,
+        ctx: ClientCommandContext<'_>,
+    ) -> ExitResult {
+        let graph = match &self.from_graph {
+            Some(graph) => graph.resolve(&ctx.working_dir),
+            None => return self.exec(matches, ctx),
+        };
+        ctx.instant_command("uquery", async move |_ctx| {
+            let (query, query_args) = self.query_common.get_query();
+            uquery_from_graph(
+                &graph,
+                &query,
+                &query_args,
+                self.query_common.output_format(),
+                &self.query_common.attributes.get()?,
+            )
+            .await
+        })
+    }
 }
 
 #[async_trait]
@@ -110,6 +110,7 @@ impl StreamingCommand for RunCommand {
                         default_info: build_providers::Action::Skip as i32,
                         run_info: build_providers::Action::Build as i32,
                         test_info: build_providers::Action::Skip as i32,
+                        validation_info: build_providers::Action::Skip as i32,
                     }),
                     response_options: None,
                     build_opts: Some(self.build_opts.to_proto()),
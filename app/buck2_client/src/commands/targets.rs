@@ -184,6 +184,26 @@ pub struct TargetsCommand {
     #[clap(long, short = 'o', value_name = "PATH")]
     output: Option<PathArg>,
 
+    /// Write a snapshot of the unconfigured target graph to this file: all the matched targets
+    /// and everything they transitively depend on, with their deps and attributes, plus the
+    /// package values and build file hashes of their packages. The snapshot can be queried later
+    /// without a daemon or a checkout using `buck2 uquery --from-graph`.
+    #[clap(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = &[
+            "json",
+            "json_lines",
+            "stats",
+            "resolve_alias",
+            "streaming",
+            "output",
+            "show_output",
+            "show_full_output",
+        ]
+    )]
+    export_graph: Option<PathArg>,
+
     /// Patterns to interpret
     #[clap(name = "TARGET_PATTERNS")]
     patterns: Vec<String>,
@@ -292,6 +312,10 @@ impl StreamingCommand for TargetsCommand {
             output_format: output_format as i32,
             targets: Some(if self.resolve_alias {
                 targets_request::Targets::ResolveAlias(targets_request::ResolveAlias {})
+            } else if self.export_graph.is_some() {
+                targets_request::Targets::ExportGraph(targets_request::ExportGraph {
+                    keep_going: self.keep_going,
+                })
             } else {
                 targets_request::Targets::Other(targets_request::Other {
                     output_attributes,
@@ -319,7 +343,8 @@ impl StreamingCommand for TargetsCommand {
                 })
             }),
             output: self
-                .output
+                .export_graph
+                .or(self.output)
                 .try_map(|x| x.resolve(&ctx.working_dir).into_string())?,
             concurrency: self
                 .num_threads
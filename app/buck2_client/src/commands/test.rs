@@ -26,8 +26,10 @@ use buck2_client_ctx::output_destination_arg::OutputDestinationArg;
 use buck2_client_ctx::path_arg::PathArg;
 use buck2_client_ctx::stdio::eprint_line;
 use buck2_client_ctx::streaming::StreamingCommand;
+use buck2_client_ctx::subscribers::subscriber::EventSubscriber;
 use buck2_client_ctx::subscribers::superconsole::test::span_from_build_failure_count;
 use buck2_client_ctx::subscribers::superconsole::test::TestCounterColumn;
+use buck2_client_ctx::subscribers::test_report::TestReportWriter;
 use buck2_core::fs::fs_util;
 use buck2_core::fs::working_dir::WorkingDir;
 use gazebo::prelude::*;
@@ -45,19 +47,38 @@ fn forward_output_to_path(
         .context("Failed to write test executor output to path")
 }
 
-fn print_error_counter(
+pub(crate) fn print_error_counter(
     console: &FinalConsole,
     counter: &CounterWithExamples,
     error_type: &str,
     symbol: &str,
+) -> anyhow::Result<()> {
+    print_counter(counter, error_type, symbol, |m| console.print_error(m))
+}
+
+/// Like `print_error_counter`, for results that don't fail the run but deserve attention.
+fn print_warning_counter(
+    console: &FinalConsole,
+    counter: &CounterWithExamples,
+    warning_type: &str,
+    symbol: &str,
+) -> anyhow::Result<()> {
+    print_counter(counter, warning_type, symbol, |m| console.print_warning(m))
+}
+
+fn print_counter(
+    counter: &CounterWithExamples,
+    counter_type: &str,
+    symbol: &str,
+    print: impl Fn(&str) -> anyhow::Result<()>,
 ) -> anyhow::Result<()> {
     if counter.count > 0 {
-        console.print_error(&format!("{} {}", counter.count, error_type))?;
+        print(&format!("{} {}", counter.count, counter_type))?;
         for test_name in &counter.example_tests {
-            console.print_error(&format!("  {} {}", symbol, test_name))?;
+            print(&format!("  {} {}", symbol, test_name))?;
         }
         if counter.count > counter.max {
-            console.print_error(&format!(
+            print(&format!(
                 "  ...and {} more not shown...",
                 counter.count - counter.max
             ))?;
@@ -65,6 +86,7 @@ fn print_error_counter(
     }
     Ok(())
 }
+
 #[derive(Debug, clap::Parser)]
 #[clap(name = "test", about = "Build and test the specified targets")]
 pub struct TestCommand {
@@ -123,6 +145,12 @@ If include patterns are present, regardless of whether exclude patterns are pres
     #[clap(long, group = "re_options", alias = "unstable-force-tests-on-re")]
     unstable_allow_all_tests_on_re: bool,
 
+    /// Run tests even if a passing result for the same test binary and inputs is available in
+    /// the action cache. Only affects tests that set `cacheable = True` in their
+    /// `ExternalRunnerTestInfo`.
+    #[clap(long)]
+    no_test_cache: bool,
+
     #[clap(name = "TARGET_PATTERNS", help = "Patterns to test")]
     patterns: Vec<String>,
 
@@ -148,6 +176,14 @@ If include patterns are present, regardless of whether exclude patterns are pres
     #[clap(long)]
     test_executor_stderr: Option<OutputDestinationArg>,
 
+    /// Writes a JUnit XML report of every test result to the provided path.
+    #[clap(long, value_name = "PATH")]
+    junit_xml: Option<PathArg>,
+
+    /// Writes a JSON report of every test result to the provided path.
+    #[clap(long, value_name = "PATH")]
+    test_report_json: Option<PathArg>,
+
     /// Additional arguments passed to the test executor.
     ///
     /// Test executor is expected to have `--env` flag to pass environment variables.
@@ -190,6 +226,7 @@ impl StreamingCommand for TestCommand {
                             || self.unstable_allow_all_tests_on_re,
                         force_use_project_relative_paths: self.unstable_allow_all_tests_on_re,
                         force_run_from_project_root: self.unstable_allow_all_tests_on_re,
+                        disable_test_cache: self.no_test_cache,
                     }),
                 },
                 ctx.stdin()
@@ -211,6 +248,7 @@ impl StreamingCommand for TestCommand {
         let failed = statuses.failed.as_ref().context("Missing `failed`")?;
         let fatals = statuses.fatals.as_ref().context("Missing `fatals`")?;
         let skipped = statuses.skipped.as_ref().context("Missing `skipped`")?;
+        let flaky = statuses.flaky.as_ref().context("Missing `flaky`")?;
 
         let console = self.common_opts.console_opts.final_console();
         print_build_result(&console, &response.error_messages)?;
@@ -238,6 +276,10 @@ impl StreamingCommand for TestCommand {
             line.push(column.to_span_from_test_statuses(statuses)?);
             line.push(Span::new_unstyled_lossy(". "));
         }
+        if flaky.count > 0 {
+            line.push(TestCounterColumn::FLAKY.to_span_from_test_statuses(statuses)?);
+            line.push(Span::new_unstyled_lossy(". "));
+        }
         line.push(span_from_build_failure_count(
             response.error_messages.len(),
         )?);
@@ -246,7 +288,8 @@ impl StreamingCommand for TestCommand {
         print_error_counter(&console, listing_failed, "LISTINGS FAILED", "⚠")?;
         print_error_counter(&console, failed, "TESTS FAILED", "✗")?;
         print_error_counter(&console, fatals, "TESTS FATALS", "⚠")?;
-        if passed.count + failed.count + fatals.count + skipped.count == 0 {
+        print_warning_counter(&console, flaky, "TESTS FLAKY", "≈")?;
+        if passed.count + failed.count + fatals.count + skipped.count + flaky.count == 0 {
             console.print_warning("NO TESTS RAN")?;
         }
 
@@ -294,4 +337,16 @@ impl StreamingCommand for TestCommand {
     fn common_opts(&self) -> &CommonBuildConfigurationOptions {
         &self.common_opts.config_opts
     }
+
+    fn extra_subscribers(&self, ctx: &ClientCommandContext<'_>) -> Vec<Box<dyn EventSubscriber>> {
+        if self.junit_xml.is_none() && self.test_report_json.is_none() {
+            return Vec::new();
+        }
+        vec![Box::new(TestReportWriter::new(
+            self.junit_xml.as_ref().map(|p| p.resolve(&ctx.working_dir)),
+            self.test_report_json
+                .as_ref()
+                .map(|p| p.resolve(&ctx.working_dir)),
+        ))]
+    }
 }
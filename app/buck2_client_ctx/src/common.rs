@@ -33,6 +33,7 @@ use termwiz::istty::IsTty;
 
 use crate::final_console::FinalConsole;
 use crate::path_arg::PathArg;
+use crate::subscribers::progress_stream::ProgressStreamDest;
 use crate::subscribers::superconsole::SuperConsoleConfig;
 
 pub const EVENT_LOG: &str = "--event-log";
@@ -128,6 +129,12 @@ pub struct CommonDaemonCommandOptions {
     /// regarding the stability of the format.
     #[clap(long, value_name = "PATH")]
     pub(crate) unstable_write_invocation_record: Option<PathArg>,
+
+    /// Write a machine-readable progress stream, as JSON lines, to this destination. This is either
+    /// `fd:N` to write to an inherited file descriptor, the path of a Unix socket to connect to,
+    /// or the path of a file to write to. See `progress_stream.rs` for the format.
+    #[clap(long, value_name = "DEST")]
+    pub(crate) progress_stream: Option<ProgressStreamDest>,
 }
 
 impl CommonDaemonCommandOptions {
@@ -137,6 +144,7 @@ impl CommonDaemonCommandOptions {
             no_event_log: false,
             write_build_id: None,
             unstable_write_invocation_record: None,
+            progress_stream: None,
         };
         &DEFAULT
     }
@@ -567,6 +567,11 @@ impl<'a, 'b> FlushingBuckdClient<'a, 'b> {
         UnstableDiceDumpRequest,
         UnstableDiceDumpResponse
     );
+    debug_method!(
+        unstable_dice_explain_invalidation,
+        UnstableDiceExplainInvalidationRequest,
+        UnstableDiceExplainInvalidationResponse
+    );
 
     wrap_method!(status(snapshot: bool), StatusResponse);
     wrap_method!(set_log_filter(log_filter: SetLogFilterRequest), ());
@@ -33,6 +33,7 @@ use crate::subscribers::get::get_console_with_root;
 use crate::subscribers::get::try_get_build_graph_stats;
 use crate::subscribers::get::try_get_build_id_writer;
 use crate::subscribers::get::try_get_event_log_subscriber;
+use crate::subscribers::get::try_get_progress_stream;
 use crate::subscribers::get::try_get_re_log_subscriber;
 use crate::subscribers::recorder::try_get_invocation_recorder;
 use crate::subscribers::subscriber::EventSubscriber;
@@ -70,6 +71,9 @@ fn default_subscribers<'a, T: StreamingCommand>(
     if let Some(build_id_writer) = try_get_build_id_writer(cmd.event_log_opts(), ctx)? {
         subscribers.push(build_id_writer)
     }
+    if let Some(progress_stream) = try_get_progress_stream(cmd, ctx)? {
+        subscribers.push(progress_stream)
+    }
     if let Some(build_graph_stats) = try_get_build_graph_stats(cmd, ctx)? {
         subscribers.push(build_graph_stats)
     }
@@ -82,7 +86,7 @@ fn default_subscribers<'a, T: StreamingCommand>(
     )?;
     subscribers.push(recorder);
 
-    subscribers.extend(cmd.extra_subscribers());
+    subscribers.extend(cmd.extra_subscribers(ctx));
     Ok(subscribers)
 }
 
@@ -120,7 +124,7 @@ pub trait StreamingCommand: Sized + Send + Sync {
 
     fn common_opts(&self) -> &CommonBuildConfigurationOptions;
 
-    fn extra_subscribers(&self) -> Vec<Box<dyn EventSubscriber>> {
+    fn extra_subscribers(&self, _ctx: &ClientCommandContext<'_>) -> Vec<Box<dyn EventSubscriber>> {
         vec![]
     }
 
@@ -22,6 +22,7 @@ use crate::streaming::StreamingCommand;
 use crate::subscribers::build_graph_stats::BuildGraphStats;
 use crate::subscribers::build_id_writer::BuildIdWriter;
 use crate::subscribers::event_log::subscriber::EventLog;
+use crate::subscribers::progress_stream::ProgressStream;
 use crate::subscribers::re_log::ReLog;
 use crate::subscribers::simpleconsole::SimpleConsole;
 use crate::subscribers::subscriber::EventSubscriber;
@@ -140,6 +141,22 @@ pub(crate) fn try_get_build_id_writer<'a>(
     }
 }
 
+pub(crate) fn try_get_progress_stream<'a, T: StreamingCommand>(
+    cmd: &T,
+    ctx: &ClientCommandContext<'a>,
+) -> anyhow::Result<Option<Box<dyn EventSubscriber + 'a>>> {
+    if let Some(dest) = cmd.event_log_opts().progress_stream.as_ref() {
+        Ok(Some(Box::new(ProgressStream::new(
+            dest,
+            &ctx.working_dir,
+            T::COMMAND_NAME,
+            ctx.trace_id.dupe(),
+        )?)))
+    } else {
+        Ok(None)
+    }
+}
+
 pub(crate) fn try_get_build_graph_stats<'a, T: StreamingCommand>(
     cmd: &T,
     ctx: &ClientCommandContext<'a>,
@@ -0,0 +1,485 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! A machine-readable progress stream for external UIs (IDEs, dashboards), so that they don't
+//! have to scrape the console.
+//!
+//! The stream is made of JSON objects, one per line. Every object has a `version` field, which is
+//! [`PROGRESS_STREAM_VERSION`], and a `type` field. Fields are only ever added within a version;
+//! consumers should ignore fields and types they don't know. The types are:
+//!
+//! * `command_start`: `{"command", "trace_id"}`.
+//! * `action_start`: `{"id", "action"}`, where `id` identifies the action in the matching
+//!   `action_end`.
+//! * `action_end`: `{"id", "action", "failed", "execution_kind"}`, where `execution_kind` is
+//!   e.g. `local`, `remote` or `action_cache`.
+//! * `progress`: a snapshot of the counters, sent about once a second. `{"elapsed_ms", "running",
+//!   "finished", "pending_estimate", "actions", "re", "tests"}`.
+//! * `command_end`: `{"success"}`, always the last line.
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::io::Write;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use buck2_core::fs::working_dir::WorkingDir;
+use buck2_event_observer::display;
+use buck2_event_observer::display::TargetDisplayOptions;
+use buck2_event_observer::event_observer::DebugEventObserverExtra;
+use buck2_event_observer::event_observer::EventObserver;
+use buck2_event_observer::pending_estimate::pending_estimate;
+use buck2_events::BuckEvent;
+use buck2_wrapper_common::invocation_id::TraceId;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::path_arg::PathArg;
+use crate::subscribers::subscriber::EventSubscriber;
+use crate::subscribers::subscriber::Tick;
+
+/// Bumped on incompatible changes to the format.
+pub const PROGRESS_STREAM_VERSION: u32 = 1;
+
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, thiserror::Error)]
+pub enum ProgressStreamError {
+    #[error("Invalid file descriptor in progress stream destination `{0}`")]
+    InvalidFd(String),
+    #[cfg(not(unix))]
+    #[error("Writing the progress stream to a file descriptor is only supported on Unix")]
+    FdNotSupported,
+}
+
+/// Where to write the progress stream, as given to `--progress-stream`.
+#[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum ProgressStreamDest {
+    /// An inherited file descriptor, given as `fd:N`.
+    Fd(i32),
+    /// A Unix socket to connect to, or otherwise a file to create.
+    Path(PathArg),
+}
+
+impl FromStr for ProgressStreamDest {
+    type Err = ProgressStreamError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.strip_prefix("fd:") {
+            Some(fd) => fd
+                .parse()
+                .map(ProgressStreamDest::Fd)
+                .map_err(|_| ProgressStreamError::InvalidFd(s.to_owned())),
+            None => Ok(ProgressStreamDest::Path(
+                PathArg::from_str(s).expect("PathBuf parsing is infallible"),
+            )),
+        }
+    }
+}
+
+impl ProgressStreamDest {
+    fn open(&self, working_dir: &WorkingDir) -> anyhow::Result<Box<dyn Write + Send>> {
+        match self {
+            ProgressStreamDest::Fd(fd) => {
+                #[cfg(unix)]
+                {
+                    use std::os::unix::io::FromRawFd;
+
+                    // SAFETY: the fd was handed to us by whoever invoked buck2 for this purpose.
+                    Ok(Box::new(unsafe { File::from_raw_fd(*fd) }))
+                }
+                #[cfg(not(unix))]
+                {
+                    let _unused = fd;
+                    Err(ProgressStreamError::FdNotSupported.into())
+                }
+            }
+            ProgressStreamDest::Path(path) => {
+                let path = path.resolve(working_dir);
+
+                #[cfg(unix)]
+                {
+                    use std::os::unix::fs::FileTypeExt;
+
+                    if std::fs::metadata(&path).map_or(false, |m| m.file_type().is_socket()) {
+                        let socket = std::os::unix::net::UnixStream::connect(&path)
+                            .with_context(|| format!("Error connecting to `{}`", path))?;
+                        return Ok(Box::new(socket));
+                    }
+                }
+
+                let file =
+                    File::create(&path).with_context(|| format!("Error creating `{}`", path))?;
+                Ok(Box::new(file))
+            }
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Message<'a> {
+    version: u32,
+    #[serde(flatten)]
+    data: MessageData<'a>,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum MessageData<'a> {
+    CommandStart {
+        command: &'a str,
+        trace_id: String,
+    },
+    ActionStart {
+        id: String,
+        action: String,
+    },
+    ActionEnd {
+        id: String,
+        action: String,
+        failed: bool,
+        /// E.g. `local`, `remote` or `action_cache`.
+        execution_kind: String,
+    },
+    Progress(Progress<'a>),
+    CommandEnd {
+        success: bool,
+    },
+}
+
+#[derive(Serialize)]
+struct Progress<'a> {
+    elapsed_ms: u128,
+    /// Spans (actions, analysis, loads...) currently running.
+    running: u64,
+    finished: u64,
+    /// Estimate of the work that hasn't started yet.
+    pending_estimate: u64,
+    actions: ActionProgress,
+    re: Option<ReProgress<'a>>,
+    tests: Option<TestProgress>,
+}
+
+#[derive(Serialize)]
+struct ActionProgress {
+    local: u64,
+    remote: u64,
+    cached: u64,
+    fallback: u64,
+    cache_hit_percentage: u8,
+}
+
+#[derive(Serialize)]
+struct ReProgress<'a> {
+    session_id: Option<&'a str>,
+    upload_bytes: u64,
+    download_bytes: u64,
+    uploads_in_progress: u32,
+    downloads_in_progress: u32,
+    executes_in_progress: u32,
+    executes_finished: u32,
+}
+
+#[derive(Serialize)]
+struct TestProgress {
+    discovered: u64,
+    pass: u64,
+    fail: u64,
+    fatal: u64,
+    skipped: u64,
+    timeout: u64,
+}
+
+/// Writes the progress stream described in the module documentation.
+pub(crate) struct ProgressStream {
+    command: &'static str,
+    observer: EventObserver<DebugEventObserverExtra>,
+    /// `None` once writing failed, e.g. because the reader went away: the UI going away shouldn't
+    /// fail the build.
+    out: Option<BufWriter<Box<dyn Write + Send>>>,
+    start: Instant,
+    last_progress: Option<Instant>,
+}
+
+impl ProgressStream {
+    pub(crate) fn new(
+        dest: &ProgressStreamDest,
+        working_dir: &WorkingDir,
+        command: &'static str,
+        trace_id: TraceId,
+    ) -> anyhow::Result<Self> {
+        Ok(Self::with_writer(
+            dest.open(working_dir)
+                .context("Error opening the progress stream")?,
+            command,
+            trace_id,
+        ))
+    }
+
+    fn with_writer(out: Box<dyn Write + Send>, command: &'static str, trace_id: TraceId) -> Self {
+        Self {
+            command,
+            observer: EventObserver::new(trace_id),
+            out: Some(BufWriter::new(out)),
+            start: Instant::now(),
+            last_progress: None,
+        }
+    }
+
+    fn write(&mut self, data: MessageData) {
+        write_message(&mut self.out, data)
+    }
+
+    fn flush(&mut self) {
+        if let Some(out) = &mut self.out {
+            if let Err(e) = out.flush() {
+                disable(&mut self.out, e.into());
+            }
+        }
+    }
+
+    fn handle_event(&mut self, event: &Arc<BuckEvent>) -> anyhow::Result<()> {
+        self.observer.observe(Instant::now(), event)?;
+
+        use buck2_data::buck_event::Data;
+        let id = || event.span_id().map(|id| id.to_string()).unwrap_or_default();
+
+        match event.data() {
+            Data::SpanStart(start) => match &start.data {
+                Some(buck2_data::span_start_event::Data::Command(..)) => {
+                    let trace_id = event.trace_id()?.to_string();
+                    self.write(MessageData::CommandStart {
+                        command: self.command,
+                        trace_id,
+                    });
+                }
+                Some(buck2_data::span_start_event::Data::ActionExecution(action)) => {
+                    let action = display::display_action_identity(
+                        action.key.as_ref(),
+                        action.name.as_ref(),
+                        TargetDisplayOptions::for_log(),
+                    )?;
+                    self.write(MessageData::ActionStart { id: id(), action });
+                }
+                _ => {}
+            },
+            Data::SpanEnd(end) => {
+                if let Some(buck2_data::span_end_event::Data::ActionExecution(action)) = &end.data {
+                    let execution_kind =
+                        buck2_data::ActionExecutionKind::from_i32(action.execution_kind)
+                            .unwrap_or(buck2_data::ActionExecutionKind::NotSet)
+                            .as_str_name()
+                            .trim_start_matches("ACTION_EXECUTION_KIND_")
+                            .to_lowercase();
+                    let display = display::display_action_identity(
+                        action.key.as_ref(),
+                        action.name.as_ref(),
+                        TargetDisplayOptions::for_log(),
+                    )?;
+                    self.write(MessageData::ActionEnd {
+                        id: id(),
+                        action: display,
+                        failed: action.failed,
+                        execution_kind,
+                    });
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn write_progress(&mut self) {
+        let observer = &self.observer;
+        let spans = observer.spans();
+        let action_stats = observer.action_stats();
+
+        let re = observer
+            .two_snapshots()
+            .last
+            .as_ref()
+            .map(|(_, s)| ReProgress {
+                session_id: observer.re_state().session_id(),
+                upload_bytes: s.re_upload_bytes,
+                download_bytes: s.re_download_bytes,
+                uploads_in_progress: s.re_uploads_started.saturating_sub(
+                    s.re_uploads_finished_successfully + s.re_uploads_finished_with_error,
+                ),
+                downloads_in_progress: s.re_downloads_started.saturating_sub(
+                    s.re_downloads_finished_successfully + s.re_downloads_finished_with_error,
+                ),
+                executes_in_progress: s.re_executes_started.saturating_sub(
+                    s.re_executes_finished_successfully + s.re_executes_finished_with_error,
+                ),
+                executes_finished: s.re_executes_finished_successfully
+                    + s.re_executes_finished_with_error,
+            });
+
+        let tests = observer.session_info().test_session.as_ref().map(|_| {
+            let t = observer.test_state();
+            TestProgress {
+                discovered: t.discovered,
+                pass: t.pass,
+                fail: t.fail,
+                fatal: t.fatal,
+                skipped: t.skipped,
+                timeout: t.timeout,
+            }
+        });
+
+        let progress = Progress {
+            elapsed_ms: self.start.elapsed().as_millis(),
+            running: spans.roots_ongoing() as u64,
+            finished: spans.roots_completed() as u64,
+            pending_estimate: pending_estimate(spans.roots(), observer.extra().dice_state()),
+            actions: ActionProgress {
+                local: action_stats.local_actions,
+                remote: action_stats.remote_actions,
+                cached: action_stats.total_cached_actions(),
+                fallback: action_stats.fallback_actions,
+                cache_hit_percentage: action_stats.total_cache_hit_percentage(),
+            },
+            re,
+            tests,
+        };
+
+        write_message(&mut self.out, MessageData::Progress(progress));
+        self.last_progress = Some(Instant::now());
+    }
+}
+
+fn write_message(out: &mut Option<BufWriter<Box<dyn Write + Send>>>, data: MessageData) {
+    let writer = match out {
+        Some(writer) => writer,
+        None => return,
+    };
+    let message = Message {
+        version: PROGRESS_STREAM_VERSION,
+        data,
+    };
+    let res: anyhow::Result<()> = try {
+        serde_json::to_writer(&mut *writer, &message)?;
+        writer.write_all(b"\n")?;
+    };
+    if let Err(e) = res {
+        disable(out, e);
+    }
+}
+
+fn disable(out: &mut Option<BufWriter<Box<dyn Write + Send>>>, error: anyhow::Error) {
+    tracing::warn!(
+        "Disabling the progress stream after a write error: {:#}",
+        error
+    );
+    *out = None;
+}
+
+#[async_trait]
+impl EventSubscriber for ProgressStream {
+    async fn handle_events(&mut self, events: &[Arc<BuckEvent>]) -> anyhow::Result<()> {
+        for event in events {
+            self.handle_event(event)?;
+        }
+        self.flush();
+        Ok(())
+    }
+
+    async fn tick(&mut self, _tick: &Tick) -> anyhow::Result<()> {
+        if self
+            .last_progress
+            .map_or(true, |last| last.elapsed() >= PROGRESS_INTERVAL)
+        {
+            self.write_progress();
+            self.flush();
+        }
+        Ok(())
+    }
+
+    async fn handle_command_result(
+        &mut self,
+        result: &buck2_cli_proto::CommandResult,
+    ) -> anyhow::Result<()> {
+        let success = !matches!(
+            result.result,
+            Some(buck2_cli_proto::command_result::Result::Error(..)) | None
+        );
+        self.write_progress();
+        self.write(MessageData::CommandEnd { success });
+        self.flush();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuf {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_parse_dest() {
+        assert_eq!(
+            ProgressStreamDest::from_str("fd:3").unwrap(),
+            ProgressStreamDest::Fd(3)
+        );
+        assert!(ProgressStreamDest::from_str("fd:x").is_err());
+        assert_eq!(
+            ProgressStreamDest::from_str("out.jsonl").unwrap(),
+            ProgressStreamDest::Path(PathArg::from_str("out.jsonl").unwrap())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_command_end() -> anyhow::Result<()> {
+        let buf = SharedBuf::default();
+        let mut stream =
+            ProgressStream::with_writer(Box::new(buf.clone()), "build", TraceId::null());
+        stream
+            .handle_command_result(&buck2_cli_proto::CommandResult {
+                result: Some(buck2_cli_proto::command_result::Result::BuildResponse(
+                    Default::default(),
+                )),
+            })
+            .await?;
+
+        let out = String::from_utf8(buf.0.lock().unwrap().clone())?;
+        let lines: Vec<serde_json::Value> = out
+            .lines()
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()?;
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["version"], PROGRESS_STREAM_VERSION);
+        assert_eq!(lines[0]["type"], "progress");
+        assert_eq!(lines[0]["running"], 0);
+        assert_eq!(lines[0]["actions"]["cache_hit_percentage"], 0);
+        assert_eq!(
+            lines[1],
+            serde_json::json!({"version": PROGRESS_STREAM_VERSION, "type": "command_end", "success": true})
+        );
+        Ok(())
+    }
+}
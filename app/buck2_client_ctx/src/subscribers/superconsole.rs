@@ -51,6 +51,11 @@ use crate::subscribers::superconsole::commands::CommandsComponent;
 use crate::subscribers::superconsole::debug_events::DebugEventsComponent;
 use crate::subscribers::superconsole::debugger::StarlarkDebuggerComponent;
 use crate::subscribers::superconsole::dice::DiceComponent;
+use crate::subscribers::superconsole::interactive::visible_roots;
+use crate::subscribers::superconsole::interactive::ActionDetailsComponent;
+use crate::subscribers::superconsole::interactive::InteractiveState;
+use crate::subscribers::superconsole::interactive::InteractiveStatusComponent;
+use crate::subscribers::superconsole::interactive::Pane;
 use crate::subscribers::superconsole::io::IoHeader;
 use crate::subscribers::superconsole::re::ReHeader;
 use crate::subscribers::superconsole::session_info::SessionInfoComponent;
@@ -63,6 +68,7 @@ mod common;
 pub(crate) mod debug_events;
 mod debugger;
 pub(crate) mod dice;
+mod interactive;
 pub(crate) mod io;
 mod re;
 pub mod session_info;
@@ -112,6 +118,7 @@ pub struct SuperConsoleState {
     /// This contains the SpanTracker, which is why it's part of the SuperConsoleState.
     simple_console: SimpleConsole<DebugEventObserverExtra>,
     config: SuperConsoleConfig,
+    interactive: InteractiveState,
 }
 
 #[derive(Clone)]
@@ -162,6 +169,13 @@ impl<'s> Component for BuckRootComponent<'s> {
             },
             mode,
         )?;
+        draw.draw(&InteractiveStatusComponent { state: self.state }, mode)?;
+
+        if mode == DrawMode::Normal && self.state.interactive.pane != Pane::Actions {
+            self.draw_focused_pane(&mut draw, mode)?;
+            return Ok(draw.finish());
+        }
+
         draw.draw(
             &ReHeader {
                 super_console_config: &self.state.config,
@@ -216,11 +230,51 @@ impl<'s> Component for BuckRootComponent<'s> {
             mode,
         )?;
         draw.draw(&TimedList::new(&CUTOFFS, self.header, self.state), mode)?;
+        draw.draw(&ActionDetailsComponent { state: self.state }, mode)?;
 
         Ok(draw.finish())
     }
 }
 
+impl<'s> BuckRootComponent<'s> {
+    /// Draws only the pane that was selected with `tab`, whether or not it is otherwise enabled.
+    fn draw_focused_pane(&self, draw: &mut DrawVertical, mode: DrawMode) -> anyhow::Result<()> {
+        let config = SuperConsoleConfig {
+            enable_dice: true,
+            enable_detailed_re: true,
+            enable_io: true,
+            ..self.state.config.clone()
+        };
+        let observer = &self.state.simple_console.observer;
+        match self.state.interactive.pane {
+            Pane::Actions => {}
+            Pane::Dice => draw.draw(
+                &DiceComponent {
+                    super_console_config: &config,
+                    dice_state: observer.extra().dice_state(),
+                },
+                mode,
+            )?,
+            Pane::Re => draw.draw(
+                &ReHeader {
+                    super_console_config: &config,
+                    re_state: observer.re_state(),
+                    two_snapshots: observer.two_snapshots(),
+                },
+                mode,
+            )?,
+            Pane::Io => draw.draw(
+                &IoHeader {
+                    super_console_config: &config,
+                    two_snapshots: observer.two_snapshots(),
+                },
+                mode,
+            )?,
+        }
+        Ok(())
+    }
+}
+
 impl StatefulSuperConsole {
     pub const FALLBACK_SIZE: Dimensions = Dimensions {
         width: 100,
@@ -339,6 +393,7 @@ impl SuperConsoleState {
             time_speed: TimeSpeed::new(replay_speed)?,
             simple_console: SimpleConsole::with_tty(trace_id, verbosity, expect_spans),
             config,
+            interactive: InteractiveState::default(),
         })
     }
 
@@ -367,6 +422,17 @@ impl StatefulSuperConsole {
         self.handle_stderr(&format!("{what}: {on_off}, press `{key}` to revert"))
             .await
     }
+
+    /// Redraws immediately, so that interactive changes show up even when paused.
+    fn redraw(&mut self) -> anyhow::Result<()> {
+        match &mut self.super_console {
+            Some(super_console) => super_console.render(&BuckRootComponent {
+                header: &self.header,
+                state: &self.state,
+            }),
+            None => Ok(()),
+        }
+    }
 }
 
 // TODO(brasselsprouts): after deprecating filetailers, simplify these code paths
@@ -478,6 +544,11 @@ impl UnpackingEventSubscriber for StatefulSuperConsole {
     }
 
     async fn handle_console_interaction(&mut self, c: char) -> anyhow::Result<()> {
+        if self.state.interactive.editing_filter {
+            self.state.interactive.edit_filter(c);
+            return self.redraw();
+        }
+
         if c == 'd' {
             self.toggle("DICE component", 'd', |s| &mut s.state.config.enable_dice)
                 .await?;
@@ -509,6 +580,31 @@ impl UnpackingEventSubscriber for StatefulSuperConsole {
             self.state.config.max_lines = self.state.config.max_lines.saturating_add(1);
         } else if c == '-' {
             self.state.config.max_lines = self.state.config.max_lines.saturating_sub(1);
+        } else if c == 'j' || c == 'k' {
+            let visible = visible_roots(&self.state)?;
+            self.state.interactive.move_selection(&visible, c == 'j');
+            self.redraw()?;
+        } else if c == 'x' {
+            self.state.interactive.expanded = !self.state.interactive.expanded;
+            if self.state.interactive.selected.is_none() {
+                let visible = visible_roots(&self.state)?;
+                self.state.interactive.move_selection(&visible, true);
+            }
+            self.redraw()?;
+        } else if c == '\t' {
+            self.state.interactive.pane = self.state.interactive.pane.next();
+            self.redraw()?;
+        } else if c == ' ' {
+            self.state.interactive.paused = !self.state.interactive.paused;
+            self.redraw()?;
+        } else if c == '/' {
+            self.state.interactive.start_filter();
+            self.redraw()?;
+        } else if c == '\x1b' {
+            self.state.interactive.selected = None;
+            self.state.interactive.expanded = false;
+            self.state.interactive.filter = None;
+            self.redraw()?;
         } else if c == '?' || c == 'h' {
             self.handle_stderr(
                 "Help:\n\
@@ -520,6 +616,12 @@ impl UnpackingEventSubscriber for StatefulSuperConsole {
                 `p` = display target configurations\n\
                 `+` = show more lines\n\
                 `-` = show fewer lines\n\
+                `j`/`k` = select the next/previous action\n\
+                `x` = expand the selected action to its command and stderr\n\
+                `tab` = switch between the actions, DICE, RE and I/O panes\n\
+                `space` = pause/resume updates\n\
+                `/` = filter actions by target, `enter` to finish, `esc` to clear\n\
+                `esc` = clear the selection and filter\n\
                 `h` = show this help",
             )
             .await?;
@@ -552,6 +654,7 @@ impl UnpackingEventSubscriber for StatefulSuperConsole {
 
     async fn tick(&mut self, tick: &Tick) -> anyhow::Result<()> {
         match &mut self.super_console {
+            Some(_) if self.state.interactive.paused => Ok(()),
             Some(super_console) => {
                 self.state.current_tick = tick.dupe();
                 super_console.render(&BuckRootComponent {
@@ -0,0 +1,303 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Keyboard-driven navigation of the superconsole while a command is running: selecting an
+//! action to see its command and stderr, focusing a single pane, pausing and filtering.
+
+use buck2_event_observer::display;
+use buck2_event_observer::display::TargetDisplayOptions;
+use buck2_event_observer::span_tracker::BuckEventSpanHandle;
+use buck2_event_observer::what_ran::CommandReproducer;
+use buck2_event_observer::what_ran::WhatRanOptions;
+use buck2_events::span::SpanId;
+use superconsole::style::Stylize;
+use superconsole::Component;
+use superconsole::Dimensions;
+use superconsole::DrawMode;
+use superconsole::Line;
+use superconsole::Lines;
+use superconsole::Span;
+
+use crate::subscribers::superconsole::SuperConsoleState;
+
+/// Number of stderr lines shown for each command of the selected action.
+const STDERR_LINES: usize = 10;
+
+/// The pane that gets the whole console.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub(crate) enum Pane {
+    /// The regular view, which is also the only one where actions can be selected.
+    #[default]
+    Actions,
+    Dice,
+    Re,
+    Io,
+}
+
+impl Pane {
+    pub(crate) fn next(self) -> Self {
+        match self {
+            Pane::Actions => Pane::Dice,
+            Pane::Dice => Pane::Re,
+            Pane::Re => Pane::Io,
+            Pane::Io => Pane::Actions,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Pane::Actions => "actions",
+            Pane::Dice => "DICE",
+            Pane::Re => "RE",
+            Pane::Io => "I/O",
+        }
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct InteractiveState {
+    pub(crate) pane: Pane,
+    /// The root span whose details are shown when `expanded` is set.
+    pub(crate) selected: Option<SpanId>,
+    pub(crate) expanded: bool,
+    /// When paused the console is not redrawn, so that it can be read.
+    pub(crate) paused: bool,
+    /// Only show roots whose description contains this.
+    pub(crate) filter: Option<String>,
+    /// Whether key presses go to the filter rather than being commands.
+    pub(crate) editing_filter: bool,
+}
+
+impl InteractiveState {
+    /// Handles a key press while the filter is being edited. Enter finishes editing, Escape
+    /// removes the filter.
+    pub(crate) fn edit_filter(&mut self, c: char) {
+        match c {
+            '\n' | '\r' => {
+                self.editing_filter = false;
+                if self.filter.as_ref().map_or(false, |f| f.is_empty()) {
+                    self.filter = None;
+                }
+            }
+            '\x1b' => {
+                self.editing_filter = false;
+                self.filter = None;
+            }
+            '\x7f' | '\x08' => {
+                if let Some(filter) = &mut self.filter {
+                    filter.pop();
+                }
+            }
+            c if !c.is_control() => self.filter.get_or_insert_with(String::new).push(c),
+            _ => {}
+        }
+    }
+
+    pub(crate) fn start_filter(&mut self) {
+        self.editing_filter = true;
+        self.filter.get_or_insert_with(String::new);
+    }
+
+    pub(crate) fn root_matches(
+        &self,
+        root: &BuckEventSpanHandle,
+        display_platform: bool,
+    ) -> anyhow::Result<bool> {
+        let filter = match &self.filter {
+            Some(filter) => filter,
+            None => return Ok(true),
+        };
+        let description = display::display_event(
+            &root.info().event,
+            TargetDisplayOptions::for_console(display_platform),
+        )?;
+        Ok(description.contains(filter.as_str()))
+    }
+
+    /// Moves the selection to the next (or previous) of the `visible` roots, wrapping around.
+    pub(crate) fn move_selection(&mut self, visible: &[SpanId], forward: bool) {
+        if visible.is_empty() {
+            self.selected = None;
+            return;
+        }
+        let current = self
+            .selected
+            .and_then(|selected| visible.iter().position(|id| *id == selected));
+        let next = match (current, forward) {
+            (None, true) => 0,
+            (None, false) => visible.len() - 1,
+            (Some(i), true) => (i + 1) % visible.len(),
+            (Some(i), false) => (i + visible.len() - 1) % visible.len(),
+        };
+        self.selected = Some(visible[next]);
+    }
+
+    fn is_default(&self) -> bool {
+        self.pane == Pane::Actions
+            && self.selected.is_none()
+            && !self.paused
+            && self.filter.is_none()
+    }
+}
+
+/// The roots that are shown given the current filter, in display order.
+pub(crate) fn visible_roots(state: &SuperConsoleState) -> anyhow::Result<Vec<SpanId>> {
+    let display_platform = state.config.display_platform;
+    let mut visible = Vec::new();
+    for root in state.simple_console.observer().spans().iter_roots() {
+        if state.interactive.root_matches(&root, display_platform)? {
+            visible.extend(root.info().event.span_id());
+        }
+    }
+    Ok(visible)
+}
+
+/// Shows the state of the interactive controls, if any are in use.
+pub(crate) struct InteractiveStatusComponent<'s> {
+    pub(crate) state: &'s SuperConsoleState,
+}
+
+impl<'s> Component for InteractiveStatusComponent<'s> {
+    fn draw_unchecked(&self, _dimensions: Dimensions, mode: DrawMode) -> anyhow::Result<Lines> {
+        let interactive = &self.state.interactive;
+        if mode == DrawMode::Final || (interactive.is_default() && !interactive.editing_filter) {
+            return Ok(Lines::new());
+        }
+
+        let mut parts = vec![format!("Pane: {}", interactive.pane.name())];
+        if interactive.paused {
+            parts.push("PAUSED (press space to resume)".to_owned());
+        }
+        if let Some(filter) = &interactive.filter {
+            let cursor = if interactive.editing_filter { "_" } else { "" };
+            parts.push(format!("Filter: {}{}", filter, cursor));
+        }
+        Ok(Lines(vec![Line::from_iter([Span::new_styled(
+            parts.join(" | ").reverse(),
+        )?])]))
+    }
+}
+
+/// Shows the commands run by the selected action and the end of their stderr.
+pub(crate) struct ActionDetailsComponent<'s> {
+    pub(crate) state: &'s SuperConsoleState,
+}
+
+impl<'s> ActionDetailsComponent<'s> {
+    fn draw_span(&self, span: &BuckEventSpanHandle, lines: &mut Vec<Line>) {
+        let event = &span.info().event;
+        if let Some(repro) =
+            CommandReproducer::from_buck_data(event.data(), &WhatRanOptions::default())
+        {
+            lines.push(Line::from_iter([Span::new_styled_lossy(
+                format!("{}: {}", repro.executor(), repro.as_human_readable()).bold(),
+            )]));
+            let stderr = event.span_id().and_then(|id| {
+                self.state
+                    .simple_console
+                    .observer()
+                    .extra()
+                    .command_stderr()
+                    .get(id)
+            });
+            if let Some(stderr) = stderr {
+                let stderr_lines: Vec<_> = stderr.lines().collect();
+                for line in &stderr_lines[stderr_lines.len().saturating_sub(STDERR_LINES)..] {
+                    let mut line = Line::sanitized(line);
+                    line.push_front(Span::padding(2));
+                    lines.push(line);
+                }
+            }
+        }
+        for child in span.children() {
+            self.draw_span(&child, lines);
+        }
+    }
+}
+
+impl<'s> Component for ActionDetailsComponent<'s> {
+    fn draw_unchecked(&self, _dimensions: Dimensions, mode: DrawMode) -> anyhow::Result<Lines> {
+        let interactive = &self.state.interactive;
+        let selected = match interactive.selected {
+            Some(selected) if interactive.expanded && mode == DrawMode::Normal => selected,
+            _ => return Ok(Lines::new()),
+        };
+        let root = self
+            .state
+            .simple_console
+            .observer()
+            .spans()
+            .iter_roots()
+            .find(|root| root.info().event.span_id() == Some(selected));
+        let root = match root {
+            Some(root) => root,
+            // The action finished.
+            None => return Ok(Lines::new()),
+        };
+
+        let mut lines = vec![Line::from_iter([Span::new_styled(
+            display::display_event(
+                &root.info().event,
+                TargetDisplayOptions::for_console(self.state.config.display_platform),
+            )?
+            .bold(),
+        )?])];
+        let header_len = lines.len();
+        self.draw_span(&root, &mut lines);
+        if lines.len() == header_len {
+            lines.push(Line::unstyled("  (no command running yet)")?);
+        }
+        Ok(Lines(lines))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edit_filter() {
+        let mut state = InteractiveState::default();
+        state.start_filter();
+        for c in "foo:barx".chars() {
+            state.edit_filter(c);
+        }
+        state.edit_filter('\x7f');
+        state.edit_filter('\r');
+        assert!(!state.editing_filter);
+        assert_eq!(state.filter.as_deref(), Some("foo:bar"));
+
+        state.start_filter();
+        state.edit_filter('\x1b');
+        assert_eq!(state.filter, None);
+
+        // An empty filter is dropped.
+        state.start_filter();
+        state.edit_filter('\n');
+        assert_eq!(state.filter, None);
+    }
+
+    #[test]
+    fn test_move_selection() {
+        let ids = [SpanId::new(), SpanId::new(), SpanId::new()];
+        let mut state = InteractiveState::default();
+        state.move_selection(&ids, true);
+        assert_eq!(state.selected, Some(ids[0]));
+        state.move_selection(&ids, false);
+        assert_eq!(state.selected, Some(ids[2]));
+        state.move_selection(&ids, true);
+        assert_eq!(state.selected, Some(ids[0]));
+
+        // If the selected root goes away, start over.
+        state.move_selection(&ids[1..], true);
+        assert_eq!(state.selected, Some(ids[1]));
+        state.move_selection(&[], true);
+        assert_eq!(state.selected, None);
+    }
+}
@@ -165,6 +165,8 @@ impl<'c> Component for TimedListBody<'c> {
 
         let spans = observer.spans();
 
+        let interactive = &self.state.interactive;
+
         let mut roots = spans.iter_roots();
 
         let mut builder = Table::new();
@@ -172,18 +174,32 @@ impl<'c> Component for TimedListBody<'c> {
         let mut first_not_rendered = None;
 
         for root in &mut roots {
-            let rows = self.draw_root(&root)?;
+            if !interactive.root_matches(&root, config.display_platform)? {
+                continue;
+            }
+
+            let mut rows = self.draw_root(&root)?;
 
             if builder.len() + rows.len() >= max_lines {
                 first_not_rendered = Some(root);
                 break;
             }
 
+            if interactive.selected.is_some() && interactive.selected == root.info().event.span_id()
+            {
+                rows[0].select();
+            }
+
             builder.rows.extend(rows.into_iter().map(Row::from));
         }
 
         // Add remaining unshown tasks, if any.
-        let more = roots.len() as u64 + first_not_rendered.map_or(0, |_| 1);
+        let mut more = first_not_rendered.map_or(0, |_| 1);
+        for root in roots {
+            if interactive.root_matches(&root, config.display_platform)? {
+                more += 1;
+            }
+        }
 
         if more > 0 {
             let remaining = format!("... and {} more currently executing", more);
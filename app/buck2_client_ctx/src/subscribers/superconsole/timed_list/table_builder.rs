@@ -144,6 +144,12 @@ impl TimedRow {
         let time = Line::from_iter([Span::new_styled(styled_for_delay(time, age, cutoffs))?]);
         Ok(Self { event: line, time })
     }
+
+    /// Marks this row as the one selected interactively.
+    pub(crate) fn select(&mut self) {
+        self.event
+            .push_front(Span::new_styled_lossy("> ".to_owned().bold()));
+    }
 }
 
 /// This component echoes the `Lines` that have been stored in it.
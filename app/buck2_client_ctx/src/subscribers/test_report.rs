@@ -0,0 +1,303 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Writes the test results of a `buck2 test` as JUnit XML and/or JSON for CI systems to ingest.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use buck2_core::fs::async_fs_util;
+use buck2_core::fs::paths::abs_path::AbsPathBuf;
+use buck2_event_observer::display::display_configured_target_label;
+use buck2_event_observer::display::TargetDisplayOptions;
+use buck2_events::BuckEvent;
+use buck2_test_api::data::TestStatus;
+use serde::Serialize;
+
+use crate::subscribers::subscriber::EventSubscriber;
+
+pub struct TestReportWriter {
+    junit_xml: Option<AbsPathBuf>,
+    json: Option<AbsPathBuf>,
+    results: Vec<TestReportEntry>,
+}
+
+#[derive(Serialize)]
+struct TestReport<'a> {
+    results: &'a [TestReportEntry],
+}
+
+#[derive(Serialize)]
+struct TestReportEntry {
+    /// Configured label of the test target, if the result belongs to one.
+    target: Option<String>,
+    name: String,
+    #[serde(serialize_with = "serialize_status")]
+    status: TestStatus,
+    duration_secs: Option<f64>,
+    msg: Option<String>,
+    details: String,
+    stdout: String,
+    stderr: String,
+}
+
+fn serialize_status<S: serde::Serializer>(
+    status: &TestStatus,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(&format_args!("{:?}", status))
+}
+
+/// How a result is represented in JUnit XML.
+#[derive(Clone, Copy, PartialEq)]
+enum JunitOutcome {
+    Pass,
+    Failure,
+    Error,
+    Skipped,
+}
+
+impl JunitOutcome {
+    /// `None` for results that aren't a final test outcome.
+    fn for_status(status: &TestStatus) -> Option<Self> {
+        match status {
+            TestStatus::PASS | TestStatus::FLAKY => Some(JunitOutcome::Pass),
+            TestStatus::FAIL | TestStatus::TIMEOUT => Some(JunitOutcome::Failure),
+            TestStatus::FATAL | TestStatus::UNKNOWN | TestStatus::LISTING_FAILED => {
+                Some(JunitOutcome::Error)
+            }
+            TestStatus::SKIP | TestStatus::OMITTED => Some(JunitOutcome::Skipped),
+            TestStatus::RERUN | TestStatus::LISTING_SUCCESS => None,
+        }
+    }
+}
+
+impl TestReportWriter {
+    pub fn new(junit_xml: Option<AbsPathBuf>, json: Option<AbsPathBuf>) -> Self {
+        Self {
+            junit_xml,
+            json,
+            results: Vec::new(),
+        }
+    }
+
+    fn handle_test_result(&mut self, result: &buck2_data::TestResult) -> anyhow::Result<()> {
+        self.results.push(TestReportEntry {
+            target: result
+                .target_label
+                .as_ref()
+                .map(|t| display_configured_target_label(t, TargetDisplayOptions::for_log()))
+                .transpose()?,
+            name: result.name.clone(),
+            status: TestStatus::try_from(result.status)?,
+            duration_secs: result
+                .duration
+                .as_ref()
+                .and_then(|d| Duration::try_from(d.clone()).ok())
+                .map(|d| d.as_secs_f64()),
+            msg: result.msg.as_ref().map(|m| m.msg.clone()),
+            details: result.details.clone(),
+            stdout: result.stdout.clone(),
+            stderr: result.stderr.clone(),
+        });
+        Ok(())
+    }
+
+    fn junit_xml(&self) -> String {
+        let mut suites: BTreeMap<&str, Vec<(&TestReportEntry, JunitOutcome)>> = BTreeMap::new();
+        for entry in &self.results {
+            if let Some(outcome) = JunitOutcome::for_status(&entry.status) {
+                suites
+                    .entry(entry.target.as_deref().unwrap_or_default())
+                    .or_default()
+                    .push((entry, outcome));
+            }
+        }
+
+        let mut xml = String::new();
+        xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        let all = suites.values().flatten().copied().collect::<Vec<_>>();
+        writeln!(xml, "<testsuites{}>", suite_attributes(&all)).unwrap();
+        for (suite, entries) in &suites {
+            writeln!(
+                xml,
+                "  <testsuite name=\"{}\"{}>",
+                escape(suite),
+                suite_attributes(entries)
+            )
+            .unwrap();
+            for (entry, outcome) in entries {
+                write!(
+                    xml,
+                    "    <testcase name=\"{}\" classname=\"{}\"",
+                    escape(&entry.name),
+                    escape(suite)
+                )
+                .unwrap();
+                if let Some(duration) = entry.duration_secs {
+                    write!(xml, " time=\"{:.3}\"", duration).unwrap();
+                }
+                xml.push_str(">\n");
+                let message = escape(entry.msg.as_deref().unwrap_or_default());
+                match outcome {
+                    JunitOutcome::Pass => {}
+                    JunitOutcome::Failure => writeln!(
+                        xml,
+                        "      <failure message=\"{}\">{}</failure>",
+                        message,
+                        escape(&entry.details)
+                    )
+                    .unwrap(),
+                    JunitOutcome::Error => writeln!(
+                        xml,
+                        "      <error message=\"{}\">{}</error>",
+                        message,
+                        escape(&entry.details)
+                    )
+                    .unwrap(),
+                    JunitOutcome::Skipped => {
+                        writeln!(xml, "      <skipped message=\"{}\"/>", message).unwrap()
+                    }
+                }
+                // Runners that don't capture the output separately only report `details`.
+                let stdout = if entry.stdout.is_empty() && entry.stderr.is_empty() {
+                    &entry.details
+                } else {
+                    &entry.stdout
+                };
+                if !stdout.is_empty() {
+                    writeln!(xml, "      <system-out>{}</system-out>", escape(stdout)).unwrap();
+                }
+                if !entry.stderr.is_empty() {
+                    writeln!(
+                        xml,
+                        "      <system-err>{}</system-err>",
+                        escape(&entry.stderr)
+                    )
+                    .unwrap();
+                }
+                xml.push_str("    </testcase>\n");
+            }
+            xml.push_str("  </testsuite>\n");
+        }
+        xml.push_str("</testsuites>\n");
+        xml
+    }
+}
+
+/// The `tests`, `failures`, `errors`, `skipped` and `time` attributes of a suite.
+fn suite_attributes(entries: &[(&TestReportEntry, JunitOutcome)]) -> String {
+    let count = |outcome| entries.iter().filter(|(_, o)| *o == outcome).count();
+    let time: f64 = entries.iter().filter_map(|(e, _)| e.duration_secs).sum();
+    format!(
+        " tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{:.3}\"",
+        entries.len(),
+        count(JunitOutcome::Failure),
+        count(JunitOutcome::Error),
+        count(JunitOutcome::Skipped),
+        time
+    )
+}
+
+/// Escapes text for use in XML content and attribute values. Characters that are not allowed in
+/// XML 1.0 at all are dropped.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c < ' ' || c == '\u{FFFE}' || c == '\u{FFFF}' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[async_trait]
+impl EventSubscriber for TestReportWriter {
+    async fn handle_events(&mut self, events: &[Arc<BuckEvent>]) -> anyhow::Result<()> {
+        for event in events {
+            if let buck2_data::buck_event::Data::Instant(instant) = event.data() {
+                if let Some(buck2_data::instant_event::Data::TestResult(result)) = &instant.data {
+                    self.handle_test_result(result)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn exit(&mut self) -> anyhow::Result<()> {
+        if let Some(path) = &self.junit_xml {
+            async_fs_util::write(path, self.junit_xml())
+                .await
+                .context("Error writing JUnit XML test report")?;
+        }
+        if let Some(path) = &self.json {
+            let json = serde_json::to_string_pretty(&TestReport {
+                results: &self.results,
+            })?;
+            async_fs_util::write(path, json)
+                .await
+                .context("Error writing JSON test report")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(target: &str, name: &str, status: TestStatus) -> TestReportEntry {
+        TestReportEntry {
+            target: Some(target.to_owned()),
+            name: name.to_owned(),
+            status,
+            duration_secs: Some(0.5),
+            msg: None,
+            details: "details <&>".to_owned(),
+            stdout: String::new(),
+            stderr: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_junit_xml() {
+        let mut writer = TestReportWriter::new(None, None);
+        writer.results = vec![
+            entry("root//:a (cfg)", "a - one", TestStatus::PASS),
+            entry("root//:a (cfg)", "a - two", TestStatus::RERUN),
+            entry("root//:a (cfg)", "a - two", TestStatus::FAIL),
+            entry("root//:b (cfg)", "b", TestStatus::SKIP),
+        ];
+        let xml = writer.junit_xml();
+        assert!(xml.contains(
+            "<testsuites tests=\"3\" failures=\"1\" errors=\"0\" skipped=\"1\" time=\"1.500\">"
+        ));
+        assert!(xml.contains(
+            "<testsuite name=\"root//:a (cfg)\" tests=\"2\" failures=\"1\" errors=\"0\" skipped=\"0\" time=\"1.000\">"
+        ));
+        assert!(xml.contains("<failure message=\"\">details &lt;&amp;&gt;</failure>"));
+        assert_eq!(1, xml.matches("a - two").count());
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!("a&quot;b\tc", escape("a\"b\tc\u{1b}"));
+    }
+}
@@ -29,20 +29,31 @@ use cmp_any::PartialEqAny;
 use derivative::Derivative;
 use derive_more::Display;
 use dice::DiceComputations;
+use dice::DiceData;
+use dice::DiceDataBuilder;
 use dice::DiceTransactionUpdater;
 use dice::Key;
+use dice::PersistentKey;
 use dupe::Dupe;
 use more_futures::cancellation::CancellationContext;
+use serde::Deserialize;
+use serde::Serialize;
 
 use crate::dice::cells::HasCellResolver;
 use crate::dice::data::HasIoProvider;
 use crate::dice::file_ops::keys::FileOpsKey;
 use crate::dice::file_ops::keys::FileOpsValue;
+use crate::dice::persistence::persistent_digest_config;
+use crate::dice::persistence::SavedCellPath;
+use crate::file_ops::FileDigest;
+use crate::file_ops::FileMetadata;
 use crate::file_ops::FileOps;
+use crate::file_ops::FileType;
 use crate::file_ops::RawDirEntry;
 use crate::file_ops::RawPathMetadata;
 use crate::file_ops::ReadDirOutput;
 use crate::file_ops::SimpleDirEntry;
+use crate::file_ops::TrackedFileDigest;
 use crate::ignores::all_cells::AllCellIgnores;
 use crate::ignores::all_cells::HasAllCellIgnores;
 use crate::io::IoProvider;
@@ -67,12 +78,32 @@ impl<'c> HasFileOps<'c> for DiceComputations {
 /// This is used as the "result" of a read_file computation so that we don't
 /// need to store the file content's in dice's cache.
 #[derive(Clone, Dupe, Allocative)]
-struct FileToken(Arc<CellPath>);
+struct FileToken {
+    path: Arc<CellPath>,
+    /// Digest of the contents when the token was computed, so that persisted tokens can be checked
+    /// against the file. `None` if the file couldn't be read.
+    digest: Option<Arc<str>>,
+}
 
 impl FileToken {
     async fn read_if_exists(&self, fs: &dyn FileOps) -> anyhow::Result<Option<String>> {
-        fs.read_file_if_exists((*self.0).as_ref()).await
+        fs.read_file_if_exists((*self.path).as_ref()).await
+    }
+}
+
+/// Identifies the contents of a file, including whether it exists.
+async fn file_contents_digest(fs: &dyn FileOps, path: CellPathRef<'_>) -> anyhow::Result<Arc<str>> {
+    let mut hasher = blake3::Hasher::new();
+    match fs.read_file_if_exists(path).await? {
+        Some(contents) => {
+            hasher.update(&[1]);
+            hasher.update(contents.as_bytes());
+        }
+        None => {
+            hasher.update(&[0]);
+        }
     }
+    Ok(Arc::from(hasher.finalize().to_hex().as_str()))
 }
 
 #[derive(Clone, Dupe, Allocative)]
@@ -86,6 +117,7 @@ pub mod keys {
     use dupe::Dupe;
 
     use crate::file_ops::FileOps;
+    use crate::file_ops::FileType;
 
     #[derive(Clone, Dupe, Display, Debug, Eq, Hash, PartialEq, Allocative)]
     #[display(fmt = "{:?}", self)]
@@ -358,10 +390,19 @@ impl Key for ReadFileKey {
     type Value = FileToken;
     async fn compute(
         &self,
-        _ctx: &mut DiceComputations,
+        ctx: &mut DiceComputations,
         _cancellations: &CancellationContext,
     ) -> Self::Value {
-        FileToken(self.0.dupe())
+        // This reads the file once more than its readers do, but files read through here are
+        // build files and their imports, which are small compared to the cost of evaluating them.
+        let digest = match get_default_file_ops(ctx).await {
+            Ok(fs) => file_contents_digest(&*fs, (*self.0).as_ref()).await.ok(),
+            Err(_) => None,
+        };
+        FileToken {
+            path: self.0.dupe(),
+            digest,
+        }
     }
 
     fn equality(_: &Self::Value, _: &Self::Value) -> bool {
@@ -369,6 +410,45 @@ impl Key for ReadFileKey {
     }
 }
 
+#[async_trait]
+impl PersistentKey for ReadFileKey {
+    const KIND: &'static str = "ReadFileKey";
+
+    fn save(&self, value: &Self::Value) -> anyhow::Result<Option<Vec<u8>>> {
+        let digest = match &value.digest {
+            Some(digest) => digest,
+            None => return Ok(None),
+        };
+        Ok(Some(serde_json::to_vec(&(
+            SavedCellPath::new((*self.0).as_ref()),
+            &**digest,
+        ))?))
+    }
+
+    fn load(data: &[u8], _global_data: &DiceData) -> anyhow::Result<(Self, Self::Value)> {
+        let (path, digest): (SavedCellPath, String) = serde_json::from_slice(data)?;
+        let path = Arc::new(path.into_cell_path()?);
+        Ok((
+            ReadFileKey(path.dupe()),
+            FileToken {
+                path,
+                digest: Some(Arc::from(digest)),
+            },
+        ))
+    }
+
+    // The file watcher does not see changes made while the daemon was not running.
+    async fn is_up_to_date(
+        &self,
+        value: &Self::Value,
+        ctx: &DiceComputations,
+    ) -> anyhow::Result<bool> {
+        let current =
+            file_contents_digest(&*get_default_file_ops(ctx).await?, (*self.0).as_ref()).await?;
+        Ok(value.digest.as_ref() == Some(&current))
+    }
+}
+
 #[derive(Clone, Display, Debug, Eq, Hash, PartialEq, Allocative)]
 struct ReadDirKey(CellPath);
 
@@ -399,6 +479,65 @@ impl Key for ReadDirKey {
     }
 }
 
+#[derive(Serialize, Deserialize)]
+struct SavedReadDir {
+    path: SavedCellPath,
+    included: Vec<(String, FileType)>,
+}
+
+#[async_trait]
+impl PersistentKey for ReadDirKey {
+    const KIND: &'static str = "ReadDirKey";
+
+    fn save(&self, value: &Self::Value) -> anyhow::Result<Option<Vec<u8>>> {
+        let value = match value {
+            Ok(value) => value,
+            Err(_) => return Ok(None),
+        };
+        Ok(Some(serde_json::to_vec(&SavedReadDir {
+            path: SavedCellPath::new(self.0.as_ref()),
+            included: value
+                .included
+                .iter()
+                .map(|e| (e.file_name.as_str().to_owned(), e.file_type.dupe()))
+                .collect(),
+        })?))
+    }
+
+    fn load(data: &[u8], _global_data: &DiceData) -> anyhow::Result<(Self, Self::Value)> {
+        let saved: SavedReadDir = serde_json::from_slice(data)?;
+        let included = saved
+            .included
+            .into_iter()
+            .map(|(file_name, file_type)| {
+                Ok(SimpleDirEntry {
+                    file_name: FileNameBuf::try_from(file_name)?,
+                    file_type,
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok((
+            ReadDirKey(saved.path.into_cell_path()?),
+            Ok(ReadDirOutput {
+                included: included.into(),
+            }),
+        ))
+    }
+
+    // The file watcher does not see changes made while the daemon was not running.
+    async fn is_up_to_date(
+        &self,
+        value: &Self::Value,
+        ctx: &DiceComputations,
+    ) -> anyhow::Result<bool> {
+        let current = get_default_file_ops(ctx)
+            .await?
+            .read_dir(self.0.as_ref())
+            .await?;
+        Ok(matches!(value, Ok(value) if *value == current))
+    }
+}
+
 #[derive(Clone, Display, Debug, Eq, Hash, PartialEq, Allocative)]
 struct PathMetadataKey(CellPath);
 
@@ -440,6 +579,83 @@ impl Key for PathMetadataKey {
     }
 }
 
+/// How the metadata of a path is persisted. Symlinks are not persisted, since their value depends
+/// on the contents of the symlink, which is not.
+#[derive(Serialize, Deserialize)]
+enum SavedPathMetadata {
+    Missing,
+    Directory,
+    File {
+        algorithm: String,
+        digest: Vec<u8>,
+        size: u64,
+        is_executable: bool,
+    },
+}
+
+#[async_trait]
+impl PersistentKey for PathMetadataKey {
+    const KIND: &'static str = "PathMetadataKey";
+
+    fn save(&self, value: &Self::Value) -> anyhow::Result<Option<Vec<u8>>> {
+        let metadata = match value {
+            Ok(None) => SavedPathMetadata::Missing,
+            Ok(Some(RawPathMetadata::Directory)) => SavedPathMetadata::Directory,
+            Ok(Some(RawPathMetadata::File(meta))) => SavedPathMetadata::File {
+                algorithm: meta.digest.raw_digest().algorithm().to_string(),
+                digest: meta.digest.raw_digest().as_bytes().to_vec(),
+                size: meta.digest.size(),
+                is_executable: meta.is_executable,
+            },
+            Ok(Some(RawPathMetadata::Symlink { .. })) | Err(_) => return Ok(None),
+        };
+        Ok(Some(serde_json::to_vec(&(
+            SavedCellPath::new(self.0.as_ref()),
+            metadata,
+        ))?))
+    }
+
+    fn load(data: &[u8], global_data: &DiceData) -> anyhow::Result<(Self, Self::Value)> {
+        let (path, metadata): (SavedCellPath, SavedPathMetadata) = serde_json::from_slice(data)?;
+        let metadata = match metadata {
+            SavedPathMetadata::Missing => None,
+            SavedPathMetadata::Directory => Some(RawPathMetadata::Directory),
+            SavedPathMetadata::File {
+                algorithm,
+                digest,
+                size,
+                is_executable,
+            } => Some(RawPathMetadata::File(FileMetadata {
+                digest: TrackedFileDigest::new(
+                    FileDigest::from_digest_bytes(algorithm.parse()?, &digest, size)?,
+                    persistent_digest_config(global_data),
+                ),
+                is_executable,
+            })),
+        };
+        Ok((PathMetadataKey(path.into_cell_path()?), Ok(metadata)))
+    }
+
+    // The file watcher does not see changes made while the daemon was not running.
+    async fn is_up_to_date(
+        &self,
+        value: &Self::Value,
+        ctx: &DiceComputations,
+    ) -> anyhow::Result<bool> {
+        let current = get_default_file_ops(ctx)
+            .await?
+            .read_path_metadata_if_exists(self.0.as_ref())
+            .await?;
+        Ok(matches!(value, Ok(value) if *value == current))
+    }
+}
+
+pub(crate) fn register_persistent_file_ops_keys(builder: &mut DiceDataBuilder) {
+    builder.register_persistent_key::<ReadFileKey>();
+    builder.register_persistent_key::<ReadDirKey>();
+    builder.register_persistent_key::<PathMetadataKey>();
+}
+
 #[async_trait]
 impl<'c> FileOps for DiceFileOps<'c> {
     async fn read_file_if_exists(
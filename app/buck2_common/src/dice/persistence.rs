@@ -0,0 +1,145 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! DICE keys whose values are persisted across daemon restarts.
+
+use std::sync::Arc;
+
+use allocative::Allocative;
+use async_trait::async_trait;
+use buck2_core::cells::cell_path::CellPath;
+use buck2_core::cells::cell_path::CellPathRef;
+use buck2_core::cells::name::CellName;
+use buck2_core::cells::paths::CellRelativePathBuf;
+use derive_more::Display;
+use dice::DiceComputations;
+use dice::DiceData;
+use dice::DiceDataBuilder;
+use dice::Key;
+use dupe::Dupe;
+use more_futures::cancellation::CancellationContext;
+use serde::Deserialize;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::cas_digest::CasDigestConfig;
+use crate::dice::cells::HasCellResolver;
+use crate::dice::file_ops::register_persistent_file_ops_keys;
+use crate::legacy_configs::dice::HasLegacyConfigs;
+use crate::package_listing::dice::register_persistent_package_listing_keys;
+use crate::result::SharedResult;
+
+#[derive(Debug, Error)]
+enum PersistenceError {
+    #[error("Cells and buckconfigs are not set")]
+    NoConfigs,
+}
+
+pub trait RegisterPersistentKeys {
+    /// Registers the keys that are persisted. File digests are restored with `digest_config`.
+    fn register_persistent_keys(&mut self, digest_config: CasDigestConfig);
+}
+
+impl RegisterPersistentKeys for DiceDataBuilder {
+    fn register_persistent_keys(&mut self, digest_config: CasDigestConfig) {
+        self.set(PersistentDigestConfig(digest_config));
+        register_persistent_file_ops_keys(self);
+        register_persistent_package_listing_keys(self);
+    }
+}
+
+struct PersistentDigestConfig(CasDigestConfig);
+
+pub(crate) fn persistent_digest_config(global_data: &DiceData) -> CasDigestConfig {
+    global_data
+        .get::<PersistentDigestConfig>()
+        .expect("set when registering persistent keys")
+        .0
+}
+
+/// Persisted values may depend on the cells and buckconfigs, which are not persisted. Restored
+/// values depend on this key instead, whose value identifies the cells and buckconfigs, so that
+/// they are invalidated when those differ from the ones the state was saved with.
+#[derive(Clone, Dupe, Display, Debug, Eq, Hash, PartialEq, Allocative)]
+#[display(fmt = "{:?}", self)]
+pub struct PersistentStateEnvironmentKey;
+
+#[async_trait]
+impl Key for PersistentStateEnvironmentKey {
+    type Value = SharedResult<Arc<str>>;
+
+    async fn compute(
+        &self,
+        ctx: &mut DiceComputations,
+        _cancellations: &CancellationContext,
+    ) -> Self::Value {
+        if !ctx.is_cell_resolver_key_set().await? || !ctx.is_legacy_configs_key_set().await? {
+            return Err(anyhow::Error::from(PersistenceError::NoConfigs).into());
+        }
+        let cells = ctx.get_cell_resolver().await?;
+        let configs = ctx.get_legacy_configs().await?;
+
+        let mut hasher = blake3::Hasher::new();
+        let mut update = |s: &str| {
+            hasher.update(s.as_bytes());
+            hasher.update(&[0]);
+        };
+        let mut cells: Vec<_> = cells.cells().collect();
+        cells.sort_by_key(|(name, _)| *name);
+        for (name, instance) in cells {
+            update(name.as_str());
+            update(instance.path().as_str());
+        }
+        for (cell, config) in configs.iter() {
+            update(cell.as_str());
+            for (section, values) in config.iter() {
+                update(section);
+                for (key, value) in values {
+                    update(key);
+                    update(value);
+                }
+            }
+        }
+        Ok(Arc::from(hasher.finalize().to_hex().as_str()))
+    }
+
+    fn equality(x: &Self::Value, y: &Self::Value) -> bool {
+        match (x, y) {
+            (Ok(x), Ok(y)) => x == y,
+            _ => false,
+        }
+    }
+
+    fn validity(x: &Self::Value) -> bool {
+        x.is_ok()
+    }
+}
+
+/// How a `CellPath` is persisted.
+#[derive(Serialize, Deserialize)]
+pub struct SavedCellPath {
+    cell: String,
+    path: String,
+}
+
+impl SavedCellPath {
+    pub fn new(path: CellPathRef) -> Self {
+        Self {
+            cell: path.cell().as_str().to_owned(),
+            path: path.path().as_str().to_owned(),
+        }
+    }
+
+    pub fn into_cell_path(self) -> anyhow::Result<CellPath> {
+        Ok(CellPath::new(
+            CellName::unchecked_new(&self.cell)?,
+            CellRelativePathBuf::try_from(self.path)?,
+        ))
+    }
+}
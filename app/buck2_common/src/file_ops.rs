@@ -24,6 +24,8 @@ use compact_str::CompactString;
 use derive_more::Display;
 use dupe::Dupe;
 use gazebo::variants::VariantName;
+use serde::Deserialize;
+use serde::Serialize;
 
 use crate::cas_digest::CasDigest;
 use crate::cas_digest::CasDigestConfig;
@@ -39,7 +41,19 @@ enum FileOpsError {
 
 /// std::fs::FileType is an opaque type that isn't constructible. This is
 /// basically the equivalent.
-#[derive(Clone, Dupe, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Allocative)]
+#[derive(
+    Clone,
+    Dupe,
+    Eq,
+    PartialEq,
+    Ord,
+    PartialOrd,
+    Hash,
+    Debug,
+    Allocative,
+    Serialize,
+    Deserialize
+)]
 pub enum FileType {
     Directory,
     File,
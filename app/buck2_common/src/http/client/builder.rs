@@ -29,6 +29,7 @@ use tokio_rustls::TlsConnector;
 
 use super::HttpClient;
 use super::RequestClient;
+use crate::http::netrc::Netrc;
 use crate::http::proxy;
 use crate::http::stats::HttpNetworkStats;
 use crate::http::tls;
@@ -71,6 +72,7 @@ pub struct HttpClientBuilder {
     max_redirects: Option<usize>,
     supports_vpnless: bool,
     timeout_config: Option<TimeoutConfig>,
+    netrc: Option<Arc<Netrc>>,
 }
 
 impl HttpClientBuilder {
@@ -107,6 +109,7 @@ impl HttpClientBuilder {
             max_redirects: None,
             supports_vpnless: false,
             timeout_config: None,
+            netrc: None,
         })
     }
 
@@ -139,6 +142,21 @@ impl HttpClientBuilder {
             }
             _ => {}
         }
+        match &config.http.netrc_file {
+            Some(path) => {
+                builder.with_netrc(Netrc::load(Path::new(path))?);
+            }
+            None => {
+                if let Some(path) = Netrc::default_path() {
+                    match Netrc::load(&path) {
+                        Ok(netrc) => {
+                            builder.with_netrc(netrc);
+                        }
+                        Err(e) => tracing::warn!("Not using netrc credentials: {:#}", e),
+                    }
+                }
+            }
+        }
 
         Ok(builder)
     }
@@ -212,6 +230,12 @@ impl HttpClientBuilder {
         self
     }
 
+    /// Send basic authentication with the credentials from `netrc` for the host of each request.
+    pub fn with_netrc(&mut self, netrc: Netrc) -> &mut Self {
+        self.netrc = Some(Arc::new(netrc));
+        self
+    }
+
     pub fn with_max_redirects(&mut self, max_redirects: usize) -> &mut Self {
         self.max_redirects = Some(max_redirects);
         self
@@ -288,6 +312,7 @@ impl HttpClientBuilder {
             max_redirects: self.max_redirects,
             supports_vpnless: self.supports_vpnless,
             stats: HttpNetworkStats::new(),
+            netrc: self.netrc.clone(),
         }
     }
 }
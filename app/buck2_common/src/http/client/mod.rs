@@ -27,6 +27,7 @@ use hyper::Response;
 use tokio::io::AsyncReadExt;
 use tokio_util::io::StreamReader;
 
+use crate::http::netrc::Netrc;
 use crate::http::redirect::PendingRequest;
 use crate::http::redirect::RedirectEngine;
 use crate::http::stats::CountingStream;
@@ -47,13 +48,25 @@ pub struct HttpClient {
     max_redirects: Option<usize>,
     supports_vpnless: bool,
     stats: HttpNetworkStats,
+    netrc: Option<Arc<Netrc>>,
 }
 
 impl HttpClient {
     fn request_builder(&self, uri: &str) -> Builder {
-        Request::builder()
+        let builder = Request::builder()
             .uri(uri)
-            .header(http::header::USER_AGENT, DEFAULT_USER_AGENT)
+            .header(http::header::USER_AGENT, DEFAULT_USER_AGENT);
+        let credentials = self.netrc.as_ref().and_then(|netrc| {
+            let uri: Uri = uri.parse().ok()?;
+            netrc.credentials(uri.host()?)
+        });
+        match credentials {
+            Some(credentials) => builder.header(
+                http::header::AUTHORIZATION,
+                credentials.basic_authorization(),
+            ),
+            None => builder,
+        }
     }
 
     /// Send a HEAD request. Assumes no body will be returned. If one is returned, it will be ignored.
@@ -79,6 +92,22 @@ impl HttpClient {
         self.request(req).await
     }
 
+    /// Send a GET request for the resource starting at byte `offset`. Servers may ignore the
+    /// range: unless the response is `206 Partial Content`, it contains the whole resource.
+    pub async fn get_range(
+        &self,
+        uri: &str,
+        offset: u64,
+    ) -> Result<Response<BoxStream<hyper::Result<Bytes>>>, HttpError> {
+        let req = self
+            .request_builder(uri)
+            .method(Method::GET)
+            .header(http::header::RANGE, format!("bytes={}-", offset))
+            .body(Bytes::new())
+            .map_err(HttpError::BuildRequest)?;
+        self.request(req).await
+    }
+
     pub async fn post(
         &self,
         uri: &str,
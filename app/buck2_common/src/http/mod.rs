@@ -13,6 +13,7 @@ use hyper::StatusCode;
 use thiserror::Error;
 
 mod client;
+pub mod netrc;
 mod proxy;
 mod redirect;
 pub mod retries;
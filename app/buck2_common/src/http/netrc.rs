@@ -0,0 +1,196 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Per-host credentials from a [netrc](https://www.gnu.org/software/inetutils/manual/html_node/The-_002enetrc-file.html)
+//! file, sent as basic authentication.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::path::PathBuf;
+
+use allocative::Allocative;
+use anyhow::Context;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+enum NetrcError {
+    #[error("Expected a value after `{0}`")]
+    MissingValue(String),
+    #[error("`{0}` must follow `machine` or `default`")]
+    OutsideMachine(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Allocative)]
+pub struct Credentials {
+    pub login: String,
+    pub password: String,
+}
+
+impl Credentials {
+    fn empty() -> Self {
+        Self {
+            login: String::new(),
+            password: String::new(),
+        }
+    }
+
+    /// Value of the `Authorization` header for these credentials.
+    pub fn basic_authorization(&self) -> String {
+        format!(
+            "Basic {}",
+            base64::encode(format!("{}:{}", self.login, self.password))
+        )
+    }
+}
+
+#[derive(Debug, Default, Allocative)]
+pub struct Netrc {
+    machines: HashMap<String, Credentials>,
+    default: Option<Credentials>,
+}
+
+impl Netrc {
+    pub fn parse(contents: &str) -> anyhow::Result<Self> {
+        let mut netrc = Netrc::default();
+        // The machine (`None` for `default`) the tokens being read apply to, and its credentials.
+        let mut current: Option<(Option<String>, Credentials)> = None;
+
+        let mut tokens = contents
+            .lines()
+            .filter(|line| !line.trim_start().starts_with('#'))
+            .flat_map(|line| line.split_whitespace());
+        while let Some(token) = tokens.next() {
+            let mut value = || {
+                tokens
+                    .next()
+                    .ok_or_else(|| NetrcError::MissingValue(token.to_owned()))
+            };
+            match token {
+                "machine" => {
+                    let machine = value()?.to_owned();
+                    netrc.insert(current.take());
+                    current = Some((Some(machine), Credentials::empty()));
+                }
+                "default" => {
+                    netrc.insert(current.take());
+                    current = Some((None, Credentials::empty()));
+                }
+                "login" | "password" | "account" => {
+                    let value = value()?.to_owned();
+                    let (_, credentials) = current
+                        .as_mut()
+                        .ok_or_else(|| NetrcError::OutsideMachine(token.to_owned()))?;
+                    match token {
+                        "login" => credentials.login = value,
+                        "password" => credentials.password = value,
+                        _ => {}
+                    }
+                }
+                "macdef" => {
+                    // Macros are only meaningful to ftp, and run until the next blank line, which
+                    // the tokenization above loses. They are rare enough to not support.
+                    value()?;
+                }
+                _ => {}
+            }
+        }
+        netrc.insert(current);
+        Ok(netrc)
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Error reading netrc file `{}`", path.display()))?;
+        Self::parse(&contents)
+            .with_context(|| format!("Error parsing netrc file `{}`", path.display()))
+    }
+
+    /// The netrc file to use when none is configured: `$NETRC`, or `~/.netrc` if it exists.
+    pub fn default_path() -> Option<PathBuf> {
+        if let Some(path) = std::env::var_os("NETRC") {
+            return Some(PathBuf::from(path));
+        }
+        let path = dirs::home_dir()?.join(".netrc");
+        path.exists().then_some(path)
+    }
+
+    pub fn credentials(&self, host: &str) -> Option<&Credentials> {
+        self.machines.get(host).or(self.default.as_ref())
+    }
+
+    fn insert(&mut self, entry: Option<(Option<String>, Credentials)>) {
+        match entry {
+            // Like curl, the first entry for a machine wins.
+            Some((Some(machine), credentials)) => {
+                self.machines.entry(machine).or_insert(credentials);
+            }
+            Some((None, credentials)) => self.default = Some(credentials),
+            None => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+
+    #[test]
+    fn test_parse() -> anyhow::Result<()> {
+        let netrc = Netrc::parse(indoc!(
+            r#"
+            # Mirrors
+            machine mirror.example.com
+              login alice
+              password s3cret
+            machine other.example.com login bob password hunter2 account ignored
+            machine mirror.example.com login shadowed password shadowed
+            default login anonymous password guest
+            "#
+        ))?;
+
+        assert_eq!(
+            netrc.credentials("mirror.example.com"),
+            Some(&Credentials {
+                login: "alice".to_owned(),
+                password: "s3cret".to_owned(),
+            })
+        );
+        assert_eq!(
+            netrc
+                .credentials("other.example.com")
+                .map(|c| c.login.as_str()),
+            Some("bob")
+        );
+        assert_eq!(
+            netrc
+                .credentials("unknown.example.com")
+                .map(|c| c.login.as_str()),
+            Some("anonymous")
+        );
+        assert_eq!(
+            Credentials {
+                login: "alice".to_owned(),
+                password: "s3cret".to_owned(),
+            }
+            .basic_authorization(),
+            "Basic YWxpY2U6czNjcmV0"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(Netrc::parse("machine").is_err());
+        assert!(Netrc::parse("login alice").is_err());
+        assert!(Netrc::parse("machine a login").is_err());
+        assert!(Netrc::parse("").unwrap().credentials("a").is_none());
+    }
+}
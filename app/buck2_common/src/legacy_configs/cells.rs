@@ -27,6 +27,9 @@ use buck2_core::fs::project_rel_path::ProjectRelativePath;
 use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
 use gazebo::prelude::*;
 
+use crate::legacy_configs::external_cells::materialize_external_cell;
+use crate::legacy_configs::external_cells::parse_external_cells;
+use crate::legacy_configs::external_cells::ExternalCellDownloader;
 use crate::legacy_configs::init::DaemonStartupConfig;
 use crate::legacy_configs::path::BuckConfigFile;
 use crate::legacy_configs::path::DEFAULT_BUCK_CONFIG_FILES;
@@ -47,6 +50,12 @@ enum CellsError {
         like `root = .` which defines the root cell name"
     )]
     MissingRootCellName,
+    #[error(
+        "External cells can only be declared in the root buckconfig, but `{0}` declares `{1}`"
+    )]
+    ExternalCellNotInRoot(CellRootPathBuf, NonEmptyCellAlias),
+    #[error("Cell `{0}` is declared in both `[repositories]` and `[external_cells]`")]
+    ExternalCellIsRepository(NonEmptyCellAlias),
 }
 
 /// Used for creating a CellResolver in a buckv1-compatible way based on values
@@ -79,6 +88,7 @@ impl BuckConfigBasedCells {
     ) -> anyhow::Result<ImmediateConfig> {
         let opts = BuckConfigParseOptions {
             follow_includes: false,
+            external_cell_downloader: None,
         };
         let cells = Self::parse_with_file_ops_and_options(
             project_fs,
@@ -100,28 +110,34 @@ impl BuckConfigBasedCells {
         })
     }
 
-    pub fn parse(project_fs: &ProjectRoot) -> anyhow::Result<Self> {
-        Self::parse_with_file_ops(
-            project_fs,
-            &mut DefaultConfigParserFileOps {},
-            &[],
-            ProjectRelativePath::empty(),
-        )
+    /// Parses the configs of all cells, fetching external cells with `downloader` if missing.
+    pub fn parse(
+        project_fs: &ProjectRoot,
+        downloader: &dyn ExternalCellDownloader,
+    ) -> anyhow::Result<Self> {
+        Self::parse_with_config_args(project_fs, &[], ProjectRelativePath::empty(), downloader)
     }
 
     pub fn parse_with_config_args(
         project_fs: &ProjectRoot,
         config_args: &[LegacyConfigCmdArg],
         cwd: &ProjectRelativePath,
+        downloader: &dyn ExternalCellDownloader,
     ) -> anyhow::Result<Self> {
-        Self::parse_with_file_ops(
+        let opts = BuckConfigParseOptions {
+            follow_includes: true,
+            external_cell_downloader: Some(downloader),
+        };
+        Self::parse_with_file_ops_and_options(
             project_fs,
             &mut DefaultConfigParserFileOps {},
             config_args,
             cwd,
+            opts,
         )
     }
 
+    /// Parses the configs of all cells without fetching external cells.
     pub fn parse_with_file_ops(
         project_fs: &ProjectRoot,
         file_ops: &mut dyn ConfigParserFileOps,
@@ -130,6 +146,7 @@ impl BuckConfigBasedCells {
     ) -> anyhow::Result<Self> {
         let opts = BuckConfigParseOptions {
             follow_includes: true,
+            external_cell_downloader: None,
         };
         Self::parse_with_file_ops_and_options(project_fs, file_ops, config_args, cwd, opts)
     }
@@ -342,6 +359,47 @@ impl BuckConfigBasedCells {
                 return Err(CellsError::MissingRootCellName.into());
             }
 
+            for setup in parse_external_cells(&config)? {
+                if !is_root {
+                    return Err(CellsError::ExternalCellNotInRoot(path.clone(), setup.alias).into());
+                }
+                if repositories.map_or(false, |r| r.get(setup.alias.as_str()).is_some()) {
+                    return Err(CellsError::ExternalCellIsRepository(setup.alias).into());
+                }
+
+                let alias_path = match &setup.local_path {
+                    Some(local_path) => CellRootPathBuf::new(
+                        path.join_normalized(RelativePath::new(local_path))
+                            .with_context(|| {
+                                format!(
+                                    "expected `local_path` to be a relative path, but found `{}` for external cell `{}`",
+                                    local_path, setup.alias
+                                )
+                            })?,
+                    ),
+                    None => {
+                        let alias_path = setup.origin.materialized_path(&setup.alias);
+                        if let Some(downloader) = options.external_cell_downloader {
+                            materialize_external_cell(
+                                project_fs,
+                                &setup.alias,
+                                &setup.origin,
+                                &alias_path,
+                                downloader,
+                            )
+                            .with_context(|| {
+                                format!("Error materializing external cell `{}`", setup.alias)
+                            })?;
+                        }
+                        cells_aggregator.set_external(alias_path.clone(), setup.origin);
+                        alias_path
+                    }
+                };
+                root_aliases.insert(setup.alias.clone(), alias_path.clone());
+                cells_aggregator.add_cell_entry(path.clone(), setup.alias, alias_path.clone())?;
+                work.push(alias_path);
+            }
+
             if let Some(aliases) = config.get_section("repository_aliases") {
                 for (alias, destination) in aliases.iter() {
                     let alias = NonEmptyCellAlias::new(alias.to_owned())?;
@@ -853,4 +911,130 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_external_cells() -> anyhow::Result<()> {
+        let mut file_ops = TestConfigParserFileOps::new(&[
+            (
+                "/.buckconfig",
+                indoc!(
+                    r#"
+                            [repositories]
+                                root = .
+                            [external_cells]
+                                fetched = git
+                                dev = archive
+                            [external_cell_fetched]
+                                git_origin = https://example.com/fetched.git
+                                commit_hash = 0123456789abcdef0123456789abcdef01234567
+                            [external_cell_dev]
+                                url = https://example.com/dev.tar.gz
+                                sha256 = 0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef
+                                local_path = dev-checkout
+                        "#
+                ),
+            ),
+            (
+                "/dev-checkout/.buckconfig",
+                indoc!(
+                    r#"
+                            [buildfile]
+                                name = TARGETS
+                        "#
+                ),
+            ),
+        ])?;
+
+        let project_fs = create_project_filesystem();
+        // Doesn't fetch anything, so the fetched cell has no config.
+        let cells =
+            BuckConfigBasedCells::parse_immediate_config_with_file_ops(&project_fs, &mut file_ops)?;
+        let resolver = &cells.cell_resolver;
+
+        let fetched = resolver.get(CellName::testing_new("fetched"))?;
+        assert_eq!(
+            "buck-out/external_cells/git/fetched/0123456789abcdef0123456789abcdef01234567",
+            fetched.path().as_str()
+        );
+        assert_eq!("git", fetched.external().unwrap().kind());
+
+        // A local override is a regular cell.
+        let dev = resolver.get(CellName::testing_new("dev"))?;
+        assert_eq!("dev-checkout", dev.path().as_str());
+        assert!(dev.external().is_none());
+        assert_eq!(
+            vec!["TARGETS.v2", "TARGETS"],
+            dev.buildfiles().map(|n| n.as_str())
+        );
+
+        assert_eq!(
+            "fetched",
+            dev.cell_alias_resolver().resolve("fetched")?.as_str()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_external_cell_errors() -> anyhow::Result<()> {
+        let parse = |config: &str| -> anyhow::Result<()> {
+            let mut file_ops = TestConfigParserFileOps::new(&[("/.buckconfig", config)])?;
+            BuckConfigBasedCells::parse_immediate_config_with_file_ops(
+                &create_project_filesystem(),
+                &mut file_ops,
+            )?;
+            Ok(())
+        };
+
+        let err = parse(indoc!(
+            r#"
+                [repositories]
+                    root = .
+                [external_cells]
+                    foo = svn
+            "#
+        ))
+        .unwrap_err();
+        assert!(err.to_string().contains("Unknown kind `svn`"), "{:#}", err);
+
+        let err = parse(indoc!(
+            r#"
+                [repositories]
+                    root = .
+                [external_cells]
+                    foo = git
+                [external_cell_foo]
+                    git_origin = https://example.com/foo.git
+                    commit_hash = main
+            "#
+        ))
+        .unwrap_err();
+        assert!(
+            err.to_string().contains("invalid `commit_hash`"),
+            "{:#}",
+            err
+        );
+
+        let err = parse(indoc!(
+            r#"
+                [repositories]
+                    root = .
+                    foo = foo
+                [external_cells]
+                    foo = git
+                [external_cell_foo]
+                    git_origin = https://example.com/foo.git
+                    commit_hash = 0123456789abcdef0123456789abcdef01234567
+            "#
+        ))
+        .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("declared in both `[repositories]` and `[external_cells]`"),
+            "{:#}",
+            err
+        );
+
+        Ok(())
+    }
 }
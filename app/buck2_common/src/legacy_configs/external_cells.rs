@@ -0,0 +1,568 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Parsing and fetching of external cells, declared in the root `.buckconfig` as:
+//!
+//! ```text
+//! [external_cells]
+//!   foo = git
+//!   bar = archive
+//!
+//! [external_cell_foo]
+//!   git_origin = https://github.com/example/foo.git
+//!   commit_hash = 0123456789abcdef0123456789abcdef01234567
+//!
+//! [external_cell_bar]
+//!   url = https://example.com/bar-1.0.tar.gz
+//!   mirrors = https://mirror.example.com/bar-1.0.tar.gz
+//!   sha256 = ...
+//!   strip_prefix = bar-1.0
+//! ```
+//!
+//! Setting `local_path` in an `external_cell_<name>` section uses that directory (relative to the
+//! project root) as a regular cell instead, which is useful when developing the cell.
+
+use std::io::Read;
+use std::iter;
+use std::process::Command;
+use std::process::ExitStatus;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use anyhow::Context;
+use buck2_core::cells::alias::NonEmptyCellAlias;
+use buck2_core::cells::cell_root_path::CellRootPath;
+use buck2_core::cells::external::ExternalCellOrigin;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
+use buck2_core::fs::project::ProjectRoot;
+use buck2_core::fs::project_rel_path::ProjectRelativePath;
+use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
+use buck2_events::dispatch::console_message;
+use dashmap::DashMap;
+use dupe::Dupe;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use sha2::Digest;
+use sha2::Sha256;
+
+use crate::legacy_configs::LegacyBuckConfig;
+
+#[derive(Debug, thiserror::Error)]
+enum ExternalCellsError {
+    #[error("Unknown kind `{1}` for external cell `{0}`, expected `git` or `archive`")]
+    UnknownKind(NonEmptyCellAlias, String),
+    #[error("External cell `{0}` is missing `{2}` in section `[{1}]`")]
+    MissingKey(NonEmptyCellAlias, String, &'static str),
+    #[error(
+        "External cell `{0}` has invalid `{1}` value `{2}`, expected {3} lowercase hex characters"
+    )]
+    InvalidHash(NonEmptyCellAlias, &'static str, String, usize),
+    #[error("Command `{0}` failed with {1}:\n{2}")]
+    CommandFailed(String, ExitStatus, String),
+    #[error("Fetched commit `{0}` from `{1}`, but `{2}` was requested")]
+    CommitMismatch(String, Arc<str>, Arc<str>),
+    #[error("Archive `{0}` has sha256 `{1}`, but `{2}` was expected")]
+    Sha256Mismatch(Arc<str>, String, Arc<str>),
+    #[error("Archive `{0}` does not contain `{1}`")]
+    MissingStripPrefix(Arc<str>, Arc<str>),
+}
+
+pub(crate) struct ExternalCellSetup {
+    pub(crate) alias: NonEmptyCellAlias,
+    pub(crate) origin: ExternalCellOrigin,
+    /// Local directory to use instead of fetching `origin`.
+    pub(crate) local_path: Option<String>,
+}
+
+/// Reads the `[external_cells]` section, and the `external_cell_<name>` section of each cell.
+pub(crate) fn parse_external_cells(
+    config: &LegacyBuckConfig,
+) -> anyhow::Result<Vec<ExternalCellSetup>> {
+    let external_cells = match config.get_section("external_cells") {
+        Some(external_cells) => external_cells,
+        None => return Ok(Vec::new()),
+    };
+
+    let mut res = Vec::new();
+    for (alias, kind) in external_cells.iter() {
+        let alias = NonEmptyCellAlias::new(alias.to_owned())?;
+        let section = format!("external_cell_{}", alias.as_str());
+        let get = |key: &'static str| -> anyhow::Result<Arc<str>> {
+            config
+                .get(&section, key)
+                .map(Arc::from)
+                .ok_or_else(|| ExternalCellsError::MissingKey(alias.clone(), section.clone(), key))
+                .map_err(anyhow::Error::from)
+        };
+        let get_hash = |key: &'static str, len: usize| -> anyhow::Result<Arc<str>> {
+            let value = get(key)?;
+            if value.len() != len
+                || !value
+                    .bytes()
+                    .all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+            {
+                return Err(ExternalCellsError::InvalidHash(
+                    alias.clone(),
+                    key,
+                    value.as_ref().to_owned(),
+                    len,
+                )
+                .into());
+            }
+            Ok(value)
+        };
+
+        let origin = match kind.as_str() {
+            "git" => ExternalCellOrigin::Git {
+                origin: get("git_origin")?,
+                commit: get_hash("commit_hash", 40)?,
+            },
+            "archive" => ExternalCellOrigin::Archive {
+                url: get("url")?,
+                mirrors: config
+                    .get(&section, "mirrors")
+                    .map(|mirrors| {
+                        mirrors
+                            .split(',')
+                            .map(str::trim)
+                            .filter(|m| !m.is_empty())
+                            .map(Arc::from)
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+                sha256: get_hash("sha256", 64)?,
+                strip_prefix: config.get(&section, "strip_prefix").map(Arc::from),
+            },
+            kind => return Err(ExternalCellsError::UnknownKind(alias, kind.to_owned()).into()),
+        };
+        let local_path = config.get(&section, "local_path").map(|p| p.to_owned());
+
+        res.push(ExternalCellSetup {
+            alias,
+            origin,
+            local_path,
+        });
+    }
+
+    Ok(res)
+}
+
+/// Downloads the archives of external cells. The daemon implements this with its HTTP client, so
+/// that mirrors are tried in order and the `[http]` config (e.g. netrc credentials) applies.
+pub trait ExternalCellDownloader: Send + Sync {
+    /// Downloads `path` from the first of `urls` that succeeds. The sha256 of the archive is
+    /// checked once it is downloaded, so implementations don't have to.
+    fn download(
+        &self,
+        urls: &[Arc<str>],
+        sha256: &Arc<str>,
+        path: &ProjectRelativePath,
+    ) -> anyhow::Result<()>;
+}
+
+/// Locks on the external cells being materialized by this process.
+static MATERIALIZING: Lazy<DashMap<ProjectRelativePathBuf, Arc<Mutex<()>>>> =
+    Lazy::new(DashMap::new);
+
+/// Fetches `origin` into `dest`, unless it is already there. Contents are fetched into a temporary
+/// directory next to `dest` and moved into place once complete, so an interrupted fetch is never
+/// mistaken for a materialized cell.
+pub(crate) fn materialize_external_cell(
+    project_fs: &ProjectRoot,
+    alias: &NonEmptyCellAlias,
+    origin: &ExternalCellOrigin,
+    dest: &CellRootPath,
+    downloader: &dyn ExternalCellDownloader,
+) -> anyhow::Result<()> {
+    let dest_abs = project_fs.resolve(dest.as_project_relative_path());
+    if fs_util::try_exists(&dest_abs)? {
+        return Ok(());
+    }
+
+    let lock = MATERIALIZING
+        .entry(dest.as_project_relative_path().to_buf())
+        .or_default()
+        .dupe();
+    let _guard = lock.lock();
+    if fs_util::try_exists(&dest_abs)? {
+        return Ok(());
+    }
+
+    console_message(format!(
+        "Fetching external cell `{}` from {}",
+        alias, origin
+    ));
+
+    // Unique, so that a fetch by another process (or a leftover of an interrupted one) is never
+    // mixed with this one.
+    static NEXT_TMP: AtomicU64 = AtomicU64::new(0);
+    let tmp = ProjectRelativePathBuf::unchecked_new(format!(
+        "{}.{}-{}.tmp",
+        dest.as_str(),
+        std::process::id(),
+        NEXT_TMP.fetch_add(1, Ordering::Relaxed)
+    ));
+    let tmp_abs = project_fs.resolve(&tmp);
+    fs_util::remove_all(&tmp_abs)?;
+    fs_util::create_dir_all(&tmp_abs)?;
+
+    let res = fetch_into(project_fs, origin, &tmp, downloader).and_then(|fetched| {
+        match fs_util::rename(&fetched, &dest_abs) {
+            Ok(()) => Ok(()),
+            // Someone else materialized the cell first, and it never changes once materialized.
+            Err(_) if fs_util::try_exists(&dest_abs)? => Ok(()),
+            Err(e) => Err(e.context(format!(
+                "Error moving external cell into `{}`",
+                dest.as_str()
+            ))),
+        }
+    });
+    if let Err(e) = fs_util::remove_all(&tmp_abs) {
+        // Don't hide why the fetch failed behind a failure to clean up after it.
+        match &res {
+            Ok(()) => return Err(e),
+            Err(_) => tracing::warn!("Error cleaning up `{}`: {:#}", tmp_abs, e),
+        }
+    }
+    res
+}
+
+/// Fetches `origin` into `tmp`, returning the directory to use as the cell root.
+fn fetch_into(
+    project_fs: &ProjectRoot,
+    origin: &ExternalCellOrigin,
+    tmp: &ProjectRelativePath,
+    downloader: &dyn ExternalCellDownloader,
+) -> anyhow::Result<AbsNormPathBuf> {
+    let tmp_abs = project_fs.resolve(tmp);
+    match origin {
+        ExternalCellOrigin::Git { origin, commit } => {
+            fetch_git(&tmp_abs, origin, commit)?;
+            Ok(tmp_abs)
+        }
+        ExternalCellOrigin::Archive {
+            url,
+            mirrors,
+            sha256,
+            strip_prefix,
+        } => {
+            let archive = tmp.join(ForwardRelativePath::unchecked_new("__archive__"));
+            let urls: Vec<Arc<str>> = iter::once(url).chain(mirrors).cloned().collect();
+            downloader.download(&urls, sha256, &archive)?;
+            extract_archive(&project_fs.resolve(&archive), &tmp_abs, url, sha256)?;
+            match strip_prefix {
+                Some(strip_prefix) => {
+                    let fetched = tmp_abs
+                        .join_normalized(strip_prefix.as_ref())
+                        .with_context(|| format!("Invalid `strip_prefix` `{}`", strip_prefix))?;
+                    if !fs_util::try_exists(&fetched)? {
+                        return Err(ExternalCellsError::MissingStripPrefix(
+                            url.dupe(),
+                            strip_prefix.dupe(),
+                        )
+                        .into());
+                    }
+                    Ok(fetched)
+                }
+                None => Ok(tmp_abs),
+            }
+        }
+    }
+}
+
+fn fetch_git(dir: &AbsNormPath, origin: &Arc<str>, commit: &Arc<str>) -> anyhow::Result<()> {
+    let git = || {
+        let mut cmd = Command::new("git");
+        cmd.arg("-C").arg(dir.as_path());
+        cmd
+    };
+    run(git().args(["init", "-q"]))?;
+    run(git()
+        .args(["fetch", "-q", "--depth", "1"])
+        .arg(origin.as_ref())
+        .arg(commit.as_ref()))?;
+    run(git().args([
+        "-c",
+        "advice.detachedHead=false",
+        "checkout",
+        "-q",
+        "FETCH_HEAD",
+    ]))?;
+
+    let head = run(git().args(["rev-parse", "HEAD"]))?;
+    if head.trim() != commit.as_ref() {
+        return Err(ExternalCellsError::CommitMismatch(
+            head.trim().to_owned(),
+            origin.dupe(),
+            commit.dupe(),
+        )
+        .into());
+    }
+
+    // The cell is a snapshot, not a checkout that should be worked in.
+    fs_util::remove_all(dir.join_normalized(".git")?)
+}
+
+/// Checks the sha256 of `archive`, then extracts it into `dir` and deletes it.
+fn extract_archive(
+    archive: &AbsNormPath,
+    dir: &AbsNormPath,
+    url: &Arc<str>,
+    sha256: &Arc<str>,
+) -> anyhow::Result<()> {
+    let mut hasher = Sha256::new();
+    let mut file = fs_util::open_file(archive)?;
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    let actual = hex::encode(hasher.finalize());
+    if actual != sha256.as_ref() {
+        return Err(ExternalCellsError::Sha256Mismatch(url.dupe(), actual, sha256.dupe()).into());
+    }
+
+    // bsdtar (the default `tar` on macOS and Windows) also extracts zip files.
+    run(Command::new("tar")
+        .arg("-xf")
+        .arg(archive.as_path())
+        .arg("-C")
+        .arg(dir.as_path()))?;
+    fs_util::remove_file(archive)
+}
+
+/// Runs `cmd`, returning its stdout.
+fn run(cmd: &mut Command) -> anyhow::Result<String> {
+    let output = cmd
+        .output()
+        .with_context(|| format!("Error spawning `{:?}`", cmd))?;
+    if !output.status.success() {
+        return Err(ExternalCellsError::CommandFailed(
+            format!("{:?}", cmd),
+            output.status,
+            String::from_utf8_lossy(&output.stderr).into_owned(),
+        )
+        .into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+    use std::sync::Arc;
+
+    use buck2_core::cells::alias::NonEmptyCellAlias;
+    use buck2_core::cells::external::ExternalCellOrigin;
+    use buck2_core::fs::fs_util;
+    use buck2_core::fs::project::ProjectRoot;
+    use buck2_core::fs::project::ProjectRootTemp;
+    use buck2_core::fs::project_rel_path::ProjectRelativePath;
+    use dupe::Dupe;
+    use sha2::Digest;
+    use sha2::Sha256;
+
+    use crate::legacy_configs::external_cells::materialize_external_cell;
+    use crate::legacy_configs::external_cells::run;
+    use crate::legacy_configs::external_cells::ExternalCellDownloader;
+
+    /// "Downloads" by copying a local archive.
+    struct CopyDownloader {
+        fs: ProjectRoot,
+        archive: &'static str,
+    }
+
+    impl ExternalCellDownloader for CopyDownloader {
+        fn download(
+            &self,
+            urls: &[Arc<str>],
+            _sha256: &Arc<str>,
+            path: &ProjectRelativePath,
+        ) -> anyhow::Result<()> {
+            assert_eq!(
+                vec![
+                    "https://example.com/foo.tar",
+                    "https://mirror.example.com/foo.tar"
+                ],
+                urls.iter().map(|u| u.as_ref()).collect::<Vec<_>>()
+            );
+            fs_util::copy(
+                self.fs.resolve(ProjectRelativePath::new(self.archive)?),
+                self.fs.resolve(path),
+            )?;
+            Ok(())
+        }
+    }
+
+    struct UnreachableDownloader;
+
+    impl ExternalCellDownloader for UnreachableDownloader {
+        fn download(
+            &self,
+            _urls: &[Arc<str>],
+            _sha256: &Arc<str>,
+            _path: &ProjectRelativePath,
+        ) -> anyhow::Result<()> {
+            panic!("Materialized cells should not be downloaded again")
+        }
+    }
+
+    #[test]
+    fn test_materialize_archive() -> anyhow::Result<()> {
+        let temp = ProjectRootTemp::new()?;
+        let fs = temp.path();
+        temp.write_file("src/foo-1.0/.buckconfig", "[foo]\n  bar = baz\n");
+        temp.write_file("src/foo-1.0/BUCK", "");
+        run(Command::new("tar")
+            .arg("-cf")
+            .arg(fs.resolve(ProjectRelativePath::new("foo.tar")?).as_path())
+            .arg("-C")
+            .arg(fs.resolve(ProjectRelativePath::new("src")?).as_path())
+            .arg("foo-1.0"))?;
+        let sha256 = hex::encode(Sha256::digest(fs_util::read(
+            fs.resolve(ProjectRelativePath::new("foo.tar")?),
+        )?));
+
+        let alias = NonEmptyCellAlias::new("foo".to_owned())?;
+        let origin = |sha256: &str| ExternalCellOrigin::Archive {
+            url: Arc::from("https://example.com/foo.tar"),
+            mirrors: vec![Arc::from("https://mirror.example.com/foo.tar")],
+            sha256: Arc::from(sha256),
+            strip_prefix: Some(Arc::from("foo-1.0")),
+        };
+        let downloader = CopyDownloader {
+            fs: fs.dupe(),
+            archive: "foo.tar",
+        };
+
+        let wrong = origin(&"0".repeat(64));
+        let wrong_dest = wrong.materialized_path(&alias);
+        let err =
+            materialize_external_cell(fs, &alias, &wrong, &wrong_dest, &downloader).unwrap_err();
+        assert!(format!("{:#}", err).contains("has sha256"), "{:#}", err);
+        // Neither the cell nor the temporary directory are left behind.
+        let parent = fs.resolve(wrong_dest.as_project_relative_path().parent().unwrap());
+        assert_eq!(0, fs_util::read_dir(parent)?.count());
+
+        let right = origin(&sha256);
+        let dest = right.materialized_path(&alias);
+        materialize_external_cell(fs, &alias, &right, &dest, &downloader)?;
+        assert_eq!(
+            "[foo]\n  bar = baz\n",
+            fs_util::read_to_string(
+                fs.resolve(
+                    &dest
+                        .as_project_relative_path()
+                        .join_normalized(".buckconfig")?
+                )
+            )?
+        );
+        assert!(fs_util::try_exists(fs.resolve(
+            &dest.as_project_relative_path().join_normalized("BUCK")?
+        ))?);
+
+        // Already materialized, so not fetched again.
+        materialize_external_cell(fs, &alias, &right, &dest, &UnreachableDownloader)?;
+
+        // The same archive with another `strip_prefix` is another cell, so it's materialized
+        // separately rather than reusing the contents above.
+        let unstripped = ExternalCellOrigin::Archive {
+            url: Arc::from("https://example.com/foo.tar"),
+            mirrors: vec![Arc::from("https://mirror.example.com/foo.tar")],
+            sha256: Arc::from(sha256.as_str()),
+            strip_prefix: None,
+        };
+        let unstripped_dest = unstripped.materialized_path(&alias);
+        assert_ne!(dest, unstripped_dest);
+        materialize_external_cell(fs, &alias, &unstripped, &unstripped_dest, &downloader)?;
+        assert!(fs_util::try_exists(
+            fs.resolve(
+                &unstripped_dest
+                    .as_project_relative_path()
+                    .join_normalized("foo-1.0/.buckconfig")?
+            )
+        )?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_materialize_git() -> anyhow::Result<()> {
+        let temp = ProjectRootTemp::new()?;
+        let fs = temp.path();
+        temp.write_file("src/.buckconfig", "[foo]\n  bar = baz\n");
+        let git = |dir: &str| -> anyhow::Result<Command> {
+            let mut cmd = Command::new("git");
+            cmd.arg("-C")
+                .arg(fs.resolve(ProjectRelativePath::new(dir)?).as_path());
+            Ok(cmd)
+        };
+        run(git("src")?.args(["init", "-q"]))?;
+        run(git("src")?.args(["add", "."]))?;
+        run(git("src")?.args([
+            "-c",
+            "user.name=test",
+            "-c",
+            "user.email=test@example.com",
+            "-c",
+            "commit.gpgsign=false",
+            "commit",
+            "-q",
+            "-m",
+            "init",
+        ]))?;
+        let commit = run(git("src")?.args(["rev-parse", "HEAD"]))?;
+        let bare = fs.resolve(ProjectRelativePath::new("foo.git")?);
+        run(git("src")?
+            .args(["clone", "-q", "--bare", "."])
+            .arg(bare.as_path()))?;
+        // Commits are fetched by hash, which isn't advertised by the repository.
+        run(git("foo.git")?.args(["config", "uploadpack.allowAnySHA1InWant", "true"]))?;
+
+        let alias = NonEmptyCellAlias::new("foo".to_owned())?;
+        let origin = |commit: &str| ExternalCellOrigin::Git {
+            origin: Arc::from(format!("file://{}", bare.as_path().display())),
+            commit: Arc::from(commit),
+        };
+
+        let missing = origin(&"0".repeat(40));
+        let missing_dest = missing.materialized_path(&alias);
+        materialize_external_cell(fs, &alias, &missing, &missing_dest, &UnreachableDownloader)
+            .unwrap_err();
+        let parent = fs.resolve(missing_dest.as_project_relative_path().parent().unwrap());
+        assert_eq!(0, fs_util::read_dir(parent)?.count());
+
+        let right = origin(commit.trim());
+        let dest = right.materialized_path(&alias);
+        materialize_external_cell(fs, &alias, &right, &dest, &UnreachableDownloader)?;
+        assert_eq!(
+            "[foo]\n  bar = baz\n",
+            fs_util::read_to_string(
+                fs.resolve(
+                    &dest
+                        .as_project_relative_path()
+                        .join_normalized(".buckconfig")?
+                )
+            )?
+        );
+        // The cell is a snapshot, not a checkout.
+        assert!(!fs_util::try_exists(fs.resolve(
+            &dest.as_project_relative_path().join_normalized(".git")?
+        ))?);
+
+        Ok(())
+    }
+}
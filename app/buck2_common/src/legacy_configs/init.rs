@@ -52,6 +52,8 @@ pub struct HttpConfig {
     read_timeout_ms: Option<u64>,
     write_timeout_ms: Option<u64>,
     pub max_redirects: Option<usize>,
+    /// Where to read credentials from, instead of `$NETRC` or `~/.netrc`.
+    pub netrc_file: Option<String>,
 }
 
 impl HttpConfig {
@@ -60,12 +62,14 @@ impl HttpConfig {
         let read_timeout_ms = config.parse("http", "read_timeout_ms")?;
         let write_timeout_ms = config.parse("http", "write_timeout_ms")?;
         let max_redirects = config.parse("http", "max_redirects")?;
+        let netrc_file = config.get("http", "netrc_file").map(ToOwned::to_owned);
 
         Ok(Self {
             connect_timeout_ms,
             read_timeout_ms,
             write_timeout_ms,
             max_redirects,
+            netrc_file,
         })
     }
 
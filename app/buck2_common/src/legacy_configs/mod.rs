@@ -12,6 +12,7 @@
 
 pub mod cells;
 pub mod dice;
+pub mod external_cells;
 pub mod init;
 pub(crate) mod path;
 pub mod view;
@@ -46,6 +47,7 @@ use starlark_map::sorted_map::SortedMap;
 use thiserror::Error;
 
 use crate::legacy_configs::cells::BuckConfigBasedCells;
+use crate::legacy_configs::external_cells::ExternalCellDownloader;
 use crate::legacy_configs::view::LegacyBuckConfigView;
 use crate::legacy_configs::view::LegacyBuckConfigsView;
 use crate::target_aliases::BuckConfigTargetAliasResolver;
@@ -1310,9 +1312,12 @@ impl LegacyBuckConfig {
 }
 
 // Options on how to exactly parse config files
-struct BuckConfigParseOptions {
+struct BuckConfigParseOptions<'a> {
     // Defines whether includes are followed, this can significantly reduce parse time.
     follow_includes: bool,
+    // Used to fetch external cells if missing. When absent, they are not fetched, and the config
+    // of a missing external cell is empty.
+    external_cell_downloader: Option<&'a dyn ExternalCellDownloader>,
 }
 
 fn push_all_files_from_a_directory(
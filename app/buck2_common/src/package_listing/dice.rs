@@ -18,15 +18,20 @@ use buck2_core::package::PackageLabel;
 use buck2_events::dispatch::async_record_root_spans;
 use buck2_events::span::SpanId;
 use dice::DiceComputations;
+use dice::DiceData;
+use dice::DiceDataBuilder;
 use dice::Key;
+use dice::PersistentKey;
 use dupe::Dupe;
 use more_futures::cancellation::CancellationContext;
 use smallvec::SmallVec;
 
 use crate::dice::cells::HasCellResolver;
 use crate::dice::file_ops::HasFileOps;
+use crate::dice::persistence::SavedCellPath;
 use crate::package_listing::interpreter::InterpreterPackageListingResolver;
 use crate::package_listing::listing::PackageListing;
+use crate::package_listing::listing::SavedPackageListing;
 use crate::package_listing::resolver::PackageListingResolver;
 use crate::result::SharedResult;
 use crate::result::ToUnsharedResultExt;
@@ -110,6 +115,37 @@ impl Key for PackageListingKey {
     }
 }
 
+// Listings depend on the cells (covered by the persisted state environment) and on the
+// persisted file ops keys, which are checked against the file system when they are restored.
+impl PersistentKey for PackageListingKey {
+    const KIND: &'static str = "PackageListingKey";
+
+    fn save(&self, value: &Self::Value) -> anyhow::Result<Option<Vec<u8>>> {
+        let value = match value {
+            Ok(value) => value,
+            Err(_) => return Ok(None),
+        };
+        Ok(Some(serde_json::to_vec(&(
+            SavedCellPath::new(self.0.as_cell_path()),
+            SavedPackageListing::new(value),
+        ))?))
+    }
+
+    fn load(data: &[u8], _global_data: &DiceData) -> anyhow::Result<(Self, Self::Value)> {
+        let (path, listing): (SavedCellPath, SavedPackageListing) = serde_json::from_slice(data)?;
+        Ok((
+            PackageListingKey(PackageLabel::from_cell_path(
+                path.into_cell_path()?.as_ref(),
+            )),
+            Ok(listing.into_listing()?),
+        ))
+    }
+}
+
+pub(crate) fn register_persistent_package_listing_keys(builder: &mut DiceDataBuilder) {
+    builder.register_persistent_key::<PackageListingKey>();
+}
+
 #[derive(Clone, Dupe)]
 pub struct DicePackageListingResolver<'compute>(&'compute DiceComputations);
 
@@ -13,8 +13,11 @@ use allocative::Allocative;
 use buck2_core::fs::paths::file_name::FileName;
 use buck2_core::fs::paths::file_name::FileNameBuf;
 use buck2_core::package::package_relative_path::PackageRelativePath;
+use buck2_core::package::package_relative_path::PackageRelativePathBuf;
 use buck2_util::arc_str::ArcS;
 use dupe::Dupe;
+use serde::Deserialize;
+use serde::Serialize;
 use starlark_map::sorted_set::SortedSet;
 use starlark_map::sorted_vec::SortedVec;
 
@@ -101,6 +104,48 @@ impl PackageListing {
     }
 }
 
+/// How a `PackageListing` is persisted across daemon restarts.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct SavedPackageListing {
+    files: Vec<String>,
+    directories: Vec<String>,
+    subpackages: Vec<String>,
+    buildfile: String,
+}
+
+impl SavedPackageListing {
+    pub(crate) fn new(listing: &PackageListing) -> Self {
+        fn paths<'a>(paths: impl Iterator<Item = &'a ArcS<PackageRelativePath>>) -> Vec<String> {
+            paths.map(|p| p.as_str().to_owned()).collect()
+        }
+
+        Self {
+            files: paths(listing.listing.files.files.iter()),
+            directories: paths(listing.listing.directories.iter()),
+            subpackages: paths(listing.listing.subpackages.iter()),
+            buildfile: listing.listing.buildfile.as_str().to_owned(),
+        }
+    }
+
+    pub(crate) fn into_listing(self) -> anyhow::Result<PackageListing> {
+        fn paths<C: FromIterator<ArcS<PackageRelativePath>>>(
+            paths: Vec<String>,
+        ) -> anyhow::Result<C> {
+            paths
+                .into_iter()
+                .map(|p| Ok(PackageRelativePathBuf::try_from(p)?.to_arc()))
+                .collect()
+        }
+
+        Ok(PackageListing::new(
+            paths(self.files)?,
+            paths(self.directories)?,
+            paths(self.subpackages)?,
+            FileNameBuf::try_from(self.buildfile)?,
+        ))
+    }
+}
+
 pub mod testing {
     use buck2_core::fs::paths::file_name::FileNameBuf;
     use buck2_core::package::package_relative_path::PackageRelativePathBuf;
@@ -89,17 +89,29 @@ impl BaseDeferredKey {
         }
     }
 
+    /// When `content_hash` is set, it replaces the configuration hash in the path, so that
+    /// the same action produces the same path regardless of the configuration it ran in.
     pub fn make_hashed_path(
         &self,
         base: &ProjectRelativePath,
         prefix: &ForwardRelativePath,
         action_key: Option<&str>,
+        content_hash: Option<&str>,
         path: &ForwardRelativePath,
     ) -> ProjectRelativePathBuf {
         match self {
             BaseDeferredKey::TargetLabel(target) => {
                 let cell_relative_path = target.pkg().cell_relative_path().as_str();
                 let escaped_target_name = Self::escape_target_name(target.name().as_str());
+                // Configuration hashes are hex, so the `content-` prefix cannot collide with them.
+                let (cfg_prefix, cfg_hash, exec_cfg_hash) = match content_hash {
+                    Some(content_hash) => ("content-", content_hash, None),
+                    None => (
+                        "",
+                        target.cfg().output_hash().as_str(),
+                        target.exec_cfg().map(|x| x.output_hash().as_str()),
+                    ),
+                };
 
                 // It is performance critical that we use slices and allocate via `join` instead of
                 // repeated calls to `join` on the path object because `join` allocates on each call,
@@ -111,12 +123,10 @@ impl BaseDeferredKey {
                     "/",
                     target.pkg().cell_name().as_str(),
                     "/",
-                    target.cfg().output_hash().as_str(),
-                    if target.exec_cfg().is_some() { "-" } else { "" },
-                    target
-                        .exec_cfg()
-                        .as_ref()
-                        .map_or("", |x| x.output_hash().as_str()),
+                    cfg_prefix,
+                    cfg_hash,
+                    if exec_cfg_hash.is_some() { "-" } else { "" },
+                    exec_cfg_hash.unwrap_or_default(),
                     "/",
                     cell_relative_path,
                     if cell_relative_path.is_empty() {
@@ -141,6 +151,10 @@ impl BaseDeferredKey {
                 ProjectRelativePathBuf::unchecked_new(parts.concat())
             }
             BaseDeferredKey::AnonTarget(d) | BaseDeferredKey::BxlLabel(d) => {
+                debug_assert!(
+                    content_hash.is_none(),
+                    "content-based paths are only supported for target labels"
+                );
                 d.make_hashed_path(base, prefix, action_key, path)
             }
         }
@@ -0,0 +1,140 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! External cells are cells whose contents don't live in the repository, but are fetched from a
+//! pinned git commit or an archive with a known hash. They are materialized into `buck-out`
+//! (see [`ExternalCellOrigin::materialized_path`]), and never change once materialized.
+
+use std::sync::Arc;
+
+use allocative::Allocative;
+use derive_more::Display;
+
+use crate::cells::alias::NonEmptyCellAlias;
+use crate::cells::cell_root_path::CellRootPathBuf;
+use crate::fs::project_rel_path::ProjectRelativePathBuf;
+
+/// Where the contents of an external cell come from.
+#[derive(Clone, Debug, Display, PartialEq, Eq, Hash, Allocative)]
+pub enum ExternalCellOrigin {
+    /// A commit of a git repository.
+    #[display(fmt = "git `{}` at `{}`", origin, commit)]
+    Git {
+        origin: Arc<str>,
+        /// Full hex commit hash.
+        commit: Arc<str>,
+    },
+    /// A `tar` (possibly compressed) or `zip` archive.
+    #[display(fmt = "archive `{}`", url)]
+    Archive {
+        url: Arc<str>,
+        /// URLs to try, in order, if `url` fails.
+        mirrors: Vec<Arc<str>>,
+        /// Hex sha256 of the archive.
+        sha256: Arc<str>,
+        /// Directory inside the archive to use as the cell root.
+        strip_prefix: Option<Arc<str>>,
+    },
+}
+
+impl ExternalCellOrigin {
+    /// The kind of origin, as written in the `[external_cells]` section.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ExternalCellOrigin::Git { .. } => "git",
+            ExternalCellOrigin::Archive { .. } => "archive",
+        }
+    }
+
+    /// The value that uniquely identifies the contents of the cell. For archives, this includes
+    /// the `strip_prefix`, since it selects which part of the archive is the cell.
+    pub fn pin(&self) -> String {
+        match self {
+            ExternalCellOrigin::Git { commit, .. } => (**commit).to_owned(),
+            ExternalCellOrigin::Archive {
+                sha256,
+                strip_prefix: None,
+                ..
+            } => (**sha256).to_owned(),
+            ExternalCellOrigin::Archive {
+                sha256,
+                strip_prefix: Some(strip_prefix),
+                ..
+            } => {
+                // Hashed since the prefix may contain path separators.
+                let prefix_hash = blake3::hash(strip_prefix.as_bytes()).to_hex();
+                format!("{}-{}", sha256, &prefix_hash[..16])
+            }
+        }
+    }
+
+    /// The cell root this origin is materialized to. It depends on the pin so that changing it
+    /// moves the cell rather than mutating files in place.
+    pub fn materialized_path(&self, alias: &NonEmptyCellAlias) -> CellRootPathBuf {
+        CellRootPathBuf::new(ProjectRelativePathBuf::unchecked_new(format!(
+            "buck-out/external_cells/{}/{}/{}",
+            self.kind(),
+            alias.as_str(),
+            self.pin()
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::cells::alias::NonEmptyCellAlias;
+    use crate::cells::external::ExternalCellOrigin;
+
+    #[test]
+    fn test_materialized_path() {
+        let alias = NonEmptyCellAlias::new("foo".to_owned()).unwrap();
+        let git = ExternalCellOrigin::Git {
+            origin: Arc::from("https://example.com/foo.git"),
+            commit: Arc::from("0123456789abcdef0123456789abcdef01234567"),
+        };
+        assert_eq!(
+            "buck-out/external_cells/git/foo/0123456789abcdef0123456789abcdef01234567",
+            git.materialized_path(&alias).as_str()
+        );
+
+        let archive = ExternalCellOrigin::Archive {
+            url: Arc::from("https://example.com/foo.tar.gz"),
+            mirrors: Vec::new(),
+            sha256: Arc::from("abcd"),
+            strip_prefix: None,
+        };
+        assert_eq!(
+            "buck-out/external_cells/archive/foo/abcd",
+            archive.materialized_path(&alias).as_str()
+        );
+    }
+
+    #[test]
+    fn test_materialized_path_depends_on_strip_prefix() {
+        let alias = NonEmptyCellAlias::new("foo".to_owned()).unwrap();
+        let archive = |strip_prefix: Option<&str>| ExternalCellOrigin::Archive {
+            url: Arc::from("https://example.com/foo.tar.gz"),
+            mirrors: Vec::new(),
+            sha256: Arc::from("abcd"),
+            strip_prefix: strip_prefix.map(Arc::from),
+        };
+        let none = archive(None).materialized_path(&alias);
+        let a = archive(Some("foo-1.0")).materialized_path(&alias);
+        let b = archive(Some("foo-1.0/src")).materialized_path(&alias);
+        assert_ne!(none, a);
+        assert_ne!(a, b);
+        assert_eq!(a, archive(Some("foo-1.0")).materialized_path(&alias));
+        assert!(
+            a.as_str()
+                .starts_with("buck-out/external_cells/archive/foo/abcd-")
+        );
+    }
+}
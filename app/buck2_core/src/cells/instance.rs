@@ -15,6 +15,7 @@ use dupe::Dupe;
 
 use crate::cells::cell_root_path::CellRootPath;
 use crate::cells::cell_root_path::CellRootPathBuf;
+use crate::cells::external::ExternalCellOrigin;
 use crate::cells::name::CellName;
 use crate::cells::nested::NestedCells;
 use crate::cells::CellAliasResolver;
@@ -46,6 +47,8 @@ struct CellData {
     /// the aliases of this specific cell
     aliases: CellAliasResolver,
     nested_cells: NestedCells,
+    /// Set if the contents of this cell are fetched rather than part of the repository.
+    external: Option<ExternalCellOrigin>,
 }
 
 impl CellInstance {
@@ -55,6 +58,7 @@ impl CellInstance {
         buildfiles: Vec<FileNameBuf>,
         aliases: CellAliasResolver,
         nested_cells: NestedCells,
+        external: Option<ExternalCellOrigin>,
     ) -> anyhow::Result<CellInstance> {
         if name != aliases.current {
             return Err(CellInstanceError::InconsistentCellName(name, aliases.current).into());
@@ -65,6 +69,7 @@ impl CellInstance {
             buildfiles,
             aliases,
             nested_cells,
+            external,
         })))
     }
 
@@ -95,4 +100,11 @@ impl CellInstance {
     pub fn nested_cells(&self) -> &NestedCells {
         &self.0.nested_cells
     }
+
+    /// Where the contents of this cell come from, if it is an external cell. External cells are
+    /// immutable once materialized.
+    #[inline]
+    pub fn external(&self) -> Option<&ExternalCellOrigin> {
+        self.0.external.as_ref()
+    }
 }
@@ -132,6 +132,7 @@ pub mod alias;
 pub mod build_file_cell;
 pub mod cell_path;
 pub mod cell_root_path;
+pub mod external;
 pub mod instance;
 pub mod name;
 pub mod nested;
@@ -161,6 +162,7 @@ use crate::cells::cell_path::CellPath;
 use crate::cells::cell_path::CellPathRef;
 use crate::cells::cell_root_path::CellRootPath;
 use crate::cells::cell_root_path::CellRootPathBuf;
+use crate::cells::external::ExternalCellOrigin;
 use crate::cells::name::CellName;
 use crate::cells::nested::NestedCells;
 use crate::fs::paths::abs_norm_path::AbsNormPath;
@@ -599,6 +601,8 @@ struct CellAggregatorInfo {
     /// The build file name in this if it's been set. If it hasn't we'll use the
     /// default `["BUCK.v2", "BUCK"]` when building the resolver.
     buildfiles: Vec<FileNameBuf>,
+    /// Set for external cells.
+    external: Option<ExternalCellOrigin>,
 }
 
 impl Default for CellAggregatorInfo {
@@ -607,6 +611,7 @@ impl Default for CellAggregatorInfo {
             name: None,
             alias_mapping: HashMap::new(),
             buildfiles: default_buildfiles(),
+            external: None,
         }
     }
 }
@@ -680,6 +685,11 @@ impl CellsAggregator {
         self.cell_info(cell_root).buildfiles.push(buildfile);
     }
 
+    /// Marks the cell at `cell_root` as an external cell fetched from `origin`.
+    pub fn set_external(&mut self, cell_root: CellRootPathBuf, origin: ExternalCellOrigin) {
+        self.cell_info(cell_root).external = Some(origin);
+    }
+
     fn get_cell_name_from_path(&self, path: &CellRootPath) -> anyhow::Result<CellName> {
         self.cell_infos
             .get(path)
@@ -723,6 +733,7 @@ impl CellsAggregator {
                 cell_info.buildfiles.clone(),
                 CellAliasResolver::new(cell_name, aliases_for_cell)?,
                 nested_cells,
+                cell_info.external.clone(),
             )?);
         }
 
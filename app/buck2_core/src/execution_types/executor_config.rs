@@ -160,6 +160,19 @@ pub enum OutputPathsBehavior {
     Compatibility,
     /// Ask for things using output_paths.
     OutputPaths,
+    /// Like the default behavior, but run actions that support it execute with their outputs
+    /// under a path derived from their command line and inputs instead of their configuration.
+    ContentBased,
+}
+
+impl OutputPathsBehavior {
+    /// How outputs are requested from RE, which is never `ContentBased`.
+    pub fn re_behavior(self) -> Self {
+        match self {
+            Self::ContentBased => Self::default(),
+            behavior => behavior,
+        }
+    }
 }
 
 impl FromStr for OutputPathsBehavior {
@@ -171,6 +184,7 @@ impl FromStr for OutputPathsBehavior {
             "compatibility" => Ok(OutputPathsBehavior::Compatibility),
             #[cfg(not(fbcode_build))]
             "output_paths" => Ok(OutputPathsBehavior::OutputPaths),
+            "content_based" => Ok(OutputPathsBehavior::ContentBased),
             _ => Err(anyhow::anyhow!("Invalid OutputPathsBehavior: `{}`", s)),
         }
     }
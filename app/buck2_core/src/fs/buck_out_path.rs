@@ -32,6 +32,9 @@ struct BuckOutPathData {
     action_key: Option<Arc<str>>,
     /// The path relative to that target.
     path: ForwardRelativePathBuf,
+    /// When set, the path is keyed by this digest of the producing action instead of the
+    /// owner's configuration (see `OutputPathsBehavior::ContentBased`).
+    content_hash: Option<Arc<str>>,
 }
 
 /// Represents a resolvable path corresponding to outputs of rules that are part
@@ -61,6 +64,18 @@ impl BuckOutPath {
             owner,
             action_key,
             path,
+            content_hash: None,
+        }))
+    }
+
+    /// Returns the same output, but located under a directory derived from `content_hash`
+    /// rather than from the owner's configuration.
+    pub fn with_content_hash(&self, content_hash: Arc<str>) -> Self {
+        BuckOutPath(Arc::new(BuckOutPathData {
+            owner: self.0.owner.dupe(),
+            action_key: self.0.action_key.dupe(),
+            path: self.0.path.clone(),
+            content_hash: Some(content_hash),
         }))
     }
 
@@ -75,6 +90,10 @@ impl BuckOutPath {
     pub fn path(&self) -> &ForwardRelativePath {
         &self.0.path
     }
+
+    pub fn content_hash(&self) -> Option<&str> {
+        self.0.content_hash.as_deref()
+    }
 }
 
 #[derive(Clone, Debug, Display, Eq, PartialEq)]
@@ -84,6 +103,8 @@ pub struct BuckOutScratchPath {
     owner: BaseDeferredKey,
     /// The path relative to that target.
     path: ForwardRelativePathBuf,
+    /// See `BuckOutPath::with_content_hash`.
+    content_hash: Option<Arc<str>>,
 }
 
 impl BuckOutScratchPath {
@@ -139,7 +160,21 @@ impl BuckOutScratchPath {
             _ => path.to_buf(),
         };
 
-        Ok(Self { owner, path })
+        Ok(Self {
+            owner,
+            path,
+            content_hash: None,
+        })
+    }
+
+    /// Returns the same scratch path, but located under a directory derived from
+    /// `content_hash` rather than from the owner's configuration.
+    pub fn with_content_hash(&self, content_hash: Arc<str>) -> Self {
+        Self {
+            owner: self.owner.dupe(),
+            path: self.path.clone(),
+            content_hash: Some(content_hash),
+        }
     }
 }
 
@@ -185,6 +220,7 @@ impl BuckOutPathResolver {
             ForwardRelativePath::unchecked_new("gen"),
             path.owner(),
             path.action_key(),
+            path.content_hash(),
             path.path(),
         )
     }
@@ -194,6 +230,7 @@ impl BuckOutPathResolver {
             ForwardRelativePath::unchecked_new("offline-cache"),
             path.owner(),
             path.action_key(),
+            path.content_hash(),
             path.path(),
         )
     }
@@ -203,6 +240,18 @@ impl BuckOutPathResolver {
             ForwardRelativePath::unchecked_new("tmp"),
             &path.owner,
             None,
+            path.content_hash.as_deref(),
+            &path.path,
+        )
+    }
+
+    /// Resolves the directory where an incremental action keeps its state between executions.
+    pub fn resolve_incremental_state(&self, path: &BuckOutScratchPath) -> ProjectRelativePathBuf {
+        self.prefixed_path_for_owner(
+            ForwardRelativePath::unchecked_new("incremental"),
+            &path.owner,
+            None,
+            path.content_hash.as_deref(),
             &path.path,
         )
     }
@@ -222,9 +271,10 @@ impl BuckOutPathResolver {
         prefix: &ForwardRelativePath,
         owner: &BaseDeferredKey,
         action_key: Option<&str>,
+        content_hash: Option<&str>,
         path: &ForwardRelativePath,
     ) -> ProjectRelativePathBuf {
-        owner.make_hashed_path(&self.0, prefix, action_key, path)
+        owner.make_hashed_path(&self.0, prefix, action_key, content_hash, path)
     }
 
     /// This function returns the exact location of the symlink of a given target.
@@ -384,6 +434,84 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn buck_content_hashed_output_path_resolves() -> anyhow::Result<()> {
+        let path_resolver =
+            BuckOutPathResolver::new(ProjectRelativePathBuf::unchecked_new("buck-out".into()));
+
+        let pkg = PackageLabel::new(
+            CellName::testing_new("foo"),
+            CellRelativePath::unchecked_new("baz-package"),
+        );
+        let target = TargetLabel::new(pkg, TargetNameRef::unchecked_new("target-name"));
+        let mk = |cfg: ConfigurationData| {
+            BuckOutPath::new(
+                BaseDeferredKey::TargetLabel(target.configure(cfg)),
+                ForwardRelativePathBuf::unchecked_new("quux".to_owned()),
+            )
+        };
+
+        let path = mk(ConfigurationData::testing_new());
+        let hashed = path.with_content_hash(Arc::from("abc"));
+        assert_ne!(path, hashed);
+        assert_eq!(
+            "buck-out/gen/foo/content-abc/baz-package/__target-name__/quux",
+            path_resolver.resolve_gen(&hashed).as_str()
+        );
+
+        // The configuration does not affect content-hashed paths.
+        let other = mk(ConfigurationData::unspecified());
+        assert_ne!(
+            path_resolver.resolve_gen(&path),
+            path_resolver.resolve_gen(&other)
+        );
+        assert_eq!(
+            path_resolver.resolve_gen(&hashed),
+            path_resolver.resolve_gen(&other.with_content_hash(Arc::from("abc")))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn buck_incremental_state_path_resolves() -> anyhow::Result<()> {
+        let path_resolver =
+            BuckOutPathResolver::new(ProjectRelativePathBuf::unchecked_new("buck-out".into()));
+
+        let pkg = PackageLabel::new(
+            CellName::testing_new("foo"),
+            CellRelativePath::unchecked_new("baz-package"),
+        );
+        let target = TargetLabel::new(pkg, TargetNameRef::unchecked_new("target-name"));
+        let cfg_target = target.configure(ConfigurationData::testing_new());
+        let category = Category::try_from("category").unwrap();
+        let path = || {
+            BuckOutScratchPath::new(
+                BaseDeferredKey::TargetLabel(cfg_target.dupe()),
+                &category,
+                Some("id"),
+            )
+        };
+
+        let resolved = path_resolver.resolve_incremental_state(&path()?);
+        let re = Regex::new(
+            "buck-out/incremental/foo/[0-9a-z]+/baz-package/__target-name__/category/id",
+        )?;
+        assert!(
+            re.is_match(resolved.as_str()),
+            "{}.is_match({})",
+            re,
+            resolved
+        );
+
+        // The state is found again by later executions, and is kept apart from the scratch path,
+        // which is cleaned up before each execution.
+        assert_eq!(resolved, path_resolver.resolve_incremental_state(&path()?));
+        assert_ne!(resolved, path_resolver.resolve_scratch(&path()?));
+
+        Ok(())
+    }
+
     #[test]
     fn test_scratch_path_is_sensible() {
         let pkg = PackageLabel::new(
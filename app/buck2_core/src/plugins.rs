@@ -57,6 +57,11 @@ impl PluginKind {
     pub fn as_str(&self) -> &str {
         &self.0.deref_static().name
     }
+
+    /// The cell path given when the kind was created, which identifies it along with its name.
+    pub fn cell(&self) -> &CellPath {
+        &self.0.deref_static().cell
+    }
 }
 
 /// This type is pretty tailor-made for storing the values of `pulls_plugins` and
@@ -119,6 +124,16 @@ impl PluginKindSet {
         }
     }
 
+    /// The kinds in the set, each with whether it is pushed in addition to pulled, or `None` for
+    /// `ALL`.
+    pub fn kinds(&self) -> Option<&'static [(PluginKind, bool)]> {
+        match self.unpack() {
+            PluginKindSetUnpacked::None => Some(&[]),
+            PluginKindSetUnpacked::All => None,
+            PluginKindSetUnpacked::Interned(i) => Some(i.deref_static().as_slice()),
+        }
+    }
+
     fn unpack(self) -> PluginKindSetUnpacked {
         if self.0 as usize == 0 {
             PluginKindSetUnpacked::None
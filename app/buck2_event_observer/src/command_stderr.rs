@@ -0,0 +1,83 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::collections::HashMap;
+
+use buck2_data::CommandStderrTail;
+use buck2_events::span::SpanId;
+use buck2_events::BuckEvent;
+
+/// The most recent stderr of commands that are still running, keyed by the span they run in.
+pub struct RunningCommandStderr {
+    tails: HashMap<SpanId, String>,
+}
+
+impl RunningCommandStderr {
+    pub fn new() -> Self {
+        Self {
+            tails: HashMap::new(),
+        }
+    }
+
+    pub fn update(&mut self, event: &BuckEvent, tail: &CommandStderrTail) {
+        if let Some(parent_id) = event.parent_id() {
+            self.tails.insert(parent_id, tail.stderr.clone());
+        }
+    }
+
+    pub fn span_end(&mut self, span_id: SpanId) {
+        self.tails.remove(&span_id);
+    }
+
+    pub fn get(&self, span_id: SpanId) -> Option<&str> {
+        self.tails.get(&span_id).map(|s| s.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::SystemTime;
+
+    use buck2_events::span::SpanId;
+    use buck2_events::BuckEvent;
+    use buck2_wrapper_common::invocation_id::TraceId;
+
+    use super::*;
+
+    fn tail_event(parent_id: SpanId, stderr: &str) -> (BuckEvent, CommandStderrTail) {
+        let tail = CommandStderrTail {
+            stderr: stderr.to_owned(),
+        };
+        let event = BuckEvent::new(
+            SystemTime::now(),
+            TraceId::new(),
+            None,
+            Some(parent_id),
+            buck2_data::buck_event::Data::Instant(buck2_data::InstantEvent {
+                data: Some(tail.clone().into()),
+            }),
+        );
+        (event, tail)
+    }
+
+    #[test]
+    fn keeps_latest_tail_until_span_ends() {
+        let mut state = RunningCommandStderr::new();
+        let span = SpanId::new();
+
+        let (event, tail) = tail_event(span, "compiling");
+        state.update(&event, &tail);
+        let (event, tail) = tail_event(span, "compiling\nlinking");
+        state.update(&event, &tail);
+        assert_eq!(state.get(span), Some("compiling\nlinking"));
+
+        state.span_end(span);
+        assert_eq!(state.get(span), None);
+    }
+}
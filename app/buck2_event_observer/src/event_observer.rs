@@ -15,6 +15,7 @@ use buck2_events::BuckEvent;
 use buck2_wrapper_common::invocation_id::TraceId;
 
 use crate::action_stats::ActionStats;
+use crate::command_stderr::RunningCommandStderr;
 use crate::debug_events::DebugEventsState;
 use crate::dice_state::DiceState;
 use crate::re_state::ReState;
@@ -173,6 +174,7 @@ pub trait EventObserverExtra: Send {
 pub struct DebugEventObserverExtra {
     dice_state: DiceState,
     debug_events: DebugEventsState,
+    command_stderr: RunningCommandStderr,
 }
 
 impl EventObserverExtra for DebugEventObserverExtra {
@@ -180,6 +182,7 @@ impl EventObserverExtra for DebugEventObserverExtra {
         Self {
             dice_state: DiceState::new(),
             debug_events: DebugEventsState::new(),
+            command_stderr: RunningCommandStderr::new(),
         }
     }
 
@@ -190,6 +193,11 @@ impl EventObserverExtra for DebugEventObserverExtra {
             use buck2_data::buck_event::Data::*;
 
             match event.data() {
+                SpanEnd(..) => {
+                    if let Some(span_id) = event.span_id() {
+                        self.command_stderr.span_end(span_id);
+                    }
+                }
                 Instant(instant) => {
                     use buck2_data::instant_event::Data::*;
 
@@ -201,6 +209,9 @@ impl EventObserverExtra for DebugEventObserverExtra {
                         DiceStateSnapshot(dice) => {
                             self.dice_state.update(dice);
                         }
+                        CommandStderrTail(tail) => {
+                            self.command_stderr.update(event, tail);
+                        }
                         _ => {}
                     }
                 }
@@ -220,6 +231,10 @@ impl DebugEventObserverExtra {
     pub fn debug_events(&self) -> &DebugEventsState {
         &self.debug_events
     }
+
+    pub fn command_stderr(&self) -> &RunningCommandStderr {
+        &self.command_stderr
+    }
 }
 
 pub struct NoopEventObserverExtra;
@@ -11,6 +11,7 @@
 
 pub mod action_stats;
 pub mod cache_hit_rate;
+pub mod command_stderr;
 pub mod debug_events;
 pub mod dice_state;
 pub mod display;
@@ -32,6 +32,10 @@ impl ReState {
         self.session_id = Some(session.session_id.clone());
     }
 
+    pub fn session_id(&self) -> Option<&str> {
+        self.session_id.as_deref()
+    }
+
     pub fn update(&mut self, snapshot: &buck2_data::Snapshot) {
         if self.first_snapshot.is_none() {
             self.first_snapshot = Some(snapshot.clone());
@@ -166,6 +166,7 @@ impl CommandExecutor {
                     Some((metadata.data.clone(), metadata.digest.dupe()))
                 }
                 CommandExecutionInput::ScratchPath(_) => None,
+                CommandExecutionInput::IncrementalStatePath(_) => None,
             });
             let action = re_create_action(
                 request.all_args_vec(),
@@ -215,7 +216,7 @@ fn re_create_action(
         ..Default::default()
     };
 
-    match output_paths_behavior {
+    match output_paths_behavior.re_behavior() {
         OutputPathsBehavior::Compatibility => {
             for (output, output_type) in outputs {
                 let path = output.as_str().to_owned();
@@ -258,6 +259,9 @@ fn re_create_action(
                 }
             }
         }
+        OutputPathsBehavior::ContentBased => {
+            unreachable!("`re_behavior` is never `ContentBased`")
+        }
     }
 
     let mut prepared_blobs = ActionBlobs::new(digest_config);
@@ -40,6 +40,10 @@ pub fn inputs_directory(
                 let path = fs.buck_out_path_resolver().resolve_scratch(path);
                 builder.insert(&path, DirectoryEntry::Dir(ActionDirectoryBuilder::empty()))?;
             }
+            CommandExecutionInput::IncrementalStatePath(path) => {
+                let path = fs.buck_out_path_resolver().resolve_incremental_state(path);
+                builder.insert(&path, DirectoryEntry::Dir(ActionDirectoryBuilder::empty()))?;
+            }
         };
     }
     Ok(builder)
@@ -54,6 +54,9 @@ pub enum CommandExecutionInput {
     Artifact(Box<dyn ArtifactGroupValuesDyn>),
     ActionMetadata(ActionMetadataBlob),
     ScratchPath(BuckOutScratchPath),
+    /// A directory that is kept across executions of the action, which are always local. It is
+    /// empty in the action digest, so its contents never affect caching.
+    IncrementalStatePath(BuckOutScratchPath),
 }
 
 #[derive(PartialEq, Eq, Debug, Copy, Clone, Dupe, Hash)]
@@ -7,6 +7,7 @@
  * of this source tree.
  */
 
+use std::io::BufWriter;
 use std::io::Write;
 use std::sync::Arc;
 use std::time::Duration;
@@ -15,13 +16,17 @@ use allocative::Allocative;
 use anyhow::Context as _;
 use buck2_common::cas_digest::CasDigestConfig;
 use buck2_common::cas_digest::DigestAlgorithmKind;
+use buck2_common::cas_digest::Digester;
 use buck2_common::file_ops::FileDigest;
+use buck2_common::file_ops::FileDigestKind;
 use buck2_common::file_ops::TrackedFileDigest;
 use buck2_common::http::retries::http_retry;
 use buck2_common::http::retries::AsHttpError;
 use buck2_common::http::retries::HttpError;
 use buck2_common::http::HttpClient;
 use buck2_core::fs::fs_util;
+use buck2_core::fs::fs_util::FileWriteGuard;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
 use buck2_core::fs::project::ProjectRoot;
 use buck2_core::fs::project_rel_path::ProjectRelativePath;
 use bytes::Bytes;
@@ -30,6 +35,7 @@ use dupe::Dupe;
 use futures::stream::Stream;
 use futures::StreamExt;
 use hyper::Response;
+use hyper::StatusCode;
 use sha1::Digest;
 use sha1::Sha1;
 use sha2::Sha256;
@@ -88,6 +94,19 @@ enum HttpDownloadError {
         path: String,
     },
 
+    #[error("Requested {url} from byte {expected}, but the response starts at byte {got}")]
+    UnexpectedRange {
+        url: String,
+        expected: u64,
+        got: u64,
+    },
+
+    #[error("No URL to download from")]
+    NoUrls,
+
+    #[error("Error downloading from all URLs:\n{}", .0.join("\n"))]
+    AllUrlsFailed(Vec<String>),
+
     #[error(transparent)]
     IoError(anyhow::Error),
 }
@@ -106,7 +125,10 @@ impl AsHttpError for HttpDownloadError {
             Self::Client(e) => Some(e),
             Self::InvalidChecksum(..)
             | Self::IoError(..)
-            | Self::MaybeNotAllowedOnVpnless { .. } => None,
+            | Self::MaybeNotAllowedOnVpnless { .. }
+            | Self::UnexpectedRange { .. }
+            | Self::NoUrls
+            | Self::AllUrlsFailed(..) => None,
         }
     }
 }
@@ -130,7 +152,7 @@ pub async fn http_download(
     fs: &ProjectRoot,
     digest_config: DigestConfig,
     path: &ProjectRelativePath,
-    url: &str,
+    urls: &[Arc<str>],
     checksum: &Checksum,
     executable: bool,
 ) -> anyhow::Result<TrackedFileDigest> {
@@ -139,144 +161,310 @@ pub async fn http_download(
         fs_util::create_dir_all(fs.resolve(dir))?;
     }
 
-    Ok(http_retry(
+    // Mirrors are tried in order, each with its own retries.
+    let mut errors = Vec::new();
+    for url in urls {
+        match http_download_from(client, &abs_path, digest_config, url, checksum).await {
+            Ok(digest) => {
+                if executable {
+                    fs.set_executable(path)?;
+                }
+                return Ok(TrackedFileDigest::new(
+                    digest,
+                    digest_config.cas_digest_config(),
+                ));
+            }
+            Err(e) => {
+                if urls.len() > 1 {
+                    tracing::warn!("Error downloading from `{}`: {:#}", url, e);
+                }
+                errors.push(e);
+            }
+        }
+    }
+
+    match errors.len() {
+        0 => Err(HttpDownloadError::NoUrls.into()),
+        1 => Err(errors.pop().unwrap().into()),
+        _ => Err(HttpDownloadError::AllUrlsFailed(
+            urls.iter()
+                .zip(errors)
+                .map(|(url, e)| format!("{}: {:#}", url, anyhow::Error::from(e)))
+                .collect(),
+        )
+        .into()),
+    }
+}
+
+/// Downloads from a single URL, retrying transient errors. When the server supports range
+/// requests, retries resume where the previous attempt stopped rather than starting over. If the
+/// server responds with another range, the download restarts from the beginning.
+async fn http_download_from(
+    client: &HttpClient,
+    abs_path: &AbsNormPath,
+    digest_config: DigestConfig,
+    url: &str,
+    checksum: &Checksum,
+) -> Result<FileDigest, HttpDownloadError> {
+    let partial = tokio::sync::Mutex::new(None::<PartialDownload>);
+
+    http_retry(
         || async {
-            let file = fs_util::create_file(&abs_path).map_err(HttpDownloadError::IoError)?;
+            let mut partial = partial.lock().await;
 
-            let stream = client
-                .get(url)
-                .await
-                .map_err(|e| HttpDownloadError::Client(HttpError::Client(e)))?
-                .into_body();
-            let buf_writer = std::io::BufWriter::new(file);
-
-            let digest = copy_and_hash(
-                url,
-                &abs_path,
-                stream,
-                buf_writer,
-                digest_config.cas_digest_config(),
-                checksum,
-                client.supports_vpnless(),
-            )
-            .await?;
-
-            if executable {
-                fs.set_executable(path)
-                    .map_err(HttpDownloadError::IoError)?;
+            let offset = partial.as_ref().map_or(0, |p| p.hasher.bytes_read());
+            let response = if offset > 0 {
+                client.get_range(url, offset).await
+            } else {
+                client.get(url).await
+            }
+            .map_err(|e| HttpDownloadError::Client(HttpError::Client(e)))?;
+
+            let (response, resumed) = match resumed_at(&response) {
+                Some(start) if start == offset && offset > 0 => (response, true),
+                None => (response, false),
+                Some(start) if offset > 0 => {
+                    // The server didn't resume where we asked it to, so start over.
+                    tracing::debug!(
+                        "Requested `{}` from byte {}, but the response starts at byte {}, restarting the download",
+                        url,
+                        offset,
+                        start
+                    );
+                    *partial = None;
+                    let response = client
+                        .get(url)
+                        .await
+                        .map_err(|e| HttpDownloadError::Client(HttpError::Client(e)))?;
+                    match resumed_at(&response) {
+                        None => (response, false),
+                        Some(start) => {
+                            return Err(HttpDownloadError::UnexpectedRange {
+                                url: url.to_owned(),
+                                expected: 0,
+                                got: start,
+                            });
+                        }
+                    }
+                }
+                Some(start) => {
+                    return Err(HttpDownloadError::UnexpectedRange {
+                        url: url.to_owned(),
+                        expected: offset,
+                        got: start,
+                    });
+                }
+            };
+            if !resumed {
+                *partial = Some(PartialDownload::new(
+                    abs_path,
+                    digest_config.cas_digest_config(),
+                    checksum,
+                )?);
             }
 
-            Result::<_, HttpDownloadError>::Ok(TrackedFileDigest::new(
-                digest,
-                digest_config.cas_digest_config(),
-            ))
+            let download = partial.as_mut().expect("Set above");
+            download.copy(url, abs_path, response.into_body()).await?;
+
+            let download = partial.take().expect("Set above");
+            download.finish(url, abs_path, client.supports_vpnless())
         },
         vec![2, 4, 8].into_iter().map(Duration::from_secs).collect(),
     )
-    .await?)
+    .await
 }
 
-/// Copy a stream into a writer while producing its digest and checksumming it.
-async fn copy_and_hash(
-    url: &str,
-    abs_path: &(impl std::fmt::Display + ?Sized),
-    mut stream: impl Stream<Item = Result<Bytes, hyper::Error>> + Unpin,
-    mut writer: impl Write,
-    digest_config: CasDigestConfig,
-    checksum: &Checksum,
-    is_vpnless: bool,
-) -> Result<FileDigest, HttpDownloadError> {
-    let mut digester = FileDigest::digester(digest_config);
+/// If the response is a `206 Partial Content`, the offset it starts at.
+fn resumed_at<B>(response: &Response<B>) -> Option<u64> {
+    if response.status() != StatusCode::PARTIAL_CONTENT {
+        return None;
+    }
+    // `Content-Range: bytes <start>-<end>/<size>`. Anything else can't be used to resume, which is
+    // reported as a mismatch with the expected start.
+    Some(
+        response
+            .headers()
+            .get(hyper::header::CONTENT_RANGE)
+            .and_then(|range| range.to_str().ok())
+            .and_then(|range| range.strip_prefix("bytes "))
+            .and_then(|range| range.split('-').next())
+            .and_then(|start| start.trim().parse().ok())
+            .unwrap_or(u64::MAX),
+    )
+}
 
-    // For each checksum entry we have, we're going to add a validator. We might have to create
-    // a new hasher, or reuse the `FileDigest::digester` if it matches.
+/// A download from a URL, kept across attempts so that they can resume it.
+struct PartialDownload<'c> {
+    writer: BufWriter<FileWriteGuard>,
+    hasher: DownloadHasher<'c>,
+}
 
-    enum Validator {
-        PrimaryDigest,
-        ExtraDigest(Box<dyn DynDigest + Send>),
+impl<'c> PartialDownload<'c> {
+    fn new(
+        abs_path: &AbsNormPath,
+        digest_config: CasDigestConfig,
+        checksum: &'c Checksum,
+    ) -> Result<Self, HttpDownloadError> {
+        let file = fs_util::create_file(abs_path).map_err(HttpDownloadError::IoError)?;
+        Ok(Self {
+            writer: BufWriter::new(file),
+            hasher: DownloadHasher::new(digest_config, checksum),
+        })
     }
 
-    let mut validators = SmallVec::<[_; 2]>::new();
+    async fn copy(
+        &mut self,
+        url: &str,
+        abs_path: &AbsNormPath,
+        stream: impl Stream<Item = Result<Bytes, hyper::Error>> + Unpin,
+    ) -> Result<(), HttpDownloadError> {
+        copy_and_hash(url, abs_path, stream, &mut self.writer, &mut self.hasher).await
+    }
 
-    if let Some(sha1) = checksum.sha1() {
-        let validator = if digester.algorithm() == DigestAlgorithmKind::Sha1 {
-            Validator::PrimaryDigest
-        } else {
-            Validator::ExtraDigest(Box::new(Sha1::new()) as _)
-        };
+    fn finish(
+        self,
+        url: &str,
+        abs_path: &AbsNormPath,
+        is_vpnless: bool,
+    ) -> Result<FileDigest, HttpDownloadError> {
+        self.hasher.finish(url, abs_path, is_vpnless)
+    }
+}
 
-        validators.push((validator, sha1, "sha1"));
+enum Validator {
+    PrimaryDigest,
+    ExtraDigest(Box<dyn DynDigest + Send>),
+}
+
+/// Produces the digest of a download and checksums it as it is written.
+struct DownloadHasher<'c> {
+    digester: Digester<FileDigestKind>,
+    validators: SmallVec<[(Validator, &'c str, &'static str); 2]>,
+}
+
+impl<'c> DownloadHasher<'c> {
+    fn new(digest_config: CasDigestConfig, checksum: &'c Checksum) -> Self {
+        let digester = FileDigest::digester(digest_config);
+
+        // For each checksum entry we have, we're going to add a validator. We might have to create
+        // a new hasher, or reuse the `FileDigest::digester` if it matches.
+        let mut validators = SmallVec::<[_; 2]>::new();
+
+        if let Some(sha1) = checksum.sha1() {
+            let validator = if digester.algorithm() == DigestAlgorithmKind::Sha1 {
+                Validator::PrimaryDigest
+            } else {
+                Validator::ExtraDigest(Box::new(Sha1::new()) as _)
+            };
+
+            validators.push((validator, sha1, "sha1"));
+        }
+
+        if let Some(sha256) = checksum.sha256() {
+            let validator = if digester.algorithm() == DigestAlgorithmKind::Sha256 {
+                Validator::PrimaryDigest
+            } else {
+                Validator::ExtraDigest(Box::new(Sha256::new()) as _)
+            };
+
+            validators.push((validator, sha256, "sha256"));
+        }
+
+        Self {
+            digester,
+            validators,
+        }
     }
 
-    if let Some(sha256) = checksum.sha256() {
-        let validator = if digester.algorithm() == DigestAlgorithmKind::Sha256 {
-            Validator::PrimaryDigest
-        } else {
-            Validator::ExtraDigest(Box::new(Sha256::new()) as _)
-        };
+    fn bytes_read(&self) -> u64 {
+        self.digester.bytes_read()
+    }
 
-        validators.push((validator, sha256, "sha256"));
+    fn update(&mut self, chunk: &[u8]) {
+        self.digester.update(chunk);
+        for (validator, _expected, _kind) in self.validators.iter_mut() {
+            if let Validator::ExtraDigest(hasher) = validator {
+                hasher.update(chunk);
+            }
+        }
     }
 
+    fn finish(
+        self,
+        url: &str,
+        abs_path: &(impl std::fmt::Display + ?Sized),
+        is_vpnless: bool,
+    ) -> Result<FileDigest, HttpDownloadError> {
+        let digest = self.digester.finalize();
+
+        // Validate
+        for (validator, expected, kind) in self.validators {
+            let obtained = match validator {
+                Validator::PrimaryDigest => digest.raw_digest().to_string(),
+                Validator::ExtraDigest(hasher) => hex::encode(hasher.finalize()),
+            };
+
+            if expected != obtained {
+                if is_vpnless {
+                    return Err(HttpDownloadError::MaybeNotAllowedOnVpnless {
+                        kind,
+                        want: expected.to_owned(),
+                        got: obtained,
+                        url: url.to_owned(),
+                        path: abs_path.to_string(),
+                    });
+                }
+                return Err(HttpDownloadError::InvalidChecksum(
+                    kind,
+                    expected.to_owned(),
+                    obtained,
+                    url.to_owned(),
+                ));
+            }
+        }
+
+        Ok(digest)
+    }
+}
+
+/// Copy a stream into a writer while hashing it.
+async fn copy_and_hash(
+    url: &str,
+    abs_path: &(impl std::fmt::Display + ?Sized),
+    mut stream: impl Stream<Item = Result<Bytes, hyper::Error>> + Unpin,
+    mut writer: impl Write,
+    hasher: &mut DownloadHasher<'_>,
+) -> Result<(), HttpDownloadError> {
     while let Some(chunk) = stream.next().await {
         let chunk = chunk.map_err(|source| HttpError::Transfer {
-            received: digester.bytes_read(),
+            received: hasher.bytes_read(),
             url: url.to_owned(),
             source,
         })?;
         writer
-            .write(&chunk)
+            .write_all(&chunk)
             .with_context(|| format!("write({})", abs_path))
             .map_err(HttpDownloadError::IoError)?;
 
-        digester.update(&chunk);
-        for (validator, _expected, _kind) in validators.iter_mut() {
-            if let Validator::ExtraDigest(hasher) = validator {
-                hasher.update(&chunk);
-            }
-        }
+        hasher.update(&chunk);
     }
     writer
         .flush()
         .with_context(|| format!("flush({})", abs_path))
         .map_err(HttpDownloadError::IoError)?;
 
-    let digest = digester.finalize();
-
-    // Validate
-    for (validator, expected, kind) in validators {
-        let obtained = match validator {
-            Validator::PrimaryDigest => digest.raw_digest().to_string(),
-            Validator::ExtraDigest(hasher) => hex::encode(hasher.finalize()),
-        };
-
-        if expected != obtained {
-            if is_vpnless {
-                return Err(HttpDownloadError::MaybeNotAllowedOnVpnless {
-                    kind,
-                    want: expected.to_owned(),
-                    got: obtained,
-                    url: url.to_owned(),
-                    path: abs_path.to_string(),
-                });
-            }
-            return Err(HttpDownloadError::InvalidChecksum(
-                kind,
-                expected.to_owned(),
-                obtained,
-                url.to_owned(),
-            ));
-        }
-    }
-
-    Ok(digest)
+    Ok(())
 }
 
 #[cfg(test)]
 mod test {
     use assert_matches::assert_matches;
     use buck2_common::cas_digest::testing;
+    use buck2_common::http::HttpClientBuilder;
+    use buck2_core::fs::project::ProjectRootTemp;
     use futures::stream;
+    use tokio::io::AsyncReadExt;
+    use tokio::io::AsyncWriteExt;
 
     use super::*;
 
@@ -285,17 +473,17 @@ mod test {
         checksum: &Checksum,
     ) -> Result<(FileDigest, Vec<u8>), HttpDownloadError> {
         let mut out = Vec::new();
+        let mut hasher = DownloadHasher::new(digest_config, checksum);
 
-        let digest = copy_and_hash(
+        copy_and_hash(
             "test",
             "test",
             stream::iter(vec![Ok(Bytes::from("foo")), Ok(Bytes::from("bar"))]),
             &mut out,
-            digest_config,
-            checksum,
-            false,
+            &mut hasher,
         )
         .await?;
+        let digest = hasher.finish("test", "test", false)?;
 
         Ok((digest, out))
     }
@@ -338,6 +526,93 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_resumed_at() -> anyhow::Result<()> {
+        let response = |status, range: Option<&str>| {
+            let mut builder = Response::builder().status(status);
+            if let Some(range) = range {
+                builder = builder.header(hyper::header::CONTENT_RANGE, range);
+            }
+            builder.body(())
+        };
+
+        assert_eq!(resumed_at(&response(StatusCode::OK, None)?), None);
+        assert_eq!(
+            resumed_at(&response(
+                StatusCode::PARTIAL_CONTENT,
+                Some("bytes 100-199/200")
+            )?),
+            Some(100)
+        );
+        assert_eq!(
+            resumed_at(&response(StatusCode::PARTIAL_CONTENT, Some("items 1-2/3"))?),
+            Some(u64::MAX)
+        );
+        Ok(())
+    }
+
+    /// Serves `responses` in order, one connection each, returning the URL to request and the
+    /// requests received.
+    async fn serve(
+        responses: Vec<&'static str>,
+    ) -> anyhow::Result<(String, tokio::task::JoinHandle<anyhow::Result<Vec<String>>>)> {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("http://{}/foo", listener.local_addr()?);
+        let server = tokio::spawn(async move {
+            let mut requests = Vec::new();
+            for response in responses {
+                let (mut socket, _) = listener.accept().await?;
+                let mut request = Vec::new();
+                while !request.ends_with(b"\r\n\r\n") {
+                    let mut buf = [0; 1024];
+                    let n = socket.read(&mut buf).await?;
+                    anyhow::ensure!(n > 0, "Connection closed mid-request");
+                    request.extend_from_slice(&buf[..n]);
+                }
+                requests.push(String::from_utf8(request)?.to_lowercase());
+                socket.write_all(response.as_bytes()).await?;
+                socket.shutdown().await?;
+            }
+            Ok(requests)
+        });
+        Ok((url, server))
+    }
+
+    #[tokio::test]
+    async fn test_http_download_restarts_on_unexpected_range() -> anyhow::Result<()> {
+        let (url, server) = serve(vec![
+            // Interrupted after 3 bytes.
+            "HTTP/1.1 200 OK\r\nContent-Length: 6\r\nConnection: close\r\n\r\nfoo",
+            // Asked to resume at byte 3, but starts at byte 0.
+            "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 0-5/6\r\nContent-Length: 6\r\nConnection: close\r\n\r\nfoobar",
+            "HTTP/1.1 200 OK\r\nContent-Length: 6\r\nConnection: close\r\n\r\nfoobar",
+        ])
+        .await?;
+
+        let fs = ProjectRootTemp::new()?;
+        let path = ProjectRelativePath::new("out/foo")?;
+        http_download(
+            &HttpClientBuilder::https_with_system_roots()?.build(),
+            fs.path(),
+            DigestConfig::testing_default(),
+            path,
+            &[Arc::from(url)],
+            &Checksum::Sha256(Arc::from(
+                "c3ab8ff13720e8ad9047dd39466b3c8974e592c2fa383d4a3960714caef0c4f2",
+            )),
+            false,
+        )
+        .await?;
+
+        assert_eq!("foobar", fs_util::read_to_string(fs.path().resolve(path))?);
+        let requests = server.await??;
+        assert_eq!(3, requests.len());
+        assert!(!requests[0].contains("range:"), "{}", requests[0]);
+        assert!(requests[1].contains("range: bytes=3-"), "{}", requests[1]);
+        assert!(!requests[2].contains("range:"), "{}", requests[2]);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_copy_and_hash_invalid_secondary_hash() -> anyhow::Result<()> {
         assert_matches!(
@@ -518,10 +518,10 @@ impl CasDownloadInfo {
 
 /// Information about a CAS download we might require when an artifact is not materialized.
 #[derive(Debug, Display)]
-#[display(fmt = "{} declared by {}", "self.url", "self.owner")]
+#[display(fmt = "{} declared by {}", "self.urls.join(\", \")", "self.owner")]
 pub struct HttpDownloadInfo {
-    /// URL to download the file from.
-    pub url: Arc<str>,
+    /// URLs to download the file from, tried in order.
+    pub urls: Arc<[Arc<str>]>,
 
     /// Size, whether the file is executable. Also contains a digest, which is a bit of a shame
     /// since it's duplicative of checksum.
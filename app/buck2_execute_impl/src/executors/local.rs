@@ -424,7 +424,9 @@ impl LocalExecutor {
                         .into_iter()
                         .map(|(k, v)| (OsString::from(k), v.to_owned()))
                         .collect();
-                    Ok(worker.exec_cmd(request.args(), env).await)
+                    Ok(worker
+                        .exec_cmd(request.args(), env, request.timeout())
+                        .await)
                 } else {
                     self.exec(
                         &args[0],
@@ -822,6 +824,14 @@ pub async fn materialize_inputs(
 
                 scratch.0 = Some(path);
             }
+            CommandExecutionInput::IncrementalStatePath(path) => {
+                let path = artifact_fs
+                    .buck_out_path_resolver()
+                    .resolve_incremental_state(path);
+
+                // Unlike the scratch path, this is left as the previous execution left it.
+                fs_util::create_dir_all(artifact_fs.fs().resolve(&path))?;
+            }
         }
     }
 
@@ -885,7 +895,8 @@ async fn check_inputs(
                     CommandExecutionInput::ActionMetadata(..) => {
                         // Ignore those here.
                     }
-                    CommandExecutionInput::ScratchPath(..) => {
+                    CommandExecutionInput::ScratchPath(..)
+                    | CommandExecutionInput::IncrementalStatePath(..) => {
                         // Nothing to look at
                     }
                 }
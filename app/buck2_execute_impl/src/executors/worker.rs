@@ -33,15 +33,22 @@ use buck2_execute::execute::result::CommandExecutionResult;
 use buck2_forkserver::client::ForkserverClient;
 use buck2_forkserver::run::GatherOutputStatus;
 use buck2_worker_proto::execute_command::EnvironmentEntry;
+use buck2_worker_proto::execute_event;
 use buck2_worker_proto::worker_client::WorkerClient;
+use buck2_worker_proto::ExecuteCancel;
 use buck2_worker_proto::ExecuteCommand;
+use buck2_worker_proto::ExecuteEvent;
 use buck2_worker_proto::ExecuteResponse;
+use futures::future;
 use futures::future::BoxFuture;
 use futures::future::Shared;
+use futures::stream;
 use futures::FutureExt;
+use futures::StreamExt;
 use host_sharing::HostSharingBroker;
 use host_sharing::HostSharingStrategy;
 use indexmap::IndexMap;
+use tokio::sync::oneshot;
 use tokio::task::JoinHandle;
 use tonic::transport::Channel;
 
@@ -376,6 +383,51 @@ impl WorkerHandle {
     }
 }
 
+/// How long a worker is given to stop a timed out command and report its output.
+const CANCEL_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+impl WorkerHandle {
+    /// Runs `request` with the streaming `Exec` call, so that the command can be cancelled once
+    /// `timeout` elapses while still receiving the output it produced so far. Returns that output
+    /// if the command timed out.
+    async fn execute_with_timeout(
+        &self,
+        request: ExecuteCommand,
+        timeout: Duration,
+    ) -> Result<Result<tonic::Response<ExecuteResponse>, tonic::Status>, Vec<u8>> {
+        let (cancel_tx, cancel_rx) = oneshot::channel();
+        let events = stream::iter([ExecuteEvent {
+            data: Some(execute_event::Data::Command(request.clone())),
+        }])
+        .chain(stream::once(cancel_rx).filter_map(|cancel| {
+            future::ready(cancel.ok().map(|()| ExecuteEvent {
+                data: Some(execute_event::Data::Cancel(ExecuteCancel {})),
+            }))
+        }));
+
+        let mut client = self.client.clone();
+        let mut response = Box::pin(client.exec(events));
+        match tokio::time::timeout(timeout, &mut response).await {
+            Ok(Err(status)) if status.code() == tonic::Code::Unimplemented => {
+                // Workers that predate `Exec` can only be cancelled by dropping the request,
+                // which they are expected to handle by stopping the command, but loses its output.
+                drop(response);
+                tokio::time::timeout(timeout, client.execute(request))
+                    .await
+                    .map_err(|_| Vec::new())
+            }
+            Ok(response) => Ok(response),
+            Err(_) => {
+                let _ignored = cancel_tx.send(());
+                match tokio::time::timeout(CANCEL_GRACE_PERIOD, response).await {
+                    Ok(Ok(response)) => Err(response.into_inner().stderr.into()),
+                    _ => Err(Vec::new()),
+                }
+            }
+        }
+    }
+}
+
 #[cfg(unix)]
 fn env_entries(env: &[(OsString, OsString)]) -> Vec<EnvironmentEntry> {
     use std::os::unix::ffi::OsStrExt;
@@ -397,6 +449,7 @@ impl WorkerHandle {
         &self,
         args: &[String],
         env: Vec<(OsString, OsString)>,
+        timeout: Option<Duration>,
     ) -> (GatherOutputStatus, Vec<u8>, Vec<u8>) {
         tracing::info!(
             "Sending worker command:\nExecuteCommand {{ argv: {:?}, env: {:?} }}\n",
@@ -407,7 +460,13 @@ impl WorkerHandle {
         let env: Vec<EnvironmentEntry> = env_entries(&env);
 
         let request = ExecuteCommand { argv, env };
-        let response = self.client.clone().execute(request).await;
+        let response = match timeout {
+            Some(timeout) => match self.execute_with_timeout(request, timeout).await {
+                Ok(response) => response,
+                Err(stderr) => return (GatherOutputStatus::TimedOut(timeout), vec![], stderr),
+            },
+            None => self.client.clone().execute(request).await,
+        };
 
         match response {
             Ok(response) => {
@@ -227,7 +227,7 @@ impl DefaultIoHandler {
                         &self.fs,
                         self.digest_config,
                         &path,
-                        &info.url,
+                        &info.urls,
                         &info.checksum,
                         info.metadata.is_executable,
                     )
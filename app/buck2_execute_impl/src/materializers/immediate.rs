@@ -176,7 +176,7 @@ impl Materializer for ImmediateMaterializer {
             &self.fs,
             self.digest_config,
             &path,
-            &info.url,
+            &info.urls,
             &info.checksum,
             info.metadata.is_executable,
         )
@@ -102,11 +102,14 @@ impl WatchmanQueryProcessor {
     ) -> anyhow::Result<()> {
         let cell_path = self.cells.get_cell_path(path)?;
 
+        // External cells are read-only once materialized, so changes to them are not ours to
+        // track (the notify watcher skips them as part of `buck-out`).
         let ignore = self
             .ignore_specs
             .get(&cell_path.cell())
             .expect("unexpected cell name mismatch")
-            .is_match(cell_path.path());
+            .is_match(cell_path.path())
+            || self.cells.get(cell_path.cell())?.external().is_some();
 
         info!("Watchman: {:?} (ignore = {})", ev, ignore);
 
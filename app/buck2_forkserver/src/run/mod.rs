@@ -20,10 +20,13 @@ use std::process::Stdio;
 use std::task::Context;
 use std::task::Poll;
 use std::time::Duration;
+use std::time::Instant;
 
 use anyhow::Context as _;
 use buck2_core::fs::fs_util;
 use buck2_core::fs::paths::abs_path::AbsPath;
+use buck2_events::dispatch::get_dispatcher_opt;
+use buck2_events::dispatch::EventDispatcher;
 use bytes::Bytes;
 use futures::future::Future;
 use futures::future::FutureExt;
@@ -270,11 +273,15 @@ where
 
     let mut stdout = Vec::<u8>::new();
     let mut stderr = Vec::<u8>::new();
+    let mut stderr_tail = StderrTailReporter::new();
 
     while let Some(event) = stream.try_next().await? {
         match event {
             CommandEvent::Stdout(bytes) => stdout.extend(&bytes),
-            CommandEvent::Stderr(bytes) => stderr.extend(&bytes),
+            CommandEvent::Stderr(bytes) => {
+                stderr.extend(&bytes);
+                stderr_tail.report(&stderr);
+            }
             CommandEvent::Exit(exit) => return Ok((exit, stdout, stderr)),
         }
     }
@@ -284,6 +291,45 @@ where
     ))
 }
 
+/// Periodically reports the end of the stderr of a running command, so that the console can show
+/// what it's doing. Nothing is reported unless there is an event dispatcher, i.e. in the daemon.
+struct StderrTailReporter {
+    dispatcher: Option<EventDispatcher>,
+    last_reported: Option<Instant>,
+}
+
+impl StderrTailReporter {
+    const INTERVAL: Duration = Duration::from_secs(1);
+    const MAX_BYTES: usize = 4096;
+
+    fn new() -> Self {
+        Self {
+            dispatcher: get_dispatcher_opt(),
+            last_reported: None,
+        }
+    }
+
+    fn report(&mut self, stderr: &[u8]) {
+        let dispatcher = match &self.dispatcher {
+            Some(dispatcher) => dispatcher,
+            None => return,
+        };
+        let now = Instant::now();
+        if self
+            .last_reported
+            .map_or(false, |last| now - last < Self::INTERVAL)
+        {
+            return;
+        }
+        self.last_reported = Some(now);
+
+        let tail = &stderr[stderr.len().saturating_sub(Self::MAX_BYTES)..];
+        dispatcher.instant_event(buck2_data::CommandStderrTail {
+            stderr: String::from_utf8_lossy(tail).into_owned(),
+        });
+    }
+}
+
 pub async fn gather_output<T>(
     cmd: Command,
     cancellation: T,
@@ -0,0 +1,457 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Validators constraining attribute values beyond their type, passed to `rule(validators = ...)`
+//! and evaluated when the target node is created.
+
+use std::fmt;
+use std::fmt::Display;
+use std::sync::Arc;
+
+use allocative::Allocative;
+use buck2_node::attrs::coerced_attr::CoercedAttr;
+use buck2_node::attrs::display::AttrDisplayWithContextExt;
+use buck2_node::attrs::fmt_context::AttrFmtContext;
+use buck2_node::attrs::inspect_options::AttrInspectOptions;
+use buck2_node::attrs::spec::AttributeSpec;
+use buck2_node::nodes::unconfigured::TargetNode;
+use derive_more::Display;
+use fancy_regex::Regex;
+use starlark::any::ProvidesStaticType;
+use starlark::coerce::Coerce;
+use starlark::environment::GlobalsBuilder;
+use starlark::environment::Methods;
+use starlark::environment::MethodsBuilder;
+use starlark::environment::MethodsStatic;
+use starlark::eval::Evaluator;
+use starlark::starlark_complex_value;
+use starlark::starlark_module;
+use starlark::values::starlark_value;
+use starlark::values::typing::StarlarkCallable;
+use starlark::values::Freeze;
+use starlark::values::Freezer;
+use starlark::values::NoSerialize;
+use starlark::values::StarlarkValue;
+use starlark::values::Trace;
+use starlark::values::Value;
+use starlark::values::ValueLike;
+use starlark::StarlarkDocs;
+
+#[derive(Debug, thiserror::Error)]
+enum AttrValidatorError {
+    #[error("Validator `{0}` refers to unknown attribute `{1}`")]
+    UnknownAttribute(String, String),
+    #[error("`attr_validators.range` requires `min`, `max` or both")]
+    RangeWithoutBounds,
+    #[error("`attr_validators.mutually_exclusive` requires at least two attributes")]
+    MutuallyExclusiveTooFew,
+    #[error("`{validator}` can't validate attribute `{attr}` with value `{value}`")]
+    UnsupportedValue {
+        validator: String,
+        attr: String,
+        value: String,
+    },
+    #[error("Attribute `{0}` must be at least {1}, got {2}")]
+    BelowMin(String, i32, i64),
+    #[error("Attribute `{0}` must be at most {1}, got {2}")]
+    AboveMax(String, i32, i64),
+    #[error("Attribute `{0}` must match regex `{1}`, got `{2}`")]
+    RegexMismatch(String, String, String),
+    #[error("Attribute `{0}` must not be empty")]
+    Empty(String),
+    #[error("Attributes {} are mutually exclusive, but {} were set", .0.join(", "), .1.join(", "))]
+    MutuallyExclusive(Vec<String>, Vec<String>),
+    #[error("Attribute `{0}` was rejected by predicate: {1}")]
+    PredicateFailed(String, String),
+    #[error("Predicate for attribute `{0}` must return a bool, got `{1}`")]
+    PredicateNotBool(String, String),
+}
+
+/// The check performed by a validator.
+#[derive(Debug, Clone, Trace, Coerce, Allocative)]
+#[repr(C)]
+pub enum AttrValidatorCheck<V> {
+    Range {
+        min: Option<i32>,
+        max: Option<i32>,
+    },
+    Regex(
+        #[trace(unsafe_ignore)]
+        #[allocative(skip)]
+        Arc<Regex>,
+    ),
+    NonEmpty,
+    MutuallyExclusive,
+    Predicate {
+        function: V,
+        message: Option<String>,
+    },
+}
+
+/// Value of `attr_validators.xxx(...)`.
+#[derive(
+    Debug,
+    Clone,
+    Trace,
+    Coerce,
+    ProvidesStaticType,
+    NoSerialize,
+    Allocative
+)]
+#[repr(C)]
+pub struct StarlarkAttrValidatorGen<V> {
+    /// Attributes this validator applies to; more than one only for `mutually_exclusive`.
+    attrs: Vec<String>,
+    check: AttrValidatorCheck<V>,
+}
+
+starlark_complex_value!(pub StarlarkAttrValidator);
+
+impl<V> Display for StarlarkAttrValidatorGen<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let attrs = self
+            .attrs
+            .iter()
+            .map(|a| format!("{:?}", a))
+            .collect::<Vec<_>>()
+            .join(", ");
+        match &self.check {
+            AttrValidatorCheck::Range { min, max } => {
+                write!(f, "attr_validators.range({}", attrs)?;
+                if let Some(min) = min {
+                    write!(f, ", min={}", min)?;
+                }
+                if let Some(max) = max {
+                    write!(f, ", max={}", max)?;
+                }
+                write!(f, ")")
+            }
+            AttrValidatorCheck::Regex(regex) => {
+                write!(f, "attr_validators.regex({}, {:?})", attrs, regex.as_str())
+            }
+            AttrValidatorCheck::NonEmpty => write!(f, "attr_validators.non_empty({})", attrs),
+            AttrValidatorCheck::MutuallyExclusive => {
+                write!(f, "attr_validators.mutually_exclusive({})", attrs)
+            }
+            AttrValidatorCheck::Predicate { .. } => {
+                write!(f, "attr_validators.predicate({}, ...)", attrs)
+            }
+        }
+    }
+}
+
+impl<'v> Freeze for StarlarkAttrValidator<'v> {
+    type Frozen = FrozenStarlarkAttrValidator;
+
+    fn freeze(self, freezer: &Freezer) -> anyhow::Result<Self::Frozen> {
+        let check = match self.check {
+            AttrValidatorCheck::Range { min, max } => AttrValidatorCheck::Range { min, max },
+            AttrValidatorCheck::Regex(regex) => AttrValidatorCheck::Regex(regex),
+            AttrValidatorCheck::NonEmpty => AttrValidatorCheck::NonEmpty,
+            AttrValidatorCheck::MutuallyExclusive => AttrValidatorCheck::MutuallyExclusive,
+            AttrValidatorCheck::Predicate { function, message } => AttrValidatorCheck::Predicate {
+                function: function.freeze(freezer)?,
+                message,
+            },
+        };
+        Ok(StarlarkAttrValidatorGen {
+            attrs: self.attrs,
+            check,
+        })
+    }
+}
+
+#[starlark_value(type = "attr_validator")]
+impl<'v, V: ValueLike<'v> + 'v> StarlarkValue<'v> for StarlarkAttrValidatorGen<V> where
+    Self: ProvidesStaticType<'v>
+{
+}
+
+impl<'v> StarlarkAttrValidator<'v> {
+    /// Validators are declared before the rule's attributes are known to them, so attribute names
+    /// are only checked when passed to `rule()`.
+    pub(crate) fn check_attrs_exist(&self, spec: &AttributeSpec) -> anyhow::Result<()> {
+        for attr in &self.attrs {
+            if spec.attribute(attr).is_none() {
+                return Err(
+                    AttrValidatorError::UnknownAttribute(self.to_string(), attr.clone()).into(),
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FrozenStarlarkAttrValidator {
+    /// Run the validator against the attributes of a newly created target node.
+    pub(crate) fn validate<'v>(
+        &self,
+        node: &TargetNode,
+        eval: &mut Evaluator<'v, '_>,
+    ) -> anyhow::Result<()> {
+        if let AttrValidatorCheck::MutuallyExclusive = self.check {
+            let set: Vec<String> = self
+                .attrs
+                .iter()
+                .filter(|attr| {
+                    node.attr_or_none(attr, AttrInspectOptions::DefinedOnly)
+                        .is_some()
+                })
+                .map(|attr| format!("`{}`", attr))
+                .collect();
+            if set.len() > 1 {
+                return Err(AttrValidatorError::MutuallyExclusive(
+                    self.attrs
+                        .iter()
+                        .map(|attr| format!("`{}`", attr))
+                        .collect(),
+                    set,
+                )
+                .into());
+            }
+            return Ok(());
+        }
+
+        for attr in &self.attrs {
+            if let Some(value) = node.attr(attr, AttrInspectOptions::All)? {
+                self.validate_value(attr, value, eval)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Validate every value an attribute may take: each branch of a `select()` is checked
+    /// separately. Concatenations are only known after configuration, so they are skipped.
+    fn validate_value<'v>(
+        &self,
+        attr: &str,
+        value: &CoercedAttr,
+        eval: &mut Evaluator<'v, '_>,
+    ) -> anyhow::Result<()> {
+        match value {
+            CoercedAttr::Selector(selector) => {
+                for value in selector.all_values() {
+                    self.validate_value(attr, value, eval)?;
+                }
+                return Ok(());
+            }
+            CoercedAttr::Concat(_) => return Ok(()),
+            CoercedAttr::OneOf(value, _) => return self.validate_value(attr, value, eval),
+            // An unset `attrs.option` has nothing to validate.
+            CoercedAttr::None => return Ok(()),
+            _ => {}
+        }
+
+        match &self.check {
+            AttrValidatorCheck::Range { min, max } => {
+                let value = match value {
+                    CoercedAttr::Int(v) => *v,
+                    _ => return Err(self.unsupported(attr, value)),
+                };
+                if let Some(min) = min {
+                    if value < *min as i64 {
+                        return Err(
+                            AttrValidatorError::BelowMin(attr.to_owned(), *min, value).into()
+                        );
+                    }
+                }
+                if let Some(max) = max {
+                    if value > *max as i64 {
+                        return Err(
+                            AttrValidatorError::AboveMax(attr.to_owned(), *max, value).into()
+                        );
+                    }
+                }
+            }
+            AttrValidatorCheck::Regex(regex) => {
+                let s = match value {
+                    CoercedAttr::String(s) | CoercedAttr::EnumVariant(s) => &*s.0,
+                    _ => return Err(self.unsupported(attr, value)),
+                };
+                if !regex.is_match(s)? {
+                    return Err(AttrValidatorError::RegexMismatch(
+                        attr.to_owned(),
+                        regex.as_str().to_owned(),
+                        s.to_owned(),
+                    )
+                    .into());
+                }
+            }
+            AttrValidatorCheck::NonEmpty => {
+                let empty = match value {
+                    CoercedAttr::String(s) => s.0.is_empty(),
+                    CoercedAttr::List(l) => l.is_empty(),
+                    CoercedAttr::Tuple(t) => t.is_empty(),
+                    CoercedAttr::Dict(d) => d.is_empty(),
+                    _ => return Err(self.unsupported(attr, value)),
+                };
+                if empty {
+                    return Err(AttrValidatorError::Empty(attr.to_owned()).into());
+                }
+            }
+            AttrValidatorCheck::MutuallyExclusive => {
+                unreachable!("handled in `validate`")
+            }
+            AttrValidatorCheck::Predicate { function, message } => {
+                let json = value.to_json(&AttrFmtContext::NO_CONTEXT)?;
+                let arg = eval.heap().alloc(&json);
+                let res = eval.eval_function(function.to_value(), &[arg], &[])?;
+                match res.unpack_bool() {
+                    Some(true) => {}
+                    Some(false) => {
+                        return Err(AttrValidatorError::PredicateFailed(
+                            attr.to_owned(),
+                            message.clone().unwrap_or_else(|| format!("got `{}`", json)),
+                        )
+                        .into());
+                    }
+                    None => {
+                        return Err(AttrValidatorError::PredicateNotBool(
+                            attr.to_owned(),
+                            res.to_repr(),
+                        )
+                        .into());
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn unsupported(&self, attr: &str, value: &CoercedAttr) -> anyhow::Error {
+        AttrValidatorError::UnsupportedValue {
+            validator: self.to_string(),
+            attr: attr.to_owned(),
+            value: value.as_display_no_ctx().to_string(),
+        }
+        .into()
+    }
+}
+
+/// Functions creating validators, passed to `rule()` as `validators = [...]`.
+/// Validators run when a target is declared, so failures point at the call in the `BUCK` file.
+///
+/// ```python
+/// my_rule = rule(
+///     impl = _impl,
+///     attrs = {
+///         "jobs": attrs.int(default = 1),
+///         "srcs": attrs.list(attrs.source()),
+///         "version": attrs.string(),
+///     },
+///     validators = [
+///         attr_validators.range("jobs", min = 1, max = 64),
+///         attr_validators.non_empty("srcs"),
+///         attr_validators.regex("version", "^[0-9]+\\.[0-9]+$"),
+///     ],
+/// )
+/// ```
+///
+/// Each branch of a `select()` is validated separately. Values built by adding a `select()` to
+/// another value are not validated.
+#[starlark_module]
+fn attr_validators_module(registry: &mut MethodsBuilder) {
+    /// Require an `attrs.int` value to be within `min` and `max`, both inclusive.
+    fn range<'v>(
+        #[starlark(this)] _this: Value<'v>,
+        #[starlark(require = pos)] attr: &str,
+        #[starlark(require = named)] min: Option<i32>,
+        #[starlark(require = named)] max: Option<i32>,
+    ) -> anyhow::Result<StarlarkAttrValidator<'v>> {
+        if min.is_none() && max.is_none() {
+            return Err(AttrValidatorError::RangeWithoutBounds.into());
+        }
+        Ok(StarlarkAttrValidatorGen {
+            attrs: vec![attr.to_owned()],
+            check: AttrValidatorCheck::Range { min, max },
+        })
+    }
+
+    /// Require an `attrs.string` or `attrs.enum` value to match a regular expression.
+    /// Use `^` and `$` to match the whole value.
+    fn regex<'v>(
+        #[starlark(this)] _this: Value<'v>,
+        #[starlark(require = pos)] attr: &str,
+        #[starlark(require = pos)] pattern: &str,
+    ) -> anyhow::Result<StarlarkAttrValidator<'v>> {
+        Ok(StarlarkAttrValidatorGen {
+            attrs: vec![attr.to_owned()],
+            check: AttrValidatorCheck::Regex(Arc::new(Regex::new(pattern)?)),
+        })
+    }
+
+    /// Require a string, list, tuple or dict attribute value to be non-empty.
+    fn non_empty<'v>(
+        #[starlark(this)] _this: Value<'v>,
+        #[starlark(require = pos)] attr: &str,
+    ) -> anyhow::Result<StarlarkAttrValidator<'v>> {
+        Ok(StarlarkAttrValidatorGen {
+            attrs: vec![attr.to_owned()],
+            check: AttrValidatorCheck::NonEmpty,
+        })
+    }
+
+    /// Allow at most one of the given attributes to be set explicitly on a target.
+    fn mutually_exclusive<'v>(
+        #[starlark(this)] _this: Value<'v>,
+        #[starlark(args)] attrs: Vec<&str>,
+    ) -> anyhow::Result<StarlarkAttrValidator<'v>> {
+        if attrs.len() < 2 {
+            return Err(AttrValidatorError::MutuallyExclusiveTooFew.into());
+        }
+        Ok(StarlarkAttrValidatorGen {
+            attrs: attrs.into_iter().map(|a| a.to_owned()).collect(),
+            check: AttrValidatorCheck::MutuallyExclusive,
+        })
+    }
+
+    /// Require a function to return `True` for the attribute value. The function receives the
+    /// value as plain data: strings, numbers, lists and dicts, with labels and sources as strings.
+    /// `message` is reported when the function returns `False`.
+    fn predicate<'v>(
+        #[starlark(this)] _this: Value<'v>,
+        #[starlark(require = pos)] attr: &str,
+        #[starlark(require = pos)] function: StarlarkCallable<'v>,
+        #[starlark(require = named)] message: Option<&str>,
+    ) -> anyhow::Result<StarlarkAttrValidator<'v>> {
+        Ok(StarlarkAttrValidatorGen {
+            attrs: vec![attr.to_owned()],
+            check: AttrValidatorCheck::Predicate {
+                function: function.0,
+                message: message.map(|m| m.to_owned()),
+            },
+        })
+    }
+}
+
+#[derive(
+    Display,
+    Debug,
+    StarlarkDocs,
+    Allocative,
+    ProvidesStaticType,
+    NoSerialize
+)]
+#[display(fmt = "<attr_validators>")]
+struct AttrValidators;
+
+#[starlark_value(type = "attr_validators")]
+impl<'v> StarlarkValue<'v> for AttrValidators {
+    fn get_methods() -> Option<&'static Methods> {
+        static RES: MethodsStatic = MethodsStatic::new();
+        RES.methods(attr_validators_module)
+    }
+}
+
+pub fn register_attr_validators(globals: &mut GlobalsBuilder) {
+    globals.set(
+        "attr_validators",
+        globals.frozen_heap().alloc_simple(AttrValidators),
+    );
+}
@@ -41,6 +41,7 @@ use starlark::values::StarlarkValue;
 use starlark::values::Value;
 use starlark::values::ValueError;
 use starlark::StarlarkDocs;
+use starlark_map::small_map::SmallMap;
 use thiserror::Error;
 use tracing::error;
 
@@ -562,6 +563,28 @@ fn attr_module(registry: &mut MethodsBuilder) {
         Attribute::attr(eval, default, doc, coercer)
     }
 
+    /// Takes a dict of named fields from the user, supplies a record with those fields to the rule.
+    ///
+    /// Each field is declared as a keyword argument with its own attribute, e.g.
+    /// `attrs.record(name = attrs.string(), count = attrs.int(default = 1))`.
+    /// Fields may be configured with `select` independently, and fields with a default
+    /// may be omitted. The names `default` and `doc` cannot be used as field names.
+    fn record<'v>(
+        #[starlark(this)] _this: Value<'v>,
+        #[starlark(kwargs)] fields: SmallMap<String, &StarlarkAttribute>,
+        #[starlark(require = named)] default: Option<Value<'v>>,
+        #[starlark(require = named, default = "")] doc: &str,
+        eval: &mut Evaluator<'v, '_>,
+    ) -> anyhow::Result<StarlarkAttribute> {
+        let coercer = AttrType::record(
+            fields
+                .into_iter()
+                .map(|(name, field)| anyhow::Ok((name, field.attribute_for_inner()?)))
+                .collect::<anyhow::Result<_>>()?,
+        );
+        Attribute::attr(eval, default, doc, coercer)
+    }
+
     /// Takes an int from the user, supplies an int to the rule.
     fn int<'v>(
         #[starlark(this)] _this: Value<'v>,
@@ -33,6 +33,7 @@ mod one_of;
 mod option;
 pub mod plugin_dep;
 pub mod query;
+mod record;
 pub mod source;
 pub mod split_transition_dep;
 mod string;
@@ -110,6 +111,7 @@ impl AttrTypeInnerExt for AttrTypeInner {
             Self::Dict(x) => x.coerce_item(configurable, ctx, value),
             Self::List(x) => x.coerce_item(configurable, ctx, value),
             Self::Tuple(x) => x.coerce_item(configurable, ctx, value),
+            Self::Record(x) => x.coerce_item(configurable, ctx, value),
             Self::OneOf(x) => x.coerce_item(configurable, ctx, value),
             Self::Option(x) => x.coerce_item(configurable, ctx, value),
             Self::Source(x) => x.coerce_item(configurable, ctx, value),
@@ -142,6 +144,7 @@ impl AttrTypeInnerExt for AttrTypeInner {
             AttrTypeInner::Enum(x) => x.starlark_type(),
             AttrTypeInner::List(x) => x.starlark_type(),
             AttrTypeInner::Tuple(x) => x.starlark_type(),
+            AttrTypeInner::Record(x) => x.starlark_type(),
             AttrTypeInner::OneOf(x) => x.starlark_type(),
             AttrTypeInner::Option(x) => x.starlark_type(),
             AttrTypeInner::Query(x) => x.starlark_type(),
@@ -0,0 +1,93 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::sync::Arc;
+
+use buck2_node::attrs::attr::CoercedValue;
+use buck2_node::attrs::attr_type::record::RecordAttrType;
+use buck2_node::attrs::attr_type::record::RecordLiteral;
+use buck2_node::attrs::coerced_attr::CoercedAttr;
+use buck2_node::attrs::coercion_context::AttrCoercionContext;
+use buck2_node::attrs::configurable::AttrIsConfigurable;
+use dupe::Dupe;
+use gazebo::prelude::SliceExt;
+use starlark::typing::Ty;
+use starlark::values::dict::Dict;
+use starlark::values::dict::DictRef;
+use starlark::values::string::STRING_TYPE;
+use starlark::values::Value;
+
+use crate::attrs::coerce::attr_type::ty_maybe_select::TyMaybeSelect;
+use crate::attrs::coerce::attr_type::AttrTypeExt;
+use crate::attrs::coerce::error::CoercionError;
+use crate::attrs::coerce::AttrTypeCoerce;
+use crate::attrs::AttributeCoerceExt;
+
+#[derive(Debug, thiserror::Error)]
+enum RecordAttrTypeCoerceError {
+    #[error("Unknown record field `{0}`, expected one of: {}", .1.map(|x| format!("`{}`", x)).join(", "))]
+    UnknownField(String, Vec<String>),
+}
+
+impl AttrTypeCoerce for Arc<RecordAttrType> {
+    fn coerce_item(
+        &self,
+        configurable: AttrIsConfigurable,
+        ctx: &dyn AttrCoercionContext,
+        value: Value,
+    ) -> anyhow::Result<CoercedAttr> {
+        let dict = match DictRef::from_value(value) {
+            Some(d) => d,
+            None => return Err(CoercionError::type_error(Dict::TYPE, value).into()),
+        };
+
+        for key in dict.keys() {
+            let key = match key.unpack_str() {
+                Some(k) => k,
+                None => return Err(CoercionError::type_error(STRING_TYPE, key).into()),
+            };
+            if !self.fields.iter().any(|(name, _)| name == key) {
+                return Err(RecordAttrTypeCoerceError::UnknownField(
+                    key.to_owned(),
+                    self.fields.map(|(name, _)| name.clone()),
+                )
+                .into());
+            }
+        }
+
+        // Fields are coerced like the attributes of a rule: a missing or `None` value
+        // picks the default, and a field without a default must be provided.
+        let mut res = Vec::with_capacity(self.fields.len());
+        for (name, field) in &self.fields {
+            let value = dict.get_str(name).unwrap_or_else(Value::new_none);
+            let coerced = match field.coerce(name, configurable, ctx, value)? {
+                CoercedValue::Custom(v) => v,
+                CoercedValue::Default => (**field
+                    .default()
+                    .expect("default is present when coercion picks it"))
+                .clone(),
+            };
+            res.push((ctx.intern_str(name), coerced));
+        }
+        Ok(CoercedAttr::Record(RecordLiteral {
+            typ: self.dupe(),
+            fields: res.into(),
+        }))
+    }
+
+    fn starlark_type(&self) -> TyMaybeSelect {
+        TyMaybeSelect::Dict(
+            Box::new(TyMaybeSelect::Basic(Ty::string())),
+            Box::new(TyMaybeSelect::Union(
+                self.fields
+                    .map(|(_, field)| field.coercer().starlark_type()),
+            )),
+        )
+    }
+}
@@ -20,6 +20,7 @@ use crate::attrs::coerce::attr_type::ty_maybe_select::TyMaybeSelect;
 use crate::attrs::coerce::attr_type::AttrTypeExt;
 use crate::attrs::coerce::error::CoercionError;
 
+pub mod attr_validators;
 pub mod attrs_global;
 pub mod coerce;
 pub(crate) mod starlark_attribute;
@@ -60,6 +60,14 @@ impl StarlarkAttribute {
         Ok(self.0.coercer().dupe())
     }
 
+    /// Attribute to put into a record field (e. g. for `attrs.record(x = xxx)`).
+    pub fn attribute_for_inner(&self) -> anyhow::Result<Attribute> {
+        if self.0.is_default_only() {
+            return Err(StarlarkAttributeError::DefaultOnlyInNested.into());
+        }
+        Ok(self.0.clone())
+    }
+
     pub fn coercer_for_default_only(&self) -> AttrType {
         self.0.coercer().dupe()
     }
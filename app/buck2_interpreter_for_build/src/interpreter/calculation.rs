@@ -39,11 +39,17 @@ use buck2_node::nodes::eval_result::EvaluationResult;
 use buck2_node::nodes::frontend::TargetGraphCalculation;
 use buck2_node::nodes::frontend::TargetGraphCalculationImpl;
 use buck2_node::nodes::frontend::TARGET_GRAPH_CALCULATION_IMPL;
+use buck2_node::nodes::persistence::load_evaluation_result;
+use buck2_node::nodes::persistence::save_evaluation_result;
+use buck2_node::nodes::persistence::REGISTER_PERSISTENT_TARGET_KEYS;
 use buck2_node::package_values_calculation::PackageValuesCalculation;
 use buck2_node::package_values_calculation::PACKAGE_VALUES_CALCULATION;
 use derive_more::Display;
 use dice::DiceComputations;
+use dice::DiceData;
+use dice::DiceDataBuilder;
 use dice::Key;
+use dice::PersistentKey;
 use dupe::Dupe;
 use futures::future::BoxFuture;
 use futures::FutureExt;
@@ -54,6 +60,7 @@ use starlark_map::small_map::SmallMap;
 
 use crate::interpreter::dice_calculation_delegate::HasCalculationDelegate;
 use crate::interpreter::global_interpreter_state::HasGlobalInterpreterState;
+use crate::super_package::package_value::SuperPackageValuesImpl;
 
 // Key for 'InterpreterCalculation::get_interpreter_results'
 #[derive(Clone, Dupe, Display, Debug, Eq, Hash, PartialEq, Allocative)]
@@ -130,6 +137,44 @@ impl TargetGraphCalculationImpl for TargetGraphCalculationInstance {
     }
 }
 
+// Evaluations depend on the build file and the `.bzl` files it loads through the persisted file
+// ops keys, and on the package listing, which are checked against the file system on restore.
+#[async_trait]
+impl PersistentKey for InterpreterResultsKey {
+    const KIND: &'static str = "InterpreterResultsKey";
+
+    fn save(&self, value: &Self::Value) -> anyhow::Result<Option<Vec<u8>>> {
+        let result = match value {
+            Ok(result) => result,
+            Err(_) => return Ok(None),
+        };
+        if !SuperPackageValuesImpl::get(&**result.super_package().package_values())?
+            .is_json_lossless()?
+        {
+            return Ok(None);
+        }
+        save_evaluation_result(result)
+    }
+
+    fn load(data: &[u8], _global_data: &DiceData) -> anyhow::Result<(Self, Self::Value)> {
+        let result = load_evaluation_result(data, |values| {
+            Ok(Arc::new(SuperPackageValuesImpl::from_json(values)))
+        })?;
+        Ok((
+            InterpreterResultsKey(result.package()),
+            Ok(Arc::new(result)),
+        ))
+    }
+}
+
+fn register_persistent_interpreter_keys(builder: &mut DiceDataBuilder) {
+    builder.register_persistent_key::<InterpreterResultsKey>();
+}
+
+pub(crate) fn init_register_persistent_target_keys() {
+    REGISTER_PERSISTENT_TARGET_KEYS.init(register_persistent_interpreter_keys);
+}
+
 struct InterpreterCalculationInstance;
 struct PackageValuesCalculationInstance;
 
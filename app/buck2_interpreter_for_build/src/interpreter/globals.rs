@@ -20,6 +20,7 @@ use buck2_interpreter::types::regex::register_buck_regex;
 use buck2_interpreter::types::target_label::register_target_label;
 use starlark::environment::GlobalsBuilder;
 
+use crate::attrs::attr_validators::register_attr_validators;
 use crate::attrs::attrs_global::register_attrs;
 use crate::interpreter::build_defs::register_path;
 use crate::interpreter::functions::dedupe::register_dedupe;
@@ -58,6 +59,7 @@ pub fn register_universal_natives(builder: &mut GlobalsBuilder) {
     register_load_symbols(builder);
     register_rule_function(builder);
     register_attrs(builder);
+    register_attr_validators(builder);
     register_plugins(builder);
     register_providers_label(builder);
     register_cell_path(builder);
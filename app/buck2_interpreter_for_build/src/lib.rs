@@ -25,6 +25,7 @@ pub fn init_late_bindings() {
         attrs::attrs_global::init_coerce_target_label();
         interpreter::calculation::init_interpreter_calculation_impl();
         interpreter::calculation::init_target_graph_calculation_impl();
+        interpreter::calculation::init_register_persistent_target_keys();
         interpreter::build_context::init_starlark_path_from_build_context();
         plugins::init_plugin_kind_from_value_impl();
         rule::init_frozen_rule_get_impl();
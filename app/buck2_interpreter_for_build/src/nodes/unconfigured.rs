@@ -22,9 +22,11 @@ use buck2_node::package::Package;
 use buck2_node::rule::Rule;
 use dupe::Dupe;
 use starlark::eval::CallStack;
+use starlark::eval::Evaluator;
 use starlark::eval::ParametersParser;
 use starlark::values::Value;
 
+use crate::attrs::attr_validators::FrozenStarlarkAttrValidator;
 use crate::interpreter::module_internals::ModuleInternals;
 use crate::nodes::attr_spec::AttributeSpecExt;
 
@@ -45,6 +47,18 @@ pub trait TargetNodeExt: Sized {
         ignore_attrs_for_profiling: bool,
         call_stack: Option<CallStack>,
     ) -> anyhow::Result<Self>;
+
+    fn validate_attrs<'v>(
+        &self,
+        validators: &[FrozenStarlarkAttrValidator],
+        eval: &mut Evaluator<'v, '_>,
+    ) -> anyhow::Result<()>;
+}
+
+#[derive(Debug, thiserror::Error)]
+enum TargetNodeValidationError {
+    #[error("Invalid attributes for target `{0}` declared at:\n{1}")]
+    InvalidAttrs(TargetLabel, StarlarkCallStack),
 }
 
 impl TargetNodeExt for TargetNode {
@@ -116,4 +130,22 @@ impl TargetNodeExt for TargetNode {
             call_stack.map(StarlarkCallStack::new),
         ))
     }
+
+    /// Run the rule's attribute validators against a newly created target, pointing errors at
+    /// the call which declared it.
+    fn validate_attrs<'v>(
+        &self,
+        validators: &[FrozenStarlarkAttrValidator],
+        eval: &mut Evaluator<'v, '_>,
+    ) -> anyhow::Result<()> {
+        for validator in validators {
+            if let Err(e) = validator.validate(self, eval) {
+                return Err(e.context(TargetNodeValidationError::InvalidAttrs(
+                    self.label().dupe(),
+                    StarlarkCallStack::new(eval.call_stack()),
+                )));
+            }
+        }
+        Ok(())
+    }
 }
@@ -58,6 +58,8 @@ use starlark::values::Trace;
 use starlark::values::Value;
 use starlark_map::small_map::SmallMap;
 
+use crate::attrs::attr_validators::FrozenStarlarkAttrValidator;
+use crate::attrs::attr_validators::StarlarkAttrValidator;
 use crate::attrs::starlark_attribute::StarlarkAttribute;
 use crate::interpreter::build_context::BuildContext;
 use crate::interpreter::build_context::PerFileTypeContext;
@@ -98,6 +100,8 @@ pub struct RuleCallable<'v> {
     /// Optional map of the promise artifact name to starlark function.
     /// `None` for normal rules, `Some` for anon targets.
     artifact_promise_mappings: Option<ArtifactPromiseMappings<'v>>,
+    /// Checks on attribute values run when a target is declared.
+    validators: Vec<StarlarkAttrValidator<'v>>,
 }
 
 /// Mappings of promise artifact name to the starlark function that will produce it, for anon targets.
@@ -154,6 +158,7 @@ impl<'v> RuleCallable<'v> {
         is_toolchain_rule: bool,
         uses_plugins: Vec<Value<'v>>,
         artifact_promise_mappings: Option<ArtifactPromiseMappings<'v>>,
+        validators: Vec<&'v StarlarkAttrValidator<'v>>,
         eval: &mut Evaluator<'v, '_>,
     ) -> anyhow::Result<RuleCallable<'v>> {
         // TODO(nmj): Add default attributes in here like 'name', 'visibility', etc
@@ -195,6 +200,11 @@ impl<'v> RuleCallable<'v> {
             AttributeSpec::from(sorted_validated_attrs, artifact_promise_mappings.is_some())?;
         let ty = Ty::ty_function(attributes.ty_function());
 
+        for validator in &validators {
+            validator.check_attrs_exist(&attributes)?;
+        }
+        let validators = validators.into_iter().cloned().collect();
+
         Ok(RuleCallable {
             import_path: bzl_path,
             id: RefCell::new(None),
@@ -207,6 +217,7 @@ impl<'v> RuleCallable<'v> {
             docs: Some(doc.to_owned()),
             ignore_attrs_for_profiling: build_context.ignore_attrs_for_profiling,
             artifact_promise_mappings,
+            validators,
         })
     }
 
@@ -289,6 +300,7 @@ impl<'v> Freeze for RuleCallable<'v> {
             }
             None => None,
         };
+        let validators = self.validators.freeze(freezer)?;
 
         Ok(FrozenRuleCallable {
             rule: Arc::new(Rule {
@@ -305,6 +317,7 @@ impl<'v> Freeze for RuleCallable<'v> {
             ty: self.ty,
             ignore_attrs_for_profiling: self.ignore_attrs_for_profiling,
             artifact_promise_mappings,
+            validators,
         })
     }
 }
@@ -321,6 +334,7 @@ pub struct FrozenRuleCallable {
     ty: Ty,
     ignore_attrs_for_profiling: bool,
     artifact_promise_mappings: Option<FrozenArtifactPromiseMappings>,
+    validators: Vec<FrozenStarlarkAttrValidator>,
 }
 starlark_simple_value!(FrozenRuleCallable);
 
@@ -383,6 +397,10 @@ impl<'v> StarlarkValue<'v> for FrozenRuleCallable {
                 self.ignore_attrs_for_profiling,
                 call_stack,
             )?;
+            if !self.ignore_attrs_for_profiling {
+                target_node.validate_attrs(&self.validators, eval)?;
+            }
+            let internals = ModuleInternals::from_context(eval, self.rule.rule_type.name())?;
             internals.record(target_node)?;
             Ok(Value::new_none())
         })
@@ -415,6 +433,9 @@ pub fn register_rule_function(builder: &mut GlobalsBuilder) {
     ///     "exe": attrs.option(attrs.bool(), default = False),
     /// })
     /// ```
+    ///
+    /// `validators` is a list of `attr_validators` checks on attribute values, run when each
+    /// target is declared.
     fn rule<'v>(
         #[starlark(require = named)] r#impl: StarlarkCallable<'v>,
         #[starlark(require = named)] attrs: DictOf<'v, &'v str, &'v StarlarkAttribute>,
@@ -423,6 +444,9 @@ pub fn register_rule_function(builder: &mut GlobalsBuilder) {
         #[starlark(require = named, default = false)] is_configuration_rule: bool,
         #[starlark(require = named, default = false)] is_toolchain_rule: bool,
         #[starlark(require = named, default = Vec::new())] uses_plugins: Vec<Value<'v>>,
+        #[starlark(require = named, default = Vec::new())] validators: Vec<
+            &'v StarlarkAttrValidator<'v>,
+        >,
         eval: &mut Evaluator<'v, '_>,
     ) -> anyhow::Result<RuleCallable<'v>> {
         RuleCallable::new(
@@ -434,6 +458,7 @@ pub fn register_rule_function(builder: &mut GlobalsBuilder) {
             is_toolchain_rule,
             uses_plugins,
             None,
+            validators,
             eval,
         )
     }
@@ -465,6 +490,7 @@ pub fn register_rule_function(builder: &mut GlobalsBuilder) {
                     .map(|(k, v)| (*k, v.0))
                     .collect::<SmallMap<_, _>>(),
             }),
+            Vec::new(),
             eval,
         )
     }
@@ -70,6 +70,33 @@ impl SuperPackageValuesImpl {
             }))
         }
     }
+
+    /// Restores values from their JSON form, which they are persisted as.
+    pub(crate) fn from_json(values: SmallMap<MetadataKey, serde_json::Value>) -> Self {
+        SuperPackageValuesImpl {
+            values: values
+                .into_iter()
+                .map(|(key, value)| {
+                    (
+                        key,
+                        OwnedFrozenStarlarkPackageValue(OwnedFrozenValue::alloc(value)),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    /// Whether `from_json` restores the values as they are. It doesn't for tuples or records, for
+    /// example, which JSON doesn't tell apart from lists or dicts.
+    pub(crate) fn is_json_lossless(&self) -> anyhow::Result<bool> {
+        for value in self.values.values() {
+            let restored = OwnedFrozenValue::alloc(value.to_json_value()?);
+            if !restored.value().equals(value.0.value())? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
 }
 
 impl SuperPackageValues for SuperPackageValuesImpl {
@@ -109,6 +109,36 @@ fn enum_works() -> SharedResult<()> {
     ))
 }
 
+#[test]
+fn record_works() -> SharedResult<()> {
+    let mut tester = tester();
+    tester.run_starlark_bzl_test(indoc!(
+        r#"
+        frozen = attrs.record(name = attrs.string(), count = attrs.int(default = 1))
+        def test():
+            not_frozen = attrs.record(
+                name = attrs.string(),
+                count = attrs.int(default = 1),
+                default = {"name": "x"},
+            )
+            assert_eq('attrs.record(name=attrs.string(), count=attrs.int(default=1))', repr(frozen))
+            assert_eq('attrs.record(name=attrs.string(), count=attrs.int(default=1), default={"name": "x","count": 1})', repr(not_frozen))
+        "#
+    ))?;
+
+    let mut tester = tester();
+    tester.run_starlark_bzl_test_expecting_error(
+        indoc!(
+            r#"
+        def test():
+            attrs.record(name = attrs.string(), default = {"nam": "x"})
+        "#
+        ),
+        "Unknown record field `nam`",
+    );
+    Ok(())
+}
+
 #[test]
 fn attr_coercer_coerces() -> anyhow::Result<()> {
     let heap = Heap::new();
@@ -7,6 +7,8 @@
  * of this source tree.
  */
 
+use std::sync::Arc;
+
 use buck2_build_api::interpreter::rule_defs::provider::registration::register_builtin_providers;
 use buck2_build_api::interpreter::rule_defs::register_rule_defs;
 use buck2_common::legacy_configs::cells::BuckConfigBasedCells;
@@ -19,10 +21,12 @@ use buck2_core::cells::name::CellName;
 use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
 use buck2_core::fs::project::ProjectRoot;
 use buck2_core::fs::project_rel_path::ProjectRelativePath;
+use buck2_core::target::name::TargetNameRef;
 use buck2_interpreter::file_loader::LoadedModules;
 use buck2_interpreter::paths::module::OwnedStarlarkModulePath;
 use buck2_interpreter::paths::path::StarlarkPath;
 use buck2_interpreter_for_build::attrs::attrs_global::register_attrs;
+use buck2_interpreter_for_build::interpreter::calculation::InterpreterResultsKey;
 use buck2_interpreter_for_build::interpreter::natives::register_module_natives;
 use buck2_interpreter_for_build::interpreter::testing::run_simple_starlark_test;
 use buck2_interpreter_for_build::interpreter::testing::CellsData;
@@ -30,6 +34,8 @@ use buck2_interpreter_for_build::interpreter::testing::Tester;
 use buck2_interpreter_for_build::rule::register_rule_function;
 use buck2_node::attrs::inspect_options::AttrInspectOptions;
 use buck2_node::nodes::unconfigured::testing::targets_to_json;
+use dice::DiceData;
+use dice::PersistentKey;
 use dupe::Dupe;
 use gazebo::prelude::SliceExt;
 use indoc::indoc;
@@ -182,6 +188,96 @@ fn test_eval_build_file() {
     assert_eq!(vec!["invoke_some-exported", "java"], target_names);
 }
 
+#[test]
+fn test_persist_evaluation_result() -> anyhow::Result<()> {
+    let mut tester = Tester::new()?;
+    tester.additional_globals(register_rule_function);
+    tester.additional_globals(register_attrs);
+    tester.additional_globals(register_builtin_providers);
+    tester.additional_globals(register_module_natives);
+
+    tester.add_import(
+        &ImportPath::testing_new("root//:rules.bzl"),
+        indoc!(
+            r#"
+            def _impl(ctx):
+                return DefaultInfo()
+
+            library = rule(
+                impl = _impl,
+                attrs = {
+                    "srcs": attrs.list(attrs.source(), default = []),
+                    "deps": attrs.list(attrs.dep(), default = []),
+                    "args": attrs.list(attrs.arg(), default = []),
+                    "kind": attrs.enum(["a", "b"], default = "a"),
+                    "opts": attrs.dict(
+                        attrs.string(),
+                        attrs.one_of(attrs.int(), attrs.bool()),
+                        default = {},
+                    ),
+                    "pair": attrs.option(
+                        attrs.tuple(attrs.string(), attrs.label()),
+                        default = None,
+                    ),
+                    "settings": attrs.option(
+                        attrs.record(level = attrs.int(), name = attrs.string(default = "x")),
+                        default = None,
+                    ),
+                },
+            )
+            "#
+        ),
+    )?;
+
+    let build_path = BuildFilePath::testing_new("root//some/package:BUILD");
+    let eval_result = tester.eval_build_file(
+        &build_path,
+        indoc!(
+            r#"
+            load("@root//:rules.bzl", "library")
+
+            oncall("team")
+
+            library(name = "lib", srcs = ["a.c"], visibility = ["PUBLIC"])
+            library(
+                name = "bin",
+                deps = [":lib", "//other:dep[sub]"],
+                args = ["--lib=$(location :lib)"],
+                kind = "b",
+                opts = {"x": 1, "y": select({"//config:c": True, "DEFAULT": False})},
+                pair = ("p", ":lib"),
+                settings = {"level": 2},
+            )
+            "#
+        ),
+        PackageListing::testing_files(&["a.c"]),
+    )?;
+
+    let eval_result = Arc::new(eval_result);
+    let key = InterpreterResultsKey(eval_result.package());
+    let data = key
+        .save(&Ok(eval_result.dupe()))?
+        .expect("package is persisted");
+    let (restored_key, restored) = InterpreterResultsKey::load(&data, &DiceData::new())?;
+    let restored = restored?;
+    assert_eq!(key, restored_key);
+    assert_eq!(eval_result.buildfile_path(), restored.buildfile_path());
+    assert_eq!(eval_result.imports(), restored.imports());
+    assert_eq!(
+        eval_result.targets().values().collect::<Vec<_>>(),
+        restored.targets().values().collect::<Vec<_>>(),
+    );
+    let bin = restored
+        .targets()
+        .get(TargetNameRef::new("bin")?)
+        .expect("target is restored");
+    assert_eq!(Some("team"), bin.oncall());
+
+    // Saving the restored targets again shows that nothing else was lost.
+    assert_eq!(Some(data), restored_key.save(&Ok(restored))?);
+    Ok(())
+}
+
 fn cells() -> CellsData {
     let repo_root = if cfg!(windows) { "C:/" } else { "/" };
     let project_fs =
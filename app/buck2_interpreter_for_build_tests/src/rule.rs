@@ -11,6 +11,7 @@ use buck2_build_api::interpreter::rule_defs::transitive_set::transitive_set_defi
 use buck2_common::result::SharedResult;
 use buck2_core::bzl::ImportPath;
 use buck2_interpreter::file_loader::LoadedModules;
+use buck2_interpreter_for_build::attrs::attr_validators::register_attr_validators;
 use buck2_interpreter_for_build::attrs::attrs_global::register_attrs;
 use buck2_interpreter_for_build::interpreter::testing::Tester;
 use buck2_interpreter_for_build::nodes::attr_spec::AttributeSpecExt;
@@ -33,6 +34,7 @@ fn rule_tester() -> Tester {
     tester.additional_globals(register_transitive_set);
     tester.additional_globals(register_rule_function);
     tester.additional_globals(register_attrs);
+    tester.additional_globals(register_attr_validators);
     tester
 }
 
@@ -265,6 +267,101 @@ fn udr_rejects_invalid_parameters() {
     );
 }
 
+#[test]
+fn udr_validates_attrs() -> anyhow::Result<()> {
+    let prefix = indoc!(
+        r#"
+        def impl(ctx):
+            pass
+
+        def _is_even(v):
+            return v % 2 == 0
+
+        foo_binary = rule(
+            impl=impl,
+            attrs={
+                "jobs": attrs.int(default=1),
+                "srcs": attrs.list(attrs.string(), default=["a"]),
+                "version": attrs.option(attrs.string(), default=None),
+                "shards": attrs.int(default=2),
+                "lib": attrs.option(attrs.string(), default=None),
+                "bin": attrs.option(attrs.string(), default=None),
+            },
+            validators=[
+                attr_validators.range("jobs", min=1, max=8),
+                attr_validators.non_empty("srcs"),
+                attr_validators.regex("version", "^[0-9]+\\.[0-9]+$"),
+                attr_validators.predicate("shards", _is_even, message="must be even"),
+                attr_validators.mutually_exclusive("lib", "bin"),
+            ],
+        )
+
+        def test():
+        "#
+    );
+
+    let run = |content: &str, msg: &str| {
+        let mut tester = rule_tester();
+        tester.run_starlark_test_expecting_error(&format!("{}\n{}", prefix, content), msg);
+    };
+
+    run(
+        r#"    foo_binary(name="t", jobs=9)"#,
+        "Attribute `jobs` must be at most 8, got 9",
+    );
+    run(
+        r#"    foo_binary(name="t", jobs=select({"DEFAULT": 0}))"#,
+        "Attribute `jobs` must be at least 1, got 0",
+    );
+    run(
+        r#"    foo_binary(name="t", srcs=[])"#,
+        "Attribute `srcs` must not be empty",
+    );
+    run(
+        r#"    foo_binary(name="t", version="1.x")"#,
+        "Attribute `version` must match regex",
+    );
+    run(
+        r#"    foo_binary(name="t", shards=3)"#,
+        "Attribute `shards` was rejected by predicate: must be even",
+    );
+    run(
+        r#"    foo_binary(name="t", lib="a", bin="b")"#,
+        "Attributes `lib`, `bin` are mutually exclusive",
+    );
+    run(
+        r#"    foo_binary(name="t", jobs=9)"#,
+        "Invalid attributes for target `root//some/package:t`",
+    );
+
+    let mut tester = rule_tester();
+    tester.run_starlark_test(&format!(
+        "{}\n{}",
+        prefix, r#"    foo_binary(name="t", jobs=8, version="1.2", shards=4, bin="b")"#
+    ))?;
+
+    let mut tester = rule_tester();
+    tester.run_starlark_bzl_test_expecting_error(
+        indoc!(
+            r#"
+            def impl(ctx):
+                pass
+
+            foo_binary = rule(
+                impl=impl,
+                attrs={"jobs": attrs.int()},
+                validators=[attr_validators.range("job", min=1)],
+            )
+
+            def test():
+                pass
+            "#
+        ),
+        "refers to unknown attribute `job`",
+    );
+    Ok(())
+}
+
 #[test]
 fn option_allows_none() -> anyhow::Result<()> {
     let mut tester = rule_tester();
@@ -32,6 +32,7 @@ impl ToJsonWithContext for ConfiguredAttr {
             ConfiguredAttr::List(list) => list.to_json(ctx),
             ConfiguredAttr::Tuple(list) => list.to_json(ctx),
             ConfiguredAttr::Dict(dict) => dict.to_json(ctx),
+            ConfiguredAttr::Record(record) => record.to_json(ctx),
             ConfiguredAttr::None => Ok(serde_json::Value::Null),
             ConfiguredAttr::OneOf(box l, _) => l.to_json(ctx),
             ConfiguredAttr::Visibility(v) => Ok(v.to_json()),
@@ -58,6 +59,7 @@ impl AnyMatches for ConfiguredAttr {
             ConfiguredAttr::List(vals) => vals.any_matches(filter),
             ConfiguredAttr::Tuple(vals) => vals.any_matches(filter),
             ConfiguredAttr::Dict(d) => d.any_matches(filter),
+            ConfiguredAttr::Record(r) => r.any_matches(filter),
             ConfiguredAttr::None => Ok(false),
             ConfiguredAttr::Bool(b) => b.any_matches(filter),
             ConfiguredAttr::Int(i) => filter(&i.to_string()),
@@ -17,6 +17,7 @@ use buck2_core::plugins::PluginKind;
 use buck2_core::plugins::PluginKindSet;
 use dupe::Dupe;
 
+use crate::attrs::attr::Attribute;
 use crate::attrs::attr_type::any::AnyAttrType;
 use crate::attrs::attr_type::arg::ArgAttrType;
 use crate::attrs::attr_type::bool::BoolAttrType;
@@ -34,6 +35,7 @@ use crate::attrs::attr_type::one_of::OneOfAttrType;
 use crate::attrs::attr_type::option::OptionAttrType;
 use crate::attrs::attr_type::plugin_dep::PluginDepAttrType;
 use crate::attrs::attr_type::query::QueryAttrType;
+use crate::attrs::attr_type::record::RecordAttrType;
 use crate::attrs::attr_type::source::SourceAttrType;
 use crate::attrs::attr_type::split_transition_dep::SplitTransitionDepAttrType;
 use crate::attrs::attr_type::string::StringAttrType;
@@ -62,6 +64,7 @@ pub mod one_of;
 pub mod option;
 pub mod plugin_dep;
 pub mod query;
+pub mod record;
 pub mod source;
 pub mod split_transition_dep;
 pub mod string;
@@ -84,6 +87,7 @@ pub enum AttrTypeInner {
     Dict(DictAttrType),
     List(ListAttrType),
     Tuple(TupleAttrType),
+    Record(Arc<RecordAttrType>),
     OneOf(OneOfAttrType),
     Option(OptionAttrType),
     PluginDep(PluginDepAttrType),
@@ -126,6 +130,7 @@ impl AttrType {
             AttrTypeInner::Dict(x) => x.fmt_with_arg(f, &arg()),
             AttrTypeInner::List(x) => x.fmt_with_arg(f, &arg()),
             AttrTypeInner::Tuple(x) => x.fmt_with_arg(f, &arg()),
+            AttrTypeInner::Record(x) => x.fmt_with_arg(f, &arg()),
             AttrTypeInner::OneOf(x) => x.fmt_with_arg(f, &arg()),
             AttrTypeInner::Option(x) => x.fmt_with_arg(f, &arg()),
             AttrTypeInner::Enum(x) => x.fmt_with_arg(f, &arg()),
@@ -259,6 +264,13 @@ impl AttrType {
         Self(Arc::new(AttrTypeInner::Tuple(TupleAttrType::new(xs))))
     }
 
+    /// A record attribute with named fields, each coerced by its own attribute.
+    pub fn record(fields: Vec<(String, Attribute)>) -> Self {
+        Self(Arc::new(AttrTypeInner::Record(Arc::new(
+            RecordAttrType::new(fields),
+        ))))
+    }
+
     pub fn one_of(xs: Vec<AttrType>) -> Self {
         Self(Arc::new(AttrTypeInner::OneOf(OneOfAttrType::new(xs))))
     }
@@ -324,6 +336,7 @@ impl AttrType {
             | AttrTypeInner::Int(_)
             | AttrTypeInner::Dep(_)
             | AttrTypeInner::Tuple(_)
+            | AttrTypeInner::Record(_)
             | AttrTypeInner::SplitTransitionDep(_)
             | AttrTypeInner::Label(_)
             | AttrTypeInner::Enum(_)
@@ -0,0 +1,154 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::any::Any;
+use std::fmt;
+use std::fmt::Formatter;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::ops::Deref;
+use std::sync::Arc;
+
+use allocative::Allocative;
+use anyhow::Context;
+use buck2_util::arc_str::ArcSlice;
+use buck2_util::arc_str::ArcStr;
+use once_cell::sync::OnceCell;
+use serde_json::Value;
+
+use crate::attrs::attr::Attribute;
+use crate::attrs::attr_type::any_matches::AnyMatches;
+use crate::attrs::display::AttrDisplayWithContext;
+use crate::attrs::display::AttrDisplayWithContextExt;
+use crate::attrs::fmt_context::AttrFmtContext;
+use crate::attrs::json::ToJsonWithContext;
+
+/// A record with a fixed set of named fields, each of which is an attribute of its own.
+#[derive(Debug, Allocative)]
+pub struct RecordAttrType {
+    pub fields: Vec<(String, Attribute)>,
+    /// The starlark record type the values of this attribute are resolved to. This crate doesn't
+    /// know about starlark values, so it is built by the analysis the first time it is needed.
+    #[allocative(skip)]
+    starlark_type: OnceCell<Box<dyn Any + Send + Sync>>,
+}
+
+impl PartialEq for RecordAttrType {
+    fn eq(&self, other: &Self) -> bool {
+        self.fields == other.fields
+    }
+}
+
+impl Eq for RecordAttrType {}
+
+impl Hash for RecordAttrType {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.fields.hash(state)
+    }
+}
+
+impl RecordAttrType {
+    pub fn new(fields: Vec<(String, Attribute)>) -> Self {
+        Self {
+            fields,
+            starlark_type: OnceCell::new(),
+        }
+    }
+
+    /// Returns the starlark record type of this attribute, building it with `init` on first use.
+    /// All values of the attribute share it, so that records with the same fields compare equal.
+    pub fn starlark_type<T: Any + Send + Sync>(
+        &self,
+        init: impl FnOnce() -> anyhow::Result<T>,
+    ) -> anyhow::Result<&T> {
+        self.starlark_type
+            .get_or_try_init(|| Ok(Box::new(init()?)))?
+            .downcast_ref()
+            .context("Record attribute starlark type was built with a different representation")
+    }
+
+    pub(crate) fn fmt_with_arg(&self, f: &mut fmt::Formatter<'_>, arg: &str) -> fmt::Result {
+        write!(f, "attrs.record(")?;
+        for (i, (name, attr)) in self.fields.iter().enumerate() {
+            if i != 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{name}={attr}")?;
+        }
+        write!(f, "{})", arg)
+    }
+}
+
+/// Values of a record attribute, one for each field of the type and in the same order.
+#[derive(Debug, Clone, Allocative)]
+pub struct RecordLiteral<C: Eq> {
+    /// The type of the attribute these are the values of.
+    #[allocative(skip)]
+    pub typ: Arc<RecordAttrType>,
+    pub fields: ArcSlice<(ArcStr, C)>,
+}
+
+impl<C: Eq> PartialEq for RecordLiteral<C> {
+    fn eq(&self, other: &Self) -> bool {
+        (Arc::ptr_eq(&self.typ, &other.typ) || self.typ == other.typ) && self.fields == other.fields
+    }
+}
+
+impl<C: Eq> Eq for RecordLiteral<C> {}
+
+impl<C: Eq + Hash> Hash for RecordLiteral<C> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // Values of different types with the same fields are rare, so skip hashing the type.
+        self.fields.hash(state)
+    }
+}
+
+impl<C: Eq> Deref for RecordLiteral<C> {
+    type Target = ArcSlice<(ArcStr, C)>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.fields
+    }
+}
+
+impl<C: Eq + AttrDisplayWithContext> AttrDisplayWithContext for RecordLiteral<C> {
+    fn fmt(&self, ctx: &AttrFmtContext, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{{")?;
+        for (i, (k, v)) in self.fields.iter().enumerate() {
+            if i != 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "\"{}\": {}", k, v.as_display(ctx))?;
+        }
+        write!(f, "}}")?;
+        Ok(())
+    }
+}
+
+impl<C: Eq + AnyMatches> AnyMatches for RecordLiteral<C> {
+    fn any_matches(&self, filter: &dyn Fn(&str) -> anyhow::Result<bool>) -> anyhow::Result<bool> {
+        for (_, v) in self.fields.iter() {
+            if v.any_matches(filter)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+}
+
+impl<C: Eq + ToJsonWithContext> ToJsonWithContext for RecordLiteral<C> {
+    fn to_json(&self, ctx: &AttrFmtContext) -> anyhow::Result<Value> {
+        let mut res: serde_json::Map<String, serde_json::Value> =
+            serde_json::Map::with_capacity(self.len());
+        for (k, v) in self.iter() {
+            res.insert((**k).to_owned(), v.to_json(ctx)?);
+        }
+        Ok(res.into())
+    }
+}
@@ -43,6 +43,7 @@ use crate::attrs::attr_type::dict::DictLiteral;
 use crate::attrs::attr_type::label::LabelAttrType;
 use crate::attrs::attr_type::list::ListLiteral;
 use crate::attrs::attr_type::query::QueryAttr;
+use crate::attrs::attr_type::record::RecordLiteral;
 use crate::attrs::attr_type::string::StringLiteral;
 use crate::attrs::attr_type::tuple::TupleLiteral;
 use crate::attrs::attr_type::AttrType;
@@ -82,6 +83,8 @@ enum SelectError {
 enum CoercedAttrError {
     #[error("Inconsistent number of elements in tuple")]
     InconsistentTupleLength,
+    #[error("Inconsistent fields in record")]
+    InconsistentRecordFields,
 }
 
 enum CoercedSelectorKeyRef<'a> {
@@ -149,11 +152,61 @@ impl CoercedSelector {
             )
     }
 
-    fn all_values(&self) -> impl Iterator<Item = &'_ CoercedAttr> {
+    pub fn all_values(&self) -> impl Iterator<Item = &'_ CoercedAttr> {
         self.all_entries().map(|(_, v)| v)
     }
 }
 
+/// Describes how a single `select()` was resolved when configuring an attribute.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+pub struct SelectResolution {
+    /// All the keys of the `select()`, in declaration order, with `DEFAULT` last if present.
+    pub keys: Vec<String>,
+    /// The key of the branch that was taken, or `DEFAULT`.
+    pub chosen: String,
+    /// The constraints of the chosen `config_setting` that matched the configuration.
+    pub matched_constraints: Vec<String>,
+    /// The buckconfig values of the chosen `config_setting` that matched.
+    pub matched_buckconfigs: Vec<String>,
+}
+
+impl SelectResolution {
+    fn new(
+        select: &CoercedSelector,
+        chosen: Option<(&TargetLabel, &ConfigSettingData)>,
+    ) -> SelectResolution {
+        let keys = select
+            .all_entries()
+            .map(|(k, _)| match k {
+                CoercedSelectorKeyRef::Target(k) => k.to_string(),
+                CoercedSelectorKeyRef::Default => "DEFAULT".to_owned(),
+            })
+            .collect();
+        match chosen {
+            Some((key, setting)) => SelectResolution {
+                keys,
+                chosen: key.to_string(),
+                matched_constraints: setting
+                    .constraints
+                    .iter()
+                    .map(|(k, v)| format!("{}={}", k, v))
+                    .collect(),
+                matched_buckconfigs: setting
+                    .buckconfigs
+                    .iter()
+                    .map(|(k, v)| format!("{}={}", k, v))
+                    .collect(),
+            },
+            None => SelectResolution {
+                keys,
+                chosen: "DEFAULT".to_owned(),
+                matched_constraints: Vec::new(),
+                matched_buckconfigs: Vec::new(),
+            },
+        }
+    }
+}
+
 /// CoercedAttr is the "coerced" representation of an attribute. It has been type-checked and converted to
 /// specific types (for example, where we expect target-like things, it has been converted to something like
 /// a TargetLabel or ProvidersLabel).
@@ -186,6 +239,7 @@ pub enum CoercedAttr {
     List(ListLiteral<CoercedAttr>),
     Tuple(TupleLiteral<CoercedAttr>),
     Dict(DictLiteral<CoercedAttr>),
+    Record(RecordLiteral<CoercedAttr>),
     None,
     // NOTE: unlike deps, labels are not traversed, as they are typically used in lieu of deps in
     // cases that would cause cycles.
@@ -253,6 +307,7 @@ impl AttrDisplayWithContext for CoercedAttr {
             CoercedAttr::List(list) => AttrDisplayWithContext::fmt(list, ctx, f),
             CoercedAttr::Tuple(v) => AttrDisplayWithContext::fmt(v, ctx, f),
             CoercedAttr::Dict(v) => AttrDisplayWithContext::fmt(v, ctx, f),
+            CoercedAttr::Record(v) => AttrDisplayWithContext::fmt(v, ctx, f),
             CoercedAttr::None => write!(f, "None"),
             CoercedAttr::OneOf(box l, _) => AttrDisplayWithContext::fmt(l, ctx, f),
             CoercedAttr::Visibility(v) => Display::fmt(v, f),
@@ -334,6 +389,7 @@ impl CoercedAttr {
             CoercedAttr::List(list) => list.to_json(ctx),
             CoercedAttr::Tuple(list) => list.to_json(ctx),
             CoercedAttr::Dict(dict) => dict.to_json(ctx),
+            CoercedAttr::Record(record) => record.to_json(ctx),
             CoercedAttr::None => Ok(serde_json::Value::Null),
             CoercedAttr::OneOf(box l, _) => l.to_json(ctx),
             CoercedAttr::Visibility(v) => Ok(v.to_json()),
@@ -440,6 +496,16 @@ impl CoercedAttr {
                 }
                 Ok(())
             }
+            CoercedAttrWithType::Record(record, t) => {
+                if record.len() != t.fields.len() {
+                    return Err(CoercedAttrError::InconsistentRecordFields.into());
+                }
+
+                for ((_, v), (_, field)) in record.iter().zip(&t.fields) {
+                    v.traverse(field.coercer(), pkg.dupe(), traversal)?;
+                }
+                Ok(())
+            }
 
             CoercedAttrWithType::OneOf(l, i, t) => {
                 let item_type = t.xs.get(i as usize).context("invalid enum")?;
@@ -478,6 +544,14 @@ impl CoercedAttr {
         ctx: &dyn AttrConfigurationContext,
         select_entries: &'a [(TargetLabel, CoercedAttr)],
     ) -> anyhow::Result<Option<&'a CoercedAttr>> {
+        Ok(Self::select_the_most_specific_entry(ctx, select_entries)?.map(|(_k, _conf, v)| v))
+    }
+
+    /// Like `select_the_most_specific`, but also returns the matching key and its setting.
+    fn select_the_most_specific_entry<'a, 'c>(
+        ctx: &'c dyn AttrConfigurationContext,
+        select_entries: &'a [(TargetLabel, CoercedAttr)],
+    ) -> anyhow::Result<Option<(&'a TargetLabel, &'c ConfigSettingData, &'a CoercedAttr)>> {
         let mut matching: Option<(&TargetLabel, &ConfigSettingData, &CoercedAttr)> = None;
         for (k, v) in select_entries {
             matching = match (ctx.matches(k), matching) {
@@ -498,7 +572,7 @@ impl CoercedAttr {
                 }
             }
         }
-        Ok(matching.map(|(_k, _conf, v)| v))
+        Ok(matching)
     }
 
     fn select<'a>(
@@ -519,6 +593,58 @@ impl CoercedAttr {
         }
     }
 
+    /// Records which branch was taken for every `select()` that is reached when configuring
+    /// this attribute in the provided context, outermost first. Branches that are not taken
+    /// are not visited, so `select()`s nested inside them are not reported.
+    pub fn select_resolutions(
+        &self,
+        ctx: &dyn AttrConfigurationContext,
+    ) -> anyhow::Result<Vec<SelectResolution>> {
+        let mut resolutions = Vec::new();
+        self.collect_select_resolutions(ctx, &mut resolutions)?;
+        Ok(resolutions)
+    }
+
+    fn collect_select_resolutions(
+        &self,
+        ctx: &dyn AttrConfigurationContext,
+        resolutions: &mut Vec<SelectResolution>,
+    ) -> anyhow::Result<()> {
+        match self {
+            CoercedAttr::Selector(select) => {
+                let value = match Self::select_the_most_specific_entry(ctx, &select.entries)? {
+                    Some((key, setting, value)) => {
+                        resolutions.push(SelectResolution::new(select, Some((key, setting))));
+                        value
+                    }
+                    None => {
+                        resolutions.push(SelectResolution::new(select, None));
+                        Self::select(ctx, select)?
+                    }
+                };
+                value.collect_select_resolutions(ctx, resolutions)
+            }
+            CoercedAttr::Concat(items) => items
+                .iter()
+                .try_for_each(|item| item.collect_select_resolutions(ctx, resolutions)),
+            CoercedAttr::List(list) => list
+                .iter()
+                .try_for_each(|item| item.collect_select_resolutions(ctx, resolutions)),
+            CoercedAttr::Tuple(tuple) => tuple
+                .iter()
+                .try_for_each(|item| item.collect_select_resolutions(ctx, resolutions)),
+            CoercedAttr::Dict(dict) => dict.iter().try_for_each(|(k, v)| {
+                k.collect_select_resolutions(ctx, resolutions)?;
+                v.collect_select_resolutions(ctx, resolutions)
+            }),
+            CoercedAttr::Record(record) => record
+                .iter()
+                .try_for_each(|(_, v)| v.collect_select_resolutions(ctx, resolutions)),
+            CoercedAttr::OneOf(value, _) => value.collect_select_resolutions(ctx, resolutions),
+            _ => Ok(()),
+        }
+    }
+
     /// Returns the "configured" representation of the attribute in the provided context.
     /// This handles the resolution of the select() conditions and delegates to
     /// the actual attr type for handling any appropriate configuration-time
@@ -584,6 +710,21 @@ impl CoercedAttr {
                 })?
                 .into(),
             )),
+            CoercedAttrWithType::Record(record, t) => {
+                if record.len() != t.fields.len() {
+                    return Err(CoercedAttrError::InconsistentRecordFields.into());
+                }
+                ConfiguredAttr::Record(RecordLiteral {
+                    typ: record.typ.dupe(),
+                    fields: record
+                        .iter()
+                        .zip(&t.fields)
+                        .map(|((k, v), (_, field))| {
+                            anyhow::Ok((k.dupe(), v.configure(field.coercer(), ctx)?))
+                        })
+                        .collect::<anyhow::Result<_>>()?,
+                })
+            }
             CoercedAttrWithType::None => ConfiguredAttr::None,
             CoercedAttrWithType::Some(attr, t) => attr.configure(&t.inner, ctx)?,
             CoercedAttrWithType::OneOf(l, i, t) => {
@@ -647,6 +788,7 @@ impl CoercedAttr {
             CoercedAttr::List(vals) => vals.any_matches(filter),
             CoercedAttr::Tuple(vals) => vals.any_matches(filter),
             CoercedAttr::Dict(d) => d.any_matches(filter),
+            CoercedAttr::Record(r) => r.any_matches(filter),
             CoercedAttr::None => Ok(false),
             CoercedAttr::Bool(b) => b.any_matches(filter),
             CoercedAttr::Int(i) => filter(&i.to_string()),
@@ -673,10 +815,13 @@ impl CoercedAttr {
 mod tests {
 
     use buck2_core::target::label::TargetLabel;
+    use buck2_util::arc_str::ArcSlice;
     use dupe::Dupe;
 
+    use crate::attrs::attr_type::list::ListLiteral;
     use crate::attrs::coerced_attr::CoercedAttr;
     use crate::attrs::coerced_attr::CoercedSelector;
+    use crate::attrs::testing::configuration_ctx;
 
     #[test]
     fn test_check_all_keys_unique_small() {
@@ -714,4 +859,37 @@ mod tests {
         long[10].0 = long[0].0.dupe();
         assert!(CoercedSelector::check_all_keys_unique(&long).is_err());
     }
+
+    #[test]
+    fn test_select_resolutions() -> anyhow::Result<()> {
+        // `root//other:config` is the only key matched by the testing configuration context.
+        let other = TargetLabel::testing_parse("root//other:config");
+        let some = TargetLabel::testing_parse("root//some:config");
+
+        let inner = CoercedAttr::Selector(Box::new(CoercedSelector::new(
+            ArcSlice::from(vec![(some.dupe(), CoercedAttr::Int(1))]),
+            Some(CoercedAttr::Int(2)),
+        )?));
+        let outer = CoercedAttr::Selector(Box::new(CoercedSelector::new(
+            ArcSlice::from(vec![
+                (some.dupe(), CoercedAttr::None),
+                (
+                    other.dupe(),
+                    CoercedAttr::List(ListLiteral(ArcSlice::from(vec![inner]))),
+                ),
+            ]),
+            None,
+        )?));
+
+        let resolutions = outer.select_resolutions(&configuration_ctx())?;
+        assert_eq!(2, resolutions.len());
+        assert_eq!(
+            vec!["root//some:config", "root//other:config"],
+            resolutions[0].keys
+        );
+        assert_eq!("root//other:config", resolutions[0].chosen);
+        assert_eq!(vec!["root//some:config", "DEFAULT"], resolutions[1].keys);
+        assert_eq!("DEFAULT", resolutions[1].chosen);
+        Ok(())
+    }
 }
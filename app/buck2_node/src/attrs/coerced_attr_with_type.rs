@@ -33,6 +33,8 @@ use crate::attrs::attr_type::option::OptionAttrType;
 use crate::attrs::attr_type::plugin_dep::PluginDepAttrType;
 use crate::attrs::attr_type::query::QueryAttr;
 use crate::attrs::attr_type::query::QueryAttrType;
+use crate::attrs::attr_type::record::RecordAttrType;
+use crate::attrs::attr_type::record::RecordLiteral;
 use crate::attrs::attr_type::source::SourceAttrType;
 use crate::attrs::attr_type::split_transition_dep::SplitTransitionDepAttrType;
 use crate::attrs::attr_type::string::StringAttrType;
@@ -85,6 +87,7 @@ pub enum CoercedAttrWithType<'a, 't> {
     List(&'a ListLiteral<CoercedAttr>, &'t ListAttrType),
     Tuple(&'a TupleLiteral<CoercedAttr>, &'t TupleAttrType),
     Dict(&'a DictLiteral<CoercedAttr>, &'t DictAttrType),
+    Record(&'a RecordLiteral<CoercedAttr>, &'t RecordAttrType),
     OneOf(&'a CoercedAttr, u32, &'t OneOfAttrType),
     Visibility(&'a VisibilitySpecification, VisibilityAttrType),
     WithinView(&'a WithinViewSpecification, WithinViewAttrType),
@@ -133,6 +136,9 @@ impl<'a, 't> CoercedAttrWithType<'a, 't> {
                 Ok(CoercedAttrWithType::Tuple(t, ty))
             }
             (CoercedAttr::Dict(d), AttrTypeInner::Dict(t)) => Ok(CoercedAttrWithType::Dict(d, t)),
+            (CoercedAttr::Record(r), AttrTypeInner::Record(t)) => {
+                Ok(CoercedAttrWithType::Record(r, t))
+            }
             (CoercedAttr::OneOf(o, i), AttrTypeInner::OneOf(t)) => {
                 Ok(CoercedAttrWithType::OneOf(o, *i, t))
             }
@@ -181,6 +187,7 @@ impl<'a, 't> CoercedAttrWithType<'a, 't> {
             | (CoercedAttr::List(_), _)
             | (CoercedAttr::Tuple(_), _)
             | (CoercedAttr::Dict(_), _)
+            | (CoercedAttr::Record(_), _)
             | (CoercedAttr::OneOf(..), _)
             | (CoercedAttr::Visibility(_), _)
             | (CoercedAttr::WithinView(_), _)
@@ -213,7 +220,8 @@ impl<'a, 't> CoercedAttrWithType<'a, 't> {
             CoercedAttr::Tuple(t) => Ok(CoercedAttrWithType::AnyTuple(t)),
             CoercedAttr::Dict(d) => Ok(CoercedAttrWithType::AnyDict(d)),
             CoercedAttr::None => Ok(CoercedAttrWithType::None),
-            CoercedAttr::OneOf(_, _)
+            CoercedAttr::Record(_)
+            | CoercedAttr::OneOf(_, _)
             | CoercedAttr::Visibility(_)
             | CoercedAttr::WithinView(_)
             | CoercedAttr::ExplicitConfiguredDep(_)
@@ -31,6 +31,7 @@ use crate::attrs::attr_type::dep::DepAttr;
 use crate::attrs::attr_type::dict::DictLiteral;
 use crate::attrs::attr_type::list::ListLiteral;
 use crate::attrs::attr_type::query::QueryAttr;
+use crate::attrs::attr_type::record::RecordLiteral;
 use crate::attrs::attr_type::split_transition_dep::ConfiguredSplitTransitionDep;
 use crate::attrs::attr_type::string::StringLiteral;
 use crate::attrs::attr_type::tuple::TupleLiteral;
@@ -88,6 +89,7 @@ pub enum ConfiguredAttr {
     List(ListLiteral<ConfiguredAttr>),
     Tuple(TupleLiteral<ConfiguredAttr>),
     Dict(DictLiteral<ConfiguredAttr>),
+    Record(RecordLiteral<ConfiguredAttr>),
     None,
     // NOTE: unlike deps, labels are not traversed, as they are typically used in lieu of deps in
     // cases that would cause cycles.
@@ -140,6 +142,7 @@ impl AttrDisplayWithContext for ConfiguredAttr {
             ConfiguredAttr::List(list) => AttrDisplayWithContext::fmt(list, ctx, f),
             ConfiguredAttr::Tuple(v) => AttrDisplayWithContext::fmt(v, ctx, f),
             ConfiguredAttr::Dict(v) => AttrDisplayWithContext::fmt(v, ctx, f),
+            ConfiguredAttr::Record(v) => AttrDisplayWithContext::fmt(v, ctx, f),
             ConfiguredAttr::None => write!(f, "None"),
             ConfiguredAttr::OneOf(box l, _) => AttrDisplayWithContext::fmt(l, ctx, f),
             ConfiguredAttr::Visibility(v) => Display::fmt(v, f),
@@ -190,6 +193,12 @@ impl ConfiguredAttr {
                 }
                 Ok(())
             }
+            ConfiguredAttr::Record(record) => {
+                for (_, v) in record.iter() {
+                    v.traverse(pkg.dupe(), traversal)?;
+                }
+                Ok(())
+            }
             ConfiguredAttr::None => Ok(()),
             ConfiguredAttr::OneOf(l, _) => l.traverse(pkg, traversal),
             ConfiguredAttr::Visibility(..) => Ok(()),
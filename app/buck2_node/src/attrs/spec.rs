@@ -102,7 +102,9 @@ impl AttributeSpec {
         usize::from(id.index_in_attribute_spec) < *INTERNAL_ATTR_COUNT
     }
 
-    fn new(attributes: OrderedMap<Box<str>, Attribute>) -> anyhow::Result<AttributeSpec> {
+    pub(crate) fn new(
+        attributes: OrderedMap<Box<str>, Attribute>,
+    ) -> anyhow::Result<AttributeSpec> {
         if attributes.len() > AttributeId::MAX_INDEX as usize {
             return Err(AttributeSpecError::TooManyAttributes(attributes.len()).into());
         }
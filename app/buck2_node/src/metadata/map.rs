@@ -28,6 +28,10 @@ impl MetadataMap {
             values: Box::new(values),
         }
     }
+
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = (&MetadataKey, &serde_json::Value)> {
+        self.values.iter()
+    }
 }
 
 impl Hash for MetadataMap {
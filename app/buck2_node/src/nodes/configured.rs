@@ -7,6 +7,7 @@
  * of this source tree.
  */
 
+use std::collections::BTreeMap;
 use std::fmt::Debug;
 use std::fmt::Formatter;
 use std::hash::Hash;
@@ -37,6 +38,7 @@ use buck2_util::arc_str::ArcStr;
 use dupe::Dupe;
 use either::Either;
 use once_cell::sync::Lazy;
+use serde::Serialize;
 use starlark_map::ordered_map::OrderedMap;
 use starlark_map::unordered_map::UnorderedMap;
 use starlark_map::Hashed;
@@ -49,6 +51,7 @@ use crate::attrs::attr_type::query::ResolvedQueryLiterals;
 use crate::attrs::attr_type::string::StringLiteral;
 use crate::attrs::attr_type::AttrType;
 use crate::attrs::coerced_attr::CoercedAttr;
+use crate::attrs::coerced_attr::SelectResolution;
 use crate::attrs::coerced_attr_full::CoercedAttrFull;
 use crate::attrs::configuration_context::AttrConfigurationContextImpl;
 use crate::attrs::configured_attr::ConfiguredAttr;
@@ -194,6 +197,20 @@ impl Debug for ConfiguredTargetNodeData {
     }
 }
 
+/// How the attributes of a configured target node were resolved, see
+/// `ConfiguredTargetNode::select_provenance`.
+#[derive(Debug, Serialize)]
+pub struct SelectProvenance {
+    /// The configuration the node's `select()`s were resolved against.
+    pub configuration: String,
+    /// For a node created by a transition on the target itself, the transitioned node.
+    pub forward_to: Option<String>,
+    /// Attributes containing `select()`s, with how each of them was resolved.
+    pub attrs: BTreeMap<String, Vec<SelectResolution>>,
+    /// Transitions applied to the dependencies of this node, with resulting configurations.
+    pub transitions: BTreeMap<String, Vec<String>>,
+}
+
 impl ConfiguredTargetNode {
     /// Creates a minimal ConfiguredTargetNode. Some operations may unexpectedly fail.
     pub fn testing_new(name: ConfiguredTargetLabel, rule_type: &str) -> Self {
@@ -539,6 +556,52 @@ impl ConfiguredTargetNode {
         })
     }
 
+    /// Describes how the attributes of this node were resolved from their unconfigured values:
+    /// which `select()` branches were taken for each attribute accepted by `attr_filter`, and
+    /// which transitions were applied.
+    pub fn select_provenance(
+        &self,
+        attr_filter: &dyn Fn(&str) -> bool,
+    ) -> anyhow::Result<SelectProvenance> {
+        let ctx = self.attr_configuration_context();
+        let mut attrs = BTreeMap::new();
+        for attr in self.0.target_node.attrs(AttrInspectOptions::All) {
+            if !attr_filter(attr.name) {
+                continue;
+            }
+            let resolutions = attr
+                .value
+                .select_resolutions(&ctx)
+                .with_context(|| format!("Error resolving selects in attribute `{}`", attr.name))?;
+            if !resolutions.is_empty() {
+                attrs.insert(attr.name.to_owned(), resolutions);
+            }
+        }
+
+        let transitions = self
+            .0
+            .resolved_transition_configurations
+            .iter()
+            .map(|(id, applied)| {
+                let configurations = match &**applied {
+                    TransitionApplied::Single(cfg) => vec![cfg.to_string()],
+                    TransitionApplied::Split(cfgs) => cfgs
+                        .iter()
+                        .map(|(name, cfg)| format!("{}: {}", name, cfg))
+                        .collect(),
+                };
+                (id.to_string(), configurations)
+            })
+            .collect();
+
+        Ok(SelectProvenance {
+            configuration: self.label().cfg().to_string(),
+            forward_to: self.forward_target().map(|t| t.label().to_string()),
+            attrs,
+            transitions,
+        })
+    }
+
     pub fn call_stack(&self) -> Option<String> {
         match &self.0.target_node {
             TargetNodeOrForward::TargetNode(n) => n.call_stack(),
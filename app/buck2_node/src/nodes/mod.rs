@@ -14,6 +14,7 @@ pub mod configured_ref;
 pub mod eval_result;
 pub mod frontend;
 pub mod lookup;
+pub mod persistence;
 pub mod targets_map;
 pub mod unconfigured;
 
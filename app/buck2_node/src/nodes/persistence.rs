@@ -0,0 +1,1418 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! How the targets of a package are persisted across daemon restarts.
+//!
+//! Targets are saved in a form that mirrors their structure. Rules and record attribute types
+//! are shared by many targets, so they are saved once per package and referred to by index.
+
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use buck2_common::dice::persistence::SavedCellPath;
+use buck2_core::build_file_path::BuildFilePath;
+use buck2_core::bzl::ImportPath;
+use buck2_core::cells::build_file_cell::BuildFileCell;
+use buck2_core::cells::name::CellName;
+use buck2_core::configuration::transition::id::TransitionId;
+use buck2_core::fs::paths::file_name::FileNameBuf;
+use buck2_core::package::package_relative_path::PackageRelativePath;
+use buck2_core::package::PackageLabel;
+use buck2_core::pattern::pattern_type::TargetPatternExtra;
+use buck2_core::pattern::ParsedPattern;
+use buck2_core::plugins::PluginKind;
+use buck2_core::plugins::PluginKindSet;
+use buck2_core::provider::id::ProviderId;
+use buck2_core::provider::label::NonDefaultProvidersName;
+use buck2_core::provider::label::ProviderName;
+use buck2_core::provider::label::ProvidersLabel;
+use buck2_core::provider::label::ProvidersName;
+use buck2_core::target::label::TargetLabel;
+use buck2_core::target::name::TargetName;
+use buck2_core::target::name::TargetNameRef;
+use buck2_util::arc_str::ArcS;
+use buck2_util::arc_str::ArcStr;
+use buck2_util::late_binding::LateBinding;
+use dice::DiceDataBuilder;
+use dupe::Dupe;
+use serde::Deserialize;
+use serde::Serialize;
+use starlark_map::ordered_map::OrderedMap;
+use starlark_map::small_map::SmallMap;
+
+use crate::attrs::attr::Attribute;
+use crate::attrs::attr_type::arg::MacroBase;
+use crate::attrs::attr_type::arg::QueryExpansion;
+use crate::attrs::attr_type::arg::StringWithMacros;
+use crate::attrs::attr_type::arg::StringWithMacrosPart;
+use crate::attrs::attr_type::arg::UnrecognizedMacro;
+use crate::attrs::attr_type::bool::BoolLiteral;
+use crate::attrs::attr_type::configured_dep::ExplicitConfiguredDepAttrType;
+use crate::attrs::attr_type::configured_dep::UnconfiguredExplicitConfiguredDep;
+use crate::attrs::attr_type::dep::DepAttrTransition;
+use crate::attrs::attr_type::dep::DepAttrType;
+use crate::attrs::attr_type::dict::DictLiteral;
+use crate::attrs::attr_type::list::ListLiteral;
+use crate::attrs::attr_type::query::QueryAttr;
+use crate::attrs::attr_type::query::QueryAttrBase;
+use crate::attrs::attr_type::query::QueryAttrType;
+use crate::attrs::attr_type::query::QueryMacroBase;
+use crate::attrs::attr_type::query::ResolvedQueryLiterals;
+use crate::attrs::attr_type::record::RecordAttrType;
+use crate::attrs::attr_type::record::RecordLiteral;
+use crate::attrs::attr_type::string::StringLiteral;
+use crate::attrs::attr_type::tuple::TupleLiteral;
+use crate::attrs::attr_type::AttrType;
+use crate::attrs::attr_type::AttrTypeInner;
+use crate::attrs::coerced_attr::CoercedAttr;
+use crate::attrs::coerced_attr::CoercedSelector;
+use crate::attrs::coerced_deps_collector::CoercedDeps;
+use crate::attrs::coerced_deps_collector::CoercedDepsCollector;
+use crate::attrs::coerced_path::CoercedDirectory;
+use crate::attrs::coerced_path::CoercedPath;
+use crate::attrs::id::AttributeId;
+use crate::attrs::inspect_options::AttrInspectOptions;
+use crate::attrs::spec::AttributeSpec;
+use crate::attrs::values::AttrValues;
+use crate::call_stack::StarlarkCallStack;
+use crate::metadata::key::MetadataKey;
+use crate::metadata::map::MetadataMap;
+use crate::metadata::super_package_values::SuperPackageValues;
+use crate::nodes::eval_result::EvaluationResult;
+use crate::nodes::targets_map::TargetsMap;
+use crate::nodes::unconfigured::RuleKind;
+use crate::nodes::unconfigured::TargetNode;
+use crate::package::Package;
+use crate::provider_id_set::ProviderIdSet;
+use crate::rule::Rule;
+use crate::rule_type::RuleType;
+use crate::rule_type::StarlarkRuleType;
+use crate::super_package::SuperPackage;
+use crate::visibility::VisibilityPattern;
+use crate::visibility::VisibilityPatternList;
+use crate::visibility::VisibilitySpecification;
+use crate::visibility::WithinViewSpecification;
+
+#[derive(Debug, thiserror::Error)]
+enum SavedTargetsError {
+    #[error("Saved rule index {0} is out of range (internal error)")]
+    RuleIndex(u32),
+    #[error("Saved record type index {0} is out of range (internal error)")]
+    RecordTypeIndex(u32),
+    #[error("Saved attribute index {0} is out of range or out of order (internal error)")]
+    AttributeIndex(u16),
+    #[error("Saved default-only attribute has no default (internal error)")]
+    NoDefault,
+}
+
+/// Registers the key whose values are the targets of a package, which the interpreter implements.
+pub static REGISTER_PERSISTENT_TARGET_KEYS: LateBinding<fn(&mut DiceDataBuilder)> =
+    LateBinding::new("REGISTER_PERSISTENT_TARGET_KEYS");
+
+/// Serializes the targets of a package, or returns `None` if the package can't be persisted.
+///
+/// Packages whose `PACKAGE` files set a configuration constructor are not persisted, since the
+/// constructor is a starlark value, and neither are targets with already configured deps.
+pub fn save_evaluation_result(result: &EvaluationResult) -> anyhow::Result<Option<Vec<u8>>> {
+    let super_package = result.super_package();
+    if super_package.cfg_constructor().is_some() {
+        return Ok(None);
+    }
+
+    let mut saver = Saver::default();
+    let mut targets = Vec::with_capacity(result.targets().len());
+    for node in result.targets().values() {
+        match saver.target_node(node) {
+            Some(node) => targets.push(node),
+            None => return Ok(None),
+        }
+    }
+
+    let saved = SavedEvaluationResult {
+        buildfile_path: SavedBuildFilePath::new(result.buildfile_path()),
+        imports: result.imports().iter().map(SavedImportPath::new).collect(),
+        package_values: super_package
+            .package_values()
+            .package_values_json()?
+            .into_iter()
+            .map(|(key, value)| (key.as_str().to_owned(), value))
+            .collect(),
+        visibility: SavedVisibility::new(&super_package.visibility().0),
+        within_view: SavedVisibility::new(&super_package.within_view().0),
+        record_types: saver.record_types,
+        rules: saver.rules,
+        targets,
+    };
+    Ok(Some(serde_json::to_vec(&saved)?))
+}
+
+/// Deserializes targets written by `save_evaluation_result`. Package values are saved as JSON,
+/// and turned back into the interpreter's representation by `package_values`.
+pub fn load_evaluation_result(
+    data: &[u8],
+    package_values: impl FnOnce(
+        SmallMap<MetadataKey, serde_json::Value>,
+    ) -> anyhow::Result<Arc<dyn SuperPackageValues>>,
+) -> anyhow::Result<EvaluationResult> {
+    let saved: SavedEvaluationResult = serde_json::from_slice(data)?;
+    let buildfile_path = Arc::new(saved.buildfile_path.into_build_file_path()?);
+
+    let mut loader = Loader::default();
+    for record_type in saved.record_types {
+        let record_type = loader.record_type(record_type)?;
+        loader.record_types.push(Arc::new(record_type));
+    }
+    for rule in saved.rules {
+        let rule = loader.rule(rule)?;
+        loader.rules.push(Arc::new(rule));
+    }
+
+    // All targets of a package share its `Package`, unless they set different oncalls.
+    let mut packages: HashMap<Option<String>, Arc<Package>> = HashMap::new();
+    let mut targets = TargetsMap::new();
+    for node in saved.targets {
+        let package = packages
+            .entry(node.oncall.clone())
+            .or_insert_with(|| {
+                Arc::new(Package {
+                    buildfile_path: buildfile_path.dupe(),
+                    oncall: node.oncall.clone().map(Arc::new),
+                })
+            })
+            .dupe();
+        targets.record(loader.target_node(node, package)?)?;
+    }
+
+    let package_values = package_values(
+        saved
+            .package_values
+            .into_iter()
+            .map(|(key, value)| Ok((MetadataKey::try_from(key)?, value)))
+            .collect::<anyhow::Result<_>>()?,
+    )?;
+    let super_package = SuperPackage::new(
+        package_values,
+        VisibilitySpecification(saved.visibility.into_list()?),
+        WithinViewSpecification(saved.within_view.into_list()?),
+        None,
+    );
+
+    Ok(EvaluationResult::new(
+        buildfile_path,
+        saved
+            .imports
+            .into_iter()
+            .map(SavedImportPath::into_import_path)
+            .collect::<anyhow::Result<_>>()?,
+        super_package,
+        targets,
+    ))
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedEvaluationResult {
+    buildfile_path: SavedBuildFilePath,
+    imports: Vec<SavedImportPath>,
+    package_values: Vec<(String, serde_json::Value)>,
+    visibility: SavedVisibility,
+    within_view: SavedVisibility,
+    /// Record types come before the record types and rules that use them.
+    record_types: Vec<SavedRecordAttrType>,
+    rules: Vec<SavedRule>,
+    targets: Vec<SavedTargetNode>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedTargetNode {
+    name: String,
+    rule: u32,
+    oncall: Option<String>,
+    /// Values of the attributes set on the target, by index in the rule's attribute spec.
+    attributes: Vec<(u16, SavedCoercedAttr)>,
+    call_stack: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedRule {
+    attributes: Vec<(String, SavedAttribute)>,
+    rule_type: SavedRuleType,
+    rule_kind: SavedRuleKind,
+    cfg: Option<SavedTransitionId>,
+    uses_plugins: Vec<SavedPluginKind>,
+}
+
+#[derive(Serialize, Deserialize)]
+enum SavedRuleType {
+    Starlark {
+        import_path: SavedImportPath,
+        name: String,
+    },
+    Forward,
+}
+
+#[derive(Serialize, Deserialize)]
+enum SavedRuleKind {
+    Normal,
+    Configuration,
+    Toolchain,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedAttribute {
+    default: Option<SavedCoercedAttr>,
+    default_only: bool,
+    doc: String,
+    coercer: SavedAttrType,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedRecordAttrType {
+    fields: Vec<(String, SavedAttribute)>,
+}
+
+#[derive(Serialize, Deserialize)]
+enum SavedAttrType {
+    Any,
+    Arg {
+        anon_target_compatible: bool,
+    },
+    ConfigurationDep,
+    ConfiguredDep {
+        required_providers: Vec<SavedProviderId>,
+    },
+    Bool,
+    Int,
+    Dep(SavedDepAttrType),
+    Dict {
+        key: Box<SavedAttrType>,
+        value: Box<SavedAttrType>,
+        sorted: bool,
+    },
+    List(Box<SavedAttrType>),
+    Tuple(Vec<SavedAttrType>),
+    /// Index of the record type in the package's record types.
+    Record(u32),
+    OneOf(Vec<SavedAttrType>),
+    Option(Box<SavedAttrType>),
+    PluginDep(SavedPluginKind),
+    Query(SavedDepAttrType),
+    Source {
+        allow_directory: bool,
+    },
+    SplitTransitionDep {
+        required_providers: Vec<SavedProviderId>,
+        transition: SavedTransitionId,
+    },
+    String,
+    Enum(Vec<String>),
+    Label,
+    Visibility,
+    WithinView,
+    Metadata,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedDepAttrType {
+    required_providers: Vec<SavedProviderId>,
+    transition: SavedDepAttrTransition,
+}
+
+#[derive(Serialize, Deserialize)]
+enum SavedDepAttrTransition {
+    /// The plugin kinds the dep pulls, each with whether it also pushes them, or `None` for all.
+    Identity(Option<Vec<(SavedPluginKind, bool)>>),
+    Exec,
+    Toolchain,
+    Transition(SavedTransitionId),
+}
+
+#[derive(Serialize, Deserialize)]
+enum SavedCoercedAttr {
+    Selector {
+        entries: Vec<(SavedTargetLabel, SavedCoercedAttr)>,
+        default: Option<Box<SavedCoercedAttr>>,
+    },
+    Concat(Vec<SavedCoercedAttr>),
+    Bool(bool),
+    Int(i64),
+    String(String),
+    EnumVariant(String),
+    List(Vec<SavedCoercedAttr>),
+    Tuple(Vec<SavedCoercedAttr>),
+    Dict(Vec<(SavedCoercedAttr, SavedCoercedAttr)>),
+    Record {
+        /// Index of the record type in the package's record types.
+        typ: u32,
+        fields: Vec<(String, SavedCoercedAttr)>,
+    },
+    None,
+    OneOf(Box<SavedCoercedAttr>, u32),
+    Visibility(SavedVisibility),
+    WithinView(SavedVisibility),
+    ExplicitConfiguredDep {
+        required_providers: Vec<SavedProviderId>,
+        label: SavedProvidersLabel,
+        platform: SavedTargetLabel,
+    },
+    SplitTransitionDep(SavedProvidersLabel),
+    ConfigurationDep(SavedTargetLabel),
+    PluginDep(SavedTargetLabel),
+    Dep(SavedProvidersLabel),
+    SourceLabel(SavedProvidersLabel),
+    Label(SavedProvidersLabel),
+    Arg(SavedStringWithMacros),
+    Query {
+        providers: Vec<SavedProviderId>,
+        query: SavedQuery,
+    },
+    SourceFile(String),
+    SourceDirectory {
+        dir: String,
+        files: Vec<String>,
+    },
+    Metadata(Vec<(String, serde_json::Value)>),
+}
+
+/// Collects the rules and record types shared by the targets of a package while saving them.
+#[derive(Default)]
+struct Saver {
+    rules: Vec<SavedRule>,
+    rule_indices: HashMap<*const Rule, u32>,
+    record_types: Vec<SavedRecordAttrType>,
+    record_type_indices: HashMap<*const RecordAttrType, u32>,
+}
+
+// Saving returns `None` for values that can't be persisted.
+impl Saver {
+    fn target_node(&mut self, node: &TargetNode) -> Option<SavedTargetNode> {
+        let rule = self.rule(&node.0.rule)?;
+        let attributes = node
+            .attr_values()
+            .into_iter()
+            .map(|(id, value)| Some((id.index_in_attribute_spec, self.coerced_attr(value)?)))
+            .collect::<Option<_>>()?;
+        Some(SavedTargetNode {
+            name: node.label().name().as_str().to_owned(),
+            rule,
+            oncall: node.oncall().map(str::to_owned),
+            attributes,
+            call_stack: node.call_stack(),
+        })
+    }
+
+    fn rule(&mut self, rule: &Arc<Rule>) -> Option<u32> {
+        if let Some(index) = self.rule_indices.get(&Arc::as_ptr(rule)) {
+            return Some(*index);
+        }
+        let saved = SavedRule {
+            attributes: self.attributes(
+                rule.attributes
+                    .attr_specs()
+                    .map(|(name, _id, attr)| (name, attr)),
+            )?,
+            rule_type: match &rule.rule_type {
+                RuleType::Starlark(rule_type) => SavedRuleType::Starlark {
+                    import_path: SavedImportPath::new(&rule_type.import_path),
+                    name: rule_type.name.clone(),
+                },
+                RuleType::Forward => SavedRuleType::Forward,
+            },
+            rule_kind: match rule.rule_kind {
+                RuleKind::Normal => SavedRuleKind::Normal,
+                RuleKind::Configuration => SavedRuleKind::Configuration,
+                RuleKind::Toolchain => SavedRuleKind::Toolchain,
+            },
+            cfg: rule.cfg.as_deref().map(SavedTransitionId::new),
+            uses_plugins: rule.uses_plugins.iter().map(SavedPluginKind::new).collect(),
+        };
+        let index = self.rules.len() as u32;
+        self.rules.push(saved);
+        self.rule_indices.insert(Arc::as_ptr(rule), index);
+        Some(index)
+    }
+
+    fn attributes<'a>(
+        &mut self,
+        attributes: impl Iterator<Item = (&'a str, &'a Attribute)>,
+    ) -> Option<Vec<(String, SavedAttribute)>> {
+        attributes
+            .map(|(name, attr)| {
+                Some((
+                    name.to_owned(),
+                    SavedAttribute {
+                        default: match attr.default() {
+                            Some(default) => Some(self.coerced_attr(default)?),
+                            None => None,
+                        },
+                        default_only: attr.is_default_only(),
+                        doc: attr.doc().to_owned(),
+                        coercer: self.attr_type(attr.coercer())?,
+                    },
+                ))
+            })
+            .collect()
+    }
+
+    fn record_type(&mut self, typ: &Arc<RecordAttrType>) -> Option<u32> {
+        if let Some(index) = self.record_type_indices.get(&Arc::as_ptr(typ)) {
+            return Some(*index);
+        }
+        // Saves the record types used by the fields first.
+        let saved = SavedRecordAttrType {
+            fields: self.attributes(typ.fields.iter().map(|(name, attr)| (&**name, attr)))?,
+        };
+        let index = self.record_types.len() as u32;
+        self.record_types.push(saved);
+        self.record_type_indices.insert(Arc::as_ptr(typ), index);
+        Some(index)
+    }
+
+    fn attr_types(&mut self, types: &[AttrType]) -> Option<Vec<SavedAttrType>> {
+        types.iter().map(|t| self.attr_type(t)).collect()
+    }
+
+    fn attr_type(&mut self, typ: &AttrType) -> Option<SavedAttrType> {
+        Some(match &*typ.0 {
+            AttrTypeInner::Any(_) => SavedAttrType::Any,
+            AttrTypeInner::Arg(t) => SavedAttrType::Arg {
+                anon_target_compatible: t.anon_target_compatible,
+            },
+            AttrTypeInner::ConfigurationDep(_) => SavedAttrType::ConfigurationDep,
+            AttrTypeInner::ConfiguredDep(t) => SavedAttrType::ConfiguredDep {
+                required_providers: SavedProviderId::new_set(&t.required_providers),
+            },
+            AttrTypeInner::Bool(_) => SavedAttrType::Bool,
+            AttrTypeInner::Int(_) => SavedAttrType::Int,
+            AttrTypeInner::Dep(t) => SavedAttrType::Dep(SavedDepAttrType::new(t)),
+            AttrTypeInner::Dict(t) => SavedAttrType::Dict {
+                key: Box::new(self.attr_type(&t.key)?),
+                value: Box::new(self.attr_type(&t.value)?),
+                sorted: t.sorted,
+            },
+            AttrTypeInner::List(t) => SavedAttrType::List(Box::new(self.attr_type(&t.inner)?)),
+            AttrTypeInner::Tuple(t) => SavedAttrType::Tuple(self.attr_types(&t.xs)?),
+            AttrTypeInner::Record(t) => SavedAttrType::Record(self.record_type(t)?),
+            AttrTypeInner::OneOf(t) => SavedAttrType::OneOf(self.attr_types(&t.xs)?),
+            AttrTypeInner::Option(t) => SavedAttrType::Option(Box::new(self.attr_type(&t.inner)?)),
+            AttrTypeInner::PluginDep(t) => SavedAttrType::PluginDep(SavedPluginKind::new(t.kind())),
+            AttrTypeInner::Query(t) => SavedAttrType::Query(SavedDepAttrType::new(&t.inner)),
+            AttrTypeInner::Source(t) => SavedAttrType::Source {
+                allow_directory: t.allow_directory,
+            },
+            AttrTypeInner::SplitTransitionDep(t) => SavedAttrType::SplitTransitionDep {
+                required_providers: SavedProviderId::new_set(&t.required_providers),
+                transition: SavedTransitionId::new(&t.transition),
+            },
+            AttrTypeInner::String(_) => SavedAttrType::String,
+            AttrTypeInner::Enum(t) => {
+                SavedAttrType::Enum(t.variants.iter().map(|v| v.as_str().to_owned()).collect())
+            }
+            AttrTypeInner::Label(_) => SavedAttrType::Label,
+            AttrTypeInner::Visibility(_) => SavedAttrType::Visibility,
+            AttrTypeInner::WithinView(_) => SavedAttrType::WithinView,
+            AttrTypeInner::Metadata(_) => SavedAttrType::Metadata,
+        })
+    }
+
+    fn coerced_attrs<'a>(
+        &mut self,
+        values: impl IntoIterator<Item = &'a CoercedAttr>,
+    ) -> Option<Vec<SavedCoercedAttr>> {
+        values.into_iter().map(|v| self.coerced_attr(v)).collect()
+    }
+
+    fn coerced_attr(&mut self, value: &CoercedAttr) -> Option<SavedCoercedAttr> {
+        Some(match value {
+            CoercedAttr::Selector(s) => SavedCoercedAttr::Selector {
+                entries: s
+                    .entries
+                    .iter()
+                    .map(|(k, v)| Some((SavedTargetLabel::new(k), self.coerced_attr(v)?)))
+                    .collect::<Option<_>>()?,
+                default: match &s.default {
+                    Some(default) => Some(Box::new(self.coerced_attr(default)?)),
+                    None => None,
+                },
+            },
+            CoercedAttr::Concat(xs) => SavedCoercedAttr::Concat(self.coerced_attrs(&**xs)?),
+            CoercedAttr::Bool(BoolLiteral(b)) => SavedCoercedAttr::Bool(*b),
+            CoercedAttr::Int(i) => SavedCoercedAttr::Int(*i),
+            CoercedAttr::String(StringLiteral(s)) => {
+                SavedCoercedAttr::String(s.as_str().to_owned())
+            }
+            CoercedAttr::EnumVariant(StringLiteral(s)) => {
+                SavedCoercedAttr::EnumVariant(s.as_str().to_owned())
+            }
+            CoercedAttr::List(ListLiteral(xs)) => {
+                SavedCoercedAttr::List(self.coerced_attrs(&**xs)?)
+            }
+            CoercedAttr::Tuple(TupleLiteral(xs)) => {
+                SavedCoercedAttr::Tuple(self.coerced_attrs(&**xs)?)
+            }
+            CoercedAttr::Dict(DictLiteral(xs)) => SavedCoercedAttr::Dict(
+                xs.iter()
+                    .map(|(k, v)| Some((self.coerced_attr(k)?, self.coerced_attr(v)?)))
+                    .collect::<Option<_>>()?,
+            ),
+            CoercedAttr::Record(r) => SavedCoercedAttr::Record {
+                typ: self.record_type(&r.typ)?,
+                fields: r
+                    .fields
+                    .iter()
+                    .map(|(k, v)| Some((k.as_str().to_owned(), self.coerced_attr(v)?)))
+                    .collect::<Option<_>>()?,
+            },
+            CoercedAttr::None => SavedCoercedAttr::None,
+            CoercedAttr::OneOf(x, i) => {
+                SavedCoercedAttr::OneOf(Box::new(self.coerced_attr(x)?), *i)
+            }
+            CoercedAttr::Visibility(v) => SavedCoercedAttr::Visibility(SavedVisibility::new(&v.0)),
+            CoercedAttr::WithinView(v) => SavedCoercedAttr::WithinView(SavedVisibility::new(&v.0)),
+            CoercedAttr::ExplicitConfiguredDep(d) => SavedCoercedAttr::ExplicitConfiguredDep {
+                required_providers: SavedProviderId::new_set(&d.attr_type.required_providers),
+                label: SavedProvidersLabel::new(&d.label),
+                platform: SavedTargetLabel::new(&d.platform),
+            },
+            CoercedAttr::SplitTransitionDep(l) => {
+                SavedCoercedAttr::SplitTransitionDep(SavedProvidersLabel::new(l))
+            }
+            // Configured deps are only set on targets that are created already configured.
+            CoercedAttr::ConfiguredDep(_) => return None,
+            CoercedAttr::ConfigurationDep(l) => {
+                SavedCoercedAttr::ConfigurationDep(SavedTargetLabel::new(l))
+            }
+            CoercedAttr::PluginDep(l) => SavedCoercedAttr::PluginDep(SavedTargetLabel::new(l)),
+            CoercedAttr::Dep(l) => SavedCoercedAttr::Dep(SavedProvidersLabel::new(l)),
+            CoercedAttr::SourceLabel(l) => {
+                SavedCoercedAttr::SourceLabel(SavedProvidersLabel::new(l))
+            }
+            CoercedAttr::Label(l) => SavedCoercedAttr::Label(SavedProvidersLabel::new(l)),
+            CoercedAttr::Arg(a) => SavedCoercedAttr::Arg(SavedStringWithMacros::new(a)),
+            CoercedAttr::Query(q) => SavedCoercedAttr::Query {
+                providers: SavedProviderId::new_set(&q.providers),
+                query: SavedQuery::new(&q.query),
+            },
+            CoercedAttr::SourceFile(CoercedPath::File(path)) => {
+                SavedCoercedAttr::SourceFile(path.as_str().to_owned())
+            }
+            CoercedAttr::SourceFile(CoercedPath::Directory(d)) => {
+                SavedCoercedAttr::SourceDirectory {
+                    dir: d.dir.as_str().to_owned(),
+                    files: d.files.iter().map(|f| f.as_str().to_owned()).collect(),
+                }
+            }
+            CoercedAttr::Metadata(m) => SavedCoercedAttr::Metadata(
+                m.iter()
+                    .map(|(k, v)| (k.as_str().to_owned(), v.clone()))
+                    .collect(),
+            ),
+        })
+    }
+}
+
+/// Holds the rules and record types shared by the targets of a package while loading them.
+#[derive(Default)]
+struct Loader {
+    rules: Vec<Arc<Rule>>,
+    record_types: Vec<Arc<RecordAttrType>>,
+}
+
+impl Loader {
+    fn target_node(
+        &self,
+        node: SavedTargetNode,
+        package: Arc<Package>,
+    ) -> anyhow::Result<TargetNode> {
+        let rule = self
+            .rules
+            .get(node.rule as usize)
+            .ok_or(SavedTargetsError::RuleIndex(node.rule))?
+            .dupe();
+        let label = TargetLabel::new(
+            package.buildfile_path.package(),
+            TargetNameRef::new(&node.name)?,
+        );
+
+        let mut attr_values = AttrValues::with_capacity(node.attributes.len());
+        let mut next_index = 0;
+        for (index, value) in node.attributes {
+            if index < next_index || usize::from(index) >= rule.attributes.len() {
+                return Err(SavedTargetsError::AttributeIndex(index).into());
+            }
+            next_index = index + 1;
+            attr_values.push_sorted(
+                AttributeId {
+                    index_in_attribute_spec: index,
+                },
+                self.coerced_attr(value)?,
+            );
+        }
+
+        // The deps are derived from the attributes as the interpreter does.
+        let mut deps_cache = CoercedDepsCollector::new();
+        for a in rule.attributes.attrs(&attr_values, AttrInspectOptions::All) {
+            a.traverse(label.pkg(), &mut deps_cache)?;
+        }
+
+        Ok(TargetNode::new(
+            rule,
+            package,
+            label,
+            attr_values,
+            CoercedDeps::from(deps_cache),
+            node.call_stack.map(StarlarkCallStack::new),
+        ))
+    }
+
+    fn rule(&self, rule: SavedRule) -> anyhow::Result<Rule> {
+        Ok(Rule {
+            attributes: AttributeSpec::new(self.attributes(rule.attributes)?)?,
+            rule_type: match rule.rule_type {
+                SavedRuleType::Starlark { import_path, name } => {
+                    RuleType::Starlark(Arc::new(StarlarkRuleType {
+                        import_path: import_path.into_import_path()?,
+                        name,
+                    }))
+                }
+                SavedRuleType::Forward => RuleType::Forward,
+            },
+            rule_kind: match rule.rule_kind {
+                SavedRuleKind::Normal => RuleKind::Normal,
+                SavedRuleKind::Configuration => RuleKind::Configuration,
+                SavedRuleKind::Toolchain => RuleKind::Toolchain,
+            },
+            cfg: match rule.cfg {
+                Some(cfg) => Some(Arc::new(cfg.into_transition_id()?)),
+                None => None,
+            },
+            uses_plugins: rule
+                .uses_plugins
+                .into_iter()
+                .map(SavedPluginKind::into_plugin_kind)
+                .collect::<anyhow::Result<_>>()?,
+        })
+    }
+
+    fn attributes<C: FromIterator<(Box<str>, Attribute)>>(
+        &self,
+        attributes: Vec<(String, SavedAttribute)>,
+    ) -> anyhow::Result<C> {
+        attributes
+            .into_iter()
+            .map(|(name, attr)| {
+                let default = match attr.default {
+                    Some(default) => Some(Arc::new(self.coerced_attr(default)?)),
+                    None => None,
+                };
+                let coercer = self.attr_type(attr.coercer)?;
+                let attr = if attr.default_only {
+                    Attribute::new_default_only(
+                        default.ok_or(SavedTargetsError::NoDefault)?,
+                        &attr.doc,
+                        coercer,
+                    )
+                } else {
+                    Attribute::new(default, &attr.doc, coercer)
+                };
+                Ok((name.into_boxed_str(), attr))
+            })
+            .collect()
+    }
+
+    fn record_type(&self, typ: SavedRecordAttrType) -> anyhow::Result<RecordAttrType> {
+        let fields: Vec<(Box<str>, Attribute)> = self.attributes(typ.fields)?;
+        Ok(RecordAttrType::new(
+            fields
+                .into_iter()
+                .map(|(name, attr)| (name.into_string(), attr))
+                .collect(),
+        ))
+    }
+
+    fn get_record_type(&self, index: u32) -> anyhow::Result<&Arc<RecordAttrType>> {
+        Ok(self
+            .record_types
+            .get(index as usize)
+            .ok_or(SavedTargetsError::RecordTypeIndex(index))?)
+    }
+
+    fn attr_types(&self, types: Vec<SavedAttrType>) -> anyhow::Result<Vec<AttrType>> {
+        types.into_iter().map(|t| self.attr_type(t)).collect()
+    }
+
+    fn attr_type(&self, typ: SavedAttrType) -> anyhow::Result<AttrType> {
+        Ok(match typ {
+            SavedAttrType::Any => AttrType::any(),
+            SavedAttrType::Arg {
+                anon_target_compatible,
+            } => AttrType::arg(anon_target_compatible),
+            SavedAttrType::ConfigurationDep => AttrType::configuration_dep(),
+            SavedAttrType::ConfiguredDep { required_providers } => {
+                AttrType::configured_dep(SavedProviderId::into_set(required_providers)?)
+            }
+            SavedAttrType::Bool => AttrType::bool(),
+            SavedAttrType::Int => AttrType::int(),
+            SavedAttrType::Dep(t) => {
+                AttrType(Arc::new(AttrTypeInner::Dep(t.into_dep_attr_type()?)))
+            }
+            SavedAttrType::Dict { key, value, sorted } => {
+                AttrType::dict(self.attr_type(*key)?, self.attr_type(*value)?, sorted)
+            }
+            SavedAttrType::List(inner) => AttrType::list(self.attr_type(*inner)?),
+            SavedAttrType::Tuple(xs) => AttrType::tuple(self.attr_types(xs)?),
+            SavedAttrType::Record(index) => AttrType(Arc::new(AttrTypeInner::Record(
+                self.get_record_type(index)?.dupe(),
+            ))),
+            SavedAttrType::OneOf(xs) => AttrType::one_of(self.attr_types(xs)?),
+            SavedAttrType::Option(inner) => AttrType::option(self.attr_type(*inner)?),
+            SavedAttrType::PluginDep(kind) => AttrType::plugin_dep(kind.into_plugin_kind()?),
+            SavedAttrType::Query(t) => AttrType(Arc::new(AttrTypeInner::Query(
+                QueryAttrType::new(t.into_dep_attr_type()?),
+            ))),
+            SavedAttrType::Source { allow_directory } => AttrType::source(allow_directory),
+            SavedAttrType::SplitTransitionDep {
+                required_providers,
+                transition,
+            } => AttrType::split_transition_dep(
+                SavedProviderId::into_set(required_providers)?,
+                Arc::new(transition.into_transition_id()?),
+            ),
+            SavedAttrType::String => AttrType::string(),
+            SavedAttrType::Enum(variants) => AttrType::enumeration(variants)?,
+            SavedAttrType::Label => AttrType::label(),
+            SavedAttrType::Visibility => AttrType::visibility(),
+            SavedAttrType::WithinView => AttrType::within_view(),
+            SavedAttrType::Metadata => AttrType::metadata(),
+        })
+    }
+
+    fn coerced_attrs<C: FromIterator<CoercedAttr>>(
+        &self,
+        values: Vec<SavedCoercedAttr>,
+    ) -> anyhow::Result<C> {
+        values.into_iter().map(|v| self.coerced_attr(v)).collect()
+    }
+
+    fn coerced_attr(&self, value: SavedCoercedAttr) -> anyhow::Result<CoercedAttr> {
+        Ok(match value {
+            SavedCoercedAttr::Selector { entries, default } => {
+                CoercedAttr::Selector(Box::new(CoercedSelector::new(
+                    entries
+                        .into_iter()
+                        .map(|(k, v)| Ok((k.into_target_label()?, self.coerced_attr(v)?)))
+                        .collect::<anyhow::Result<_>>()?,
+                    match default {
+                        Some(default) => Some(self.coerced_attr(*default)?),
+                        None => None,
+                    },
+                )?))
+            }
+            SavedCoercedAttr::Concat(xs) => CoercedAttr::Concat(self.coerced_attrs(xs)?),
+            SavedCoercedAttr::Bool(b) => CoercedAttr::Bool(BoolLiteral(b)),
+            SavedCoercedAttr::Int(i) => CoercedAttr::Int(i),
+            SavedCoercedAttr::String(s) => CoercedAttr::String(StringLiteral(ArcStr::from(s))),
+            SavedCoercedAttr::EnumVariant(s) => {
+                CoercedAttr::EnumVariant(StringLiteral(ArcStr::from(s)))
+            }
+            SavedCoercedAttr::List(xs) => CoercedAttr::List(ListLiteral(self.coerced_attrs(xs)?)),
+            SavedCoercedAttr::Tuple(xs) => {
+                CoercedAttr::Tuple(TupleLiteral(self.coerced_attrs(xs)?))
+            }
+            SavedCoercedAttr::Dict(xs) => CoercedAttr::Dict(DictLiteral(
+                xs.into_iter()
+                    .map(|(k, v)| Ok((self.coerced_attr(k)?, self.coerced_attr(v)?)))
+                    .collect::<anyhow::Result<_>>()?,
+            )),
+            SavedCoercedAttr::Record { typ, fields } => CoercedAttr::Record(RecordLiteral {
+                typ: self.get_record_type(typ)?.dupe(),
+                fields: fields
+                    .into_iter()
+                    .map(|(k, v)| Ok((ArcStr::from(k), self.coerced_attr(v)?)))
+                    .collect::<anyhow::Result<_>>()?,
+            }),
+            SavedCoercedAttr::None => CoercedAttr::None,
+            SavedCoercedAttr::OneOf(x, i) => {
+                CoercedAttr::OneOf(Box::new(self.coerced_attr(*x)?), i)
+            }
+            SavedCoercedAttr::Visibility(v) => {
+                CoercedAttr::Visibility(VisibilitySpecification(v.into_list()?))
+            }
+            SavedCoercedAttr::WithinView(v) => {
+                CoercedAttr::WithinView(WithinViewSpecification(v.into_list()?))
+            }
+            SavedCoercedAttr::ExplicitConfiguredDep {
+                required_providers,
+                label,
+                platform,
+            } => CoercedAttr::ExplicitConfiguredDep(Box::new(UnconfiguredExplicitConfiguredDep {
+                attr_type: ExplicitConfiguredDepAttrType {
+                    required_providers: SavedProviderId::into_set(required_providers)?,
+                },
+                label: label.into_providers_label()?,
+                platform: platform.into_target_label()?,
+            })),
+            SavedCoercedAttr::SplitTransitionDep(l) => {
+                CoercedAttr::SplitTransitionDep(l.into_providers_label()?)
+            }
+            SavedCoercedAttr::ConfigurationDep(l) => {
+                CoercedAttr::ConfigurationDep(l.into_target_label()?)
+            }
+            SavedCoercedAttr::PluginDep(l) => CoercedAttr::PluginDep(l.into_target_label()?),
+            SavedCoercedAttr::Dep(l) => CoercedAttr::Dep(l.into_providers_label()?),
+            SavedCoercedAttr::SourceLabel(l) => CoercedAttr::SourceLabel(l.into_providers_label()?),
+            SavedCoercedAttr::Label(l) => CoercedAttr::Label(l.into_providers_label()?),
+            SavedCoercedAttr::Arg(a) => CoercedAttr::Arg(a.into_string_with_macros()?),
+            SavedCoercedAttr::Query { providers, query } => {
+                CoercedAttr::Query(Box::new(QueryAttr {
+                    providers: SavedProviderId::into_set(providers)?,
+                    query: query.into_query()?,
+                }))
+            }
+            SavedCoercedAttr::SourceFile(path) => {
+                CoercedAttr::SourceFile(CoercedPath::File(package_relative_path(&path)?))
+            }
+            SavedCoercedAttr::SourceDirectory { dir, files } => {
+                CoercedAttr::SourceFile(CoercedPath::Directory(Box::new(CoercedDirectory {
+                    dir: package_relative_path(&dir)?,
+                    files: files
+                        .iter()
+                        .map(|f| package_relative_path(f))
+                        .collect::<anyhow::Result<_>>()?,
+                })))
+            }
+            SavedCoercedAttr::Metadata(values) => CoercedAttr::Metadata(MetadataMap::new(
+                values
+                    .into_iter()
+                    .map(|(k, v)| Ok((MetadataKey::try_from(k)?, v)))
+                    .collect::<anyhow::Result<OrderedMap<_, _>>>()?,
+            )),
+        })
+    }
+}
+
+fn package_relative_path(path: &str) -> anyhow::Result<ArcS<PackageRelativePath>> {
+    Ok(<&PackageRelativePath>::try_from(path)?.to_arc())
+}
+
+fn save_package(package: &PackageLabel) -> SavedCellPath {
+    SavedCellPath::new(package.as_cell_path())
+}
+
+fn load_package(package: SavedCellPath) -> anyhow::Result<PackageLabel> {
+    Ok(PackageLabel::from_cell_path(
+        package.into_cell_path()?.as_ref(),
+    ))
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedBuildFilePath {
+    package: SavedCellPath,
+    filename: String,
+}
+
+impl SavedBuildFilePath {
+    fn new(path: &BuildFilePath) -> Self {
+        Self {
+            package: save_package(&path.package()),
+            filename: path.filename().as_str().to_owned(),
+        }
+    }
+
+    fn into_build_file_path(self) -> anyhow::Result<BuildFilePath> {
+        Ok(BuildFilePath::new(
+            load_package(self.package)?,
+            FileNameBuf::try_from(self.filename)?,
+        ))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedImportPath {
+    path: SavedCellPath,
+    build_file_cell: String,
+}
+
+impl SavedImportPath {
+    fn new(path: &ImportPath) -> Self {
+        Self {
+            path: SavedCellPath::new(path.path().as_ref()),
+            build_file_cell: path.build_file_cell().name().as_str().to_owned(),
+        }
+    }
+
+    fn into_import_path(self) -> anyhow::Result<ImportPath> {
+        ImportPath::new_with_build_file_cells(
+            self.path.into_cell_path()?,
+            BuildFileCell::new(CellName::unchecked_new(&self.build_file_cell)?),
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedTransitionId {
+    path: SavedImportPath,
+    name: String,
+}
+
+impl SavedTransitionId {
+    fn new(id: &TransitionId) -> Self {
+        Self {
+            path: SavedImportPath::new(&id.path),
+            name: id.name.clone(),
+        }
+    }
+
+    fn into_transition_id(self) -> anyhow::Result<TransitionId> {
+        Ok(TransitionId {
+            path: self.path.into_import_path()?,
+            name: self.name,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedPluginKind {
+    name: String,
+    cell: SavedCellPath,
+}
+
+impl SavedPluginKind {
+    fn new(kind: &PluginKind) -> Self {
+        Self {
+            name: kind.as_str().to_owned(),
+            cell: SavedCellPath::new(kind.cell().as_ref()),
+        }
+    }
+
+    fn into_plugin_kind(self) -> anyhow::Result<PluginKind> {
+        Ok(PluginKind::new(self.name, self.cell.into_cell_path()?))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedProviderId {
+    path: Option<SavedCellPath>,
+    name: String,
+}
+
+impl SavedProviderId {
+    fn new_set(ids: &ProviderIdSet) -> Vec<Self> {
+        ids.providers()
+            .iter()
+            .map(|id| Self {
+                path: id.path.as_ref().map(|p| SavedCellPath::new(p.as_ref())),
+                name: id.name.clone(),
+            })
+            .collect()
+    }
+
+    fn into_set(ids: Vec<Self>) -> anyhow::Result<ProviderIdSet> {
+        Ok(ProviderIdSet::from(
+            ids.into_iter()
+                .map(|id| {
+                    Ok(Arc::new(ProviderId {
+                        path: match id.path {
+                            Some(path) => Some(path.into_cell_path()?),
+                            None => None,
+                        },
+                        name: id.name,
+                    }))
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?,
+        ))
+    }
+}
+
+impl SavedDepAttrType {
+    fn new(typ: &DepAttrType) -> Self {
+        Self {
+            required_providers: SavedProviderId::new_set(&typ.required_providers),
+            transition: match &typ.transition {
+                DepAttrTransition::Identity(plugins) => {
+                    SavedDepAttrTransition::Identity(plugins.kinds().map(|kinds| {
+                        kinds
+                            .iter()
+                            .map(|(kind, pushes)| (SavedPluginKind::new(kind), *pushes))
+                            .collect()
+                    }))
+                }
+                DepAttrTransition::Exec => SavedDepAttrTransition::Exec,
+                DepAttrTransition::Toolchain => SavedDepAttrTransition::Toolchain,
+                DepAttrTransition::Transition(t) => {
+                    SavedDepAttrTransition::Transition(SavedTransitionId::new(t))
+                }
+            },
+        }
+    }
+
+    fn into_dep_attr_type(self) -> anyhow::Result<DepAttrType> {
+        Ok(DepAttrType::new(
+            SavedProviderId::into_set(self.required_providers)?,
+            match self.transition {
+                SavedDepAttrTransition::Identity(None) => {
+                    DepAttrTransition::Identity(PluginKindSet::ALL)
+                }
+                SavedDepAttrTransition::Identity(Some(kinds)) => {
+                    let mut pulls = Vec::new();
+                    let mut pulls_and_pushes = Vec::new();
+                    for (kind, pushes) in kinds {
+                        let kind = kind.into_plugin_kind()?;
+                        if pushes {
+                            pulls_and_pushes.push(kind);
+                        } else {
+                            pulls.push(kind);
+                        }
+                    }
+                    DepAttrTransition::Identity(PluginKindSet::new(pulls, pulls_and_pushes)?)
+                }
+                SavedDepAttrTransition::Exec => DepAttrTransition::Exec,
+                SavedDepAttrTransition::Toolchain => DepAttrTransition::Toolchain,
+                SavedDepAttrTransition::Transition(t) => {
+                    DepAttrTransition::Transition(Arc::new(t.into_transition_id()?))
+                }
+            },
+        ))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedTargetLabel {
+    package: SavedCellPath,
+    name: String,
+}
+
+impl SavedTargetLabel {
+    fn new(label: &TargetLabel) -> Self {
+        Self {
+            package: save_package(&label.pkg()),
+            name: label.name().as_str().to_owned(),
+        }
+    }
+
+    fn into_target_label(self) -> anyhow::Result<TargetLabel> {
+        Ok(TargetLabel::new(
+            load_package(self.package)?,
+            TargetNameRef::new(&self.name)?,
+        ))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+enum SavedProvidersName {
+    Default,
+    Named(Vec<String>),
+    UnrecognizedFlavor(String),
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedProvidersLabel {
+    target: SavedTargetLabel,
+    name: SavedProvidersName,
+}
+
+impl SavedProvidersLabel {
+    fn new(label: &ProvidersLabel) -> Self {
+        Self {
+            target: SavedTargetLabel::new(label.target()),
+            name: match label.name() {
+                ProvidersName::Default => SavedProvidersName::Default,
+                ProvidersName::NonDefault(name) => match &**name {
+                    NonDefaultProvidersName::Named(names) => SavedProvidersName::Named(
+                        names.iter().map(|n| n.as_str().to_owned()).collect(),
+                    ),
+                    NonDefaultProvidersName::UnrecognizedFlavor(flavor) => {
+                        SavedProvidersName::UnrecognizedFlavor((**flavor).to_owned())
+                    }
+                },
+            },
+        }
+    }
+
+    fn into_providers_label(self) -> anyhow::Result<ProvidersLabel> {
+        let name = match self.name {
+            SavedProvidersName::Default => ProvidersName::Default,
+            SavedProvidersName::Named(names) => {
+                ProvidersName::NonDefault(Box::new(NonDefaultProvidersName::Named(
+                    names
+                        .into_iter()
+                        .map(ProviderName::new)
+                        .collect::<anyhow::Result<_>>()?,
+                )))
+            }
+            SavedProvidersName::UnrecognizedFlavor(flavor) => ProvidersName::NonDefault(Box::new(
+                NonDefaultProvidersName::UnrecognizedFlavor(flavor.into_boxed_str()),
+            )),
+        };
+        Ok(ProvidersLabel::new(self.target.into_target_label()?, name))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+enum SavedVisibilityPattern {
+    Target(SavedCellPath, String),
+    Package(SavedCellPath),
+    Recursive(SavedCellPath),
+}
+
+#[derive(Serialize, Deserialize)]
+enum SavedVisibility {
+    Public,
+    List(Vec<SavedVisibilityPattern>),
+}
+
+impl SavedVisibility {
+    fn new(list: &VisibilityPatternList) -> Self {
+        match list {
+            VisibilityPatternList::Public => SavedVisibility::Public,
+            VisibilityPatternList::List(patterns) => SavedVisibility::List(
+                patterns
+                    .iter()
+                    .map(|p| match &p.0 {
+                        ParsedPattern::Target(package, name, TargetPatternExtra) => {
+                            SavedVisibilityPattern::Target(
+                                save_package(package),
+                                name.as_str().to_owned(),
+                            )
+                        }
+                        ParsedPattern::Package(package) => {
+                            SavedVisibilityPattern::Package(save_package(package))
+                        }
+                        ParsedPattern::Recursive(path) => {
+                            SavedVisibilityPattern::Recursive(SavedCellPath::new(path.as_ref()))
+                        }
+                    })
+                    .collect(),
+            ),
+        }
+    }
+
+    fn into_list(self) -> anyhow::Result<VisibilityPatternList> {
+        Ok(match self {
+            SavedVisibility::Public => VisibilityPatternList::Public,
+            SavedVisibility::List(patterns) => VisibilityPatternList::List(
+                patterns
+                    .into_iter()
+                    .map(|p| {
+                        Ok(VisibilityPattern(match p {
+                            SavedVisibilityPattern::Target(package, name) => ParsedPattern::Target(
+                                load_package(package)?,
+                                TargetName::new(&name)?,
+                                TargetPatternExtra,
+                            ),
+                            SavedVisibilityPattern::Package(package) => {
+                                ParsedPattern::Package(load_package(package)?)
+                            }
+                            SavedVisibilityPattern::Recursive(path) => {
+                                ParsedPattern::Recursive(path.into_cell_path()?)
+                            }
+                        }))
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()?
+                    .into_iter()
+                    .collect(),
+            ),
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedQuery {
+    query: String,
+    resolved_literals: Vec<(String, SavedProvidersLabel)>,
+}
+
+impl SavedQuery {
+    fn new(query: &QueryAttrBase<ProvidersLabel>) -> Self {
+        Self {
+            query: query.query.clone(),
+            resolved_literals: query
+                .resolved_literals
+                .0
+                .iter()
+                .map(|(literal, label)| (literal.clone(), SavedProvidersLabel::new(label)))
+                .collect(),
+        }
+    }
+
+    fn into_query(self) -> anyhow::Result<QueryAttrBase<ProvidersLabel>> {
+        Ok(QueryAttrBase {
+            query: self.query,
+            resolved_literals: ResolvedQueryLiterals(
+                self.resolved_literals
+                    .into_iter()
+                    .map(|(literal, label)| Ok((literal, label.into_providers_label()?)))
+                    .collect::<anyhow::Result<BTreeMap<_, _>>>()?,
+            ),
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+enum SavedStringWithMacros {
+    StringPart(String),
+    ManyParts(Vec<SavedStringWithMacrosPart>),
+}
+
+#[derive(Serialize, Deserialize)]
+enum SavedStringWithMacrosPart {
+    String(String),
+    Macro {
+        write_to_file: bool,
+        macro_: SavedMacro,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+enum SavedQueryExpansion {
+    Output,
+    Target,
+    TargetAndOutput(Option<String>),
+}
+
+#[derive(Serialize, Deserialize)]
+enum SavedMacro {
+    Location(SavedProvidersLabel),
+    Exe {
+        label: SavedProvidersLabel,
+        exec_dep: bool,
+    },
+    UserUnkeyedPlaceholder(String),
+    UserKeyedPlaceholder(String, SavedProvidersLabel, Option<String>),
+    Query {
+        expansion_type: SavedQueryExpansion,
+        query: SavedQuery,
+    },
+    UnrecognizedMacro {
+        macro_type: String,
+        args: Vec<String>,
+    },
+}
+
+impl SavedStringWithMacros {
+    fn new(value: &StringWithMacros<ProvidersLabel>) -> Self {
+        match value {
+            StringWithMacros::StringPart(s) => Self::StringPart(s.as_str().to_owned()),
+            StringWithMacros::ManyParts(parts) => Self::ManyParts(
+                parts
+                    .iter()
+                    .map(|part| match part {
+                        StringWithMacrosPart::String(s) => {
+                            SavedStringWithMacrosPart::String(s.as_str().to_owned())
+                        }
+                        StringWithMacrosPart::Macro(write_to_file, m) => {
+                            SavedStringWithMacrosPart::Macro {
+                                write_to_file: *write_to_file,
+                                macro_: SavedMacro::new(m),
+                            }
+                        }
+                    })
+                    .collect(),
+            ),
+        }
+    }
+
+    fn into_string_with_macros(self) -> anyhow::Result<StringWithMacros<ProvidersLabel>> {
+        Ok(match self {
+            Self::StringPart(s) => StringWithMacros::StringPart(ArcStr::from(s)),
+            Self::ManyParts(parts) => StringWithMacros::ManyParts(
+                parts
+                    .into_iter()
+                    .map(|part| {
+                        Ok(match part {
+                            SavedStringWithMacrosPart::String(s) => {
+                                StringWithMacrosPart::String(ArcStr::from(s))
+                            }
+                            SavedStringWithMacrosPart::Macro {
+                                write_to_file,
+                                macro_,
+                            } => StringWithMacrosPart::Macro(write_to_file, macro_.into_macro()?),
+                        })
+                    })
+                    .collect::<anyhow::Result<_>>()?,
+            ),
+        })
+    }
+}
+
+impl SavedMacro {
+    fn new(value: &MacroBase<ProvidersLabel>) -> Self {
+        match value {
+            MacroBase::Location(label) => Self::Location(SavedProvidersLabel::new(label)),
+            MacroBase::Exe { label, exec_dep } => Self::Exe {
+                label: SavedProvidersLabel::new(label),
+                exec_dep: *exec_dep,
+            },
+            MacroBase::UserUnkeyedPlaceholder(name) => {
+                Self::UserUnkeyedPlaceholder((**name).to_owned())
+            }
+            MacroBase::UserKeyedPlaceholder(placeholder) => {
+                let (name, label, arg) = &**placeholder;
+                Self::UserKeyedPlaceholder(
+                    (**name).to_owned(),
+                    SavedProvidersLabel::new(label),
+                    arg.as_deref().map(str::to_owned),
+                )
+            }
+            MacroBase::Query(query) => Self::Query {
+                expansion_type: match &query.expansion_type {
+                    QueryExpansion::Output => SavedQueryExpansion::Output,
+                    QueryExpansion::Target => SavedQueryExpansion::Target,
+                    QueryExpansion::TargetAndOutput(separator) => {
+                        SavedQueryExpansion::TargetAndOutput(separator.clone())
+                    }
+                },
+                query: SavedQuery::new(&query.query),
+            },
+            MacroBase::UnrecognizedMacro(m) => Self::UnrecognizedMacro {
+                macro_type: (*m.macro_type).to_owned(),
+                args: m.args.to_vec(),
+            },
+        }
+    }
+
+    fn into_macro(self) -> anyhow::Result<MacroBase<ProvidersLabel>> {
+        Ok(match self {
+            Self::Location(label) => MacroBase::Location(label.into_providers_label()?),
+            Self::Exe { label, exec_dep } => MacroBase::Exe {
+                label: label.into_providers_label()?,
+                exec_dep,
+            },
+            Self::UserUnkeyedPlaceholder(name) => {
+                MacroBase::UserUnkeyedPlaceholder(name.into_boxed_str())
+            }
+            Self::UserKeyedPlaceholder(name, label, arg) => {
+                MacroBase::UserKeyedPlaceholder(Box::new((
+                    name.into_boxed_str(),
+                    label.into_providers_label()?,
+                    arg.map(String::into_boxed_str),
+                )))
+            }
+            Self::Query {
+                expansion_type,
+                query,
+            } => MacroBase::Query(Box::new(QueryMacroBase {
+                expansion_type: match expansion_type {
+                    SavedQueryExpansion::Output => QueryExpansion::Output,
+                    SavedQueryExpansion::Target => QueryExpansion::Target,
+                    SavedQueryExpansion::TargetAndOutput(separator) => {
+                        QueryExpansion::TargetAndOutput(separator)
+                    }
+                },
+                query: query.into_query()?,
+            })),
+            Self::UnrecognizedMacro { macro_type, args } => {
+                MacroBase::UnrecognizedMacro(Box::new(UnrecognizedMacro {
+                    macro_type: macro_type.into_boxed_str(),
+                    args: args.into_boxed_slice(),
+                }))
+            }
+        })
+    }
+}
@@ -249,6 +249,11 @@ impl TargetNode {
         self.0.rule.attributes.attrs(&self.0.attributes, opts)
     }
 
+    /// The values of the attributes that are set on the target, without the defaults.
+    pub(crate) fn attr_values(&self) -> &AttrValues {
+        &self.0.attributes
+    }
+
     pub fn platform_deps(&self) -> impl Iterator<Item = &TargetLabel> {
         self.deps_cache().platform_deps.iter()
     }
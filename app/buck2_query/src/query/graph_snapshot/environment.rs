@@ -0,0 +1,308 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Query environment over a [`GraphSnapshot`], used to run uquery without a daemon.
+
+use std::borrow::Cow;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use buck2_core::build_file_path::BuildFilePath;
+use buck2_core::cells::cell_path::CellPath;
+use buck2_core::configuration::compatibility::MaybeCompatible;
+use buck2_core::pattern::ParsedPattern;
+use buck2_core::target::label::TargetLabel;
+use dupe::Dupe;
+use dupe::IterDupedExt;
+use dupe::OptionDupedExt;
+use serde::Serialize;
+
+use crate::query::environment::LabeledNode;
+use crate::query::environment::QueryEnvironment;
+use crate::query::environment::QueryEnvironmentError;
+use crate::query::environment::QueryTarget;
+use crate::query::graph_snapshot::GraphSnapshot;
+use crate::query::syntax::simple::eval::error::QueryError;
+use crate::query::syntax::simple::eval::file_set::FileNode;
+use crate::query::syntax::simple::eval::file_set::FileSet;
+use crate::query::syntax::simple::eval::set::TargetSet;
+use crate::query::traversal::async_depth_first_postorder_traversal;
+use crate::query::traversal::async_depth_limited_traversal;
+use crate::query::traversal::AsyncNodeLookup;
+use crate::query::traversal::AsyncTraversalDelegate;
+
+#[derive(Debug, thiserror::Error)]
+enum SnapshotQueryError {
+    #[error("Target `{0}` is not in the target graph snapshot")]
+    MissingTarget(TargetLabel),
+}
+
+/// A target read from a snapshot.
+#[derive(Debug, Clone, Dupe)]
+pub struct SnapshotNode(pub(crate) Arc<SnapshotNodeData>);
+
+#[derive(Debug)]
+pub(crate) struct SnapshotNodeData {
+    pub(crate) label: TargetLabel,
+    pub(crate) buildfile_path: Arc<BuildFilePath>,
+    pub(crate) rule_type: String,
+    pub(crate) deps: Vec<TargetLabel>,
+    pub(crate) exec_deps: Vec<TargetLabel>,
+    pub(crate) target_deps: Vec<TargetLabel>,
+    pub(crate) tests: Vec<TargetLabel>,
+    pub(crate) inputs: Vec<CellPath>,
+    pub(crate) special_attrs: serde_json::Map<String, serde_json::Value>,
+    pub(crate) attrs: serde_json::Map<String, serde_json::Value>,
+    pub(crate) call_stack: Option<String>,
+}
+
+impl LabeledNode for SnapshotNode {
+    type NodeRef = TargetLabel;
+
+    fn node_ref(&self) -> &Self::NodeRef {
+        &self.0.label
+    }
+}
+
+impl QueryTarget for SnapshotNode {
+    type Attr<'a> = serde_json::Value;
+
+    fn inputs_for_each<E, F: FnMut(CellPath) -> Result<(), E>>(
+        &self,
+        mut func: F,
+    ) -> Result<(), E> {
+        for input in &self.0.inputs {
+            func(input.clone())?;
+        }
+        Ok(())
+    }
+
+    fn rule_type(&self) -> Cow<str> {
+        Cow::Borrowed(&self.0.rule_type)
+    }
+
+    fn buildfile_path(&self) -> &BuildFilePath {
+        &self.0.buildfile_path
+    }
+
+    fn deps<'a>(&'a self) -> Box<dyn Iterator<Item = &'a Self::NodeRef> + Send + 'a> {
+        Box::new(self.0.deps.iter())
+    }
+
+    fn exec_deps<'a>(&'a self) -> Box<dyn Iterator<Item = &'a Self::NodeRef> + Send + 'a> {
+        Box::new(self.0.exec_deps.iter())
+    }
+
+    fn target_deps<'a>(&'a self) -> Box<dyn Iterator<Item = &'a Self::NodeRef> + Send + 'a> {
+        Box::new(self.0.target_deps.iter())
+    }
+
+    fn tests<'a>(&'a self) -> Option<Box<dyn Iterator<Item = Self::NodeRef> + Send + 'a>> {
+        Some(Box::new(self.0.tests.iter().duped()))
+    }
+
+    fn attr_to_string_alternate(&self, attr: &Self::Attr<'_>) -> String {
+        match attr {
+            serde_json::Value::String(s) => s.clone(),
+            attr => attr.to_string(),
+        }
+    }
+
+    fn attr_serialize<S: serde::Serializer>(
+        &self,
+        attr: &Self::Attr<'_>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        attr.serialize(serializer)
+    }
+
+    fn attr_any_matches(
+        attr: &Self::Attr<'_>,
+        filter: &dyn Fn(&str) -> anyhow::Result<bool>,
+    ) -> anyhow::Result<bool> {
+        match attr {
+            serde_json::Value::String(s) => filter(s),
+            serde_json::Value::Array(items) => {
+                for item in items {
+                    if Self::attr_any_matches(item, filter)? {
+                        return Ok(true);
+                    }
+                }
+                Ok(false)
+            }
+            serde_json::Value::Object(entries) => {
+                for (key, value) in entries {
+                    if filter(key)? || Self::attr_any_matches(value, filter)? {
+                        return Ok(true);
+                    }
+                }
+                Ok(false)
+            }
+            serde_json::Value::Null | serde_json::Value::Bool(_) | serde_json::Value::Number(_) => {
+                filter(&attr.to_string())
+            }
+        }
+    }
+
+    fn special_attrs_for_each<E, F: FnMut(&str, &Self::Attr<'_>) -> Result<(), E>>(
+        &self,
+        mut func: F,
+    ) -> Result<(), E> {
+        for (name, attr) in &self.0.special_attrs {
+            func(name, attr)?;
+        }
+        Ok(())
+    }
+
+    fn attrs_for_each<E, F: FnMut(&str, &Self::Attr<'_>) -> Result<(), E>>(
+        &self,
+        mut func: F,
+    ) -> Result<(), E> {
+        for (name, attr) in &self.0.attrs {
+            func(name, attr)?;
+        }
+        Ok(())
+    }
+
+    fn map_attr<R, F: FnMut(Option<&Self::Attr<'_>>) -> R>(&self, key: &str, mut func: F) -> R {
+        func(self.0.attrs.get(key))
+    }
+
+    fn call_stack(&self) -> Option<String> {
+        self.0.call_stack.clone()
+    }
+}
+
+/// Evaluates uquery expressions against a snapshot. Target patterns and file literals are
+/// interpreted relative to the root cell of the snapshot.
+pub struct SnapshotQueryEnvironment<'a> {
+    snapshot: &'a GraphSnapshot,
+}
+
+impl<'a> SnapshotQueryEnvironment<'a> {
+    pub fn new(snapshot: &'a GraphSnapshot) -> Self {
+        Self { snapshot }
+    }
+
+    fn get(&self, label: &TargetLabel) -> anyhow::Result<SnapshotNode> {
+        self.snapshot
+            .get(label)
+            .duped()
+            .ok_or_else(|| SnapshotQueryError::MissingTarget(label.dupe()).into())
+    }
+}
+
+#[async_trait]
+impl<'a> QueryEnvironment for SnapshotQueryEnvironment<'a> {
+    type Target = SnapshotNode;
+
+    async fn get_node(&self, node_ref: &TargetLabel) -> anyhow::Result<Self::Target> {
+        self.get(node_ref)
+    }
+
+    async fn get_node_for_default_configured_target(
+        &self,
+        _node_ref: &TargetLabel,
+    ) -> anyhow::Result<MaybeCompatible<Self::Target>> {
+        Err(QueryError::FunctionUnimplemented(
+            "get_node_for_default_configured_target() only for CqueryEnvironment",
+        )
+        .into())
+    }
+
+    async fn eval_literals(&self, literals: &[&str]) -> anyhow::Result<TargetSet<Self::Target>> {
+        let mut targets = TargetSet::new();
+        for literal in literals {
+            match self.snapshot.parse_pattern(literal)? {
+                ParsedPattern::Target(package, name, _) => {
+                    let label = TargetLabel::new(package.dupe(), name.as_ref());
+                    match self.snapshot.get(&label) {
+                        Some(node) => {
+                            targets.insert(node.dupe());
+                        }
+                        None => {
+                            let existing = self
+                                .snapshot
+                                .packages()
+                                .get(&package)
+                                .map(|p| p.targets.iter().map(|t| t.name().to_string()).collect())
+                                .unwrap_or_else(Vec::<String>::new);
+                            return Err(
+                                QueryEnvironmentError::missing_target(&label, existing).into()
+                            );
+                        }
+                    }
+                }
+                ParsedPattern::Package(package) => {
+                    if let Some(package) = self.snapshot.packages().get(&package) {
+                        for target in &package.targets {
+                            targets.insert(self.get(target)?);
+                        }
+                    }
+                }
+                ParsedPattern::Recursive(path) => {
+                    for (package_label, package) in self.snapshot.packages() {
+                        if package_label.as_cell_path().starts_with(path.as_ref()) {
+                            for target in &package.targets {
+                                targets.insert(self.get(target)?);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        Ok(targets)
+    }
+
+    async fn eval_file_literal(&self, literal: &str) -> anyhow::Result<FileSet> {
+        let path = self.snapshot.parse_cell_path(literal)?;
+        Ok(FileSet::new(std::iter::once(FileNode(path)).collect()))
+    }
+
+    async fn dfs_postorder(
+        &self,
+        root: &TargetSet<Self::Target>,
+        delegate: &mut dyn AsyncTraversalDelegate<Self::Target>,
+    ) -> anyhow::Result<()> {
+        async_depth_first_postorder_traversal(self, root.iter_names(), delegate).await
+    }
+
+    async fn depth_limited_traversal(
+        &self,
+        root: &TargetSet<Self::Target>,
+        delegate: &mut dyn AsyncTraversalDelegate<Self::Target>,
+        depth: u32,
+    ) -> anyhow::Result<()> {
+        async_depth_limited_traversal(self, root.iter_names(), delegate, depth).await
+    }
+
+    async fn allbuildfiles(&self, universe: &TargetSet<Self::Target>) -> anyhow::Result<FileSet> {
+        // The snapshot doesn't record imports, so this is only the build files themselves.
+        Ok(universe.buildfile())
+    }
+
+    async fn owner(&self, paths: &FileSet) -> anyhow::Result<TargetSet<Self::Target>> {
+        let mut result = TargetSet::new();
+        for path in paths.iter() {
+            for node in self.snapshot.nodes() {
+                if node.0.inputs.contains(path) {
+                    result.insert(node.dupe());
+                }
+            }
+        }
+        Ok(result)
+    }
+}
+
+#[async_trait]
+impl<'a> AsyncNodeLookup<SnapshotNode> for SnapshotQueryEnvironment<'a> {
+    async fn get(&self, label: &TargetLabel) -> anyhow::Result<SnapshotNode> {
+        SnapshotQueryEnvironment::get(self, label)
+    }
+}
@@ -0,0 +1,419 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! A serialized snapshot of the target graph, written by `buck2 targets --export-graph` and
+//! queried offline by `buck2 query --from-graph`.
+//!
+//! The snapshot is a JSON-lines file. The first line is a [`GraphSnapshotHeader`], followed by
+//! one [`GraphSnapshotPackage`] line per package, each followed by the
+//! [`GraphSnapshotTarget`] lines of the targets defined in that package. Labels and paths are
+//! written in their canonical `cell//path` form, so the snapshot can be read without a cell
+//! configuration.
+
+mod environment;
+mod tests;
+
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::io::BufRead;
+use std::io::Write;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use anyhow::Context;
+use buck2_core::build_file_path::BuildFilePath;
+use buck2_core::cells::alias::NonEmptyCellAlias;
+use buck2_core::cells::cell_path::CellPath;
+use buck2_core::cells::cell_root_path::CellRootPathBuf;
+use buck2_core::cells::paths::CellRelativePath;
+use buck2_core::cells::CellResolver;
+use buck2_core::cells::CellsAggregator;
+use buck2_core::fs::paths::file_name::FileNameBuf;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
+use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
+use buck2_core::package::PackageLabel;
+use buck2_core::pattern::pattern_type::TargetPatternExtra;
+use buck2_core::pattern::ParsedPattern;
+use buck2_core::target::label::TargetLabel;
+use buck2_util::late_binding::LateBinding;
+use dupe::Dupe;
+use serde::Deserialize;
+use serde::Serialize;
+use starlark_map::small_map::SmallMap;
+
+use crate::query::environment::LabeledNode;
+use crate::query::environment::QueryTarget;
+pub use crate::query::graph_snapshot::environment::SnapshotNode;
+use crate::query::graph_snapshot::environment::SnapshotNodeData;
+pub use crate::query::graph_snapshot::environment::SnapshotQueryEnvironment;
+use crate::query::syntax::simple::eval::values::QueryEvaluationResult;
+
+/// Prints the result of a query over a snapshot like the daemon prints query results, in the
+/// given `QueryOutputFormat`. Implemented by the query result printer of the server commands.
+pub static PRINT_SNAPSHOT_QUERY_RESULT: LateBinding<
+    for<'a> fn(
+        stdout: &'a mut (dyn Write + Send),
+        result: QueryEvaluationResult<SnapshotNode>,
+        output_format: i32,
+        output_attributes: &'a [String],
+        cell_resolver: &'a CellResolver,
+    ) -> Pin<Box<dyn Future<Output = anyhow::Result<()>> + Send + 'a>>,
+> = LateBinding::new("PRINT_SNAPSHOT_QUERY_RESULT");
+
+/// Version of the snapshot format. Bump when making incompatible changes.
+pub const GRAPH_SNAPSHOT_VERSION: u32 = 1;
+
+#[derive(Debug, thiserror::Error)]
+enum GraphSnapshotError {
+    #[error("Target graph snapshot is empty")]
+    Empty,
+    #[error("Target graph snapshot must start with a header, line {0} is not a header")]
+    MissingHeader(usize),
+    #[error(
+        "Target graph snapshot has version {0}, but this buck2 only understands version {}",
+        GRAPH_SNAPSHOT_VERSION
+    )]
+    UnsupportedVersion(u32),
+    #[error("Target graph snapshot contains more than one header (line {0})")]
+    DuplicateHeader(usize),
+    #[error("Target `{0}` appears before the package it belongs to")]
+    TargetBeforePackage(TargetLabel),
+    #[error("Target `{0}` belongs to a package without a build file")]
+    PackageWithoutBuildFile(TargetLabel),
+    #[error("Expected a package, got `{0}`")]
+    NotAPackage(String),
+    #[error("Expected a `cell//path`, got `{0}`")]
+    NotACellPath(String),
+}
+
+/// What kind of graph a snapshot holds.
+#[derive(Debug, Clone, Copy, Dupe, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GraphSnapshotKind {
+    Unconfigured,
+}
+
+/// One line of a snapshot.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum GraphSnapshotEntry {
+    Header(GraphSnapshotHeader),
+    Package(GraphSnapshotPackage),
+    Target(GraphSnapshotTarget),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GraphSnapshotHeader {
+    pub version: u32,
+    pub graph: GraphSnapshotKind,
+    pub root_cell: String,
+    /// Cell name to the cell root, relative to the project root.
+    pub cells: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GraphSnapshotPackage {
+    pub package: String,
+    /// File name of the build file, e.g. `BUCK`. Not set if the package failed to load.
+    pub buildfile: Option<String>,
+    /// `<algorithm>:<hex digest>` of the build file contents.
+    pub buildfile_digest: Option<String>,
+    #[serde(default)]
+    pub package_values: serde_json::Map<String, serde_json::Value>,
+    /// Set if the package failed to load (with `--keep-going`). Such packages have no targets.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GraphSnapshotTarget {
+    pub label: String,
+    pub rule_type: String,
+    #[serde(default)]
+    pub deps: Vec<String>,
+    #[serde(default)]
+    pub exec_deps: Vec<String>,
+    #[serde(default)]
+    pub target_deps: Vec<String>,
+    #[serde(default)]
+    pub tests: Vec<String>,
+    #[serde(default)]
+    pub inputs: Vec<String>,
+    /// The `buck.*` attributes.
+    #[serde(default)]
+    pub special_attrs: serde_json::Map<String, serde_json::Value>,
+    /// Rule attributes, including defaulted ones.
+    #[serde(default)]
+    pub attrs: serde_json::Map<String, serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub call_stack: Option<String>,
+}
+
+impl GraphSnapshotEntry {
+    /// Appends this entry as a single line.
+    pub fn write_line(&self, buffer: &mut String) -> anyhow::Result<()> {
+        buffer.push_str(&serde_json::to_string(self)?);
+        buffer.push('\n');
+        Ok(())
+    }
+}
+
+impl GraphSnapshotTarget {
+    pub fn from_node<T: QueryTarget>(node: &T) -> anyhow::Result<Self> {
+        let mut inputs = Vec::new();
+        node.inputs_for_each(|input| {
+            inputs.push(input.to_string());
+            anyhow::Ok(())
+        })?;
+
+        let mut special_attrs = serde_json::Map::new();
+        node.special_attrs_for_each(|name, attr| {
+            let value = node.attr_serialize(attr, serde_json::value::Serializer)?;
+            special_attrs.insert(name.to_owned(), value);
+            anyhow::Ok(())
+        })?;
+
+        let mut attrs = serde_json::Map::new();
+        node.attrs_for_each(|name, attr| {
+            let value = node.attr_serialize(attr, serde_json::value::Serializer)?;
+            attrs.insert(name.to_owned(), value);
+            anyhow::Ok(())
+        })?;
+
+        Ok(Self {
+            label: node.node_ref().to_string(),
+            rule_type: node.rule_type().into_owned(),
+            deps: node.deps().map(|t| t.to_string()).collect(),
+            exec_deps: node.exec_deps().map(|t| t.to_string()).collect(),
+            target_deps: node.target_deps().map(|t| t.to_string()).collect(),
+            tests: node
+                .tests()
+                .map_or_else(Vec::new, |tests| tests.map(|t| t.to_string()).collect()),
+            inputs,
+            special_attrs,
+            attrs,
+            call_stack: node.call_stack(),
+        })
+    }
+}
+
+impl GraphSnapshotHeader {
+    pub fn new(cell_resolver: &CellResolver) -> Self {
+        Self {
+            version: GRAPH_SNAPSHOT_VERSION,
+            graph: GraphSnapshotKind::Unconfigured,
+            root_cell: cell_resolver.root_cell().to_string(),
+            cells: cell_resolver
+                .cells()
+                .map(|(name, instance)| {
+                    (
+                        name.as_str().to_owned(),
+                        instance.path().as_project_relative_path().to_string(),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    /// A cell resolver where every cell can refer to every other cell by its canonical name,
+    /// which is all that's needed to parse the labels in the snapshot.
+    fn cell_resolver(&self) -> anyhow::Result<CellResolver> {
+        let roots = self
+            .cells
+            .iter()
+            .map(|(name, path)| {
+                Ok((
+                    NonEmptyCellAlias::new(name.clone())?,
+                    CellRootPathBuf::new(ProjectRelativePathBuf::try_from(path.clone())?),
+                ))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let mut aggregator = CellsAggregator::new();
+        for (_, cell_root) in &roots {
+            for (alias, alias_root) in &roots {
+                aggregator.add_cell_entry(cell_root.clone(), alias.clone(), alias_root.clone())?;
+            }
+        }
+        aggregator.make_cell_resolver()
+    }
+}
+
+/// A package as it was recorded in the snapshot.
+pub struct SnapshotPackage {
+    pub buildfile_path: Option<Arc<BuildFilePath>>,
+    pub buildfile_digest: Option<String>,
+    pub package_values: serde_json::Map<String, serde_json::Value>,
+    pub error: Option<String>,
+    pub targets: Vec<TargetLabel>,
+}
+
+/// A target graph snapshot loaded in memory.
+pub struct GraphSnapshot {
+    cell_resolver: CellResolver,
+    packages: BTreeMap<PackageLabel, SnapshotPackage>,
+    targets: SmallMap<TargetLabel, SnapshotNode>,
+}
+
+impl GraphSnapshot {
+    pub fn read(reader: impl BufRead) -> anyhow::Result<GraphSnapshot> {
+        let mut lines = reader.lines().enumerate();
+
+        let (_, first) = lines.next().ok_or(GraphSnapshotError::Empty)?;
+        let header = match serde_json::from_str(&first?)
+            .context("Error parsing target graph snapshot header")?
+        {
+            GraphSnapshotEntry::Header(header) => header,
+            _ => return Err(GraphSnapshotError::MissingHeader(1).into()),
+        };
+        if header.version != GRAPH_SNAPSHOT_VERSION {
+            return Err(GraphSnapshotError::UnsupportedVersion(header.version).into());
+        }
+
+        let mut snapshot = GraphSnapshot {
+            cell_resolver: header.cell_resolver()?,
+            packages: BTreeMap::new(),
+            targets: SmallMap::new(),
+        };
+
+        for (index, line) in lines {
+            let line_number = index + 1;
+            let line = line?;
+            if line.is_empty() {
+                continue;
+            }
+            let entry: GraphSnapshotEntry = serde_json::from_str(&line).with_context(|| {
+                format!("Error parsing target graph snapshot line {line_number}")
+            })?;
+            let added = match entry {
+                GraphSnapshotEntry::Header(_) => {
+                    return Err(GraphSnapshotError::DuplicateHeader(line_number).into());
+                }
+                GraphSnapshotEntry::Package(package) => snapshot.add_package(package),
+                GraphSnapshotEntry::Target(target) => snapshot.add_target(target),
+            };
+            added.with_context(|| format!("Error in target graph snapshot line {line_number}"))?;
+        }
+
+        Ok(snapshot)
+    }
+
+    fn add_package(&mut self, package: GraphSnapshotPackage) -> anyhow::Result<()> {
+        let label = self.parse_package(&package.package)?;
+        let buildfile_path = match package.buildfile {
+            Some(buildfile) => Some(Arc::new(BuildFilePath::new(
+                label.dupe(),
+                FileNameBuf::try_from(buildfile)?,
+            ))),
+            None => None,
+        };
+        self.packages.insert(
+            label,
+            SnapshotPackage {
+                buildfile_path,
+                buildfile_digest: package.buildfile_digest,
+                package_values: package.package_values,
+                error: package.error,
+                targets: Vec::new(),
+            },
+        );
+        Ok(())
+    }
+
+    fn add_target(&mut self, target: GraphSnapshotTarget) -> anyhow::Result<()> {
+        let label = self.parse_target(&target.label)?;
+        let package = self
+            .packages
+            .get_mut(&label.pkg())
+            .ok_or_else(|| GraphSnapshotError::TargetBeforePackage(label.dupe()))?;
+        let buildfile_path = package
+            .buildfile_path
+            .dupe()
+            .ok_or_else(|| GraphSnapshotError::PackageWithoutBuildFile(label.dupe()))?;
+        package.targets.push(label.dupe());
+
+        let node = SnapshotNodeData {
+            label: label.dupe(),
+            buildfile_path,
+            rule_type: target.rule_type,
+            deps: self.parse_targets(&target.deps)?,
+            exec_deps: self.parse_targets(&target.exec_deps)?,
+            target_deps: self.parse_targets(&target.target_deps)?,
+            tests: self.parse_targets(&target.tests)?,
+            inputs: target
+                .inputs
+                .iter()
+                .map(|input| self.parse_cell_path(input))
+                .collect::<anyhow::Result<_>>()?,
+            special_attrs: target.special_attrs,
+            attrs: target.attrs,
+            call_stack: target.call_stack,
+        };
+        self.targets.insert(label, SnapshotNode(Arc::new(node)));
+        Ok(())
+    }
+
+    pub fn cell_resolver(&self) -> &CellResolver {
+        &self.cell_resolver
+    }
+
+    pub fn packages(&self) -> &BTreeMap<PackageLabel, SnapshotPackage> {
+        &self.packages
+    }
+
+    pub fn get(&self, label: &TargetLabel) -> Option<&SnapshotNode> {
+        self.targets.get(label)
+    }
+
+    pub fn nodes(&self) -> impl Iterator<Item = &SnapshotNode> {
+        self.targets.values()
+    }
+
+    pub(crate) fn parse_pattern(
+        &self,
+        pattern: &str,
+    ) -> anyhow::Result<ParsedPattern<TargetPatternExtra>> {
+        ParsedPattern::parse_precise(pattern, self.cell_resolver.root_cell(), &self.cell_resolver)
+    }
+
+    fn parse_target(&self, label: &str) -> anyhow::Result<TargetLabel> {
+        TargetLabel::parse(label, self.cell_resolver.root_cell(), &self.cell_resolver)
+    }
+
+    fn parse_targets(&self, labels: &[String]) -> anyhow::Result<Vec<TargetLabel>> {
+        labels
+            .iter()
+            .map(|label| self.parse_target(label))
+            .collect()
+    }
+
+    fn parse_package(&self, package: &str) -> anyhow::Result<PackageLabel> {
+        match self.parse_pattern(&format!("{package}:"))? {
+            ParsedPattern::Package(package) => Ok(package),
+            _ => Err(GraphSnapshotError::NotAPackage(package.to_owned()).into()),
+        }
+    }
+
+    /// Parses `cell//path`. Paths without a cell are relative to the root cell.
+    pub(crate) fn parse_cell_path(&self, path: &str) -> anyhow::Result<CellPath> {
+        let (cell, path) = match path.split_once("//") {
+            Some((cell, path)) => (
+                self.cell_resolver
+                    .root_cell_cell_alias_resolver()
+                    .resolve(cell)?,
+                path,
+            ),
+            None if !path.starts_with('/') => (self.cell_resolver.root_cell(), path),
+            None => return Err(GraphSnapshotError::NotACellPath(path.to_owned()).into()),
+        };
+        Ok(CellPath::new(
+            cell,
+            CellRelativePath::new(ForwardRelativePath::new(path)?).to_buf(),
+        ))
+    }
+}
@@ -0,0 +1,86 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+#![cfg(test)]
+
+use indoc::indoc;
+
+use crate::query::environment::LabeledNode;
+use crate::query::graph_snapshot::GraphSnapshot;
+use crate::query::graph_snapshot::SnapshotQueryEnvironment;
+use crate::query::syntax::simple::eval::evaluator::QueryEvaluator;
+use crate::query::syntax::simple::eval::values::QueryEvaluationValue;
+use crate::query::syntax::simple::functions::DefaultQueryFunctionsModule;
+
+const SNAPSHOT: &str = indoc!(
+    r#"
+    {"kind":"header","version":1,"graph":"unconfigured","root_cell":"root","cells":{"root":"","other":"other"}}
+    {"kind":"package","package":"root//app","buildfile":"BUCK","buildfile_digest":"sha1:aa","package_values":{"team.name":"app"}}
+    {"kind":"target","label":"root//app:bin","rule_type":"cxx_binary","deps":["root//app:lib"],"inputs":["root//app/main.cpp"],"attrs":{"name":"bin"}}
+    {"kind":"target","label":"root//app:lib","rule_type":"cxx_library","deps":["other//lib:base"],"inputs":["root//app/lib.cpp"],"attrs":{"name":"lib"}}
+    {"kind":"package","package":"other//lib","buildfile":"TARGETS","buildfile_digest":"sha1:bb"}
+    {"kind":"target","label":"other//lib:base","rule_type":"cxx_library","attrs":{"name":"base"}}
+    "#
+);
+
+/// Evaluates the query and returns the sorted result.
+async fn eval(snapshot: &GraphSnapshot, query: &str) -> anyhow::Result<Vec<String>> {
+    let env = SnapshotQueryEnvironment::new(snapshot);
+    let functions = DefaultQueryFunctionsModule::new();
+    let mut result: Vec<String> = match QueryEvaluator::new(&env, &functions)
+        .eval_query(query)
+        .await?
+    {
+        QueryEvaluationValue::TargetSet(targets) => {
+            targets.iter().map(|t| t.node_ref().to_string()).collect()
+        }
+        QueryEvaluationValue::FileSet(files) => files.iter().map(|f| f.to_string()).collect(),
+    };
+    result.sort();
+    Ok(result)
+}
+
+#[tokio::test]
+async fn test_query_snapshot() -> anyhow::Result<()> {
+    let snapshot = GraphSnapshot::read(SNAPSHOT.as_bytes())?;
+    assert_eq!(2, snapshot.packages().len());
+
+    assert_eq!(
+        vec!["other//lib:base", "root//app:bin", "root//app:lib"],
+        eval(&snapshot, "deps(//app:bin)").await?
+    );
+    assert_eq!(
+        vec!["other//lib:base", "root//app:lib"],
+        eval(
+            &snapshot,
+            "rdeps(set(//... other//...), other//lib:base, 1)"
+        )
+        .await?
+    );
+    assert_eq!(
+        vec!["root//app:lib"],
+        eval(&snapshot, "owner(app/lib.cpp)").await?
+    );
+    assert_eq!(
+        vec!["other//lib:base"],
+        eval(&snapshot, "kind(cxx_library, other//...)").await?
+    );
+    Ok(())
+}
+
+#[test]
+fn test_unsupported_version() {
+    let err = GraphSnapshot::read(
+        r#"{"kind":"header","version":999,"graph":"unconfigured","root_cell":"root","cells":{"root":""}}"#
+            .as_bytes(),
+    )
+    .err()
+    .unwrap();
+    assert!(format!("{:#}", err).contains("version 999"));
+}
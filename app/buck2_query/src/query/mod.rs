@@ -10,5 +10,6 @@
 pub mod buck_types;
 pub mod environment;
 pub(crate) mod futures_queue_generic;
+pub mod graph_snapshot;
 pub mod syntax;
 pub mod traversal;
@@ -8,17 +8,25 @@
  */
 
 use std::collections::HashSet;
+use std::sync::Arc;
 
 use anyhow::Context;
 use buck2_cli_proto::config_override::ConfigType;
 use buck2_cli_proto::ConfigOverride;
+use buck2_common::http::HttpClient;
 use buck2_common::legacy_configs::cells::BuckConfigBasedCells;
+use buck2_common::legacy_configs::external_cells::ExternalCellDownloader;
 use buck2_common::legacy_configs::LegacyBuckConfigs;
 use buck2_common::legacy_configs::LegacyConfigCmdArg;
 use buck2_core::cells::CellResolver;
 use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
 use buck2_core::fs::project::ProjectRoot;
 use buck2_core::fs::project_rel_path::ProjectRelativePath;
+use buck2_execute::digest_config::DigestConfig;
+use buck2_execute::materialize::http::http_download;
+use buck2_execute::materialize::http::Checksum;
+use dupe::Dupe;
+use tokio::runtime::Handle;
 
 fn config_type_from_i32(value: i32) -> anyhow::Result<ConfigType> {
     ConfigType::from_i32(value).with_context(|| {
@@ -43,17 +51,59 @@ pub fn get_legacy_config_args<'a, Iter: IntoIterator<Item = &'a ConfigOverride>>
         .collect::<anyhow::Result<Vec<LegacyConfigCmdArg>>>()
 }
 
+/// Downloads external cells with the daemon's HTTP client, so that the `[http]` config applies to
+/// them as it does to `download_file`.
+pub struct HttpExternalCellDownloader {
+    client: HttpClient,
+    fs: ProjectRoot,
+    digest_config: DigestConfig,
+}
+
+impl HttpExternalCellDownloader {
+    pub fn new(client: HttpClient, fs: ProjectRoot, digest_config: DigestConfig) -> Self {
+        Self {
+            client,
+            fs,
+            digest_config,
+        }
+    }
+}
+
+impl ExternalCellDownloader for HttpExternalCellDownloader {
+    fn download(
+        &self,
+        urls: &[Arc<str>],
+        sha256: &Arc<str>,
+        path: &ProjectRelativePath,
+    ) -> anyhow::Result<()> {
+        // Configs are parsed synchronously, but always on the daemon's runtime.
+        tokio::task::block_in_place(|| {
+            Handle::current().block_on(http_download(
+                &self.client,
+                &self.fs,
+                self.digest_config,
+                path,
+                urls,
+                &Checksum::Sha256(sha256.dupe()),
+                false,
+            ))
+        })?;
+        Ok(())
+    }
+}
+
 /// Read the configs, returning the cell resolver and the legacy configs
 pub fn parse_legacy_cells(
     config_overrides: &[LegacyConfigCmdArg],
     cwd: &ProjectRelativePath,
     fs: &ProjectRoot,
+    downloader: &dyn ExternalCellDownloader,
 ) -> anyhow::Result<(CellResolver, LegacyBuckConfigs, HashSet<AbsNormPathBuf>)> {
     // TODO: We do not need to reparse _all_ configs, instead we just need to
     // overlay any custom configs for the current build command on top of
     // the base configs derived from the config files. This requires us to
     // store the base configs + overlaid ones separately, so we can cheaply
     // recompose.
-    let res = BuckConfigBasedCells::parse_with_config_args(fs, config_overrides, cwd)?;
+    let res = BuckConfigBasedCells::parse_with_config_args(fs, config_overrides, cwd, downloader)?;
     Ok((res.cell_resolver, res.configs_by_name, res.config_paths))
 }
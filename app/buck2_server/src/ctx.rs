@@ -13,6 +13,7 @@ use std::io::BufWriter;
 use std::marker::PhantomData;
 use std::ops::Deref;
 use std::sync::Arc;
+use std::time::Duration;
 
 use allocative::Allocative;
 use anyhow::Context;
@@ -120,6 +121,7 @@ use tracing::warn;
 use crate::active_commands::ActiveCommandDropGuard;
 use crate::configs::get_legacy_config_args;
 use crate::configs::parse_legacy_cells;
+use crate::configs::HttpExternalCellDownloader;
 use crate::daemon::common::get_default_executor_config;
 use crate::daemon::common::parse_concurrency;
 use crate::daemon::common::CommandExecutorFactory;
@@ -306,6 +308,11 @@ impl<'a> ServerCommandContext<'a> {
             working_dir: working_dir_project_relative.to_buf().into(),
             reuse_current_config: client_context.reuse_current_config,
             config_overrides,
+            external_cell_downloader: HttpExternalCellDownloader::new(
+                base_context.daemon.http_client.dupe(),
+                base_context.project_root.dupe(),
+                base_context.daemon.digest_config,
+            ),
             loaded_cell_configs: AsyncOnceCell::new(),
         });
 
@@ -465,6 +472,7 @@ struct CellConfigLoader {
     /// Reuses build config from the previous invocation if there is one
     reuse_current_config: bool,
     config_overrides: Vec<LegacyConfigCmdArg>,
+    external_cell_downloader: HttpExternalCellDownloader,
     loaded_cell_configs:
         AsyncOnceCell<SharedResult<(CellResolver, LegacyBuckConfigs, HashSet<AbsNormPathBuf>)>>,
 }
@@ -499,8 +507,13 @@ impl CellConfigLoader {
                         );
                     }
                 }
-                parse_legacy_cells(&self.config_overrides, &self.working_dir, &self.project_root)
-                    .shared_error()
+                parse_legacy_cells(
+                    &self.config_overrides,
+                    &self.working_dir,
+                    &self.project_root,
+                    &self.external_cell_downloader,
+                )
+                .shared_error()
             })
             .await
             .clone()
@@ -601,6 +614,7 @@ impl DiceDataProvider for DiceCommandDataProvider {
         run_action_knobs.use_network_action_output_cache |= root_config
             .parse::<bool>("buck2", "use_network_action_output_cache")?
             .unwrap_or(false);
+        run_action_knobs.default_timeouts = Arc::new(parse_action_timeouts(root_config)?);
 
         let mut data = UserComputationData {
             data,
@@ -663,6 +677,28 @@ impl DiceDataProvider for DiceCommandDataProvider {
     }
 }
 
+/// Reads the `[action_timeouts]` section, which maps action categories to a timeout in seconds.
+fn parse_action_timeouts(config: &LegacyBuckConfig) -> anyhow::Result<HashMap<String, Duration>> {
+    let section = match config.get_section("action_timeouts") {
+        Some(section) => section,
+        None => return Ok(HashMap::new()),
+    };
+    section
+        .iter()
+        .map(|(category, value)| {
+            let seconds = value.as_str().parse::<u64>().with_context(|| {
+                format!(
+                    "Invalid timeout `{}` for category `{}` in `[action_timeouts]` (defined {}), expected a number of seconds",
+                    value.as_str(),
+                    category,
+                    value.location(),
+                )
+            })?;
+            Ok((category.to_owned(), Duration::from_secs(seconds)))
+        })
+        .collect()
+}
+
 fn create_cycle_detector() -> Arc<dyn UserCycleDetector> {
     Arc::new(PairDiceCycleDetector(
         CycleDetectorAdapter::<LoadCycleDescriptor>::new(),
@@ -952,3 +988,54 @@ impl<'a> ServerCommandContextTrait for ServerCommandContext<'a> {
         self.cancellations
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use buck2_common::legacy_configs::testing::parse;
+    use maplit::hashmap;
+
+    use crate::ctx::parse_action_timeouts;
+
+    #[test]
+    fn test_parse_action_timeouts() -> anyhow::Result<()> {
+        let config = parse(&[("/config", "[foo]\n  bar = baz\n")], "/config")?;
+        assert_eq!(hashmap! {}, parse_action_timeouts(&config)?);
+
+        let config = parse(
+            &[(
+                "/config",
+                "[action_timeouts]\n  cxx_compile = 600\n  cxx_link = 3600\n",
+            )],
+            "/config",
+        )?;
+        assert_eq!(
+            hashmap! {
+                "cxx_compile".to_owned() => Duration::from_secs(600),
+                "cxx_link".to_owned() => Duration::from_secs(3600),
+            },
+            parse_action_timeouts(&config)?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_action_timeouts_invalid() -> anyhow::Result<()> {
+        for value in ["10m", "-1", "1.5", ""] {
+            let content = format!("[action_timeouts]\n  cxx_compile = {}\n", value);
+            let config = parse(&[("/config", content.as_str())], "/config")?;
+            let err = parse_action_timeouts(&config).unwrap_err();
+            assert!(
+                format!("{:#}", err).contains(&format!(
+                    "Invalid timeout `{}` for category `cxx_compile`",
+                    value
+                )),
+                "{:#}",
+                err
+            );
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,51 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Explains why DICE keys were recomputed, for `buck2 debug dice-explain-invalidation`.
+
+use std::fmt::Write;
+
+use dice::introspection::invalidation::InvalidationCauses;
+use dice::Dice;
+
+pub(crate) async fn dice_explain_invalidation(dice: &Dice, query: &str) -> anyhow::Result<String> {
+    let causes = dice.invalidation_causes().await?;
+    Ok(format_explanation(&causes, query))
+}
+
+fn format_explanation(causes: &InvalidationCauses, query: &str) -> String {
+    let version = match causes.version {
+        Some(version) => version,
+        None => return "Nothing has been invalidated yet".to_owned(),
+    };
+    let chains = causes.explain(query);
+    if chains.is_empty() {
+        return format!(
+            "None of the {} keys invalidated at version {} match `{}`",
+            causes.len(),
+            version,
+            query
+        );
+    }
+
+    let mut out = String::new();
+    for chain in chains {
+        for (i, key) in chain.iter().enumerate() {
+            if i > 0 {
+                out.push_str("  invalidated by ");
+            }
+            write!(out, "{} ({})", key.key, key.type_name).unwrap();
+            if i == chain.len() - 1 {
+                write!(out, ", changed at version {}", version).unwrap();
+            }
+            out.push('\n');
+        }
+    }
+    out
+}
@@ -0,0 +1,125 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Keeps the daemon within a memory budget by evicting DICE values. Enabled by
+//! `buck2.dice_memory_budget_mb`.
+
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use allocative::Allocative;
+use buck2_common::legacy_configs::LegacyBuckConfig;
+use buck2_util::process_stats::process_stats;
+use dice::Dice;
+use dice::WhichDice;
+use dupe::Dupe;
+
+#[derive(Allocative)]
+pub(crate) struct DiceMemoryBudget {
+    budget_bytes: u64,
+    #[allocative(skip)]
+    evicting: Arc<AtomicBool>,
+    /// RSS after the last eviction if it didn't reduce RSS (e.g. because the allocator kept the
+    /// freed pages), zero otherwise. Evicting more won't get below it, so only what RSS grows past
+    /// it is evicted.
+    #[allocative(skip)]
+    unreclaimed_bytes: Arc<AtomicU64>,
+}
+
+/// How many bytes of DICE values to evict when RSS is `rss_bytes`, if any.
+fn bytes_to_evict(budget_bytes: u64, unreclaimed_bytes: u64, rss_bytes: u64) -> Option<u64> {
+    let limit = budget_bytes.max(unreclaimed_bytes);
+    if rss_bytes <= limit {
+        return None;
+    }
+    Some(rss_bytes - limit)
+}
+
+impl DiceMemoryBudget {
+    pub(crate) fn new(
+        root_config: &LegacyBuckConfig,
+        which_dice: WhichDice,
+    ) -> anyhow::Result<Option<Self>> {
+        let budget_mb: u64 = match root_config.parse("buck2", "dice_memory_budget_mb")? {
+            Some(budget_mb) => budget_mb,
+            None => return Ok(None),
+        };
+        if !matches!(which_dice, WhichDice::Modern) {
+            tracing::warn!(
+                "`buck2.dice_memory_budget_mb` requires `buck2.dice = modern`, ignoring it"
+            );
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            budget_bytes: budget_mb * 1024 * 1024,
+            evicting: Arc::new(AtomicBool::new(false)),
+            unreclaimed_bytes: Arc::new(AtomicU64::new(0)),
+        }))
+    }
+
+    /// If the daemon's RSS is over budget, starts evicting enough DICE values to get back under
+    /// it. Eviction only happens if no other command is running, and backs off if the previous
+    /// one didn't reduce RSS.
+    pub(crate) fn maybe_evict(&self, dice: &Arc<Dice>) {
+        let rss_bytes = match process_stats().rss_bytes {
+            Some(rss_bytes) => rss_bytes,
+            None => return,
+        };
+        let bytes = match bytes_to_evict(
+            self.budget_bytes,
+            self.unreclaimed_bytes.load(Ordering::SeqCst),
+            rss_bytes,
+        ) {
+            Some(bytes) => bytes,
+            None => return,
+        };
+        if self.evicting.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let dice = dice.dupe();
+        let evicting = self.evicting.dupe();
+        let unreclaimed_bytes = self.unreclaimed_bytes.dupe();
+        tokio::spawn(async move {
+            match dice.evict(bytes).await {
+                Ok(Some(stats)) => tracing::debug!(
+                    "RSS is {} bytes over budget, evicted {} DICE values retaining {} bytes",
+                    bytes,
+                    stats.evicted_key_count,
+                    stats.evicted_bytes
+                ),
+                Ok(None) => {}
+                Err(e) => tracing::warn!("Failed to evict DICE values: {:#}", e),
+            }
+            let unreclaimed = match process_stats().rss_bytes {
+                Some(rss_after) if rss_after >= rss_bytes => rss_after,
+                _ => 0,
+            };
+            unreclaimed_bytes.store(unreclaimed, Ordering::SeqCst);
+            evicting.store(false, Ordering::SeqCst);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::daemon::dice_memory::bytes_to_evict;
+
+    #[test]
+    fn test_bytes_to_evict() {
+        assert_eq!(None, bytes_to_evict(100, 0, 80));
+        assert_eq!(None, bytes_to_evict(100, 0, 100));
+        assert_eq!(Some(20), bytes_to_evict(100, 0, 120));
+        // The last eviction didn't reduce RSS below 150: only evict what it grew by since.
+        assert_eq!(None, bytes_to_evict(100, 150, 150));
+        assert_eq!(Some(10), bytes_to_evict(100, 150, 160));
+        assert_eq!(Some(20), bytes_to_evict(100, 50, 120));
+    }
+}
@@ -0,0 +1,266 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Saving DICE state to disk after commands and restoring it on daemon startup, so that computed
+//! values survive daemon restarts. Enabled by `buck2.persist_dice_state`.
+//!
+//! Restored values are checked against the file system before they are used, and are discarded
+//! entirely if the cells or buckconfigs differ from those the state was saved with.
+
+use std::collections::HashMap;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use allocative::Allocative;
+use anyhow::Context;
+use buck2_common::dice::cells::SetCellResolver;
+use buck2_common::dice::persistence::PersistentStateEnvironmentKey;
+use buck2_common::invocation_paths::InvocationPaths;
+use buck2_common::legacy_configs::dice::SetLegacyConfigs;
+use buck2_common::legacy_configs::LegacyBuckConfigs;
+use buck2_core::cells::CellResolver;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
+use buck2_core::fs::project::ProjectRoot;
+use buck2_events::dispatch::with_dispatcher_async;
+use buck2_events::dispatch::EventDispatcher;
+use buck2_execute::execute::blocking::BlockingExecutor;
+use buck2_file_watcher::file_watcher::FileWatcher;
+use dice::Dice;
+use dice::DiceTransaction;
+use dice::WhichDice;
+use dupe::Dupe;
+
+use crate::daemon::disk_state::DiskStateOptions;
+
+/// How long to wait after a command before saving, so that a burst of commands only saves once.
+const SAVE_DELAY: Duration = Duration::from_secs(30);
+
+/// Key in the saved versions holding the fingerprint of the cells and buckconfigs.
+const ENVIRONMENT_VERSION: &str = "environment";
+
+/// Saved state is discarded when any of these differ from the daemon loading it.
+fn versions() -> HashMap<String, String> {
+    let metadata = buck2_events::metadata::collect();
+    ["buck2_revision", "buck2_build_time", "hostname"]
+        .iter()
+        .filter_map(|k| Some(((*k).to_owned(), metadata.get(*k)?.to_owned())))
+        .collect()
+}
+
+fn versions_path(dir: &AbsNormPathBuf) -> AbsNormPathBuf {
+    dir.join(ForwardRelativePath::unchecked_new("versions.json"))
+}
+
+fn state_path(dir: &AbsNormPathBuf) -> AbsNormPathBuf {
+    dir.join(ForwardRelativePath::unchecked_new("state.bin"))
+}
+
+async fn environment(ctx: &DiceTransaction) -> anyhow::Result<Arc<str>> {
+    Ok(ctx.compute(&PersistentStateEnvironmentKey).await??)
+}
+
+/// Restores DICE state saved by a previous daemon, if enabled. This must be called after the file
+/// watcher is created, so that changes made while restored values are checked are not missed.
+/// Saved state that can't be used is deleted.
+pub(crate) async fn maybe_load_dice_state(
+    options: &DiskStateOptions,
+    paths: &InvocationPaths,
+    io_executor: Arc<dyn BlockingExecutor>,
+    fs: ProjectRoot,
+    dice: &Arc<Dice>,
+    file_watcher: &dyn FileWatcher,
+    cells: CellResolver,
+    legacy_configs: LegacyBuckConfigs,
+) -> anyhow::Result<Option<DiceStateSaver>> {
+    let dir = paths.dice_state_path();
+    if !options.persist_dice_state {
+        io_executor
+            .execute_io_inline(|| fs.remove_path_recursive(&dir))
+            .await?;
+        return Ok(None);
+    }
+    if !matches!(dice.which_dice(), WhichDice::Modern) {
+        tracing::warn!("`buck2.persist_dice_state` requires `buck2.dice = modern`, ignoring it");
+        return Ok(None);
+    }
+
+    let saver = DiceStateSaver {
+        dir: dir.clone(),
+        dirty: Arc::new(AtomicBool::new(false)),
+        saving: Arc::new(AtomicBool::new(false)),
+    };
+
+    // The first sync of a file watcher may report a fresh instance (Watchman always does), which
+    // drops all of DICE. Get it out of the way so that it doesn't drop the restored values, and
+    // so that the first command only sees the changes made since they were checked.
+    let synced = with_dispatcher_async(EventDispatcher::null(), async {
+        let (updater, _mergebase) = file_watcher.sync(dice.updater()).await?;
+        updater.commit().await;
+        anyhow::Ok(())
+    })
+    .await;
+    if let Err(e) = synced {
+        tracing::warn!(
+            "Not restoring DICE state, syncing the file watcher failed: {:#}",
+            e
+        );
+        return Ok(Some(saver));
+    }
+
+    let saved = io_executor
+        .execute_io_inline(|| {
+            let mut saved_versions = match fs_util::read_if_exists(versions_path(&dir))? {
+                Some(v) => serde_json::from_slice::<HashMap<String, String>>(&v)?,
+                None => return Ok(None),
+            };
+            let saved_environment = saved_versions.remove(ENVIRONMENT_VERSION);
+            if saved_versions != versions() {
+                return Ok(None);
+            }
+            Ok(saved_environment.zip(fs_util::read_if_exists(state_path(&dir))?))
+        })
+        .await;
+
+    let loaded = match saved {
+        Ok(Some((saved_environment, saved))) => {
+            load_dice_state(dice, cells, legacy_configs, &saved_environment, &saved).await
+        }
+        Ok(None) => Ok(None),
+        Err(e) => Err(e),
+    };
+    let discard = match loaded {
+        Ok(Some(restored)) => {
+            tracing::debug!("Restored {} DICE values", restored);
+            false
+        }
+        Ok(None) => true,
+        Err(e) => {
+            tracing::warn!("Discarding saved DICE state: {:#}", e);
+            true
+        }
+    };
+    if discard {
+        io_executor
+            .execute_io_inline(|| fs.remove_path_recursive(&dir))
+            .await?;
+    }
+
+    Ok(Some(saver))
+}
+
+/// Returns the number of restored values, or `None` if the saved state was made with different
+/// cells or buckconfigs.
+async fn load_dice_state(
+    dice: &Arc<Dice>,
+    cells: CellResolver,
+    legacy_configs: LegacyBuckConfigs,
+    saved_environment: &str,
+    saved: &[u8],
+) -> anyhow::Result<Option<usize>> {
+    let mut updater = dice.updater();
+    updater.set_cell_resolver(cells)?;
+    updater.set_legacy_configs(legacy_configs)?;
+    let ctx = updater.commit().await;
+
+    let restored = if &*environment(&ctx).await? == saved_environment {
+        Some(
+            dice.load_persistent_state(saved, &ctx, &PersistentStateEnvironmentKey)
+                .await?,
+        )
+    } else {
+        None
+    };
+    drop(ctx);
+
+    // Commands set the cells and buckconfigs themselves, unset them again so that the first
+    // command behaves as if nothing was restored (e.g. for `--reuse-current-config`).
+    let mut updater = dice.updater();
+    updater.set_none_cell_resolver()?;
+    updater.set_none_legacy_configs()?;
+    updater.commit().await;
+
+    Ok(restored)
+}
+
+/// Writes DICE state to disk in the background.
+#[derive(Allocative)]
+pub(crate) struct DiceStateSaver {
+    dir: AbsNormPathBuf,
+    #[allocative(skip)]
+    dirty: Arc<AtomicBool>,
+    #[allocative(skip)]
+    saving: Arc<AtomicBool>,
+}
+
+impl DiceStateSaver {
+    /// Schedules saving the current state after [`SAVE_DELAY`]. Commands finishing while a save is
+    /// scheduled or in progress are covered by a single later save.
+    pub(crate) fn save(&self, dice: &Arc<Dice>) {
+        self.dirty.store(true, Ordering::SeqCst);
+        if self.saving.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let dice = dice.dupe();
+        let dir = self.dir.clone();
+        let dirty = self.dirty.dupe();
+        let saving = self.saving.dupe();
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(SAVE_DELAY).await;
+                dirty.store(false, Ordering::SeqCst);
+                if let Err(e) = save_dice_state(&dice, &dir).await {
+                    tracing::warn!("Failed to save DICE state: {:#}", e);
+                }
+                saving.store(false, Ordering::SeqCst);
+                // A command may have finished after `dirty` was cleared but seen `saving` still
+                // set, in which case it is up to us to save again.
+                if !dirty.load(Ordering::SeqCst) || saving.swap(true, Ordering::SeqCst) {
+                    break;
+                }
+            }
+        });
+    }
+}
+
+async fn save_dice_state(dice: &Arc<Dice>, dir: &AbsNormPathBuf) -> anyhow::Result<()> {
+    let ctx = dice.updater().existing_state().await;
+    let environment = environment(&ctx)
+        .await
+        .context("Error computing the environment of DICE state")?;
+    let state = dice.save_persistent_state(&ctx).await?;
+    drop(ctx);
+
+    let mut versions = versions();
+    versions.insert(ENVIRONMENT_VERSION.to_owned(), environment.to_string());
+    let dir = dir.clone();
+    tokio::task::spawn_blocking(move || write_state(&dir, &state, &versions))
+        .await
+        .context("Failed to spawn")?
+}
+
+fn write_state(
+    dir: &AbsNormPathBuf,
+    state: &[u8],
+    versions: &HashMap<String, String>,
+) -> anyhow::Result<()> {
+    fs_util::create_dir_all(dir)?;
+    // Write to a temporary file first so that an interrupted write never leaves truncated state.
+    let tmp = dir.join(ForwardRelativePath::unchecked_new("state.bin.tmp"));
+    fs_util::write(&tmp, state)?;
+    fs_util::rename(&tmp, state_path(dir))?;
+    fs_util::write(
+        versions_path(dir),
+        serde_json::to_vec(versions).context("Error serializing versions")?,
+    )?;
+    Ok(())
+}
@@ -32,6 +32,7 @@ use crate::daemon::server::BuckdServerInitPreferences;
 #[derive(Allocative)]
 pub struct DiskStateOptions {
     pub sqlite_materializer_state: bool,
+    pub persist_dice_state: bool,
     // In future, this will include the config for dep files on disk
 }
 
@@ -48,8 +49,12 @@ impl DiskStateOptions {
             .parse::<RolloutPercentage>("buck2", "sqlite_materializer_state")?
             .unwrap_or_else(RolloutPercentage::never)
             .roll();
+        let persist_dice_state = root_config
+            .parse("buck2", "persist_dice_state")?
+            .unwrap_or(false);
         Ok(Self {
             sqlite_materializer_state,
+            persist_dice_state,
         })
     }
 }
@@ -11,6 +11,9 @@ pub mod check_working_dir;
 pub mod common;
 pub mod daemon_tcp;
 pub mod dice_dump;
+pub(crate) mod dice_invalidation;
+pub(crate) mod dice_memory;
+pub(crate) mod dice_state;
 pub mod disk_state;
 pub mod forkserver;
 pub(crate) mod io_provider;
@@ -453,6 +453,12 @@ impl BuckdServer {
                         func(&context, PartialResultDispatcher::new(dispatch.dupe()), req).await?
                     };
                     dispatch.command_result(result_to_command_result(result));
+                    if let Some(saver) = &data.dice_state_saver {
+                        saver.save(data.dice_manager.unsafe_dice());
+                    }
+                    if let Some(budget) = &data.dice_memory_budget {
+                        budget.maybe_evict(data.dice_manager.unsafe_dice());
+                    }
                 }
                 .boxed()
             },
@@ -1160,6 +1166,29 @@ impl DaemonApi for BuckdServer {
             .map_err(|e| Status::internal(format!("{:#}", e)))
     }
 
+    async fn unstable_dice_explain_invalidation(
+        &self,
+        req: Request<UnstableDiceExplainInvalidationRequest>,
+    ) -> Result<Response<UnstableDiceExplainInvalidationResponse>, Status> {
+        self.check_if_accepting_requests()?;
+
+        let query = req.into_inner().query;
+        let res: anyhow::Result<_> = try {
+            let explanation = self
+                .0
+                .daemon_state
+                .data()?
+                .dice_explain_invalidation(&query)
+                .await
+                .context("Failed to explain DICE invalidations")?;
+
+            UnstableDiceExplainInvalidationResponse { explanation }
+        };
+
+        res.map(Response::new)
+            .map_err(|e| Status::internal(format!("{:#}", e)))
+    }
+
     type AllocativeStream = ResponseStream;
     async fn allocative(
         &self,
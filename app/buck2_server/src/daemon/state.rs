@@ -70,8 +70,12 @@ use tokio::runtime::Handle;
 use tokio::sync::Mutex;
 
 use crate::active_commands::ActiveCommandDropGuard;
+use crate::configs::HttpExternalCellDownloader;
 use crate::ctx::BaseServerCommandContext;
 use crate::daemon::check_working_dir;
+use crate::daemon::dice_memory::DiceMemoryBudget;
+use crate::daemon::dice_state::maybe_load_dice_state;
+use crate::daemon::dice_state::DiceStateSaver;
 use crate::daemon::disk_state::delete_unknown_disk_state;
 use crate::daemon::disk_state::maybe_initialize_materializer_sqlite_db;
 use crate::daemon::disk_state::DiskStateOptions;
@@ -150,6 +154,13 @@ pub struct DaemonStateData {
     /// What buck2 state to store on disk, ex. materializer state on sqlite
     pub disk_state_options: DiskStateOptions,
 
+    /// Saves DICE state after every command, if `buck2.persist_dice_state` is enabled.
+    pub(crate) dice_state_saver: Option<DiceStateSaver>,
+
+    /// Evicts DICE values after commands when over budget, if `buck2.dice_memory_budget_mb` is
+    /// set.
+    pub(crate) dice_memory_budget: Option<DiceMemoryBudget>,
+
     pub start_time: Instant,
 
     #[allocative(skip)]
@@ -165,6 +176,9 @@ pub struct DaemonStateData {
     /// Http client used for materializer and RunAction implementations.
     pub http_client: HttpClient,
 
+    /// Digest config the daemon was started with.
+    pub digest_config: DigestConfig,
+
     /// If enabled, paranoid RE downloads.
     pub paranoid: Option<ParanoidDownloader>,
 
@@ -181,6 +195,14 @@ impl DaemonStateData {
         crate::daemon::dice_dump::dice_dump_spawn(self.dice_manager.unsafe_dice(), path, format)
             .await
     }
+
+    pub async fn dice_explain_invalidation(&self, query: &str) -> anyhow::Result<String> {
+        crate::daemon::dice_invalidation::dice_explain_invalidation(
+            self.dice_manager.unsafe_dice(),
+            query,
+        )
+        .await
+    }
 }
 
 impl DaemonStatePanicDiceDump for DaemonStateData {
@@ -233,18 +255,6 @@ impl DaemonState {
         rt.spawn(async move {
             let fs = paths.project_root().clone();
 
-            tracing::info!("Reading config...");
-            let legacy_cells = BuckConfigBasedCells::parse(&fs)?;
-
-            tracing::info!("Starting...");
-
-            let (legacy_configs, cells) =
-                (legacy_cells.configs_by_name, legacy_cells.cell_resolver);
-
-            let root_config = legacy_configs
-                .get(cells.root_cell())
-                .context("No config for root cell")?;
-
             static DEFAULT_DIGEST_ALGORITHM: EnvHelper<DigestAlgorithmKind> =
                 EnvHelper::new("BUCK_DEFAULT_DIGEST_ALGORITHM");
 
@@ -284,6 +294,26 @@ impl DaemonState {
                 DigestConfig::leak_new(digest_algorithms, preferred_source_algorithm)
                     .context("Error initializing DigestConfig")?;
 
+            let http_client =
+                HttpClientBuilder::from_startup_config(&init_ctx.daemon_startup_config)
+                    .context("Error creating HTTP client")?
+                    .build();
+
+            tracing::info!("Reading config...");
+            let legacy_cells = BuckConfigBasedCells::parse(
+                &fs,
+                &HttpExternalCellDownloader::new(http_client.dupe(), fs.dupe(), digest_config),
+            )?;
+
+            tracing::info!("Starting...");
+
+            let (legacy_configs, cells) =
+                (legacy_cells.configs_by_name, legacy_cells.cell_resolver);
+
+            let root_config = legacy_configs
+                .get(cells.root_cell())
+                .context("No config for root cell")?;
+
             // TODO(rafaelc): merge configs from all cells once they are consistent
             let static_metadata = Arc::new(RemoteExecutionStaticMetadata::from_legacy_config(
                 root_config,
@@ -375,11 +405,6 @@ impl DaemonState {
             )
             .await?;
 
-            let http_client =
-                HttpClientBuilder::from_startup_config(&init_ctx.daemon_startup_config)
-                    .context("Error creating HTTP client")?
-                    .build();
-
             let materializer_state_identity =
                 materializer_db.as_ref().map(|d| d.identity().clone());
 
@@ -414,6 +439,7 @@ impl DaemonState {
             let dice = init_ctx
                 .construct_dice(io.dupe(), digest_config, root_config)
                 .await?;
+            let dice_memory_budget = DiceMemoryBudget::new(root_config, dice.which_dice())?;
 
             // TODO(cjhopman): We want to use Expr::True here, but we need to workaround
             // https://github.com/facebook/watchman/issues/911. Adding other filetypes to
@@ -432,6 +458,20 @@ impl DaemonState {
                 )
             })?;
 
+            // Restored values are checked against the file system, so do this once the file
+            // watcher is watching it.
+            let dice_state_saver = maybe_load_dice_state(
+                &disk_state_options,
+                &paths,
+                blocking_executor.dupe() as Arc<dyn BlockingExecutor>,
+                fs.dupe(),
+                &dice,
+                &*file_watcher,
+                cells.dupe(),
+                legacy_configs.dupe(),
+            )
+            .await?;
+
             let hash_all_commands = root_config
                 .parse::<RolloutPercentage>("buck2", "hash_all_commands")?
                 .unwrap_or_else(RolloutPercentage::never)
@@ -497,11 +537,14 @@ impl DaemonState {
                 hash_all_commands,
                 use_network_action_output_cache,
                 disk_state_options,
+                dice_state_saver,
+                dice_memory_budget,
                 start_time: std::time::Instant::now(),
                 create_unhashed_outputs_lock,
                 materializer_state_identity,
                 enable_restarter,
                 http_client,
+                digest_config,
                 paranoid,
                 spawner: Arc::new(BuckSpawner::new(daemon_state_data_rt)),
             }))
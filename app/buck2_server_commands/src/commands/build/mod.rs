@@ -460,6 +460,10 @@ fn build_providers_to_providers_to_build(build_providers: &BuildProviders) -> Pr
         providers_to_build.run = true;
     }
 
+    if build_providers.validation_info != BuildProviderAction::Skip as i32 {
+        providers_to_build.validations = true;
+    }
+
     providers_to_build
 }
 
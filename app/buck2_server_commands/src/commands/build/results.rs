@@ -116,6 +116,11 @@ pub mod result_report {
                             continue;
                         }
 
+                        // Validations may belong to dependencies, they aren't outputs of the target.
+                        if matches!(provider_type, BuildProviderType::Validation) {
+                            continue;
+                        }
+
                         for (artifact, _value) in values.iter() {
                             let entry =
                                 artifacts
@@ -140,6 +145,7 @@ pub mod result_report {
                                 BuildProviderType::Test => {
                                     entry.test_info = true;
                                 }
+                                BuildProviderType::Validation => {}
                             }
                         }
                     }
@@ -344,6 +350,9 @@ pub mod build_report {
                                     // describes the type of the artifact
                                     is_other = true;
                                 }
+                                BuildProviderType::Validation => {
+                                    // Validations only affect whether the target succeeded.
+                                }
                             }
 
                             for (artifact, _value) in artifacts.values.iter() {
@@ -23,6 +23,7 @@ use dice::DiceTransaction;
 
 use crate::commands::query::printer::QueryResultPrinter;
 use crate::commands::query::printer::ShouldPrintProviders;
+use crate::commands::query::printer::ShouldPrintSelectProvenance;
 
 pub(crate) async fn aquery_command(
     ctx: &dyn ServerCommandContextTrait,
@@ -104,12 +105,24 @@ async fn aquery(
     match query_result {
         QueryEvaluationResult::Single(targets) => {
             output_configuration
-                .print_single_output(&mut stdout, targets, false, ShouldPrintProviders::No)
+                .print_single_output(
+                    &mut stdout,
+                    targets,
+                    false,
+                    ShouldPrintProviders::No,
+                    ShouldPrintSelectProvenance::No,
+                )
                 .await?
         }
         QueryEvaluationResult::Multiple(results) => {
             output_configuration
-                .print_multi_output(&mut stdout, results, false, ShouldPrintProviders::No)
+                .print_multi_output(
+                    &mut stdout,
+                    results,
+                    false,
+                    ShouldPrintProviders::No,
+                    ShouldPrintSelectProvenance::No,
+                )
                 .await?
         }
     };
@@ -22,6 +22,7 @@ use buck2_core::configuration::compatibility::MaybeCompatible;
 use buck2_core::provider::label::ConfiguredProvidersLabel;
 use buck2_core::provider::label::ProvidersName;
 use buck2_node::nodes::configured::ConfiguredTargetNode;
+use buck2_node::nodes::configured::SelectProvenance;
 use buck2_query::query::syntax::simple::eval::values::QueryEvaluationResult;
 use buck2_server_ctx::ctx::ServerCommandContextTrait;
 use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
@@ -35,7 +36,9 @@ use dupe::Dupe;
 
 use crate::commands::query::printer::ProviderLookUp;
 use crate::commands::query::printer::QueryResultPrinter;
+use crate::commands::query::printer::SelectProvenanceLookUp;
 use crate::commands::query::printer::ShouldPrintProviders;
+use crate::commands::query::printer::ShouldPrintSelectProvenance;
 
 pub(crate) async fn cquery_command(
     ctx: &dyn ServerCommandContextTrait,
@@ -104,6 +107,7 @@ async fn cquery(
         context,
         show_providers,
         correct_owner,
+        show_select_provenance,
         ..
     } = request;
     // The request will always have a universe value, an empty one indicates the user didn't provide a universe.
@@ -145,6 +149,12 @@ async fn cquery(
         ShouldPrintProviders::No
     };
 
+    let should_print_select_provenance = if *show_select_provenance {
+        ShouldPrintSelectProvenance::Yes(&ConfiguredSelectProvenanceLookUp)
+    } else {
+        ShouldPrintSelectProvenance::No
+    };
+
     match query_result {
         QueryEvaluationResult::Single(targets) => {
             output_configuration
@@ -153,6 +163,7 @@ async fn cquery(
                     targets,
                     target_call_stacks,
                     should_print_providers,
+                    should_print_select_provenance,
                 )
                 .await?
         }
@@ -163,6 +174,7 @@ async fn cquery(
                     results,
                     target_call_stacks,
                     should_print_providers,
+                    should_print_select_provenance,
                 )
                 .await?
         }
@@ -184,3 +196,15 @@ impl ProviderLookUp<ConfiguredTargetNode> for DiceComputations {
         .await
     }
 }
+
+struct ConfiguredSelectProvenanceLookUp;
+
+impl SelectProvenanceLookUp<ConfiguredTargetNode> for ConfiguredSelectProvenanceLookUp {
+    fn lookup(
+        &self,
+        t: &ConfiguredTargetNode,
+        attr_filter: &dyn Fn(&str) -> bool,
+    ) -> anyhow::Result<SelectProvenance> {
+        t.select_provenance(attr_filter)
+    }
+}
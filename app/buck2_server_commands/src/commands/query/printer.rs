@@ -20,11 +20,14 @@ use buck2_build_api::interpreter::rule_defs::provider::collection::FrozenProvide
 use buck2_cli_proto::QueryOutputFormat;
 use buck2_core::cells::CellResolver;
 use buck2_core::configuration::compatibility::MaybeCompatible;
+use buck2_node::nodes::configured::SelectProvenance;
 use buck2_query::query::environment::QueryTarget;
 use buck2_query::query::environment::QueryTargets;
+use buck2_query::query::graph_snapshot::PRINT_SNAPSHOT_QUERY_RESULT;
 use buck2_query::query::syntax::simple::eval::file_set::FileSet;
 use buck2_query::query::syntax::simple::eval::multi_query::MultiQueryResult;
 use buck2_query::query::syntax::simple::eval::set::TargetSet;
+use buck2_query::query::syntax::simple::eval::values::QueryEvaluationResult;
 use buck2_query::query::syntax::simple::eval::values::QueryEvaluationValue;
 use buck2_util::indent::indent;
 use dupe::Clone_;
@@ -55,6 +58,18 @@ pub trait ProviderLookUp<T: QueryTarget>: Send + Sync {
     -> anyhow::Result<MaybeCompatible<FrozenProviderCollectionValue>>;
 }
 
+#[derive(Copy_, Dupe_, Clone_, UnpackVariants)]
+pub enum ShouldPrintSelectProvenance<'a, T> {
+    No,
+    Yes(&'a dyn SelectProvenanceLookUp<T>),
+}
+
+/// Explains how the attributes of a target were configured. Only meaningful for configured targets.
+pub trait SelectProvenanceLookUp<T: QueryTarget>: Send + Sync {
+    fn lookup(&self, t: &T, attr_filter: &dyn Fn(&str) -> bool)
+    -> anyhow::Result<SelectProvenance>;
+}
+
 #[derive(Debug)]
 pub struct QueryResultPrinter<'a> {
     resolver: &'a CellResolver,
@@ -71,15 +86,23 @@ impl<'a, T: QueryTarget> TargetSetJsonPrinter<'a, T> {
     async fn new(
         target_call_stacks: bool,
         print_providers: ShouldPrintProviders<'a, T>,
+        print_select_provenance: ShouldPrintSelectProvenance<'a, T>,
         attributes: &'a Option<RegexSet>,
         targets: &'a TargetSet<T>,
     ) -> anyhow::Result<TargetSetJsonPrinter<'a, T>> {
         Ok(TargetSetJsonPrinter {
-            value: printable_targets(targets, print_providers, attributes, target_call_stacks)
-                .await?,
+            value: printable_targets(
+                targets,
+                print_providers,
+                print_select_provenance,
+                attributes,
+                target_call_stacks,
+            )
+            .await?,
             is_complex: attributes.is_some()
                 || target_call_stacks
-                || print_providers.unpack_yes().is_some(),
+                || print_providers.unpack_yes().is_some()
+                || print_select_provenance.unpack_yes().is_some(),
         })
     }
 }
@@ -88,6 +111,7 @@ struct PrintableQueryTarget<'a, T: QueryTarget> {
     value: &'a T,
     attributes: &'a Option<RegexSet>,
     providers: Option<FrozenProviderCollectionValue>,
+    select_provenance: Option<SelectProvenance>,
     target_call_stacks: bool,
 }
 
@@ -101,7 +125,7 @@ impl<'a, T: QueryTarget> Display for PrintableQueryTarget<'a, T> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.value.node_ref())?;
 
-        if self.target_call_stacks || self.providers.is_some() {
+        if self.target_call_stacks || self.providers.is_some() || self.select_provenance.is_some() {
             writeln!(f)?;
         }
 
@@ -125,6 +149,12 @@ impl<'a, T: QueryTarget> Display for PrintableQueryTarget<'a, T> {
             )?;
         }
 
+        if let Some(select_provenance) = &self.select_provenance {
+            let json =
+                serde_json::to_string_pretty(select_provenance).map_err(|_| std::fmt::Error)?;
+            writeln!(f, "{}", indent("  ", &json))?;
+        }
+
         Ok(())
     }
 }
@@ -173,6 +203,10 @@ impl<'a, T: QueryTarget> Serialize for PrintableQueryTarget<'a, T> {
             map.serialize_entry("buck.providers", providers)?;
         }
 
+        if let Some(select_provenance) = &self.select_provenance {
+            map.serialize_entry("buck.select_provenance", select_provenance)?;
+        }
+
         map.end()
     }
 }
@@ -259,6 +293,7 @@ impl<'a> QueryResultPrinter<'a> {
         multi_result: MultiQueryResult<T>,
         target_call_stacks: bool,
         print_providers: ShouldPrintProviders<'b, T>,
+        print_select_provenance: ShouldPrintSelectProvenance<'b, T>,
     ) -> anyhow::Result<()> {
         match (self.output_format, &self.attributes) {
             // A multi-query only has interesting output with --json output. For non-json output it gets merged together.
@@ -279,6 +314,7 @@ impl<'a> QueryResultPrinter<'a> {
                                 &TargetSetJsonPrinter::new(
                                     target_call_stacks,
                                     print_providers,
+                                    print_select_provenance,
                                     &self.attributes,
                                     &targets,
                                 )
@@ -313,6 +349,7 @@ impl<'a> QueryResultPrinter<'a> {
                     multi_result.merged()?,
                     target_call_stacks,
                     print_providers,
+                    print_select_provenance,
                 )
                 .await
             }
@@ -325,13 +362,19 @@ impl<'a> QueryResultPrinter<'a> {
         result: QueryEvaluationValue<T>,
         call_stack: bool,
         print_providers: ShouldPrintProviders<'b, T>,
+        print_select_provenance: ShouldPrintSelectProvenance<'b, T>,
     ) -> anyhow::Result<()> {
         match result {
             QueryEvaluationValue::TargetSet(targets) => match self.output_format {
                 QueryOutputFormat::Default => {
-                    for target in
-                        printable_targets(&targets, print_providers, &self.attributes, call_stack)
-                            .await?
+                    for target in printable_targets(
+                        &targets,
+                        print_providers,
+                        print_select_provenance,
+                        &self.attributes,
+                        call_stack,
+                    )
+                    .await?
                     {
                         writeln!(&mut output, "{}", target)?;
                     }
@@ -341,6 +384,7 @@ impl<'a> QueryResultPrinter<'a> {
                     TargetSetJsonPrinter::new(
                         call_stack,
                         print_providers,
+                        print_select_provenance,
                         &self.attributes,
                         &targets,
                     )
@@ -411,6 +455,7 @@ impl<'a> QueryResultPrinter<'a> {
 async fn printable_targets<'a, T: QueryTarget>(
     targets: &'a TargetSet<T>,
     print_providers: ShouldPrintProviders<'a, T>,
+    print_select_provenance: ShouldPrintSelectProvenance<'a, T>,
     attributes: &'a Option<RegexSet>,
     target_call_stacks: bool,
 ) -> anyhow::Result<Vec<PrintableQueryTarget<'a, T>>> {
@@ -427,6 +472,15 @@ async fn printable_targets<'a, T: QueryTarget>(
                         Some(lookup.lookup(t).await?.require_compatible()?)
                     }
                 },
+                select_provenance: match print_select_provenance {
+                    ShouldPrintSelectProvenance::No => None,
+                    ShouldPrintSelectProvenance::Yes(lookup) => {
+                        Some(lookup.lookup(t, &|attr_name| match attributes {
+                            Some(attributes) => attributes.is_match(attr_name),
+                            None => true,
+                        })?)
+                    }
+                },
             })
         }
     }))
@@ -464,6 +518,7 @@ async fn print_action_node(
             QueryEvaluationValue::TargetSet(result),
             false,
             ShouldPrintProviders::No,
+            ShouldPrintSelectProvenance::No,
         )
         .await
 }
@@ -479,3 +534,41 @@ pub(crate) fn init_print_action_node() {
         ))
     });
 }
+
+pub(crate) fn init_print_snapshot_query_result() {
+    PRINT_SNAPSHOT_QUERY_RESULT.init(
+        |stdout, result, output_format, output_attributes, cell_resolver| {
+            Box::pin(async move {
+                let printer = QueryResultPrinter::from_request_options(
+                    cell_resolver,
+                    output_attributes,
+                    output_format,
+                )?;
+                match result {
+                    QueryEvaluationResult::Single(value) => {
+                        printer
+                            .print_single_output(
+                                stdout,
+                                value,
+                                false,
+                                ShouldPrintProviders::No,
+                                ShouldPrintSelectProvenance::No,
+                            )
+                            .await
+                    }
+                    QueryEvaluationResult::Multiple(results) => {
+                        printer
+                            .print_multi_output(
+                                stdout,
+                                results,
+                                false,
+                                ShouldPrintProviders::No,
+                                ShouldPrintSelectProvenance::No,
+                            )
+                            .await
+                    }
+                }
+            })
+        },
+    );
+}
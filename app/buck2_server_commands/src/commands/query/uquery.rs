@@ -25,6 +25,7 @@ use dice::DiceTransaction;
 
 use crate::commands::query::printer::QueryResultPrinter;
 use crate::commands::query::printer::ShouldPrintProviders;
+use crate::commands::query::printer::ShouldPrintSelectProvenance;
 
 pub(crate) async fn uquery_command(
     ctx: &dyn ServerCommandContextTrait,
@@ -113,6 +114,7 @@ async fn uquery(
                     targets,
                     target_call_stacks,
                     ShouldPrintProviders::No,
+                    ShouldPrintSelectProvenance::No,
                 )
                 .await?
         }
@@ -123,6 +125,7 @@ async fn uquery(
                     results,
                     target_call_stacks,
                     ShouldPrintProviders::No,
+                    ShouldPrintSelectProvenance::No,
                 )
                 .await?
         }
@@ -0,0 +1,183 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Server-side implementation of `buck2 targets --export-graph`.
+
+use std::collections::HashSet;
+use std::io::Write;
+use std::mem;
+
+use buck2_cli_proto::targets_request;
+use buck2_cli_proto::TargetsResponse;
+use buck2_common::dice::cells::HasCellResolver;
+use buck2_common::dice::file_ops::HasFileOps;
+use buck2_common::file_ops::FileOps;
+use buck2_common::file_ops::PathMetadata;
+use buck2_common::file_ops::PathMetadataOrRedirection;
+use buck2_core::build_file_path::BuildFilePath;
+use buck2_core::package::PackageLabel;
+use buck2_core::pattern::pattern_type::TargetPatternExtra;
+use buck2_core::pattern::ParsedPattern;
+use buck2_core::target::label::TargetLabel;
+use buck2_node::load_patterns::load_patterns;
+use buck2_node::load_patterns::MissingTargetBehavior;
+use buck2_node::nodes::frontend::TargetGraphCalculation;
+use buck2_node::nodes::unconfigured::TargetNode;
+use buck2_query::query::graph_snapshot::GraphSnapshotEntry;
+use buck2_query::query::graph_snapshot::GraphSnapshotHeader;
+use buck2_query::query::graph_snapshot::GraphSnapshotPackage;
+use buck2_query::query::graph_snapshot::GraphSnapshotTarget;
+use buck2_server_ctx::ctx::ServerCommandContextTrait;
+use dice::DiceTransaction;
+use dupe::Dupe;
+use dupe::IterDupedExt;
+use futures::future;
+use starlark_map::small_map::SmallMap;
+
+use crate::commands::targets::fmt::package_error_to_stderr;
+use crate::commands::targets::mk_error;
+
+/// The targets of a package that go in the snapshot.
+#[derive(Default)]
+struct ExportedPackage {
+    targets: Vec<TargetNode>,
+    error: Option<String>,
+}
+
+/// Writes the targets matching the patterns, and every target they transitively depend on, so
+/// that all edges of the snapshot can be followed offline.
+pub(crate) async fn targets_export_graph(
+    server_ctx: &dyn ServerCommandContextTrait,
+    dice: DiceTransaction,
+    request: &targets_request::ExportGraph,
+    parsed_patterns: Vec<ParsedPattern<TargetPatternExtra>>,
+    target_call_stacks: bool,
+) -> anyhow::Result<TargetsResponse> {
+    let cell_resolver = dice.get_cell_resolver().await?;
+    let results = load_patterns(&dice, parsed_patterns, MissingTargetBehavior::Fail).await?;
+
+    let mut packages: SmallMap<PackageLabel, ExportedPackage> = SmallMap::new();
+    let mut exported: HashSet<TargetLabel> = HashSet::new();
+    let mut errors = 0;
+    for (package, result) in results.iter() {
+        let exported_package = packages.entry(package.dupe()).or_default();
+        match result {
+            Ok(targets) => {
+                for node in targets.values() {
+                    if exported.insert(node.label().dupe()) {
+                        exported_package.targets.push(node.dupe());
+                    }
+                }
+            }
+            Err(e) => {
+                errors += 1;
+                let mut stderr = String::new();
+                package_error_to_stderr(&package, e.inner(), &mut stderr);
+                server_ctx.stderr()?.write_all(stderr.as_bytes())?;
+                if !request.keep_going {
+                    return Err(mk_error(errors));
+                }
+                exported_package.error = Some(format!("{:?}", e.inner()));
+            }
+        }
+    }
+
+    let mut pending: Vec<TargetLabel> = packages
+        .values()
+        .flat_map(|p| p.targets.iter().flat_map(|node| node.deps()))
+        .duped()
+        .collect();
+    while !pending.is_empty() {
+        let batch: Vec<TargetLabel> = mem::take(&mut pending)
+            .into_iter()
+            .filter(|label| exported.insert(label.dupe()))
+            .collect();
+        let nodes = future::join_all(batch.iter().map(|label| dice.get_target_node(label))).await;
+        for (label, node) in batch.iter().zip(nodes) {
+            match node {
+                Ok(node) => {
+                    pending.extend(node.deps().filter(|dep| !exported.contains(*dep)).duped());
+                    packages.entry(label.pkg()).or_default().targets.push(node);
+                }
+                Err(e) => {
+                    errors += 1;
+                    server_ctx.stderr()?.write_all(
+                        format!("Error loading dependency `{}`\n{:?}\n", label, e).as_bytes(),
+                    )?;
+                    if !request.keep_going {
+                        return Err(mk_error(errors));
+                    }
+                }
+            }
+        }
+    }
+
+    let mut buffer = String::new();
+    GraphSnapshotEntry::Header(GraphSnapshotHeader::new(&cell_resolver)).write_line(&mut buffer)?;
+
+    for (package, ExportedPackage { targets, error }) in packages {
+        // Loading the targets already evaluated the build file, so this is cached.
+        let (buildfile, package_values) = match &error {
+            None => {
+                let eval_result = dice.get_interpreter_results(package.dupe()).await?;
+                let package_values = eval_result
+                    .super_package()
+                    .package_values()
+                    .package_values_json()?
+                    .into_iter()
+                    .map(|(k, v)| (k.as_str().to_owned(), v))
+                    .collect();
+                (Some(eval_result.buildfile_path().dupe()), package_values)
+            }
+            Some(_) => (None, serde_json::Map::new()),
+        };
+
+        let buildfile_digest = match &buildfile {
+            Some(buildfile) => buildfile_digest(&dice, buildfile).await?,
+            None => None,
+        };
+        GraphSnapshotEntry::Package(GraphSnapshotPackage {
+            package: package.to_string(),
+            buildfile: buildfile.map(|b| b.filename().to_string()),
+            buildfile_digest,
+            package_values,
+            error,
+        })
+        .write_line(&mut buffer)?;
+
+        for node in &targets {
+            let mut target = GraphSnapshotTarget::from_node(node)?;
+            if !target_call_stacks {
+                target.call_stack = None;
+            }
+            GraphSnapshotEntry::Target(target).write_line(&mut buffer)?;
+        }
+    }
+
+    Ok(TargetsResponse {
+        error_count: errors,
+        serialized_targets_output: buffer,
+    })
+}
+
+/// `<algorithm>:<hex digest>` of the build file, as tracked by DICE.
+async fn buildfile_digest(
+    dice: &DiceTransaction,
+    buildfile: &BuildFilePath,
+) -> anyhow::Result<Option<String>> {
+    let path = buildfile.path();
+    let metadata = <dyn FileOps>::read_path_metadata(&dice.file_ops(), path.as_ref()).await?;
+    Ok(match PathMetadataOrRedirection::from(metadata) {
+        PathMetadataOrRedirection::PathMetadata(PathMetadata::File(m)) => {
+            let digest = m.digest.raw_digest();
+            Some(format!("{}:{}", digest.algorithm(), digest))
+        }
+        _ => None,
+    })
+}
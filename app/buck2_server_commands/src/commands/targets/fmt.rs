@@ -52,7 +52,11 @@ pub(crate) struct TargetInfo<'a> {
     pub(crate) super_package: &'a SuperPackage,
 }
 
-fn package_error_to_stderr(package: &PackageLabel, error: &anyhow::Error, stderr: &mut String) {
+pub(crate) fn package_error_to_stderr(
+    package: &PackageLabel,
+    error: &anyhow::Error,
+    stderr: &mut String,
+) {
     writeln!(stderr, "Error parsing {package}\n{error:?}").unwrap();
 }
 
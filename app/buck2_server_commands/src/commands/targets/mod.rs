@@ -8,6 +8,7 @@
  */
 
 mod default;
+mod export_graph;
 pub(crate) mod fmt;
 mod resolve_alias;
 mod streaming;
@@ -35,6 +36,7 @@ use dice::DiceTransaction;
 
 use crate::commands::targets::default::targets_batch;
 use crate::commands::targets::default::TargetHashOptions;
+use crate::commands::targets::export_graph::targets_export_graph;
 use crate::commands::targets::fmt::create_formatter;
 use crate::commands::targets::resolve_alias::targets_resolve_aliases;
 use crate::commands::targets::streaming::targets_streaming;
@@ -180,6 +182,16 @@ async fn targets(
         Some(targets_request::Targets::ResolveAlias(_)) => {
             targets_resolve_aliases(dice, request, parsed_target_patterns).await?
         }
+        Some(targets_request::Targets::ExportGraph(export_graph)) => {
+            targets_export_graph(
+                server_ctx,
+                dice,
+                export_graph,
+                parsed_target_patterns,
+                request.client_context()?.target_call_stacks,
+            )
+            .await?
+        }
         Some(targets_request::Targets::Other(other)) => {
             if other.streaming {
                 let formatter = create_formatter(request, other)?;
@@ -21,4 +21,5 @@ pub mod target_hash;
 pub fn init_late_bindings() {
     commands::init_commands::init_other_server_commands();
     commands::query::printer::init_print_action_node();
+    commands::query::printer::init_print_snapshot_query_result();
 }
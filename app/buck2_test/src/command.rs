@@ -183,6 +183,7 @@ struct TestStatuses {
     fatals: CounterWithExamples,
     listing_success: CounterWithExamples,
     listing_failed: CounterWithExamples,
+    flaky: CounterWithExamples,
 }
 impl TestStatuses {
     fn ingest(&mut self, result: &TestResult) {
@@ -197,6 +198,7 @@ impl TestStatuses {
             TestStatus::RERUN => {}
             TestStatus::LISTING_SUCCESS => self.listing_success.add(&result.name),
             TestStatus::LISTING_FAILED => self.listing_failed.add(&result.name),
+            TestStatus::FLAKY => self.flaky.add(&result.name),
         }
     }
 }
@@ -305,6 +307,7 @@ async fn test(
         allow_re: options.allow_re,
         force_use_project_relative_paths: options.force_use_project_relative_paths,
         force_run_from_project_root: options.force_run_from_project_root,
+        disable_test_cache: options.disable_test_cache,
     });
 
     let build_opts = request
@@ -377,6 +380,13 @@ async fn test(
                 .listing_failed
                 .to_cli_proto_counter(),
         ),
+        flaky: Some(
+            test_outcome
+                .executor_report
+                .statuses
+                .flaky
+                .to_cli_proto_counter(),
+        ),
     };
 
     Ok(TestResponse {
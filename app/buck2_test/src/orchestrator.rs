@@ -11,6 +11,7 @@
 
 use std::collections::HashMap;
 use std::ffi::OsStr;
+use std::ops::ControlFlow;
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -32,6 +33,7 @@ use buck2_build_api::interpreter::rule_defs::provider::builtin::external_runner_
 use buck2_build_api::interpreter::rule_defs::provider::builtin::external_runner_test_info::TestCommandMember;
 use buck2_common::dice::cells::HasCellResolver;
 use buck2_common::events::HasEvents;
+use buck2_common::file_ops::FileDigest;
 use buck2_common::liveliness_observer::LivelinessObserver;
 use buck2_common::local_resource_state::LocalResourceState;
 use buck2_common::result::SharedError;
@@ -45,6 +47,7 @@ use buck2_core::execution_types::executor_config::LocalExecutorOptions;
 use buck2_core::execution_types::executor_config::PathSeparatorKind;
 use buck2_core::fs::artifact_path_resolver::ArtifactFs;
 use buck2_core::fs::buck_out_path::BuckOutTestPath;
+use buck2_core::fs::fs_util;
 use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
 use buck2_core::fs::paths::forward_rel_path::ForwardRelativePathBuf;
 use buck2_core::fs::project_rel_path::ProjectRelativePath;
@@ -66,6 +69,7 @@ use buck2_execute::artifact::fs::ExecutorFs;
 use buck2_execute::digest_config::DigestConfig;
 use buck2_execute::digest_config::HasDigestConfig;
 use buck2_execute::execute::blocking::HasBlockingExecutor;
+use buck2_execute::execute::cache_uploader::CacheUploadInfo;
 use buck2_execute::execute::cache_uploader::NoOpCacheUploader;
 use buck2_execute::execute::claim::MutexClaimManager;
 use buck2_execute::execute::command_executor::CommandExecutor;
@@ -111,6 +115,7 @@ use buck2_test_api::data::PrepareForLocalExecutionResult;
 use buck2_test_api::data::RequiredLocalResources;
 use buck2_test_api::data::TestResult;
 use buck2_test_api::protocol::TestOrchestrator;
+use dashmap::DashMap;
 use derive_more::From;
 use dice::DiceTransaction;
 use dupe::Dupe;
@@ -121,8 +126,10 @@ use indexmap::indexset;
 use indexmap::IndexMap;
 use indexmap::IndexSet;
 use more_futures::cancellation::CancellationContext;
+use once_cell::sync::Lazy;
 use sorted_vector_map::SortedVectorMap;
 use starlark::values::FrozenRef;
+use tokio::sync::Mutex;
 use uuid::Uuid;
 
 use crate::local_resource_api::LocalResourcesSetupResult;
@@ -141,6 +148,11 @@ pub enum ExecutorMessage {
     InfoMessage(String),
 }
 
+/// Locks on the output roots of cacheable tests, which are shared by all test sessions of this
+/// daemon (see `expand_test_executable`).
+static SHARED_OUTPUT_ROOTS: Lazy<DashMap<ForwardRelativePathBuf, Arc<Mutex<()>>>> =
+    Lazy::new(DashMap::new);
+
 pub struct BuckTestOrchestrator<'a> {
     dice: DiceTransaction,
     session: Arc<TestSession>,
@@ -224,8 +236,9 @@ impl<'a> BuckTestOrchestrator<'a> {
         let fs = self.dice.get_artifact_fs().await?;
 
         let test_info = self.get_test_info(&test_target).await?;
+        let cacheable = test_info.cacheable() && !self.session.options().disable_test_cache;
         let test_executor = self
-            .get_test_executor(&test_target, &test_info, executor_override, &fs)
+            .get_test_executor(&test_target, &test_info, executor_override, cacheable, &fs)
             .await?;
         let test_executable_expanded = self
             .expand_test_executable(
@@ -234,6 +247,7 @@ impl<'a> BuckTestOrchestrator<'a> {
                 cmd,
                 env,
                 pre_create_dirs,
+                cacheable,
                 &test_executor.executor_fs(),
             )
             .await?;
@@ -245,8 +259,23 @@ impl<'a> BuckTestOrchestrator<'a> {
             inputs,
             supports_re,
             declared_outputs,
+            shared_output_root,
         } = test_executable_expanded;
 
+        // Hold the shared output root of a cacheable test until its outputs are moved into this
+        // session, so that concurrent sessions don't overwrite each other's outputs.
+        let shared_output_root_lock = match &shared_output_root {
+            Some(root) => Some(
+                SHARED_OUTPUT_ROOTS
+                    .entry(root.clone())
+                    .or_default()
+                    .dupe()
+                    .lock_owned()
+                    .await,
+            ),
+            None => None,
+        };
+
         let executor_preference = self.executor_preference(supports_re)?;
 
         let required_resources = if test_executor.is_local_execution_possible(executor_preference) {
@@ -295,26 +324,77 @@ impl<'a> BuckTestOrchestrator<'a> {
 
         self.require_alive().await?;
 
-        let (outputs, paths_to_materialize) = outputs
+        let materializer = self.dice.per_transaction_data().get_materializer();
+        let paths_to_materialize: Vec<_> = outputs
+            .iter()
+            .map(|test_path| fs.buck_out_path_resolver().resolve_test(test_path))
+            .collect();
+
+        // Request materialization in case this ran on RE. Eventually Tpx should be able to
+        // understand remote outputs but currently we don't have this.
+        materializer
+            .ensure_materialized(paths_to_materialize.clone())
+            .await
+            .context("Error materializing test outputs")?;
+
+        let outputs = match shared_output_root {
+            Some(shared_output_root) => {
+                let session_output_root =
+                    self.session
+                        .prefix()
+                        .join(ForwardRelativePathBuf::unchecked_new(
+                            Uuid::new_v4().to_string(),
+                        ));
+                let resolve_root = |root: &ForwardRelativePathBuf| {
+                    fs.fs().resolve(&fs.buck_out_path_resolver().resolve_test(
+                        &BuckOutTestPath::new(
+                            root.clone(),
+                            ForwardRelativePathBuf::unchecked_new(String::new()),
+                        ),
+                    ))
+                };
+                let from = resolve_root(&shared_output_root);
+                let to = resolve_root(&session_output_root);
+                self.dice
+                    .get_blocking_executor()
+                    .execute_io_inline(|| {
+                        if fs_util::try_exists(&from)? {
+                            fs_util::create_dir_all(to.parent().context("No parent")?)?;
+                            fs_util::rename(&from, &to)?;
+                        }
+                        Ok(())
+                    })
+                    .await
+                    .context("Error moving test outputs into the test session")?;
+                // The outputs are no longer at the shared path, so the materializer has to
+                // forget them for the next run to write them again.
+                materializer
+                    .invalidate_many(paths_to_materialize)
+                    .await
+                    .context("Error invalidating shared test outputs")?;
+                outputs
+                    .into_iter()
+                    .map(|test_path| {
+                        BuckOutTestPath::new(session_output_root.clone(), test_path.into_path())
+                    })
+                    .collect()
+            }
+            None => outputs,
+        };
+        drop(shared_output_root_lock);
+
+        let outputs = outputs
             .into_iter()
             .map(|test_path| {
-                let project_path = fs.buck_out_path_resolver().resolve_test(&test_path);
-                let abs_path = fs.fs().resolve(&project_path);
+                let abs_path = fs
+                    .fs()
+                    .resolve(&fs.buck_out_path_resolver().resolve_test(&test_path));
                 let declared_output = DeclaredOutput {
                     name: test_path.into_path(),
                 };
-                ((declared_output, Output::LocalPath(abs_path)), project_path)
+                (declared_output, Output::LocalPath(abs_path))
             })
-            .unzip();
-
-        // Request materialization in case this ran on RE. Eventually Tpx should be able to
-        // understand remote outputs but currently we don't have this.
-        self.dice
-            .per_transaction_data()
-            .get_materializer()
-            .ensure_materialized(paths_to_materialize)
-            .await
-            .context("Error materializing test outputs")?;
+            .collect();
 
         Ok(ExecutionResult2 {
             status,
@@ -443,9 +523,9 @@ impl<'a> TestOrchestrator for BuckTestOrchestrator<'a> {
         let fs = self.dice.get_artifact_fs().await?;
 
         let test_info = self.get_test_info(&test_target).await?;
-        // Tests are not run, so there is no executor override.
+        // Tests are not run, so there is no executor override and nothing to cache.
         let executor = self
-            .get_test_executor(&test_target, &test_info, None, &fs)
+            .get_test_executor(&test_target, &test_info, None, false, &fs)
             .await?;
         let test_executable_expanded = self
             .expand_test_executable(
@@ -454,6 +534,7 @@ impl<'a> TestOrchestrator for BuckTestOrchestrator<'a> {
                 cmd,
                 env,
                 pre_create_dirs,
+                false,
                 &executor.executor_fs(),
             )
             .await?;
@@ -465,6 +546,7 @@ impl<'a> TestOrchestrator for BuckTestOrchestrator<'a> {
             inputs,
             supports_re: _,
             declared_outputs,
+            shared_output_root: _,
         } = test_executable_expanded;
 
         let execution_request = self
@@ -566,8 +648,6 @@ impl<'b> BuckTestOrchestrator<'b> {
             action_key_suffix,
         };
 
-        // For test execution, we currently do not do any cache queries
-
         let prepared_action = executor.prepare_action(&request, self.digest_config)?;
         let prepared_command = PreparedCommand {
             target: &test_target as _,
@@ -575,7 +655,42 @@ impl<'b> BuckTestOrchestrator<'b> {
             prepared_action: &prepared_action,
             digest_config: self.digest_config,
         };
-        let command = executor.exec_cmd(manager, &prepared_command, self.cancellations);
+        // Only executors of cacheable tests have a cache checker and uploader, for other tests
+        // both are no-ops.
+        let command = async {
+            let manager = match executor
+                .action_cache(manager, &prepared_command, self.cancellations)
+                .await
+            {
+                ControlFlow::Break(result) => return result,
+                ControlFlow::Continue(manager) => manager,
+            };
+            let mut result = executor
+                .exec_cmd(manager, &prepared_command, self.cancellations)
+                .await;
+            if result.was_success() {
+                let upload = executor
+                    .cache_upload(
+                        &CacheUploadInfo {
+                            target: &test_target as _,
+                            action_digest: prepared_action.action.dupe(),
+                            digest_config: self.digest_config,
+                        },
+                        &result,
+                        None,
+                    )
+                    .await;
+                match upload {
+                    Ok(upload) => result.did_cache_upload = upload.did_cache_upload,
+                    Err(e) => tracing::warn!(
+                        "Cache upload for test `{}` failed: {:#}",
+                        test_target.target,
+                        e
+                    ),
+                }
+            }
+            result
+        };
 
         // instrument execution with a span.
         // TODO(brasselsprouts): migrate this into the executor to get better accuracy.
@@ -708,6 +823,7 @@ impl<'b> BuckTestOrchestrator<'b> {
         fs: &ArtifactFs,
         test_target_node: &ConfiguredTargetNode,
         executor_override: Option<&CommandExecutorConfig>,
+        cacheable: bool,
     ) -> anyhow::Result<CommandExecutor> {
         let executor_config = match executor_override {
             Some(o) => o,
@@ -720,14 +836,22 @@ impl<'b> BuckTestOrchestrator<'b> {
         let CommandExecutorResponse {
             executor,
             platform,
-            cache_checker: _,
-            cache_uploader: _,
+            cache_checker,
+            cache_uploader,
         } = self.dice.get_command_executor(fs, executor_config)?;
+        // Only tests that declare themselves cacheable can be served from the action cache.
+        let (cache_checker, cache_uploader) = if cacheable {
+            (cache_checker, cache_uploader)
+        } else {
+            (
+                Arc::new(NoOpCommandOptionalExecutor {}) as _,
+                Arc::new(NoOpCacheUploader {}) as _,
+            )
+        };
         let executor = CommandExecutor::new(
             executor,
-            // Caching is not enabled for tests yet. Use the NoOp
-            Arc::new(NoOpCommandOptionalExecutor {}),
-            Arc::new(NoOpCacheUploader {}),
+            cache_checker,
+            cache_uploader,
             fs.clone(),
             executor_config.options,
             platform,
@@ -781,6 +905,7 @@ impl<'b> BuckTestOrchestrator<'b> {
         test_target: &ConfiguredProvidersLabel,
         test_info: &FrozenExternalRunnerTestInfo,
         executor_override: Option<ExecutorConfigOverride>,
+        cacheable: bool,
         fs: &ArtifactFs,
     ) -> anyhow::Result<CommandExecutor> {
         // NOTE: get_providers() implicitly calls this already but it's not the end of the world
@@ -811,6 +936,7 @@ impl<'b> BuckTestOrchestrator<'b> {
             fs,
             &node,
             resolved_executor_override.as_ref().map(|a| &***a),
+            cacheable,
         )
         .context("Error constructing CommandExecutor")
     }
@@ -822,14 +948,27 @@ impl<'b> BuckTestOrchestrator<'b> {
         cmd: Vec<ArgValue>,
         env: SortedVectorMap<String, ArgValue>,
         pre_create_dirs: Vec<DeclaredOutput>,
+        cacheable: bool,
         executor_fs: &ExecutorFs<'_>,
     ) -> anyhow::Result<ExpandedTestExecutable> {
-        let output_root = self
-            .session
-            .prefix()
-            .join(ForwardRelativePathBuf::unchecked_new(
-                Uuid::new_v4().to_string(),
-            ));
+        let output_root = if cacheable {
+            // Output paths are part of the action digest, so they have to be the same every time
+            // the same test command runs. This root is shared by all sessions, so the outputs are
+            // moved into the session once the test has run (see `execute2`).
+            let key = format!(
+                "{}\0{:?}\0{:?}\0{:?}",
+                test_target, cmd, env, pre_create_dirs
+            );
+            let digest =
+                FileDigest::from_content(key.as_bytes(), self.digest_config.cas_digest_config());
+            ForwardRelativePathBuf::unchecked_new(format!("cached/{}", digest.raw_digest()))
+        } else {
+            self.session
+                .prefix()
+                .join(ForwardRelativePathBuf::unchecked_new(
+                    Uuid::new_v4().to_string(),
+                ))
+        };
 
         let mut declared_outputs = IndexMap::<BuckOutTestPath, OutputCreationBehavior>::new();
 
@@ -884,6 +1023,7 @@ impl<'b> BuckTestOrchestrator<'b> {
             inputs,
             declared_outputs,
             supports_re,
+            shared_output_root: if cacheable { Some(output_root) } else { None },
         })
     }
 
@@ -1274,6 +1414,8 @@ struct ExpandedTestExecutable {
     inputs: IndexSet<ArtifactGroup>,
     supports_re: bool,
     declared_outputs: IndexMap<BuckOutTestPath, OutputCreationBehavior>,
+    /// The output root of a cacheable test, which is shared by all test sessions.
+    shared_output_root: Option<ForwardRelativePathBuf>,
 }
 
 fn create_prepare_for_local_execution_result(
@@ -1483,6 +1625,8 @@ mod tests {
                     name: "First - test".to_owned(),
                     duration: Some(Duration::from_micros(1)),
                     details: "1".to_owned(),
+                    stdout: String::new(),
+                    stderr: String::new(),
                 })
                 .await?;
 
@@ -1494,6 +1638,8 @@ mod tests {
                     name: "Second - test".to_owned(),
                     duration: Some(Duration::from_micros(2)),
                     details: "2".to_owned(),
+                    stdout: String::new(),
+                    stderr: String::new(),
                 })
                 .await?;
 
@@ -1515,6 +1661,8 @@ mod tests {
                     name: "First - test".to_owned(),
                     duration: Some(Duration::from_micros(1)),
                     details: "1".to_owned(),
+                    stdout: String::new(),
+                    stderr: String::new(),
                 }),
                 ExecutorMessage::TestResult(TestResult {
                     target,
@@ -1524,6 +1672,8 @@ mod tests {
                     name: "Second - test".to_owned(),
                     duration: Some(Duration::from_micros(2)),
                     details: "2".to_owned(),
+                    stdout: String::new(),
+                    stderr: String::new(),
                 }),
                 ExecutorMessage::ExitCode(0),
             ]
@@ -25,6 +25,8 @@ pub struct TestSessionOptions {
     pub allow_re: bool,
     pub force_use_project_relative_paths: bool,
     pub force_run_from_project_root: bool,
+    /// Whether to run cacheable tests even if their result is in the action cache.
+    pub disable_test_cache: bool,
 }
 
 /// The state of a buck2 test command.
@@ -52,6 +52,8 @@ pub fn convert_test_result(
         duration,
         details,
         target: test_target,
+        stdout,
+        stderr,
     } = test_result;
 
     let test_target = session.get(test_target)?;
@@ -63,5 +65,7 @@ pub fn convert_test_result(
         duration: duration.and_then(|d| d.try_into().ok()),
         details,
         target_label: Some(test_target.target().as_proto()),
+        stdout,
+        stderr,
     })
 }
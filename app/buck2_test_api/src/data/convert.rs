@@ -213,6 +213,7 @@ impl TryFrom<i32> for TestStatus {
             buck2_test_proto::TestStatus::Rerun => TestStatus::RERUN,
             buck2_test_proto::TestStatus::ListingSuccess => TestStatus::LISTING_SUCCESS,
             buck2_test_proto::TestStatus::ListingFailed => TestStatus::LISTING_FAILED,
+            buck2_test_proto::TestStatus::Flaky => TestStatus::FLAKY,
         })
     }
 }
@@ -232,6 +233,7 @@ impl TryInto<i32> for TestStatus {
             TestStatus::RERUN => buck2_test_proto::TestStatus::Rerun,
             TestStatus::LISTING_SUCCESS => buck2_test_proto::TestStatus::ListingSuccess,
             TestStatus::LISTING_FAILED => buck2_test_proto::TestStatus::ListingFailed,
+            TestStatus::FLAKY => buck2_test_proto::TestStatus::Flaky,
         } as i32)
     }
 }
@@ -247,6 +249,8 @@ impl TryFrom<buck2_test_proto::TestResult> for TestResult {
             msg,
             duration,
             details,
+            stdout,
+            stderr,
         } = s;
 
         let duration = duration
@@ -264,6 +268,8 @@ impl TryFrom<buck2_test_proto::TestResult> for TestResult {
             msg: msg.map(|m| m.msg),
             duration,
             details,
+            stdout,
+            stderr,
         })
     }
 }
@@ -281,6 +287,8 @@ impl TryInto<buck2_test_proto::TestResult> for TestResult {
             details: self.details,
             msg: self.msg.map(|msg| OptionalMsg { msg }),
             duration: self.duration.try_map(|d| d.try_into())?,
+            stdout: self.stdout,
+            stderr: self.stderr,
         })
     }
 }
@@ -298,6 +306,7 @@ impl TryFrom<buck2_test_proto::ExternalRunnerSpec> for ExternalRunnerSpec {
             contacts,
             oncall,
             working_dir_cell,
+            shards,
         } = s;
 
         Ok(Self {
@@ -317,6 +326,7 @@ impl TryFrom<buck2_test_proto::ExternalRunnerSpec> for ExternalRunnerSpec {
             contacts,
             oncall,
             working_dir_cell: CellName::unchecked_new(&working_dir_cell)?,
+            shards: shards.max(1),
         })
     }
 }
@@ -334,6 +344,7 @@ impl TryInto<buck2_test_proto::ExternalRunnerSpec> for ExternalRunnerSpec {
             contacts,
             oncall,
             working_dir_cell,
+            shards,
         } = self;
         Ok(buck2_test_proto::ExternalRunnerSpec {
             target: Some(target.try_into().context("Invalid `target`")?),
@@ -349,6 +360,7 @@ impl TryInto<buck2_test_proto::ExternalRunnerSpec> for ExternalRunnerSpec {
             contacts,
             oncall,
             working_dir_cell: working_dir_cell.as_str().to_owned(),
+            shards,
         })
     }
 }
@@ -873,6 +885,7 @@ mod tests {
             contacts: vec!["contact1".to_owned(), "contact2".to_owned()],
             oncall: Some("contact1".to_owned()),
             working_dir_cell: CellName::testing_new("qux"),
+            shards: 4,
         };
         assert_roundtrips::<buck2_test_proto::ExternalRunnerSpec, ExternalRunnerSpec>(&test_spec);
     }
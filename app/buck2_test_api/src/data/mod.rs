@@ -93,6 +93,10 @@ pub struct TestResult {
     pub duration: Option<Duration>,
     // the output of the test execution (combining stdout and stderr)
     pub details: String,
+    // the stdout and stderr of the test execution, empty if the test runner only reports them as
+    // part of `details`
+    pub stdout: String,
+    pub stderr: String,
 }
 
 /// different possible test results
@@ -112,6 +116,8 @@ pub enum TestStatus {
     RERUN,
     LISTING_SUCCESS,
     LISTING_FAILED,
+    // Failed, then passed when retried.
+    FLAKY,
 }
 
 /// The set of information about a test rule that is passed to the test executor
@@ -135,6 +141,9 @@ pub struct ExternalRunnerSpec {
     pub oncall: Option<String>,
     /// Cell of current working directory for test command.
     pub working_dir_cell: CellName,
+    /// Number of parallel executions the test cases should be split across. 1 means the test is
+    /// not sharded.
+    pub shards: u32,
 }
 
 /// Command line argument or environment variable value
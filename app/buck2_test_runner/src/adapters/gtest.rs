@@ -0,0 +1,237 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! GoogleTest binaries.
+
+use std::time::Duration;
+
+use buck2_test_api::data::ArgValue;
+use buck2_test_api::data::ExecutionResult2;
+use buck2_test_api::data::TestStatus;
+
+use crate::adapters::stream_to_str;
+use crate::adapters::verbatim;
+use crate::adapters::CaseResult;
+use crate::adapters::TestAdapter;
+
+/// Longest `--gtest_filter` argument we pass. Linux rejects any single argument longer than
+/// `MAX_ARG_STRLEN` (128 KiB) with `E2BIG`, so stay well below that.
+const MAX_FILTER_ARG_LEN: usize = 64 * 1024;
+
+const FILTER_ARG_PREFIX: &str = "--gtest_filter=";
+
+pub(crate) struct GtestAdapter;
+
+impl TestAdapter for GtestAdapter {
+    fn list_args(&self) -> Vec<ArgValue> {
+        vec![verbatim("--gtest_list_tests")]
+    }
+
+    fn parse_list(&self, stdout: &str) -> anyhow::Result<Vec<String>> {
+        // Suites are unindented and end with a `.`, their cases follow indented. Both may be
+        // followed by a `# TypeParam = ...` or `# GetParam() = ...` comment.
+        let mut suite = None;
+        let mut cases = Vec::new();
+        for line in stdout.lines() {
+            let name = match line.split_once('#') {
+                Some((name, _comment)) => name.trim_end(),
+                None => line.trim_end(),
+            };
+            if name.is_empty() {
+                continue;
+            }
+            if let Some(case) = name.strip_prefix("  ") {
+                if let Some(suite) = suite {
+                    cases.push(format!("{}{}", suite, case));
+                }
+            } else if name.ends_with('.') {
+                suite = Some(name);
+            }
+        }
+        Ok(cases)
+    }
+
+    fn run_args(&self) -> Vec<ArgValue> {
+        vec![verbatim("--gtest_color=no")]
+    }
+
+    fn filter_args(&self, testcases: &[String]) -> Vec<ArgValue> {
+        vec![verbatim(&format!(
+            "{}{}",
+            FILTER_ARG_PREFIX,
+            testcases.join(":")
+        ))]
+    }
+
+    fn filter_batches<'a>(&self, testcases: &'a [String]) -> Vec<&'a [String]> {
+        split_filter(testcases, MAX_FILTER_ARG_LEN)
+    }
+
+    fn parse_results(&self, result: &ExecutionResult2) -> anyhow::Result<Vec<CaseResult>> {
+        Ok(parse_output(&stream_to_str(&result.stdout)))
+    }
+}
+
+/// Splits `testcases` into consecutive batches whose `--gtest_filter` argument is at most `max_len`
+/// bytes long. A case too long to fit on its own still gets a batch.
+fn split_filter(testcases: &[String], max_len: usize) -> Vec<&[String]> {
+    let mut batches = Vec::new();
+    let mut start = 0;
+    let mut len = FILTER_ARG_PREFIX.len();
+    for (i, testcase) in testcases.iter().enumerate() {
+        // Cases after the first one are preceded by a `:`.
+        let case_len = testcase.len() + usize::from(i > start);
+        if i > start && len + case_len > max_len {
+            batches.push(&testcases[start..i]);
+            start = i;
+            len = FILTER_ARG_PREFIX.len() + testcase.len();
+        } else {
+            len += case_len;
+        }
+    }
+    if start < testcases.len() {
+        batches.push(&testcases[start..]);
+    }
+    batches
+}
+
+/// Parses the `[ RUN      ]` ... `[       OK ]` blocks gtest prints for every case. The failure
+/// summary at the end repeats the names of failed cases, it is ignored because those lines are
+/// not preceded by a `[ RUN      ]` line.
+fn parse_output(stdout: &str) -> Vec<CaseResult> {
+    let mut results = Vec::new();
+    let mut current: Option<(&str, String)> = None;
+    for line in stdout.lines() {
+        if let Some(name) = line.strip_prefix("[ RUN      ] ") {
+            current = Some((name.trim(), String::new()));
+            continue;
+        }
+        let outcome = [
+            ("[       OK ] ", TestStatus::PASS),
+            ("[  FAILED  ] ", TestStatus::FAIL),
+            ("[  SKIPPED ] ", TestStatus::SKIP),
+        ]
+        .into_iter()
+        .find_map(|(prefix, status)| Some((line.strip_prefix(prefix)?, status)));
+        match (outcome, current.take()) {
+            (Some((rest, status)), Some((name, details)))
+                if rest
+                    .strip_prefix(name)
+                    .map_or(false, |r| r.is_empty() || r.starts_with([' ', ','])) =>
+            {
+                results.push(CaseResult {
+                    name: name.to_owned(),
+                    status,
+                    duration: parse_duration(rest),
+                    details,
+                });
+            }
+            (_, Some((name, mut details))) => {
+                details.push_str(line);
+                details.push('\n');
+                current = Some((name, details));
+            }
+            (_, None) => {}
+        }
+    }
+    results
+}
+
+/// Parses the `(<n> ms)` suffix of a result line.
+fn parse_duration(rest: &str) -> Option<Duration> {
+    let millis = rest.strip_suffix(" ms)")?.rsplit_once('(')?.1;
+    Some(Duration::from_millis(millis.parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_list() {
+        let stdout = "\
+Math.
+  Add
+  Sub
+Param/Math.
+  Mul/0  # GetParam() = 1
+";
+        assert_eq!(
+            vec!["Math.Add", "Math.Sub", "Param/Math.Mul/0"],
+            GtestAdapter.parse_list(stdout).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_filter_batches_large() {
+        let testcases: Vec<String> = (0..20000)
+            .map(|i| format!("VeryLongSuiteName/Fixture.TestCase{}/{}", i, i))
+            .collect();
+        let batches = GtestAdapter.filter_batches(&testcases);
+
+        assert!(batches.len() > 1);
+        assert_eq!(testcases, batches.concat());
+        for batch in batches {
+            let args = GtestAdapter.filter_args(batch);
+            assert_eq!(1, args.len());
+            let filter = format!("{}{}", FILTER_ARG_PREFIX, batch.join(":"));
+            assert!(filter.len() <= MAX_FILTER_ARG_LEN, "{}", filter.len());
+        }
+    }
+
+    #[test]
+    fn test_split_filter() {
+        let testcases = ["A.a", "A.b", "A.c"].map(|s| s.to_owned());
+        let prefix = FILTER_ARG_PREFIX.len();
+
+        assert_eq!(vec![&testcases[..]], split_filter(&testcases, prefix + 11));
+        assert_eq!(
+            vec![&testcases[..2], &testcases[2..]],
+            split_filter(&testcases, prefix + 10)
+        );
+        // Each case gets its own batch even if it doesn't fit.
+        assert_eq!(
+            vec![&testcases[..1], &testcases[1..2], &testcases[2..]],
+            split_filter(&testcases, 0)
+        );
+        assert!(split_filter(&[], prefix).is_empty());
+    }
+
+    #[test]
+    fn test_parse_output() {
+        let stdout = "\
+[==========] Running 2 tests from 1 test suite.
+[ RUN      ] Math.Add
+[       OK ] Math.Add (0 ms)
+[ RUN      ] Math.Sub
+math.cpp:10: Failure
+[  FAILED  ] Math.Sub (3 ms)
+[==========] 2 tests from 1 test suite ran. (3 ms total)
+[  FAILED  ] 1 test, listed below:
+[  FAILED  ] Math.Sub
+";
+        assert_eq!(
+            vec![
+                CaseResult {
+                    name: "Math.Add".to_owned(),
+                    status: TestStatus::PASS,
+                    duration: Some(Duration::from_millis(0)),
+                    details: String::new(),
+                },
+                CaseResult {
+                    name: "Math.Sub".to_owned(),
+                    status: TestStatus::FAIL,
+                    duration: Some(Duration::from_millis(3)),
+                    details: "math.cpp:10: Failure\n".to_owned(),
+                },
+            ],
+            parse_output(stdout)
+        );
+    }
+}
@@ -0,0 +1,150 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Rust's built-in test harness.
+
+use std::collections::HashMap;
+
+use buck2_test_api::data::ArgValue;
+use buck2_test_api::data::ExecutionResult2;
+use buck2_test_api::data::TestStatus;
+
+use crate::adapters::stream_to_str;
+use crate::adapters::verbatim;
+use crate::adapters::CaseResult;
+use crate::adapters::TestAdapter;
+
+pub(crate) struct LibtestAdapter;
+
+impl TestAdapter for LibtestAdapter {
+    fn list_args(&self) -> Vec<ArgValue> {
+        vec![verbatim("--list"), verbatim("--format"), verbatim("terse")]
+    }
+
+    fn parse_list(&self, stdout: &str) -> anyhow::Result<Vec<String>> {
+        // One `<name>: test` or `<name>: benchmark` line per case. Benchmarks are not run as
+        // tests, so they are not reported.
+        Ok(stdout
+            .lines()
+            .filter_map(|line| line.strip_suffix(": test"))
+            .map(|name| name.to_owned())
+            .collect())
+    }
+
+    fn run_args(&self) -> Vec<ArgValue> {
+        Vec::new()
+    }
+
+    fn filter_args(&self, testcases: &[String]) -> Vec<ArgValue> {
+        std::iter::once(verbatim("--exact"))
+            .chain(testcases.iter().map(|t| verbatim(t)))
+            .collect()
+    }
+
+    fn parse_results(&self, result: &ExecutionResult2) -> anyhow::Result<Vec<CaseResult>> {
+        Ok(parse_output(&stream_to_str(&result.stdout)))
+    }
+}
+
+/// Parses the default (`pretty`) output format, e.g. `test foo::bar ... ok`.
+fn parse_output(stdout: &str) -> Vec<CaseResult> {
+    let details = failure_details(stdout);
+    stdout
+        .lines()
+        .filter_map(|line| {
+            let (name, outcome) = line.strip_prefix("test ")?.split_once(" ... ")?;
+            let status = match outcome.trim() {
+                "ok" => TestStatus::PASS,
+                "FAILED" => TestStatus::FAIL,
+                outcome if outcome.starts_with("ignored") => TestStatus::SKIP,
+                _ => return None,
+            };
+            Some(CaseResult {
+                name: name.to_owned(),
+                status,
+                duration: None,
+                details: details.get(name).cloned().unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+/// The captured output of failed cases, printed as `---- <name> stdout ----` sections after all
+/// cases finished.
+fn failure_details(stdout: &str) -> HashMap<&str, String> {
+    let mut details = HashMap::new();
+    let mut current: Option<(&str, String)> = None;
+    for line in stdout.lines() {
+        let header = line
+            .strip_prefix("---- ")
+            .and_then(|l| l.strip_suffix(" stdout ----"));
+        if header.is_some() || line == "failures:" {
+            if let Some((name, text)) = current.take() {
+                details.insert(name, text);
+            }
+        }
+        if let Some(name) = header {
+            current = Some((name, String::new()));
+        } else if let Some((_, text)) = &mut current {
+            text.push_str(line);
+            text.push('\n');
+        }
+    }
+    if let Some((name, text)) = current {
+        details.insert(name, text);
+    }
+    details
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_list() {
+        let stdout = "a::one: test\na::two: test\nbench_it: benchmark\n";
+        assert_eq!(
+            vec!["a::one", "a::two"],
+            LibtestAdapter.parse_list(stdout).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_output() {
+        let stdout = "\
+running 3 tests
+test a::one ... ok
+test a::two ... FAILED
+test a::three ... ignored, slow
+
+failures:
+
+---- a::two stdout ----
+thread 'a::two' panicked at 'boom'
+
+failures:
+    a::two
+
+test result: FAILED. 1 passed; 1 failed; 1 ignored; 0 measured; 0 filtered out
+";
+        let results = parse_output(stdout);
+        assert_eq!(
+            vec![
+                ("a::one", TestStatus::PASS),
+                ("a::two", TestStatus::FAIL),
+                ("a::three", TestStatus::SKIP),
+            ],
+            results
+                .iter()
+                .map(|r| (r.name.as_str(), r.status.clone()))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!("thread 'a::two' panicked at 'boom'\n\n", results[1].details);
+    }
+}
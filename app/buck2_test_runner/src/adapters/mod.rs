@@ -0,0 +1,88 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Adapters that let the test runner look inside a test binary: list the test cases it contains
+//! and turn its output into one result per test case.
+
+mod gtest;
+mod libtest;
+mod pyunit;
+
+use std::borrow::Cow;
+use std::time::Duration;
+
+use buck2_test_api::data::ArgValue;
+use buck2_test_api::data::ArgValueContent;
+use buck2_test_api::data::ExecutionResult2;
+use buck2_test_api::data::ExecutionStream;
+use buck2_test_api::data::ExternalRunnerSpecValue;
+use buck2_test_api::data::TestStatus;
+
+use crate::adapters::gtest::GtestAdapter;
+use crate::adapters::libtest::LibtestAdapter;
+use crate::adapters::pyunit::PyunitAdapter;
+
+/// The outcome of a single test case.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct CaseResult {
+    pub(crate) name: String,
+    pub(crate) status: TestStatus,
+    pub(crate) duration: Option<Duration>,
+    pub(crate) details: String,
+}
+
+/// Knows how to talk to one kind of test binary.
+pub(crate) trait TestAdapter: Send + Sync {
+    /// Arguments appended to the test command to make the binary print its test cases and exit.
+    fn list_args(&self) -> Vec<ArgValue>;
+
+    /// Parses the test case names out of the output of the listing command.
+    fn parse_list(&self, stdout: &str) -> anyhow::Result<Vec<String>>;
+
+    /// Arguments appended to the test command when running the tests.
+    fn run_args(&self) -> Vec<ArgValue>;
+
+    /// Arguments appended to the test command, after `run_args`, to only run the given test
+    /// cases. Used to run the cases of a shard, and to retry failed cases.
+    fn filter_args(&self, testcases: &[String]) -> Vec<ArgValue>;
+
+    /// Splits the test cases to run into batches whose `filter_args` fit in a single execution.
+    fn filter_batches<'a>(&self, testcases: &'a [String]) -> Vec<&'a [String]> {
+        vec![testcases]
+    }
+
+    /// Parses the results of the test cases that ran. Cases missing from the output are not
+    /// returned.
+    fn parse_results(&self, result: &ExecutionResult2) -> anyhow::Result<Vec<CaseResult>>;
+}
+
+/// Returns the adapter for the `type` of an `ExternalRunnerTestInfo`, if there is one.
+pub(crate) fn adapter_for_test_type(test_type: &str) -> Option<Box<dyn TestAdapter>> {
+    match test_type {
+        "rust" => Some(Box::new(LibtestAdapter)),
+        "gtest" => Some(Box::new(GtestAdapter)),
+        "pyunit" => Some(Box::new(PyunitAdapter)),
+        _ => None,
+    }
+}
+
+fn verbatim(arg: &str) -> ArgValue {
+    ArgValue {
+        content: ArgValueContent::ExternalRunnerSpecValue(ExternalRunnerSpecValue::Verbatim(
+            arg.to_owned(),
+        )),
+        format: None,
+    }
+}
+
+pub(crate) fn stream_to_str(stream: &ExecutionStream) -> Cow<str> {
+    match stream {
+        ExecutionStream::Inline(data) => String::from_utf8_lossy(data),
+    }
+}
@@ -0,0 +1,165 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Python tests run through the prelude's `__test_main__.py`.
+
+use std::time::Duration;
+
+use anyhow::Context;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePathBuf;
+use buck2_test_api::data::ArgValue;
+use buck2_test_api::data::ArgValueContent;
+use buck2_test_api::data::DeclaredOutput;
+use buck2_test_api::data::ExecutionResult2;
+use buck2_test_api::data::Output;
+use buck2_test_api::data::TestStatus;
+use serde::Deserialize;
+
+use crate::adapters::verbatim;
+use crate::adapters::CaseResult;
+use crate::adapters::TestAdapter;
+
+pub(crate) struct PyunitAdapter;
+
+impl PyunitAdapter {
+    fn results_output() -> DeclaredOutput {
+        DeclaredOutput {
+            name: ForwardRelativePathBuf::unchecked_new("pyunit_results.json".to_owned()),
+        }
+    }
+}
+
+/// An entry of the file written by `__test_main__.py --output`. The file also contains a
+/// coverage entry, which has none of these fields.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PyunitResult {
+    test_case_name: Option<String>,
+    test_case: Option<String>,
+    #[serde(rename = "type")]
+    status: Option<String>,
+    time: Option<u64>,
+    message: Option<String>,
+    stacktrace: Option<String>,
+    std_out: Option<String>,
+    std_err: Option<String>,
+}
+
+impl TestAdapter for PyunitAdapter {
+    fn list_args(&self) -> Vec<ArgValue> {
+        vec![
+            verbatim("--list-tests"),
+            verbatim("--list-format"),
+            verbatim("buck"),
+        ]
+    }
+
+    fn parse_list(&self, stdout: &str) -> anyhow::Result<Vec<String>> {
+        // One `module.Class#method` line per case.
+        Ok(stdout
+            .lines()
+            .filter(|line| line.contains('#'))
+            .map(|line| line.trim().to_owned())
+            .collect())
+    }
+
+    fn run_args(&self) -> Vec<ArgValue> {
+        vec![ArgValue {
+            content: ArgValueContent::DeclaredOutput(Self::results_output()),
+            format: Some("--output={}".to_owned()),
+        }]
+    }
+
+    fn filter_args(&self, testcases: &[String]) -> Vec<ArgValue> {
+        // Tests are selected as `module.Class.method`.
+        testcases
+            .iter()
+            .map(|t| verbatim(&t.replace('#', ".")))
+            .collect()
+    }
+
+    fn parse_results(&self, result: &ExecutionResult2) -> anyhow::Result<Vec<CaseResult>> {
+        let path = match result.outputs.get(&Self::results_output()) {
+            Some(Output::LocalPath(path)) => path,
+            // The binary died before writing any results.
+            None => return Ok(Vec::new()),
+        };
+        let results = match std::fs::read_to_string(path) {
+            Ok(results) => results,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => {
+                return Err(e).with_context(|| format!("Error reading `{}`", path.display()));
+            }
+        };
+        parse_results_json(&results)
+    }
+}
+
+fn parse_results_json(results: &str) -> anyhow::Result<Vec<CaseResult>> {
+    let results: Vec<PyunitResult> =
+        serde_json::from_str(results).context("Error parsing pyunit results")?;
+    Ok(results
+        .into_iter()
+        .filter_map(|r| {
+            let name = format!("{}#{}", r.test_case_name?, r.test_case?);
+            let status = match r.status.as_deref() {
+                Some("SUCCESS") => TestStatus::PASS,
+                Some("FAILURE") => TestStatus::FAIL,
+                Some("ASSUMPTION_VIOLATION") => TestStatus::SKIP,
+                Some("EXCLUDED") => TestStatus::OMITTED,
+                _ => TestStatus::UNKNOWN,
+            };
+            let details = [r.message, r.stacktrace, r.std_out, r.std_err]
+                .into_iter()
+                .flatten()
+                .filter(|s| !s.is_empty())
+                .collect::<Vec<_>>()
+                .join("\n");
+            Some(CaseResult {
+                name,
+                status,
+                duration: r.time.map(Duration::from_millis),
+                details,
+            })
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_results_json() {
+        let results = r#"[
+            {"testCaseName": "m.T", "testCase": "test_a", "type": "SUCCESS", "time": 3,
+             "message": "", "stacktrace": null, "stdOut": "", "stdErr": ""},
+            {"testCaseName": "m.T", "testCase": "test_b", "type": "FAILURE", "time": 1,
+             "message": "AssertionError", "stacktrace": "Traceback", "stdOut": "", "stdErr": ""},
+            {"coverage": {}}
+        ]"#;
+        assert_eq!(
+            vec![
+                CaseResult {
+                    name: "m.T#test_a".to_owned(),
+                    status: TestStatus::PASS,
+                    duration: Some(Duration::from_millis(3)),
+                    details: String::new(),
+                },
+                CaseResult {
+                    name: "m.T#test_b".to_owned(),
+                    status: TestStatus::FAIL,
+                    duration: Some(Duration::from_millis(1)),
+                    details: "AssertionError\nTraceback".to_owned(),
+                },
+            ],
+            parse_results_json(results).unwrap()
+        );
+    }
+}
@@ -27,12 +27,47 @@ pub struct Config {
     #[clap(long, hidden = true)]
     buck_test_info: String,
 
+    /// Run every test target as a single opaque test, instead of listing the test cases of
+    /// test binaries that have a known format and reporting a result per test case.
+    #[clap(long)]
+    pub no_test_discovery: bool,
+
+    /// Number of times a failing test is retried. A test that fails and then passes on retry is
+    /// reported as flaky.
+    #[clap(long, default_value = "0")]
+    pub retries: u32,
+
+    /// Retries for tests of targets with the given label, using format: --retries-for-label
+    /// LABEL=N. If several labels match, the largest number of retries wins.
+    #[clap(long)]
+    pub retries_for_label: Vec<RetriesOverride>,
+
+    /// Retries for tests of the given target, using format: --retries-for-target
+    /// cell//package:name=N. Takes precedence over `--retries-for-label`.
+    #[clap(long)]
+    pub retries_for_target: Vec<RetriesOverride>,
+
     /// Passthrough argments to test binary.
     /// Available as a workaround for when test features are available.
     #[clap(long, multiple = true, allow_hyphen_values = true)]
     pub test_arg: Vec<String>,
 }
 
+impl Config {
+    /// The number of times to retry a failing test of the given target.
+    pub fn retries_for(&self, target: &str, labels: &[String]) -> u32 {
+        if let Some(o) = self.retries_for_target.iter().rfind(|o| o.key == target) {
+            return o.retries;
+        }
+        self.retries_for_label
+            .iter()
+            .filter(|o| labels.contains(&o.key))
+            .map(|o| o.retries)
+            .max()
+            .unwrap_or(self.retries)
+    }
+}
+
 /// Uiltity that can be used to parse Env values from CLI arguments.
 #[derive(Debug, PartialEq)]
 pub struct EnvValue {
@@ -70,3 +105,26 @@ fn try_parse_timeout_from_str(input: &str) -> anyhow::Result<Duration> {
     let seconds = input.parse().context("Could not parse provided timeout")?;
     Ok(Duration::from_secs(seconds))
 }
+
+/// A `KEY=N` number of retries for a target or label.
+#[derive(Debug, PartialEq)]
+pub struct RetriesOverride {
+    pub key: String,
+    pub retries: u32,
+}
+
+impl FromStr for RetriesOverride {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let (key, retries) = input
+            .rsplit_once('=')
+            .with_context(|| format!("Expected KEY=N, got `{}`", input))?;
+        Ok(RetriesOverride {
+            key: key.to_owned(),
+            retries: retries
+                .parse()
+                .with_context(|| format!("Invalid number of retries in `{}`", input))?,
+        })
+    }
+}
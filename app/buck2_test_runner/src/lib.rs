@@ -9,6 +9,7 @@
 
 #![feature(async_closure)]
 
+mod adapters;
 mod config;
 mod executor;
 mod runner;
@@ -7,6 +7,9 @@
  * of this source tree.
  */
 
+use std::collections::HashMap;
+use std::collections::HashSet;
+
 use anyhow::Context;
 use buck2_test_api::data::ArgValue;
 use buck2_test_api::data::ArgValueContent;
@@ -23,10 +26,15 @@ use buck2_test_api::data::TestStatus;
 use buck2_test_api::grpc::TestOrchestratorClient;
 use clap::Parser;
 use futures::channel::mpsc::UnboundedReceiver;
+use futures::future::try_join_all;
 use futures::StreamExt;
 use host_sharing::HostSharingRequirements;
 use parking_lot::Mutex;
 
+use crate::adapters::adapter_for_test_type;
+use crate::adapters::stream_to_str;
+use crate::adapters::CaseResult;
+use crate::adapters::TestAdapter;
 use crate::config::Config;
 use crate::config::EnvValue;
 
@@ -70,30 +78,16 @@ impl Buck2TestRunner {
         }
         let run_verdict = receiver
             .map(async move |spec| {
-                let name = format!(
-                    "{}//{}:{}",
-                    spec.target.cell, spec.target.package, spec.target.target
-                );
-                let target_handle = spec.target.handle.to_owned();
-
-                let execution_response = self
-                    .execute_test_from_spec(spec)
-                    .await
-                    .expect("Test execution request failed");
-
-                let execution_result = match execution_response {
-                    ExecuteResponse::Result(r) => r,
-                    ExecuteResponse::Cancelled => return TestStatus::OMITTED,
+                let adapter = if self.config.no_test_discovery {
+                    None
+                } else {
+                    adapter_for_test_type(&spec.test_type)
                 };
-
-                let test_result = get_test_result(name, target_handle, execution_result);
-                let test_status = test_result.status.clone();
-
-                self.report_test_result(test_result)
-                    .await
-                    .expect("Test result reporting failed");
-
-                test_status
+                match adapter {
+                    Some(adapter) => self.run_test_cases(spec, &*adapter).await,
+                    None => self.run_test_target(spec).await,
+                }
+                .expect("Test execution request failed")
             })
             // Use an arbitrarily large buffer -- execution throttling will be handled by the Buck2
             // executor, so no need to hold back on requests here.
@@ -115,15 +109,268 @@ impl Buck2TestRunner {
             .await
     }
 
-    async fn execute_test_from_spec(
+    /// Runs the test target as one test and reports a single result for it. A sharded test runs
+    /// once per shard and the results of the shards are merged. A failing test is retried as
+    /// configured, only running the shards that failed. The failed attempts are reported as
+    /// reruns.
+    async fn run_test_target(&self, spec: ExternalRunnerSpec) -> anyhow::Result<TestStatus> {
+        let name = target_name(&spec);
+        let target_handle = spec.target.handle.to_owned();
+        let retries = self.config.retries_for(&name, &spec.labels);
+
+        let mut pending = Shard::all(spec.shards);
+        for attempt in 0..=retries {
+            let executions = pending.iter().map(|shard| {
+                let display_metadata = DisplayMetadata::Testing {
+                    suite: spec.target.target.clone(),
+                    testcases: Vec::new(),
+                };
+                self.execute_test_from_spec(spec.clone(), display_metadata, Vec::new(), *shard)
+            });
+            let mut shard_results = Vec::with_capacity(pending.len());
+            for (shard, response) in pending.iter().zip(try_join_all(executions).await?) {
+                match response {
+                    ExecuteResponse::Result(r) => shard_results.push((*shard, r)),
+                    ExecuteResponse::Cancelled => return Ok(TestStatus::OMITTED),
+                }
+            }
+
+            pending = shard_results
+                .iter()
+                .filter(|(_, r)| !is_passing(&get_test_status(&r.status)))
+                .map(|(shard, _)| *shard)
+                .collect();
+            let mut test_result = merge_shard_results(name.clone(), target_handle, shard_results);
+            let test_status = test_result.status.clone();
+            let retry = !is_passing(&test_status) && attempt < retries;
+            if retry {
+                test_result.status = TestStatus::RERUN;
+            } else if test_status == TestStatus::PASS && attempt > 0 {
+                test_result.status = TestStatus::FLAKY;
+            }
+
+            self.report_test_result(test_result)
+                .await
+                .context("Test result reporting failed")?;
+
+            if !retry {
+                return Ok(test_status);
+            }
+        }
+        unreachable!("the last attempt is never retried")
+    }
+
+    /// Lists the test cases of the test target, runs them and reports a result per test case.
+    /// The test cases of a sharded test are split across its shards, which run in parallel.
+    async fn run_test_cases(
         &self,
         spec: ExternalRunnerSpec,
-    ) -> anyhow::Result<ExecuteResponse> {
-        let display_metadata = DisplayMetadata::Testing {
-            suite: spec.target.target,
-            testcases: Vec::new(),
+        adapter: &dyn TestAdapter,
+    ) -> anyhow::Result<TestStatus> {
+        let name = target_name(&spec);
+        let target_handle = spec.target.handle.to_owned();
+        let suite = spec.target.target.clone();
+
+        let listing_result = match self
+            .execute_test_from_spec(
+                spec.clone(),
+                DisplayMetadata::Listing(suite.clone()),
+                adapter.list_args(),
+                None,
+            )
+            .await?
+        {
+            ExecuteResponse::Result(r) => r,
+            ExecuteResponse::Cancelled => return Ok(TestStatus::OMITTED),
+        };
+        let testcases = match &listing_result.status {
+            ExecutionStatus::Finished { exitcode: 0 } => {
+                adapter.parse_list(&stream_to_str(&listing_result.stdout))
+            }
+            status => Err(anyhow::anyhow!("Listing test cases failed: {:?}", status)),
         };
+        let testcases = match testcases {
+            Ok(testcases) => testcases,
+            Err(e) => {
+                self.report_test_result(TestResult {
+                    target: target_handle,
+                    name,
+                    status: TestStatus::LISTING_FAILED,
+                    msg: Some(format!("{:#}", e)),
+                    duration: Some(listing_result.execution_time),
+                    details: execution_details(&listing_result),
+                    stdout: stream_to_str(&listing_result.stdout).into_owned(),
+                    stderr: stream_to_str(&listing_result.stderr).into_owned(),
+                })
+                .await?;
+                return Ok(TestStatus::LISTING_FAILED);
+            }
+        };
+        // Nothing to report per case, e.g. the binary isn't using the framework its test type
+        // suggests. Fall back to treating the target as one test.
+        if testcases.is_empty() {
+            return self.run_test_target(spec).await;
+        }
+
+        self.orchestrator_client
+            .report_tests_discovered(target_handle, suite, testcases.clone())
+            .await?;
+
+        let shards = Shard::all(spec.shards.min(testcases.len() as u32));
+        let mut shard_testcases = vec![Vec::new(); shards.len()];
+        for (i, testcase) in testcases.into_iter().enumerate() {
+            shard_testcases[i % shards.len()].push(testcase);
+        }
+        let statuses = try_join_all(
+            shards
+                .into_iter()
+                .zip(shard_testcases)
+                .map(|(shard, testcases)| self.run_shard(&spec, adapter, testcases, shard)),
+        )
+        .await?;
+
+        Ok(statuses
+            .into_iter()
+            .find(|status| *status != TestStatus::PASS)
+            .unwrap_or(TestStatus::PASS))
+    }
+
+    /// Runs the given test cases of the test target and reports a result per test case. Failing
+    /// test cases are retried as configured, the failed attempts are reported as reruns.
+    async fn run_shard(
+        &self,
+        spec: &ExternalRunnerSpec,
+        adapter: &dyn TestAdapter,
+        testcases: Vec<String>,
+        shard: Option<Shard>,
+    ) -> anyhow::Result<TestStatus> {
+        let name = target_name(spec);
+        let target_handle = spec.target.handle.to_owned();
+        let suite = spec.target.target.clone();
+        let retries = self.config.retries_for(&name, &spec.labels);
 
+        let mut test_status = TestStatus::PASS;
+        let mut pending = testcases;
+        let mut rerun = HashSet::new();
+        for attempt in 0..=retries {
+            // Test binaries don't know about the shard they run, so the cases are selected for
+            // them, rather than exposing the shard in the environment as for `run_test_target`.
+            // The filter may not fit in the arguments of a single execution, in which case the
+            // cases are run in several batches.
+            let batches = if attempt > 0 || shard.is_some() {
+                adapter
+                    .filter_batches(&pending)
+                    .into_iter()
+                    .map(|batch| (adapter.filter_args(batch), batch.to_vec()))
+                    .collect()
+            } else {
+                vec![(Vec::new(), pending.clone())]
+            };
+            let mut failed = Vec::new();
+            for (filter_args, testcases) in batches {
+                let mut args = adapter.run_args();
+                args.extend(filter_args);
+                let display_metadata = DisplayMetadata::Testing {
+                    suite: suite.clone(),
+                    testcases: testcases.clone(),
+                };
+                // The cases are already restricted to the shard, so the binary isn't told about
+                // it.
+                let execution_result = match self
+                    .execute_test_from_spec(spec.clone(), display_metadata, args, None)
+                    .await?
+                {
+                    ExecuteResponse::Result(r) => r,
+                    ExecuteResponse::Cancelled => return Ok(TestStatus::OMITTED),
+                };
+
+                let binary_status = get_test_status(&execution_result.status);
+                let case_results = match adapter.parse_results(&execution_result) {
+                    Ok(case_results) => {
+                        complete_case_results(case_results, &testcases, &binary_status)
+                    }
+                    Err(e) => {
+                        self.report_test_result(TestResult {
+                            target: target_handle,
+                            name: shard_name(name, shard),
+                            status: TestStatus::FATAL,
+                            msg: Some(format!("{:#}", e)),
+                            duration: Some(execution_result.execution_time),
+                            details: execution_details(&execution_result),
+                            stdout: stream_to_str(&execution_result.stdout).into_owned(),
+                            stderr: stream_to_str(&execution_result.stderr).into_owned(),
+                        })
+                        .await?;
+                        return Ok(TestStatus::FATAL);
+                    }
+                };
+
+                let mut case_failed = false;
+                for case in case_results {
+                    case_failed |= !is_passing(&case.status);
+                    let status = if is_passing(&case.status) {
+                        if case.status == TestStatus::PASS && rerun.contains(&case.name) {
+                            TestStatus::FLAKY
+                        } else {
+                            case.status
+                        }
+                    } else if attempt < retries {
+                        rerun.insert(case.name.clone());
+                        failed.push(case.name.clone());
+                        TestStatus::RERUN
+                    } else {
+                        test_status = TestStatus::FAIL;
+                        case.status
+                    };
+                    self.report_test_result(TestResult {
+                        target: target_handle,
+                        name: format!("{} - {}", name, case.name),
+                        status,
+                        msg: None,
+                        duration: case.duration,
+                        details: case.details,
+                        stdout: String::new(),
+                        stderr: String::new(),
+                    })
+                    .await?;
+                }
+
+                // The binary failed without any case failing, e.g. it crashed during teardown.
+                // Report that against the target so that the failure is not lost.
+                if binary_status != TestStatus::PASS && !case_failed {
+                    self.report_test_result(TestResult {
+                        target: target_handle,
+                        name: shard_name(name, shard),
+                        status: TestStatus::FATAL,
+                        msg: None,
+                        duration: Some(execution_result.execution_time),
+                        details: execution_details(&execution_result),
+                        stdout: stream_to_str(&execution_result.stdout).into_owned(),
+                        stderr: stream_to_str(&execution_result.stderr).into_owned(),
+                    })
+                    .await?;
+                    return Ok(TestStatus::FATAL);
+                }
+            }
+
+            if failed.is_empty() {
+                break;
+            }
+            pending = failed;
+        }
+
+        Ok(test_status)
+    }
+
+    /// Runs the test binary. `shard` is exposed to it as `TEST_SHARD_INDEX` and
+    /// `TEST_TOTAL_SHARDS`, for binaries that select their share of the work themselves.
+    async fn execute_test_from_spec(
+        &self,
+        spec: ExternalRunnerSpec,
+        display_metadata: DisplayMetadata,
+        adapter_args: Vec<ArgValue>,
+        shard: Option<Shard>,
+    ) -> anyhow::Result<ExecuteResponse> {
         let config_args = self.config.test_arg.iter().map(|arg| ArgValue {
             content: ArgValueContent::ExternalRunnerSpecValue(ExternalRunnerSpecValue::Verbatim(
                 arg.to_owned(),
@@ -138,6 +385,7 @@ impl Buck2TestRunner {
                 content: ArgValueContent::ExternalRunnerSpecValue(spec_value),
                 format: None,
             })
+            .chain(adapter_args)
             .chain(config_args)
             .collect();
 
@@ -153,6 +401,24 @@ impl Buck2TestRunner {
             )
         });
 
+        let shard_env = shard.into_iter().flat_map(|shard| {
+            [
+                ("TEST_SHARD_INDEX", shard.index),
+                ("TEST_TOTAL_SHARDS", shard.total),
+            ]
+            .map(|(name, value)| {
+                (
+                    name.to_owned(),
+                    ArgValue {
+                        content: ArgValueContent::ExternalRunnerSpecValue(
+                            ExternalRunnerSpecValue::Verbatim(value.to_string()),
+                        ),
+                        format: None,
+                    },
+                )
+            })
+        });
+
         let env = spec
             .env
             .into_iter()
@@ -166,6 +432,7 @@ impl Buck2TestRunner {
                 )
             })
             .chain(config_env)
+            .chain(shard_env)
             .collect();
 
         let target_handle = spec.target.handle;
@@ -195,28 +462,161 @@ impl Buck2TestRunner {
     }
 }
 
-fn get_test_result(
-    name: String,
-    target: ConfiguredTargetHandle,
-    execution_result: ExecutionResult2,
-) -> TestResult {
-    let status = match execution_result.status {
+/// One of the parallel executions the test cases of a sharded test are split across.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Shard {
+    /// Zero-based.
+    index: u32,
+    total: u32,
+}
+
+impl Shard {
+    /// The shards of a test with the given shard count, a single `None` if it isn't sharded.
+    fn all(total: u32) -> Vec<Option<Shard>> {
+        if total <= 1 {
+            vec![None]
+        } else {
+            (0..total)
+                .map(|index| Some(Shard { index, total }))
+                .collect()
+        }
+    }
+}
+
+impl std::fmt::Display for Shard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "shard {}/{}", self.index + 1, self.total)
+    }
+}
+
+fn shard_name(name: String, shard: Option<Shard>) -> String {
+    match shard {
+        Some(shard) => format!("{} ({})", name, shard),
+        None => name,
+    }
+}
+
+fn target_name(spec: &ExternalRunnerSpec) -> String {
+    format!(
+        "{}//{}:{}",
+        spec.target.cell, spec.target.package, spec.target.target
+    )
+}
+
+fn get_test_status(status: &ExecutionStatus) -> TestStatus {
+    match status {
         ExecutionStatus::Finished { exitcode } => match exitcode {
             0 => TestStatus::PASS,
             _ => TestStatus::FAIL,
         },
         ExecutionStatus::TimedOut { .. } => TestStatus::TIMEOUT,
+    }
+}
+
+/// Whether a test with this status should not fail the run.
+fn is_passing(status: &TestStatus) -> bool {
+    matches!(
+        status,
+        TestStatus::PASS | TestStatus::SKIP | TestStatus::OMITTED | TestStatus::FLAKY
+    )
+}
+
+/// Restricts the results to the cases that were supposed to run, and adds a failing result for
+/// those that didn't report one. Those most likely didn't get to run because the binary crashed or
+/// timed out while running an earlier case.
+fn complete_case_results(
+    case_results: Vec<CaseResult>,
+    testcases: &[String],
+    binary_status: &TestStatus,
+) -> Vec<CaseResult> {
+    let missing_status = match binary_status {
+        TestStatus::TIMEOUT => TestStatus::TIMEOUT,
+        _ => TestStatus::FATAL,
     };
+    let mut case_results: HashMap<String, CaseResult> = case_results
+        .into_iter()
+        .map(|r| (r.name.clone(), r))
+        .collect();
+    testcases
+        .iter()
+        .map(|testcase| {
+            case_results.remove(testcase).unwrap_or_else(|| CaseResult {
+                name: testcase.clone(),
+                status: missing_status.clone(),
+                duration: None,
+                details: String::new(),
+            })
+        })
+        .collect()
+}
+
+fn execution_details(execution_result: &ExecutionResult2) -> String {
+    format!(
+        "---- STDOUT ----\n{:?}\n---- STDERR ----\n{:?}\n",
+        execution_result.stdout, execution_result.stderr
+    )
+}
+
+fn get_test_result(
+    name: String,
+    target: ConfiguredTargetHandle,
+    execution_result: ExecutionResult2,
+) -> TestResult {
     TestResult {
         target,
         name,
-        status,
+        status: get_test_status(&execution_result.status),
         msg: None,
         duration: Some(execution_result.execution_time),
-        details: format!(
-            "---- STDOUT ----\n{:?}\n---- STDERR ----\n{:?}\n",
-            execution_result.stdout, execution_result.stderr
-        ),
+        details: execution_details(&execution_result),
+        stdout: stream_to_str(&execution_result.stdout).into_owned(),
+        stderr: stream_to_str(&execution_result.stderr).into_owned(),
+    }
+}
+
+/// Merges the executions of the shards of a test into one result. The first failing shard decides
+/// the status, and the test took as long as its slowest shard.
+fn merge_shard_results(
+    name: String,
+    target: ConfiguredTargetHandle,
+    mut shard_results: Vec<(Option<Shard>, ExecutionResult2)>,
+) -> TestResult {
+    if let [(None, _)] = shard_results.as_slice() {
+        let (_, execution_result) = shard_results.pop().unwrap();
+        return get_test_result(name, target, execution_result);
+    }
+
+    let mut status = TestStatus::PASS;
+    let mut duration = None;
+    let mut details = String::new();
+    let mut stdout = String::new();
+    let mut stderr = String::new();
+    for (shard, execution_result) in shard_results {
+        let shard_status = get_test_status(&execution_result.status);
+        if status == TestStatus::PASS {
+            status = shard_status;
+        }
+        duration = duration.max(Some(execution_result.execution_time));
+        let header = match shard {
+            Some(shard) => format!("==== {} ====\n", shard),
+            None => String::new(),
+        };
+        details.push_str(&header);
+        details.push_str(&execution_details(&execution_result));
+        stdout.push_str(&header);
+        stdout.push_str(&stream_to_str(&execution_result.stdout));
+        stderr.push_str(&header);
+        stderr.push_str(&stream_to_str(&execution_result.stderr));
+    }
+    TestResult {
+        target,
+        name,
+        status,
+        msg: None,
+        duration,
+        details,
+        stdout,
+        stderr,
     }
 }
 
@@ -234,3 +634,46 @@ impl RunVerdict {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_complete_case_results() {
+        let case = |name: &str, status: TestStatus| CaseResult {
+            name: name.to_owned(),
+            status,
+            duration: None,
+            details: String::new(),
+        };
+        let testcases = ["a".to_owned(), "b".to_owned()];
+        let statuses = |binary_status| {
+            complete_case_results(
+                vec![case("a", TestStatus::PASS), case("other", TestStatus::FAIL)],
+                &testcases,
+                &binary_status,
+            )
+            .into_iter()
+            .map(|r| (r.name, r.status))
+            .collect::<Vec<_>>()
+        };
+
+        // A case that reports nothing fails the run, even if the binary exited successfully.
+        assert_eq!(
+            statuses(TestStatus::PASS),
+            vec![
+                ("a".to_owned(), TestStatus::PASS),
+                ("b".to_owned(), TestStatus::FATAL)
+            ]
+        );
+        assert!(!is_passing(&TestStatus::FATAL));
+        assert_eq!(
+            statuses(TestStatus::TIMEOUT),
+            vec![
+                ("a".to_owned(), TestStatus::PASS),
+                ("b".to_owned(), TestStatus::TIMEOUT)
+            ]
+        );
+    }
+}
@@ -11,6 +11,7 @@ use buck2_node::attrs::coerced_attr::CoercedAttr;
 use buck2_node::attrs::display::AttrDisplayWithContextExt;
 use starlark::values::dict::Dict;
 use starlark::values::list::AllocList;
+use starlark::values::structs::AllocStruct;
 use starlark::values::tuple::AllocTuple;
 use starlark::values::Heap;
 use starlark::values::Value;
@@ -64,6 +65,13 @@ impl CoercedAttrResolveExt for CoercedAttr {
                 }
                 Ok(heap.alloc(Dict::new(m)))
             }
+            CoercedAttr::Record(r) => {
+                let mut v = Vec::with_capacity(r.len());
+                for (k, e) in r.iter() {
+                    v.push((k.as_str(), e.to_value(heap)?));
+                }
+                Ok(heap.alloc(AllocStruct(v)))
+            }
             x => {
                 // For now this function is used to convert attributes to Starlark values
                 // for transition rules which access attributes.
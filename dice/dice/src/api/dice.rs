@@ -194,8 +194,13 @@ use futures::future::Future;
 use serde::Serializer;
 
 use crate::api::cycles::DetectCycles;
+use crate::api::key::Key;
+use crate::api::persistence::PersistentKey;
+use crate::api::transaction::DiceTransaction;
 use crate::api::transaction::DiceTransactionUpdater;
 use crate::api::user_data::UserComputationData;
+use crate::introspection::invalidation::InvalidationCauses;
+use crate::metrics::EvictionStats;
 use crate::metrics::Metrics;
 use crate::DiceDataBuilderImpl;
 use crate::DiceImplementation;
@@ -273,6 +278,43 @@ impl Dice {
     pub async fn is_idle(&self) -> bool {
         self.implementation.is_idle().await
     }
+
+    /// Serializes the values of the registered `PersistentKey`s that are up to date at the
+    /// version of the transaction. Only supported by modern dice.
+    pub async fn save_persistent_state(&self, ctx: &DiceTransaction) -> anyhow::Result<Vec<u8>> {
+        self.implementation.save_persistent_state(ctx).await
+    }
+
+    /// Restores values saved by `save_persistent_state` into this instance at the version of the
+    /// transaction, returning how many were restored. Values that are not up to date are
+    /// invalidated, and restored values depend on `environment` in place of the keys that are not
+    /// persisted. Should be called before anything else is computed. Only supported by modern dice.
+    pub async fn load_persistent_state<E: Key>(
+        &self,
+        data: &[u8],
+        ctx: &DiceTransaction,
+        environment: &E,
+    ) -> anyhow::Result<usize> {
+        self.implementation
+            .load_persistent_state(data, ctx, environment)
+            .await
+    }
+
+    /// Drops the least recently used computed values, weighted by the memory they retain, until
+    /// values retaining at least `bytes` are dropped. Everything depending on a dropped value is
+    /// dropped too, and recomputed when next requested. Injected values are never dropped.
+    /// Returns `None` without dropping anything while transactions are active. Only supported by
+    /// modern dice.
+    pub async fn evict(&self, bytes: u64) -> anyhow::Result<Option<EvictionStats>> {
+        self.implementation.evict(bytes).await
+    }
+
+    /// Why keys were invalidated by the most recent transaction that changed anything, which can
+    /// be used to trace a recomputation back to the change that caused it. Only supported by
+    /// modern dice.
+    pub async fn invalidation_causes(&self) -> anyhow::Result<InvalidationCauses> {
+        self.implementation.invalidation_causes().await
+    }
 }
 
 pub struct DiceDataBuilder(DiceDataBuilderImpl);
@@ -282,6 +324,12 @@ impl DiceDataBuilder {
         self.0.set(val);
     }
 
+    /// Allows the values of `K` to be saved and restored by `Dice::save_persistent_state` and
+    /// `Dice::load_persistent_state`.
+    pub fn register_persistent_key<K: PersistentKey>(&mut self) {
+        self.0.register_persistent_key::<K>();
+    }
+
     pub fn build(self, detect_cycles: DetectCycles) -> Arc<Dice> {
         self.0.build(detect_cycles)
     }
@@ -0,0 +1,52 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//!
+//! Keys whose computed values can be saved and restored into a new instance of Dice, so that
+//! they survive process restarts.
+//!
+//! Keys are registered via `DiceDataBuilder::register_persistent_key`. Only values that are up to
+//! date at the version of the transaction passed to `Dice::save_persistent_state` are saved,
+//! together with their dependency edges to other saved values, either direct or through keys that
+//! are not persisted. All restored values also depend on an "environment" key chosen by the user,
+//! which stands for the inputs that are not persisted themselves (e.g. injected keys), and should
+//! change whenever any of these do. Restored values are checked with
+//! `PersistentKey::is_up_to_date` and invalidated if they are not.
+//!
+
+use async_trait::async_trait;
+
+use crate::api::computations::DiceComputations;
+use crate::api::data::DiceData;
+use crate::api::key::Key;
+
+/// A `Key` whose values can be persisted across instances of Dice.
+#[async_trait]
+pub trait PersistentKey: Key {
+    /// Identifies this type of key in the saved state. Must be unique and stable across releases.
+    const KIND: &'static str;
+
+    /// Serializes the key and its value, or returns `None` if the value shouldn't be persisted.
+    fn save(&self, value: &Self::Value) -> anyhow::Result<Option<Vec<u8>>>;
+
+    /// Deserializes a key and its value written by `save`.
+    fn load(data: &[u8], global_data: &DiceData) -> anyhow::Result<(Self, Self::Value)>;
+
+    /// Checks whether a restored value still matches the state outside of Dice it was computed
+    /// from, e.g. the file system, which might have changed while no Dice was running. Values that
+    /// are not up to date are invalidated right after they are restored, and their dependents are
+    /// only reused if the recomputed value is equal.
+    async fn is_up_to_date(
+        &self,
+        _value: &Self::Value,
+        _ctx: &DiceComputations,
+    ) -> anyhow::Result<bool> {
+        Ok(true)
+    }
+}
@@ -15,6 +15,8 @@ use gazebo::variants::UnpackVariants;
 /// The oldest entry will be evicted once the cache stores more than N entries of the same key
 /// request to compute them. TODO think about whether or not we can
 /// optimize to delete injected keys when no more computation will request that version
+/// Independently of this, computed (but not injected) entries can be dropped under memory pressure
+/// via `Dice::evict`.
 #[derive(UnpackVariants, Debug, Clone, Copy, Dupe, Allocative)]
 pub enum StorageType {
     LastN(usize),
@@ -0,0 +1,188 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//!
+//! Tracks how recently computed values were used, so that the least recently used ones can be
+//! dropped from the graph when the process is under memory pressure.
+//!
+//! Only computed keys are tracked. Injected values can't be recomputed, so they are never evicted.
+//!
+//! Measuring the memory retained by values is expensive, so it is not done on the state thread.
+//! Values are measured once, before they are first considered for eviction.
+//!
+
+use std::cmp::Reverse;
+
+use allocative::Allocative;
+use thiserror::Error;
+
+use crate::impls::key::DiceKey;
+use crate::impls::value::DiceValidValue;
+use crate::HashMap;
+
+#[derive(Debug, Error)]
+pub(crate) enum EvictionError {
+    #[error("Evicting DICE values is only supported by the modern DICE implementation")]
+    LegacyDice,
+}
+
+/// The values of a key to measure, see `LruTracker::unmeasured`.
+pub(crate) struct UnmeasuredKey {
+    pub(crate) key: DiceKey,
+    pub(crate) computed_at: u64,
+    pub(crate) values: Vec<DiceValidValue>,
+}
+
+#[derive(Allocative)]
+struct Tracked {
+    last_used: u64,
+    /// When the current values of the key were computed, to tell apart measurements of values
+    /// that have been replaced since.
+    computed_at: u64,
+    /// The memory retained by the values of the key, once measured.
+    size: Option<usize>,
+}
+
+#[derive(Allocative, Default)]
+pub(crate) struct LruTracker {
+    /// Incremented on every use, so that larger is more recent.
+    tick: u64,
+    tracked: HashMap<DiceKey, Tracked>,
+    /// Totals over the lifetime of the graph, for metrics.
+    pub(crate) evicted_key_count: usize,
+    pub(crate) evicted_bytes: u64,
+}
+
+impl LruTracker {
+    /// Records a use of a computed key, starting to track it if needed. The values of the key
+    /// changed, so they have to be measured again.
+    pub(crate) fn computed(&mut self, key: DiceKey) {
+        self.tick += 1;
+        self.tracked.insert(
+            key,
+            Tracked {
+                last_used: self.tick,
+                computed_at: self.tick,
+                size: None,
+            },
+        );
+    }
+
+    /// Records a use of a key, if it is tracked.
+    pub(crate) fn used(&mut self, key: DiceKey) {
+        if let Some(tracked) = self.tracked.get_mut(&key) {
+            self.tick += 1;
+            tracked.last_used = self.tick;
+        }
+    }
+
+    pub(crate) fn is_tracked(&self, key: DiceKey) -> bool {
+        self.tracked.contains_key(&key)
+    }
+
+    /// The tracked keys whose values have not been measured yet, along with a token to pass to
+    /// `measured`.
+    pub(crate) fn unmeasured(&self) -> impl Iterator<Item = (DiceKey, u64)> + '_ {
+        self.tracked
+            .iter()
+            .filter(|(_, tracked)| tracked.size.is_none())
+            .map(|(key, tracked)| (*key, tracked.computed_at))
+    }
+
+    /// Records the size of the values of a key, unless they were recomputed since they were
+    /// returned by `unmeasured`.
+    pub(crate) fn measured(&mut self, key: DiceKey, computed_at: u64, size: usize) {
+        if let Some(tracked) = self.tracked.get_mut(&key) {
+            if tracked.computed_at == computed_at {
+                tracked.size = Some(size);
+            }
+        }
+    }
+
+    /// Stops tracking an evicted key, returning the size of its values if it was tracked.
+    pub(crate) fn removed(&mut self, key: DiceKey) -> Option<usize> {
+        let tracked = self.tracked.remove(&key)?;
+        let size = tracked.size.unwrap_or(0);
+        self.evicted_key_count += 1;
+        self.evicted_bytes += size as u64;
+        Some(size)
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.tracked.clear();
+    }
+
+    /// The tracked keys in the order they should be evicted. Older values are evicted first,
+    /// weighted by how much memory they retain, so that a large value that wasn't used in a while
+    /// goes before many small values that were used slightly longer ago. Values that were not
+    /// measured yet go last.
+    pub(crate) fn eviction_order(&self) -> Vec<DiceKey> {
+        let mut candidates: Vec<_> = self
+            .tracked
+            .iter()
+            .map(|(key, tracked)| {
+                let age = (self.tick - tracked.last_used + 1) as u128;
+                (age * tracked.size.unwrap_or(0) as u128, *key)
+            })
+            .collect();
+        candidates.sort_by_key(|(score, _)| Reverse(*score));
+        candidates.into_iter().map(|(_, key)| key).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::impls::core::eviction::LruTracker;
+    use crate::impls::key::DiceKey;
+
+    #[test]
+    fn eviction_order_weighs_age_by_size() {
+        let mut tracker = LruTracker::default();
+        tracker.computed(DiceKey { index: 0 });
+        tracker.computed(DiceKey { index: 1 });
+        tracker.computed(DiceKey { index: 2 });
+        tracker.used(DiceKey { index: 0 });
+        // untracked keys are ignored
+        tracker.used(DiceKey { index: 3 });
+
+        let unmeasured: Vec<_> = tracker.unmeasured().collect();
+        assert_eq!(3, unmeasured.len());
+        for (key, computed_at) in unmeasured {
+            tracker.measured(key, computed_at, if key.index == 2 { 100 } else { 1 });
+        }
+        assert_eq!(0, tracker.unmeasured().count());
+
+        let order = tracker.eviction_order();
+        assert_eq!(
+            order,
+            vec![
+                DiceKey { index: 2 },
+                DiceKey { index: 1 },
+                DiceKey { index: 0 }
+            ]
+        );
+
+        assert_eq!(Some(100), tracker.removed(DiceKey { index: 2 }));
+        assert!(!tracker.is_tracked(DiceKey { index: 2 }));
+        assert_eq!(tracker.evicted_key_count, 1);
+        assert_eq!(tracker.evicted_bytes, 100);
+    }
+
+    #[test]
+    fn measurements_of_replaced_values_are_ignored() {
+        let mut tracker = LruTracker::default();
+        tracker.computed(DiceKey { index: 0 });
+        let unmeasured: Vec<_> = tracker.unmeasured().collect();
+        tracker.computed(DiceKey { index: 0 });
+
+        let (key, computed_at) = unmeasured[0];
+        tracker.measured(key, computed_at, 100);
+        assert_eq!(1, tracker.unmeasured().count());
+    }
+}
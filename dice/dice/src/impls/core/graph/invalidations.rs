@@ -0,0 +1,106 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//!
+//! Records why each key was invalidated at the most recent version with changes, so that
+//! unexpected recomputations can be traced back to the change that caused them.
+//!
+
+use allocative::Allocative;
+use dupe::Dupe;
+
+use crate::impls::key::DiceKey;
+use crate::versions::VersionNumber;
+use crate::HashMap;
+
+#[derive(Allocative, Clone, Copy, Dupe, Debug, PartialEq, Eq)]
+pub(crate) enum InvalidationCause {
+    /// The key itself was changed as part of the update.
+    Changed,
+    /// The key was invalidated because the given dependency was.
+    Dep(DiceKey),
+}
+
+#[derive(Allocative, Default)]
+pub(crate) struct InvalidationCauses {
+    version: Option<VersionNumber>,
+    causes: HashMap<DiceKey, InvalidationCause>,
+}
+
+impl InvalidationCauses {
+    /// Records that the key was changed directly at the given version.
+    pub(crate) fn changed(&mut self, v: VersionNumber, key: DiceKey) {
+        self.at_version(v).insert(key, InvalidationCause::Changed);
+    }
+
+    /// Records that the key was invalidated at the given version due to `dep`. Only the first
+    /// cause is kept, which is enough to explain the invalidation.
+    pub(crate) fn invalidated_by(&mut self, v: VersionNumber, key: DiceKey, dep: DiceKey) {
+        self.at_version(v)
+            .entry(key)
+            .or_insert(InvalidationCause::Dep(dep));
+    }
+
+    fn at_version(&mut self, v: VersionNumber) -> &mut HashMap<DiceKey, InvalidationCause> {
+        if self.version != Some(v) {
+            self.version = Some(v);
+            self.causes.clear();
+        }
+        &mut self.causes
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.version = None;
+        self.causes.clear();
+    }
+
+    pub(crate) fn snapshot(&self) -> (Option<VersionNumber>, HashMap<DiceKey, InvalidationCause>) {
+        (self.version, self.causes.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::impls::core::graph::invalidations::InvalidationCause;
+    use crate::impls::core::graph::invalidations::InvalidationCauses;
+    use crate::impls::key::DiceKey;
+    use crate::versions::VersionNumber;
+
+    #[test]
+    fn only_latest_version_is_kept() {
+        let mut causes = InvalidationCauses::default();
+        causes.changed(VersionNumber::new(1), DiceKey { index: 0 });
+        causes.invalidated_by(
+            VersionNumber::new(1),
+            DiceKey { index: 1 },
+            DiceKey { index: 0 },
+        );
+        // the first cause wins
+        causes.invalidated_by(
+            VersionNumber::new(1),
+            DiceKey { index: 1 },
+            DiceKey { index: 2 },
+        );
+        let (v, snapshot) = causes.snapshot();
+        assert_eq!(v, Some(VersionNumber::new(1)));
+        assert_eq!(
+            snapshot.get(&DiceKey { index: 1 }),
+            Some(&InvalidationCause::Dep(DiceKey { index: 0 }))
+        );
+
+        causes.changed(VersionNumber::new(2), DiceKey { index: 2 });
+        let (v, snapshot) = causes.snapshot();
+        assert_eq!(v, Some(VersionNumber::new(2)));
+        assert_eq!(snapshot.len(), 1);
+        assert_eq!(
+            snapshot.get(&DiceKey { index: 2 }),
+            Some(&InvalidationCause::Changed)
+        );
+    }
+}
@@ -12,6 +12,7 @@ mod dependencies;
 pub(crate) mod history;
 #[allow(unused)]
 pub(crate) mod introspection;
+pub(crate) mod invalidations;
 mod nodes;
 pub(crate) mod storage;
 pub(crate) mod types;
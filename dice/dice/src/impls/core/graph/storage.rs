@@ -30,6 +30,7 @@ use crate::arc::Arc;
 use crate::impls::core::graph::dependencies::VersionedDependencies;
 use crate::impls::core::graph::history::CellHistory;
 use crate::impls::core::graph::history::HistoryState;
+use crate::impls::core::graph::invalidations::InvalidationCauses;
 use crate::impls::core::graph::nodes::OccupiedGraphNode;
 use crate::impls::core::graph::nodes::VacantGraphNode;
 use crate::impls::core::graph::nodes::VersionedGraphNode;
@@ -37,6 +38,7 @@ use crate::impls::core::graph::types::VersionedGraphKey;
 use crate::impls::core::graph::types::VersionedGraphResult;
 use crate::impls::core::graph::types::VersionedGraphResultMismatch;
 use crate::impls::key::DiceKey;
+use crate::impls::persistence::SnapshotNode;
 use crate::impls::value::DiceComputedValue;
 use crate::impls::value::DiceValidValue;
 use crate::versions::VersionNumber;
@@ -57,12 +59,15 @@ pub(crate) struct VersionedGraph {
     /// VacantGraphEntries can only be present when no other entries are present for the key at
     /// any version.
     pub(crate) last_n: HashMap<DiceKey, SortedVectorMap<VersionNumber, VersionedGraphNode>>,
+    /// Why keys were invalidated at the most recent version with changes, for debugging.
+    pub(crate) invalidations: InvalidationCauses,
 }
 
 impl VersionedGraph {
     pub(crate) fn new() -> Self {
         Self {
             last_n: Default::default(),
+            invalidations: Default::default(),
         }
     }
 
@@ -129,6 +134,79 @@ impl VersionedGraph {
         }
     }
 
+    /// All the entries that are verified at the given version, with their deps.
+    pub(crate) fn verified_at(&self, v: VersionNumber) -> Vec<SnapshotNode> {
+        self.last_n
+            .iter()
+            .filter_map(|(key, versioned)| {
+                match versioned
+                    .range((Bound::Unbounded, Bound::Included(v)))
+                    .next_back()?
+                    .1
+                {
+                    VersionedGraphNode::Occupied(entry) => {
+                        match entry.metadata().hist.get_history(&v) {
+                            HistoryState::Verified => Some(SnapshotNode {
+                                key: *key,
+                                value: entry.val().dupe(),
+                                deps: entry.metadata().deps.deps(),
+                            }),
+                            HistoryState::Unknown(_) | HistoryState::Dirty => None,
+                        }
+                    }
+                    VersionedGraphNode::Vacant(_) => None,
+                }
+            })
+            .collect()
+    }
+
+    /// The values of the key at all versions.
+    pub(crate) fn values(&self, key: DiceKey) -> Vec<DiceValidValue> {
+        self.last_n.get(&key).map_or_else(Vec::new, |versioned| {
+            versioned
+                .values()
+                .filter_map(|node| match node {
+                    VersionedGraphNode::Occupied(entry) => Some(entry.val().dupe()),
+                    VersionedGraphNode::Vacant(_) => None,
+                })
+                .collect()
+        })
+    }
+
+    /// Removes the key at all versions, along with everything that transitively depends on it,
+    /// since invalidations can no longer propagate through a removed node. The removed keys are
+    /// also removed from the rdeps of the keys they depend on. Returns the removed keys.
+    /// This must only be done when no computation is running, since computations record their
+    /// values with edges to deps that are expected to exist.
+    pub(crate) fn evict(&mut self, key: DiceKey) -> Vec<DiceKey> {
+        let mut removed = Vec::new();
+        let mut dep_edges = Vec::new();
+        let mut queue = vec![key];
+        while let Some(key) = queue.pop() {
+            if let Some(versioned) = self.last_n.remove(&key) {
+                for node in versioned.values() {
+                    if let VersionedGraphNode::Occupied(entry) = node {
+                        queue.extend(entry.metadata().rdeps.rdeps().keys().copied());
+                        dep_edges
+                            .extend(entry.metadata().deps.deps().iter().map(|dep| (*dep, key)));
+                    }
+                }
+                removed.push(key);
+            }
+        }
+        // The deps are kept, so they must not keep edges to the removed keys.
+        for (dep, key) in dep_edges {
+            if let Some(versioned) = self.last_n.get_mut(&dep) {
+                for (_, node) in versioned.iter_mut() {
+                    if let VersionedGraphNode::Occupied(entry) = node {
+                        entry.metadata_mut().rdeps.remove_rdep(key);
+                    }
+                }
+            }
+        }
+        removed
+    }
+
     /// gets the cache entry corresponding to the cache entry if up to date.
     /// returns 'None' if entry is missing or versions are out of date.
     fn get_internal<'a>(
@@ -372,6 +450,14 @@ impl VersionedGraph {
         key: VersionedGraphKey,
         invalidate: InvalidateKind,
     ) -> bool {
+        let changed = self.invalidate_entry(key, invalidate);
+        if changed {
+            self.invalidations.changed(key.v, key.k);
+        }
+        changed
+    }
+
+    fn invalidate_entry(&mut self, key: VersionedGraphKey, invalidate: InvalidateKind) -> bool {
         let rdeps = {
             match invalidate {
                 invalidate @ (InvalidateKind::ForceDirty | InvalidateKind::Invalidate) => {
@@ -398,7 +484,7 @@ impl VersionedGraph {
 
                                     rdeps
                                         .iter()
-                                        .map(|(r, v)| (r.dupe(), *v))
+                                        .map(|(r, v)| (r.dupe(), *v, key.k))
                                         .collect::<Vec<_>>()
                                 };
 
@@ -438,7 +524,7 @@ impl VersionedGraph {
                                         .rdeps
                                         .rdeps()
                                         .iter()
-                                        .map(|(r, v)| (r.dupe(), *v))
+                                        .map(|(r, v)| (r.dupe(), *v, key.k))
                                         .collect::<Vec<_>>()
                                 } else {
                                     return false;
@@ -496,13 +582,17 @@ impl VersionedGraph {
         true
     }
 
+    /// Invalidates the given rdeps and their transitive rdeps. Each rdep is queued along with the
+    /// key whose invalidation caused it, which is recorded in `invalidations`.
     fn invalidate_rdeps(
         &mut self,
         version: VersionNumber,
-        mut queue: Vec<(DiceKey, VersionNumber)>,
+        mut queue: Vec<(DiceKey, VersionNumber, DiceKey)>,
     ) {
-        while let Some((rdep, relevant_version)) = queue.pop() {
-            if let Some(node) = self.get_internal(VersionedGraphKey::new(relevant_version, rdep)) {
+        while let Some((rdep, relevant_version, cause)) = queue.pop() {
+            let invalidated = if let Some(node) =
+                self.get_internal(VersionedGraphKey::new(relevant_version, rdep))
+            {
                 if node.mark_invalidated(version) {
                     // since dirty always occurs in increasing order, it must be the case that if
                     // the history was already dirtied, it was by a version number less than the
@@ -517,11 +607,19 @@ impl VersionedGraph {
 
                             rdeps
                                 .iter()
-                                .map(|(r, v)| (r.dupe(), *v))
+                                .map(|(r, v)| (r.dupe(), *v, rdep))
                                 .collect::<Vec<_>>()
                         })
                     }
+                    true
+                } else {
+                    false
                 }
+            } else {
+                false
+            };
+            if invalidated {
+                self.invalidations.invalidated_by(version, rdep, cause);
             }
         }
     }
@@ -1524,6 +1622,42 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn evict_removes_dependents_from_rdeps() -> anyhow::Result<()> {
+        let mut cache = VersionedGraph::new();
+        let res = DiceValidValue::testing_new(DiceKeyValue::<K>::new(100));
+
+        for (index, deps) in [(0, vec![]), (1, vec![0]), (2, vec![1])] {
+            cache.update(
+                VersionedGraphKey::new(VersionNumber::new(0), DiceKey { index }),
+                res.dupe(),
+                ValueReusable::EqualityBased,
+                Arc::new(deps.into_iter().map(|index| DiceKey { index }).collect()),
+                StorageType::LastN(1),
+            );
+        }
+
+        assert_eq!(
+            cache.evict(DiceKey { index: 1 }),
+            vec![DiceKey { index: 1 }, DiceKey { index: 2 }]
+        );
+
+        assert!(!cache.last_n.contains_key(&DiceKey { index: 1 }));
+        assert!(!cache.last_n.contains_key(&DiceKey { index: 2 }));
+        let remaining = cache
+            .last_n
+            .get(&DiceKey { index: 0 })
+            .unwrap()
+            .values()
+            .next()
+            .unwrap()
+            .unpack_occupied()
+            .unwrap();
+        assert!(remaining.metadata().rdeps.rdeps().is_empty());
+
+        Ok(())
+    }
+
     #[test]
     fn dirty_same_nodes() -> anyhow::Result<()> {
         let mut cache = VersionedGraph::new();
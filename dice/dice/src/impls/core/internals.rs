@@ -12,7 +12,10 @@ use gazebo::prelude::SliceExt;
 use crate::api::storage_type::StorageType;
 use crate::arc::Arc;
 use crate::impls::cache::SharedCache;
+use crate::impls::core::eviction::LruTracker;
+use crate::impls::core::eviction::UnmeasuredKey;
 use crate::impls::core::graph::introspection::VersionedGraphIntrospectable;
+use crate::impls::core::graph::invalidations::InvalidationCause;
 use crate::impls::core::graph::storage::InvalidateKind;
 use crate::impls::core::graph::storage::ValueReusable;
 use crate::impls::core::graph::storage::VersionedGraph;
@@ -22,20 +25,26 @@ use crate::impls::core::versions::introspection::VersionIntrospectable;
 use crate::impls::core::versions::VersionEpoch;
 use crate::impls::core::versions::VersionTracker;
 use crate::impls::key::DiceKey;
+use crate::impls::persistence::RestoredNode;
+use crate::impls::persistence::SnapshotNode;
 use crate::impls::task::dice::DiceTask;
 use crate::impls::task::dice::TerminationObserver;
 use crate::impls::transaction::ChangeType;
 use crate::impls::value::DiceComputedValue;
 use crate::impls::value::DiceValidValue;
+use crate::metrics::EvictionStats;
 use crate::metrics::Metrics;
 use crate::result::CancellableResult;
 use crate::result::Cancelled;
 use crate::versions::VersionNumber;
+use crate::HashMap;
+use crate::HashSet;
 
 /// Core state of DICE, holding the actual graph and version information
 pub(super) struct CoreState {
     version_tracker: VersionTracker,
     graph: VersionedGraph,
+    lru: LruTracker,
     pending_termination_tasks: Vec<DiceTask>,
 }
 
@@ -44,6 +53,7 @@ impl CoreState {
         Self {
             version_tracker: VersionTracker::new(),
             graph: VersionedGraph::new(),
+            lru: LruTracker::default(),
             pending_termination_tasks: Vec::new(),
         }
     }
@@ -92,6 +102,7 @@ impl CoreState {
     }
 
     pub(super) fn lookup_key(&mut self, key: VersionedGraphKey) -> VersionedGraphResult {
+        self.lru.used(key.k);
         self.graph.get(key)
     }
 
@@ -107,6 +118,7 @@ impl CoreState {
         if self.version_tracker.is_relevant(key.v, epoch) {
             debug!(msg = "update graph entry", k = ?key.k, v = %key.v, v_epoch = %epoch);
 
+            self.lru.computed(key.k);
             Ok(self.graph.update(key, value, reusability, deps, storage).0)
         } else {
             debug!(msg = "update is rejected due to outdated epoch", k = ?key.k, v = %key.v, v_epoch = %epoch);
@@ -129,6 +141,8 @@ impl CoreState {
         // Do the actual drop on a different thread because we may have to drop a lot of stuff
         // here.
         let map = std::mem::take(&mut self.graph.last_n);
+        self.lru.clear();
+        self.graph.invalidations.clear();
         std::thread::spawn(move || drop(map));
     }
 
@@ -146,6 +160,8 @@ impl CoreState {
             key_count: self.graph.last_n.len(),
             currently_active_key_count: currently_running_key_count,
             active_transaction_count: active_transaction_count as u32, // probably won't support more than u32 transactions
+            evicted_key_count: self.lru.evicted_key_count,
+            evicted_bytes: self.lru.evicted_bytes,
         }
     }
 
@@ -155,6 +171,108 @@ impl CoreState {
 
         (graph, version_data)
     }
+
+    pub(super) fn invalidation_causes(
+        &self,
+    ) -> (Option<VersionNumber>, HashMap<DiceKey, InvalidationCause>) {
+        self.graph.invalidations.snapshot()
+    }
+
+    pub(super) fn persistent_snapshot(&self, v: VersionNumber) -> Vec<SnapshotNode> {
+        self.graph.verified_at(v)
+    }
+
+    /// Inserts the nodes as verified at the given version, then dirties the ones that are not up to
+    /// date at a new version. Nodes that are already in the graph are not restored, nor are the
+    /// nodes depending on them, since the restored values may be inconsistent with the computed
+    /// ones. Nothing is restored if the version is not the current one, since changes made at later
+    /// versions would not apply to the restored values.
+    pub(super) fn restore(&mut self, v: VersionNumber, nodes: Vec<RestoredNode>) -> usize {
+        if v != self.version_tracker.current() {
+            return 0;
+        }
+
+        let mut skipped = HashSet::default();
+        let mut restored = 0;
+        let mut to_revalidate = Vec::new();
+        for node in nodes {
+            if self.graph.last_n.contains_key(&node.key)
+                || node.deps.iter().any(|dep| skipped.contains(dep))
+            {
+                skipped.insert(node.key);
+                continue;
+            }
+
+            debug!(msg = "restore graph entry", k = ?node.key, v = %v);
+            self.graph.update(
+                VersionedGraphKey::new(v, node.key),
+                node.value,
+                ValueReusable::EqualityBased,
+                node.deps,
+                node.storage,
+            );
+            self.lru.computed(node.key);
+            restored += 1;
+            if node.revalidate {
+                to_revalidate.push((node.key, ChangeType::Invalidate));
+            }
+        }
+        self.update_state(to_revalidate);
+
+        restored
+    }
+
+    /// The values of the tracked keys that have not been measured yet, to be measured off the
+    /// state thread.
+    pub(super) fn unmeasured_keys(&self) -> Vec<UnmeasuredKey> {
+        self.lru
+            .unmeasured()
+            .map(|(key, computed_at)| UnmeasuredKey {
+                key,
+                computed_at,
+                values: self.graph.values(key),
+            })
+            .collect()
+    }
+
+    pub(super) fn record_sizes(&mut self, sizes: Vec<(DiceKey, u64, usize)>) {
+        for (key, computed_at, size) in sizes {
+            self.lru.measured(key, computed_at, size);
+        }
+    }
+
+    /// Drops the least recently used computed values until the values dropped retain at least
+    /// `bytes` of memory, or nothing is left to drop. Nothing is dropped while transactions are
+    /// active, since their computations may depend on the values.
+    pub(super) fn evict(&mut self, bytes: u64) -> Option<EvictionStats> {
+        if self.version_tracker.currently_active().next().is_some() {
+            return None;
+        }
+
+        let order = self.lru.eviction_order();
+        let mut stats = EvictionStats::default();
+        for key in order {
+            if stats.evicted_bytes >= bytes {
+                break;
+            }
+            // might have been evicted already as a dependent of a previous key
+            if !self.lru.is_tracked(key) {
+                continue;
+            }
+            for key in self.graph.evict(key) {
+                let size = self.lru.removed(key).unwrap_or(0);
+                stats.evicted_key_count += 1;
+                stats.evicted_bytes += size as u64;
+            }
+        }
+        debug!(
+            msg = "evicted graph entries",
+            count = stats.evicted_key_count,
+            bytes = stats.evicted_bytes
+        );
+
+        Some(stats)
+    }
 }
 
 #[cfg(test)]
@@ -7,6 +7,7 @@
  * of this source tree.
  */
 
+pub(crate) mod eviction;
 pub(crate) mod graph;
 mod internals;
 mod processor;
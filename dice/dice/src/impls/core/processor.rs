@@ -118,6 +118,26 @@ impl StateProcessor {
             StateRequest::Introspection { resp } => {
                 let _ignored = resp.send(self.state.introspection());
             }
+            StateRequest::InvalidationCauses { resp } => {
+                let _ignored = resp.send(self.state.invalidation_causes());
+            }
+            StateRequest::PersistentSnapshot { version, resp } => {
+                let _ignored = resp.send(self.state.persistent_snapshot(version));
+            }
+            StateRequest::UnmeasuredKeys { resp } => {
+                let _ignored = resp.send(self.state.unmeasured_keys());
+            }
+            StateRequest::RecordSizes { sizes } => self.state.record_sizes(sizes),
+            StateRequest::Evict { bytes, resp } => {
+                let _ignored = resp.send(self.state.evict(bytes));
+            }
+            StateRequest::Restore {
+                version,
+                nodes,
+                resp,
+            } => {
+                let _ignored = resp.send(self.state.restore(version, nodes));
+            }
         }
     }
 }
@@ -15,7 +15,9 @@ use tokio::sync::oneshot::Sender;
 
 use crate::api::storage_type::StorageType;
 use crate::arc::Arc;
+use crate::impls::core::eviction::UnmeasuredKey;
 use crate::impls::core::graph::introspection::VersionedGraphIntrospectable;
+use crate::impls::core::graph::invalidations::InvalidationCause;
 use crate::impls::core::graph::types::VersionedGraphKey;
 use crate::impls::core::graph::types::VersionedGraphResult;
 use crate::impls::core::graph::types::VersionedGraphResultMismatch;
@@ -24,14 +26,18 @@ use crate::impls::core::versions::introspection::VersionIntrospectable;
 use crate::impls::core::versions::VersionEpoch;
 use crate::impls::ctx::SharedLiveTransactionCtx;
 use crate::impls::key::DiceKey;
+use crate::impls::persistence::RestoredNode;
+use crate::impls::persistence::SnapshotNode;
 use crate::impls::task::dice::TerminationObserver;
 use crate::impls::transaction::ActiveTransactionGuard;
 use crate::impls::transaction::ChangeType;
 use crate::impls::value::DiceComputedValue;
 use crate::impls::value::DiceValidValue;
+use crate::metrics::EvictionStats;
 use crate::metrics::Metrics;
 use crate::result::CancellableResult;
 use crate::versions::VersionNumber;
+use crate::HashMap;
 
 /// Core state is accessed via message passing to a single threaded processor
 #[derive(Derivative, VariantName)]
@@ -98,6 +104,38 @@ pub(crate) enum StateRequest {
         #[derivative(Debug = "ignore")]
         resp: Sender<(VersionedGraphIntrospectable, VersionIntrospectable)>,
     },
+    /// Collects why keys were invalidated at the most recent version with changes
+    InvalidationCauses {
+        #[derivative(Debug = "ignore")]
+        resp: Sender<(Option<VersionNumber>, HashMap<DiceKey, InvalidationCause>)>,
+    },
+    /// Collects the nodes that are verified at the given version, to be persisted
+    PersistentSnapshot {
+        version: VersionNumber,
+        #[derivative(Debug = "ignore")]
+        resp: Sender<Vec<SnapshotNode>>,
+    },
+    /// Gets the values of the computed keys that were not measured yet, see `RecordSizes`
+    UnmeasuredKeys {
+        #[derivative(Debug = "ignore")]
+        resp: Sender<Vec<UnmeasuredKey>>,
+    },
+    /// Records the memory retained by the values of keys, as measured from `UnmeasuredKeys`
+    RecordSizes { sizes: Vec<(DiceKey, u64, usize)> },
+    /// Drops least recently used computed values retaining at least the given number of bytes.
+    /// `None` is sent back if nothing could be dropped due to active transactions
+    Evict {
+        bytes: u64,
+        resp: Sender<Option<EvictionStats>>,
+    },
+    /// Inserts previously persisted values into the graph at the given version. The number of
+    /// values restored is sent back via the channel provided
+    Restore {
+        version: VersionNumber,
+        #[derivative(Debug = "ignore")]
+        nodes: Vec<RestoredNode>,
+        resp: Sender<usize>,
+    },
 }
 
 /// A handle to the core state that allows sending requests
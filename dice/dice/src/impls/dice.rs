@@ -16,14 +16,22 @@ use dupe::Dupe;
 
 use crate::api::cycles::DetectCycles;
 use crate::api::data::DiceData;
+use crate::api::key::Key;
+use crate::api::persistence::PersistentKey;
 use crate::api::user_data::UserComputationData;
+use crate::impls::core::graph::invalidations::InvalidationCause as InternalInvalidationCause;
 use crate::impls::core::state::init_state;
 use crate::impls::core::state::CoreStateHandle;
 use crate::impls::core::state::StateRequest;
+use crate::impls::ctx::BaseComputeCtx;
 use crate::impls::key_index::DiceKeyIndex;
+use crate::impls::persistence::PersistentKeys;
 use crate::impls::transaction::TransactionUpdater;
 use crate::introspection::graph::GraphIntrospectable;
 use crate::introspection::graph::ModernIntrospectable;
+use crate::introspection::invalidation::InvalidationCause;
+use crate::introspection::invalidation::InvalidationCauses;
+use crate::metrics::EvictionStats;
 use crate::metrics::Metrics;
 
 #[derive(Allocative)]
@@ -31,6 +39,7 @@ pub(crate) struct DiceModern {
     pub(crate) key_index: DiceKeyIndex,
     pub(crate) state_handle: CoreStateHandle,
     pub(crate) global_data: DiceData,
+    persistent_keys: PersistentKeys,
 }
 
 impl Debug for DiceModern {
@@ -39,30 +48,42 @@ impl Debug for DiceModern {
     }
 }
 
-pub(crate) struct DiceModernDataBuilder(DiceData);
+pub(crate) struct DiceModernDataBuilder(DiceData, PersistentKeys);
 
 impl DiceModernDataBuilder {
     pub(crate) fn new() -> Self {
-        Self(DiceData::new())
+        Self(DiceData::new(), PersistentKeys::default())
     }
 
     pub fn set<K: Send + Sync + 'static>(&mut self, val: K) {
         self.0.set(val);
     }
 
+    pub fn register_persistent_key<K: PersistentKey>(&mut self) {
+        self.1.register::<K>();
+    }
+
     pub fn build(self, _detect_cycles: DetectCycles) -> Arc<DiceModern> {
-        DiceModern::new(self.0)
+        DiceModern::new_with_persistent_keys(self.0, self.1)
     }
 }
 
 impl DiceModern {
     pub(crate) fn new(global_data: DiceData) -> Arc<Self> {
+        Self::new_with_persistent_keys(global_data, PersistentKeys::default())
+    }
+
+    fn new_with_persistent_keys(
+        global_data: DiceData,
+        persistent_keys: PersistentKeys,
+    ) -> Arc<Self> {
         let state_handle = init_state();
 
         Arc::new(DiceModern {
             key_index: Default::default(),
             state_handle,
             global_data,
+            persistent_keys,
         })
     }
 
@@ -111,6 +132,110 @@ impl DiceModern {
         }
     }
 
+    /// Serializes the values of the registered `PersistentKey`s that are up to date at the version
+    /// of the transaction.
+    pub async fn save_persistent_state(&self, ctx: &BaseComputeCtx) -> anyhow::Result<Vec<u8>> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+
+        self.state_handle.request(StateRequest::PersistentSnapshot {
+            version: ctx.get_version(),
+            resp: tx,
+        });
+
+        let snapshot = rx.await.unwrap();
+        // as with introspection, the `key_index` has all the keys of the snapshot once we've
+        // received it.
+        self.persistent_keys.save(&self.key_index, snapshot)
+    }
+
+    /// Restores values saved by `save_persistent_state` at the version of the transaction,
+    /// returning how many were restored. Restored values depend on `environment` in place of the
+    /// keys that weren't persisted. This should be done before anything else is computed, since
+    /// values that are already in the graph aren't restored.
+    pub async fn load_persistent_state<E: Key>(
+        &self,
+        data: &[u8],
+        ctx: &BaseComputeCtx,
+        environment: &E,
+    ) -> anyhow::Result<usize> {
+        // the environment must be in the graph at the version the values are restored at.
+        ctx.as_computations().compute(environment).await?;
+        let environment = self.key_index.index_key(environment.clone());
+        let nodes = self
+            .persistent_keys
+            .load(&self.key_index, data, ctx.as_computations(), environment)
+            .await?;
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+
+        self.state_handle.request(StateRequest::Restore {
+            version: ctx.get_version(),
+            nodes,
+            resp: tx,
+        });
+
+        Ok(rx.await.unwrap())
+    }
+
+    /// Collects why keys were invalidated at the most recent version with changes.
+    pub async fn invalidation_causes(&self) -> InvalidationCauses {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+
+        self.state_handle
+            .request(StateRequest::InvalidationCauses { resp: tx });
+
+        let (version, causes) = rx.await.unwrap();
+        // keys are never removed from the `key_index`, so every key in the graph has an entry
+        let any_key = |k| self.key_index.get(k).introspect();
+        InvalidationCauses {
+            version: version.map(|v| v.to_introspectable()),
+            causes: causes
+                .into_iter()
+                .map(|(k, cause)| {
+                    let cause = match cause {
+                        InternalInvalidationCause::Changed => InvalidationCause::Changed,
+                        InternalInvalidationCause::Dep(dep) => InvalidationCause::Dep(any_key(dep)),
+                    };
+                    (any_key(k), cause)
+                })
+                .collect(),
+        }
+    }
+
+    /// Drops the least recently used computed values, and everything depending on them, until
+    /// values retaining at least `bytes` of memory are dropped. Returns `None` without dropping
+    /// anything if transactions are active.
+    pub async fn evict(&self, bytes: u64) -> Option<EvictionStats> {
+        // Measuring values is expensive, so it's done here rather than on the state thread, and
+        // only once for each value.
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.state_handle
+            .request(StateRequest::UnmeasuredKeys { resp: tx });
+        let unmeasured = rx.await.unwrap();
+        if !unmeasured.is_empty() {
+            let sizes = tokio::task::spawn_blocking(move || {
+                unmeasured
+                    .into_iter()
+                    .map(|unmeasured| {
+                        let size = unmeasured.values.iter().map(|v| v.allocated_size()).sum();
+                        (unmeasured.key, unmeasured.computed_at, size)
+                    })
+                    .collect()
+            })
+            .await
+            .unwrap();
+            self.state_handle
+                .request(StateRequest::RecordSizes { sizes });
+        }
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+
+        self.state_handle
+            .request(StateRequest::Evict { bytes, resp: tx });
+
+        rx.await.unwrap()
+    }
+
     /// Note: modern dice does not support cycle detection yet
     pub fn detect_cycles(&self) -> &DetectCycles {
         // TODO(bobyf) actually have cycles for dice modern
@@ -0,0 +1,414 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Saving and restoring the values of registered `PersistentKey`s.
+
+use std::any::TypeId;
+use std::marker::PhantomData;
+
+use allocative::Allocative;
+use anyhow::Context;
+use dupe::Dupe;
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use futures::StreamExt;
+use serde::Deserialize;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::api::computations::DiceComputations;
+use crate::api::data::DiceData;
+use crate::api::persistence::PersistentKey;
+use crate::api::storage_type::StorageType;
+use crate::arc::Arc;
+use crate::impls::key::DiceKey;
+use crate::impls::key::DiceKeyErased;
+use crate::impls::key_index::DiceKeyIndex;
+use crate::impls::value::DiceKeyValue;
+use crate::impls::value::DiceValidValue;
+use crate::impls::value::DiceValidity;
+use crate::impls::value::MaybeValidDiceValue;
+use crate::HashMap;
+
+/// Saved state written with a different format version is rejected.
+const FORMAT_VERSION: u32 = 2;
+
+/// How many restored values are checked with `PersistentKey::is_up_to_date` at once.
+const VALIDATION_CONCURRENCY: usize = 64;
+
+#[derive(Debug, Error)]
+pub(crate) enum PersistenceError {
+    #[error("Saved DICE state has format version {0}, but only version {1} is supported")]
+    FormatVersion(u32, u32),
+    #[error("Persisting DICE state is only supported by the modern DICE implementation")]
+    LegacyDice,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedState {
+    version: u32,
+    entries: Vec<SavedEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedEntry {
+    kind: String,
+    data: Vec<u8>,
+    /// Indexes of the saved entries this entry depends on, directly or through entries that are
+    /// not saved. Deps are always saved before their dependents.
+    deps: Vec<u32>,
+}
+
+/// A node of the graph that is verified at the current version.
+pub(crate) struct SnapshotNode {
+    pub(crate) key: DiceKey,
+    pub(crate) value: DiceValidValue,
+    pub(crate) deps: Arc<Vec<DiceKey>>,
+}
+
+/// A saved value to be inserted into the graph. Deps are always restored before their dependents.
+/// Values that are not up to date are invalidated once restored.
+pub(crate) struct RestoredNode {
+    pub(crate) key: DiceKey,
+    pub(crate) value: DiceValidValue,
+    pub(crate) deps: Arc<Vec<DiceKey>>,
+    pub(crate) storage: StorageType,
+    pub(crate) revalidate: bool,
+}
+
+/// The saved entries that a node with `deps` depends on, directly or through nodes that are not
+/// saved, since changes to the former propagate to the node through the latter. The saved deps of
+/// the nodes that are not saved are memoized in `unsaved_deps`. All the `deps` must have been
+/// visited already.
+fn saved_deps(
+    deps: &[DiceKey],
+    nodes: &HashMap<DiceKey, &SnapshotNode>,
+    saved: &HashMap<DiceKey, Option<u32>>,
+    unsaved_deps: &mut HashMap<DiceKey, Vec<u32>>,
+) -> Vec<u32> {
+    let is_unsaved = |key: &DiceKey| matches!(saved.get(key), Some(None));
+
+    // Depth first, so that the saved deps of nodes are known before those of their dependents.
+    for dep in deps {
+        if !is_unsaved(dep) || unsaved_deps.contains_key(dep) {
+            continue;
+        }
+        let mut stack = vec![(*dep, 0)];
+        while let Some((key, next_dep)) = stack.last_mut() {
+            let node_deps = &nodes[&*key].deps;
+            if let Some(dep) = node_deps.get(*next_dep) {
+                *next_dep += 1;
+                if is_unsaved(dep) && !unsaved_deps.contains_key(dep) {
+                    stack.push((*dep, 0));
+                }
+                continue;
+            }
+
+            let key = *key;
+            stack.pop();
+            let deps = direct_saved_deps(node_deps, saved, unsaved_deps);
+            unsaved_deps.insert(key, deps);
+        }
+    }
+
+    direct_saved_deps(deps, saved, unsaved_deps)
+}
+
+/// The saved entries among `deps`, along with the memoized saved deps of those that are not saved.
+fn direct_saved_deps(
+    deps: &[DiceKey],
+    saved: &HashMap<DiceKey, Option<u32>>,
+    unsaved_deps: &HashMap<DiceKey, Vec<u32>>,
+) -> Vec<u32> {
+    let mut res = Vec::new();
+    for dep in deps {
+        match saved.get(dep) {
+            Some(Some(index)) => res.push(*index),
+            Some(None) => res.extend(unsaved_deps.get(dep).into_iter().flatten()),
+            // not verified at the version being saved
+            None => {}
+        }
+    }
+    res.sort_unstable();
+    res.dedup();
+    res
+}
+
+/// Type erased `PersistentKey`.
+trait PersistentKeyDyn: Send + Sync + 'static {
+    fn kind(&self) -> &'static str;
+
+    fn storage_type(&self) -> StorageType;
+
+    fn save(&self, key: &DiceKeyErased, value: &DiceValidValue) -> anyhow::Result<Option<Vec<u8>>>;
+
+    /// Returns `None` for values that are not valid, which are never stored in the graph.
+    fn load(
+        &self,
+        key_index: &DiceKeyIndex,
+        data: &[u8],
+        global_data: &DiceData,
+    ) -> anyhow::Result<Option<(DiceKey, DiceValidValue)>>;
+
+    fn is_up_to_date<'a>(
+        &self,
+        key: &'a DiceKeyErased,
+        value: &'a DiceValidValue,
+        ctx: &'a DiceComputations,
+    ) -> BoxFuture<'a, anyhow::Result<bool>>;
+}
+
+struct PersistentKeyImpl<K>(PhantomData<fn() -> K>);
+
+impl<K: PersistentKey> PersistentKeyDyn for PersistentKeyImpl<K> {
+    fn kind(&self) -> &'static str {
+        K::KIND
+    }
+
+    fn storage_type(&self) -> StorageType {
+        K::storage_type()
+    }
+
+    fn save(&self, key: &DiceKeyErased, value: &DiceValidValue) -> anyhow::Result<Option<Vec<u8>>> {
+        let key = key
+            .as_any()
+            .downcast_ref::<K>()
+            .expect("registered for the type of the key");
+        let value = value
+            .downcast_ref::<K::Value>()
+            .expect("value should be of the type of the key");
+        key.save(value)
+    }
+
+    fn load(
+        &self,
+        key_index: &DiceKeyIndex,
+        data: &[u8],
+        global_data: &DiceData,
+    ) -> anyhow::Result<Option<(DiceKey, DiceValidValue)>> {
+        let (key, value) = K::load(data, global_data)?;
+        let value = MaybeValidDiceValue::new(
+            std::sync::Arc::new(DiceKeyValue::<K>::new(value)),
+            DiceValidity::Valid,
+        );
+        Ok(value
+            .into_valid_value()
+            .ok()
+            .map(|value| (key_index.index_key(key), value)))
+    }
+
+    fn is_up_to_date<'a>(
+        &self,
+        key: &'a DiceKeyErased,
+        value: &'a DiceValidValue,
+        ctx: &'a DiceComputations,
+    ) -> BoxFuture<'a, anyhow::Result<bool>> {
+        let key = key
+            .as_any()
+            .downcast_ref::<K>()
+            .expect("registered for the type of the key");
+        let value = value
+            .downcast_ref::<K::Value>()
+            .expect("value should be of the type of the key");
+        key.is_up_to_date(value, ctx).boxed()
+    }
+}
+
+/// The `PersistentKey`s registered on a Dice.
+#[derive(Allocative, Default)]
+pub(crate) struct PersistentKeys {
+    #[allocative(skip)]
+    by_type: HashMap<TypeId, std::sync::Arc<dyn PersistentKeyDyn>>,
+    #[allocative(skip)]
+    by_kind: HashMap<&'static str, std::sync::Arc<dyn PersistentKeyDyn>>,
+}
+
+impl PersistentKeys {
+    pub(crate) fn register<K: PersistentKey>(&mut self) {
+        let key: std::sync::Arc<dyn PersistentKeyDyn> =
+            std::sync::Arc::new(PersistentKeyImpl::<K>(PhantomData));
+        let previous = self.by_kind.insert(K::KIND, key.dupe());
+        assert!(
+            previous.is_none(),
+            "persistent key kind `{}` registered more than once",
+            K::KIND
+        );
+        self.by_type.insert(TypeId::of::<K>(), key);
+    }
+
+    /// Serializes the registered keys of the snapshot, along with the edges between them, which
+    /// may go through keys that are not registered.
+    pub(crate) fn save(
+        &self,
+        key_index: &DiceKeyIndex,
+        snapshot: Vec<SnapshotNode>,
+    ) -> anyhow::Result<Vec<u8>> {
+        let nodes: HashMap<DiceKey, &SnapshotNode> = snapshot.iter().map(|n| (n.key, n)).collect();
+        // `None` for nodes that are being visited or that are not saved.
+        let mut saved: HashMap<DiceKey, Option<u32>> = HashMap::default();
+        let mut unsaved_deps = HashMap::default();
+        let mut entries = Vec::new();
+
+        for node in &snapshot {
+            if saved.contains_key(&node.key) {
+                continue;
+            }
+            saved.insert(node.key, None);
+
+            // Depth first, so that deps are saved before their dependents.
+            let mut stack = vec![(node, 0)];
+            while let Some((node, next_dep)) = stack.last_mut() {
+                if let Some(dep) = node.deps.get(*next_dep) {
+                    *next_dep += 1;
+                    if let Some(dep) = nodes.get(dep) {
+                        if !saved.contains_key(&dep.key) {
+                            saved.insert(dep.key, None);
+                            stack.push((*dep, 0));
+                        }
+                    }
+                    continue;
+                }
+
+                let node = *node;
+                stack.pop();
+                if let Some(entry) =
+                    self.save_entry(key_index, node, &nodes, &saved, &mut unsaved_deps)?
+                {
+                    saved.insert(node.key, Some(entries.len() as u32));
+                    entries.push(entry);
+                }
+            }
+        }
+
+        Ok(bincode::serialize(&SavedState {
+            version: FORMAT_VERSION,
+            entries,
+        })?)
+    }
+
+    fn save_entry(
+        &self,
+        key_index: &DiceKeyIndex,
+        node: &SnapshotNode,
+        nodes: &HashMap<DiceKey, &SnapshotNode>,
+        saved: &HashMap<DiceKey, Option<u32>>,
+        unsaved_deps: &mut HashMap<DiceKey, Vec<u32>>,
+    ) -> anyhow::Result<Option<SavedEntry>> {
+        let key = key_index.get(node.key);
+        let persistent = match key {
+            DiceKeyErased::Key(k) => match self.by_type.get(&(*k.as_any()).type_id()) {
+                Some(persistent) => persistent,
+                None => return Ok(None),
+            },
+            DiceKeyErased::Projection(_) => return Ok(None),
+        };
+        let data = match persistent
+            .save(key, &node.value)
+            .with_context(|| format!("Error saving the value of `{}`", key))?
+        {
+            Some(data) => data,
+            None => return Ok(None),
+        };
+
+        Ok(Some(SavedEntry {
+            kind: persistent.kind().to_owned(),
+            data,
+            deps: saved_deps(&node.deps, nodes, saved, unsaved_deps),
+        }))
+    }
+
+    /// Deserializes saved state, and checks whether the restored values are up to date. Entries of
+    /// kinds that are not registered, and entries depending on them, are dropped. Restored values
+    /// depend on `environment` in place of the keys that are not persisted.
+    pub(crate) async fn load(
+        &self,
+        key_index: &DiceKeyIndex,
+        data: &[u8],
+        ctx: &DiceComputations,
+        environment: DiceKey,
+    ) -> anyhow::Result<Vec<RestoredNode>> {
+        let state: SavedState =
+            bincode::deserialize(data).context("Error deserializing saved DICE state")?;
+        if state.version != FORMAT_VERSION {
+            return Err(PersistenceError::FormatVersion(state.version, FORMAT_VERSION).into());
+        }
+
+        let mut restored: Vec<Option<DiceKey>> = Vec::with_capacity(state.entries.len());
+        let mut nodes = Vec::new();
+        for entry in state.entries {
+            let node =
+                self.load_entry(key_index, entry, &restored, ctx.global_data(), environment)?;
+            restored.push(node.as_ref().map(|(n, _)| n.key));
+            nodes.extend(node);
+        }
+
+        let up_to_date: Vec<bool> = futures::stream::iter(&nodes)
+            .map(|(node, persistent)| async move {
+                persistent
+                    .is_up_to_date(key_index.get(node.key), &node.value, ctx)
+                    .await
+                    // values that can't be checked are recomputed
+                    .unwrap_or(false)
+            })
+            .buffered(VALIDATION_CONCURRENCY)
+            .collect()
+            .await;
+
+        Ok(nodes
+            .into_iter()
+            .zip(up_to_date)
+            .map(|((node, _), up_to_date)| RestoredNode {
+                revalidate: !up_to_date,
+                ..node
+            })
+            .collect())
+    }
+
+    fn load_entry(
+        &self,
+        key_index: &DiceKeyIndex,
+        entry: SavedEntry,
+        restored: &[Option<DiceKey>],
+        global_data: &DiceData,
+        environment: DiceKey,
+    ) -> anyhow::Result<Option<(RestoredNode, std::sync::Arc<dyn PersistentKeyDyn>)>> {
+        let persistent = match self.by_kind.get(entry.kind.as_str()) {
+            Some(persistent) => persistent,
+            None => return Ok(None),
+        };
+        let mut deps = match entry
+            .deps
+            .iter()
+            .map(|dep| restored.get(*dep as usize).copied().flatten())
+            .collect::<Option<Vec<_>>>()
+        {
+            Some(deps) => deps,
+            None => return Ok(None),
+        };
+        deps.push(environment);
+        let (key, value) = match persistent
+            .load(key_index, &entry.data, global_data)
+            .with_context(|| format!("Error loading a saved `{}`", entry.kind))?
+        {
+            Some(loaded) => loaded,
+            None => return Ok(None),
+        };
+
+        Ok(Some((
+            RestoredNode {
+                key,
+                value,
+                deps: Arc::new(deps),
+                storage: persistent.storage_type(),
+                revalidate: false,
+            },
+            persistent.dupe(),
+        )))
+    }
+}
@@ -0,0 +1,190 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use allocative::Allocative;
+use async_trait::async_trait;
+use derive_more::Display;
+use dupe::Dupe;
+use more_futures::cancellation::CancellationContext;
+
+use crate::api::computations::DiceComputations;
+use crate::api::cycles::DetectCycles;
+use crate::api::injected::InjectedKey;
+use crate::api::key::Key;
+use crate::Dice;
+
+#[derive(Default)]
+struct Computed {
+    big: AtomicUsize,
+    small: AtomicUsize,
+    other: AtomicUsize,
+}
+
+fn computed(ctx: &DiceComputations) -> &Computed {
+    ctx.global_data().get::<Computed>().unwrap()
+}
+
+#[derive(Clone, Dupe, Debug, Display, PartialEq, Eq, Hash, Allocative)]
+#[display(fmt = "{:?}", self)]
+struct Base;
+
+impl InjectedKey for Base {
+    type Value = usize;
+
+    fn equality(x: &Self::Value, y: &Self::Value) -> bool {
+        x == y
+    }
+}
+
+/// Retains a lot of memory, so it's evicted first.
+#[derive(Clone, Dupe, Debug, Display, PartialEq, Eq, Hash, Allocative)]
+#[display(fmt = "{:?}", self)]
+struct Big;
+
+#[async_trait]
+impl Key for Big {
+    type Value = Arc<Vec<u8>>;
+
+    async fn compute(
+        &self,
+        ctx: &mut DiceComputations,
+        _cancellations: &CancellationContext,
+    ) -> Self::Value {
+        computed(ctx).big.fetch_add(1, Ordering::SeqCst);
+        Arc::new(vec![0; ctx.compute(&Base).await.unwrap()])
+    }
+
+    fn equality(x: &Self::Value, y: &Self::Value) -> bool {
+        x == y
+    }
+}
+
+#[derive(Clone, Dupe, Debug, Display, PartialEq, Eq, Hash, Allocative)]
+#[display(fmt = "{:?}", self)]
+struct Small;
+
+#[async_trait]
+impl Key for Small {
+    type Value = usize;
+
+    async fn compute(
+        &self,
+        ctx: &mut DiceComputations,
+        _cancellations: &CancellationContext,
+    ) -> Self::Value {
+        computed(ctx).small.fetch_add(1, Ordering::SeqCst);
+        ctx.compute(&Big).await.unwrap().len()
+    }
+
+    fn equality(x: &Self::Value, y: &Self::Value) -> bool {
+        x == y
+    }
+}
+
+#[derive(Clone, Dupe, Debug, Display, PartialEq, Eq, Hash, Allocative)]
+#[display(fmt = "{:?}", self)]
+struct Other;
+
+#[async_trait]
+impl Key for Other {
+    type Value = usize;
+
+    async fn compute(
+        &self,
+        ctx: &mut DiceComputations,
+        _cancellations: &CancellationContext,
+    ) -> Self::Value {
+        computed(ctx).other.fetch_add(1, Ordering::SeqCst);
+        1
+    }
+
+    fn equality(x: &Self::Value, y: &Self::Value) -> bool {
+        x == y
+    }
+}
+
+fn new_dice() -> Arc<Dice> {
+    let mut builder = Dice::modern();
+    builder.set(Computed::default());
+    builder.build(DetectCycles::Disabled)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn evicts_values_and_their_dependents() -> anyhow::Result<()> {
+    let dice = new_dice();
+    let mut updater = dice.updater();
+    updater.changed_to(vec![(Base, 10000)])?;
+    let ctx = updater.commit().await;
+    assert_eq!(10000, ctx.compute(&Small).await?);
+    assert_eq!(1, ctx.compute(&Other).await?);
+    drop(ctx);
+
+    let stats = dice.evict(1).await?.unwrap();
+    // `Small` depends on `Big`, so it is evicted with it.
+    assert_eq!(2, stats.evicted_key_count);
+    assert!(stats.evicted_bytes >= 10000);
+
+    let metrics = dice.metrics();
+    assert_eq!(2, metrics.evicted_key_count);
+    assert_eq!(stats.evicted_bytes, metrics.evicted_bytes);
+
+    let ctx = dice.updater().commit().await;
+    assert_eq!(10000, ctx.compute(&Small).await?);
+    assert_eq!(1, ctx.compute(&Other).await?);
+    let computed = computed(&ctx);
+    assert_eq!(2, computed.big.load(Ordering::SeqCst));
+    assert_eq!(2, computed.small.load(Ordering::SeqCst));
+    assert_eq!(1, computed.other.load(Ordering::SeqCst));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn injected_values_are_not_evicted() -> anyhow::Result<()> {
+    let dice = new_dice();
+    let mut updater = dice.updater();
+    updater.changed_to(vec![(Base, 10)])?;
+    let ctx = updater.commit().await;
+    ctx.compute(&Small).await?;
+    drop(ctx);
+
+    let stats = dice.evict(u64::MAX).await?.unwrap();
+    assert_eq!(2, stats.evicted_key_count);
+
+    let ctx = dice.updater().commit().await;
+    assert_eq!(10, ctx.compute(&Base).await?);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn nothing_is_evicted_while_transactions_are_active() -> anyhow::Result<()> {
+    let dice = new_dice();
+    let mut updater = dice.updater();
+    updater.changed_to(vec![(Base, 10)])?;
+    let ctx = updater.commit().await;
+    ctx.compute(&Small).await?;
+
+    assert!(dice.evict(u64::MAX).await?.is_none());
+
+    drop(ctx);
+    assert!(dice.evict(u64::MAX).await?.is_some());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn legacy_dice_does_not_evict() {
+    let dice = Dice::builder().build(DetectCycles::Disabled);
+    assert!(dice.evict(u64::MAX).await.is_err());
+}
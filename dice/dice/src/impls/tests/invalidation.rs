@@ -0,0 +1,155 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use allocative::Allocative;
+use async_trait::async_trait;
+use derive_more::Display;
+use dupe::Dupe;
+use more_futures::cancellation::CancellationContext;
+
+use crate::api::computations::DiceComputations;
+use crate::api::cycles::DetectCycles;
+use crate::api::injected::InjectedKey;
+use crate::api::key::Key;
+use crate::introspection::invalidation::InvalidatedKey;
+use crate::Dice;
+
+fn describe(chains: Vec<Vec<InvalidatedKey>>) -> Vec<Vec<String>> {
+    chains
+        .into_iter()
+        .map(|chain| chain.into_iter().map(|k| k.key).collect())
+        .collect()
+}
+
+#[derive(Clone, Dupe, Debug, Display, PartialEq, Eq, Hash, Allocative)]
+#[display(fmt = "{:?}", self)]
+struct Base;
+
+impl InjectedKey for Base {
+    type Value = usize;
+
+    fn equality(x: &Self::Value, y: &Self::Value) -> bool {
+        x == y
+    }
+}
+
+#[derive(Clone, Dupe, Debug, Display, PartialEq, Eq, Hash, Allocative)]
+#[display(fmt = "{:?}", self)]
+struct Mid;
+
+#[async_trait]
+impl Key for Mid {
+    type Value = usize;
+
+    async fn compute(
+        &self,
+        ctx: &mut DiceComputations,
+        _cancellations: &CancellationContext,
+    ) -> Self::Value {
+        ctx.compute(&Base).await.unwrap() + 1
+    }
+
+    fn equality(x: &Self::Value, y: &Self::Value) -> bool {
+        x == y
+    }
+}
+
+#[derive(Clone, Dupe, Debug, Display, PartialEq, Eq, Hash, Allocative)]
+#[display(fmt = "{:?}", self)]
+struct Top;
+
+#[async_trait]
+impl Key for Top {
+    type Value = usize;
+
+    async fn compute(
+        &self,
+        ctx: &mut DiceComputations,
+        _cancellations: &CancellationContext,
+    ) -> Self::Value {
+        ctx.compute(&Mid).await.unwrap() + 1
+    }
+
+    fn equality(x: &Self::Value, y: &Self::Value) -> bool {
+        x == y
+    }
+}
+
+#[derive(Clone, Dupe, Debug, Display, PartialEq, Eq, Hash, Allocative)]
+#[display(fmt = "{:?}", self)]
+struct Other;
+
+#[async_trait]
+impl Key for Other {
+    type Value = usize;
+
+    async fn compute(
+        &self,
+        _ctx: &mut DiceComputations,
+        _cancellations: &CancellationContext,
+    ) -> Self::Value {
+        1
+    }
+
+    fn equality(x: &Self::Value, y: &Self::Value) -> bool {
+        x == y
+    }
+}
+
+#[tokio::test]
+async fn explains_invalidations_of_last_change() -> anyhow::Result<()> {
+    let dice = Dice::modern().build(DetectCycles::Disabled);
+    let mut updater = dice.updater();
+    updater.changed_to(vec![(Base, 1)])?;
+    let ctx = updater.commit().await;
+    assert_eq!(3, ctx.compute(&Top).await?);
+    assert_eq!(1, ctx.compute(&Other).await?);
+    drop(ctx);
+
+    let mut updater = dice.updater();
+    updater.changed_to(vec![(Base, 2)])?;
+    let ctx = updater.commit().await;
+    assert_eq!(4, ctx.compute(&Top).await?);
+    drop(ctx);
+
+    let causes = dice.invalidation_causes().await?;
+    assert_eq!(causes.len(), 3);
+    assert!(causes.explain("Other").is_empty());
+    assert_eq!(
+        describe(causes.explain("Top")),
+        vec![vec!["Top".to_owned(), "Mid".to_owned(), "Base".to_owned()]]
+    );
+    assert_eq!(
+        causes.explain("Base"),
+        vec![vec![InvalidatedKey {
+            key: "Base".to_owned(),
+            type_name: "Base",
+        }]]
+    );
+
+    // only the most recent change is explained
+    let mut updater = dice.updater();
+    updater.changed(vec![Other])?;
+    drop(updater.commit().await);
+
+    let causes = dice.invalidation_causes().await?;
+    assert!(causes.explain("Top").is_empty());
+    assert_eq!(
+        describe(causes.explain("Other")),
+        vec![vec!["Other".to_owned()]]
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn legacy_dice_does_not_explain_invalidations() {
+    let dice = Dice::builder().build(DetectCycles::Disabled);
+    assert!(dice.invalidation_causes().await.is_err());
+}
@@ -0,0 +1,408 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::sync::atomic::AtomicU32;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use allocative::Allocative;
+use async_trait::async_trait;
+use derive_more::Display;
+use dupe::Dupe;
+use futures::future::join_all;
+use more_futures::cancellation::CancellationContext;
+
+use crate::api::computations::DiceComputations;
+use crate::api::cycles::DetectCycles;
+use crate::api::data::DiceData;
+use crate::api::injected::InjectedKey;
+use crate::api::key::Key;
+use crate::api::persistence::PersistentKey;
+use crate::impls::ctx::BaseComputeCtx;
+use crate::impls::dice::DiceModern;
+use crate::Dice;
+
+#[derive(Default)]
+struct Computed {
+    leaves: AtomicUsize,
+    sums: AtomicUsize,
+}
+
+/// Stands for the state outside of Dice that leaves are computed from.
+struct Factor(AtomicU32);
+
+#[derive(Clone, Copy, Dupe, Debug, Display, PartialEq, Eq, Hash, Allocative)]
+#[display(fmt = "{:?}", self)]
+struct Environment;
+
+impl InjectedKey for Environment {
+    type Value = u32;
+
+    fn equality(x: &Self::Value, y: &Self::Value) -> bool {
+        x == y
+    }
+}
+
+#[derive(Clone, Copy, Dupe, Debug, Display, PartialEq, Eq, Hash, Allocative)]
+#[display(fmt = "{:?}", self)]
+struct Leaf(u32);
+
+impl Leaf {
+    fn value(&self, data: &DiceData) -> u32 {
+        self.0 * data.get::<Factor>().unwrap().0.load(Ordering::SeqCst)
+    }
+}
+
+#[async_trait]
+impl Key for Leaf {
+    type Value = u32;
+
+    async fn compute(
+        &self,
+        ctx: &mut DiceComputations,
+        _cancellations: &CancellationContext,
+    ) -> Self::Value {
+        ctx.global_data()
+            .get::<Computed>()
+            .unwrap()
+            .leaves
+            .fetch_add(1, Ordering::SeqCst);
+        self.value(ctx.global_data())
+    }
+
+    fn equality(x: &Self::Value, y: &Self::Value) -> bool {
+        x == y
+    }
+}
+
+#[async_trait]
+impl PersistentKey for Leaf {
+    const KIND: &'static str = "leaf";
+
+    fn save(&self, value: &Self::Value) -> anyhow::Result<Option<Vec<u8>>> {
+        Ok(Some(bincode::serialize(&(self.0, *value))?))
+    }
+
+    fn load(data: &[u8], _global_data: &DiceData) -> anyhow::Result<(Self, Self::Value)> {
+        let (key, value) = bincode::deserialize(data)?;
+        Ok((Leaf(key), value))
+    }
+
+    async fn is_up_to_date(
+        &self,
+        value: &Self::Value,
+        ctx: &DiceComputations,
+    ) -> anyhow::Result<bool> {
+        Ok(*value == self.value(ctx.global_data()))
+    }
+}
+
+#[derive(Clone, Copy, Dupe, Debug, Display, PartialEq, Eq, Hash, Allocative)]
+#[display(fmt = "{:?}", self)]
+struct Sum(u32);
+
+#[async_trait]
+impl Key for Sum {
+    type Value = u32;
+
+    async fn compute(
+        &self,
+        ctx: &mut DiceComputations,
+        _cancellations: &CancellationContext,
+    ) -> Self::Value {
+        ctx.global_data()
+            .get::<Computed>()
+            .unwrap()
+            .sums
+            .fetch_add(1, Ordering::SeqCst);
+        let ctx = &*ctx;
+        join_all((0..self.0).map(|i| ctx.compute(&Leaf(i))))
+            .await
+            .into_iter()
+            .map(|v| v.unwrap())
+            .sum()
+    }
+
+    fn equality(x: &Self::Value, y: &Self::Value) -> bool {
+        x == y
+    }
+}
+
+impl PersistentKey for Sum {
+    const KIND: &'static str = "sum";
+
+    fn save(&self, value: &Self::Value) -> anyhow::Result<Option<Vec<u8>>> {
+        Ok(Some(bincode::serialize(&(self.0, *value))?))
+    }
+
+    fn load(data: &[u8], _global_data: &DiceData) -> anyhow::Result<(Self, Self::Value)> {
+        let (key, value) = bincode::deserialize(data)?;
+        Ok((Sum(key), value))
+    }
+}
+
+/// Not persisted, so that the edges from `SumOfDoubles` to the leaves go through it.
+#[derive(Clone, Copy, Dupe, Debug, Display, PartialEq, Eq, Hash, Allocative)]
+#[display(fmt = "{:?}", self)]
+struct Double(u32);
+
+#[async_trait]
+impl Key for Double {
+    type Value = u32;
+
+    async fn compute(
+        &self,
+        ctx: &mut DiceComputations,
+        _cancellations: &CancellationContext,
+    ) -> Self::Value {
+        2 * ctx.compute(&Leaf(self.0)).await.unwrap()
+    }
+
+    fn equality(x: &Self::Value, y: &Self::Value) -> bool {
+        x == y
+    }
+}
+
+#[derive(Clone, Copy, Dupe, Debug, Display, PartialEq, Eq, Hash, Allocative)]
+#[display(fmt = "{:?}", self)]
+struct SumOfDoubles(u32);
+
+#[async_trait]
+impl Key for SumOfDoubles {
+    type Value = u32;
+
+    async fn compute(
+        &self,
+        ctx: &mut DiceComputations,
+        _cancellations: &CancellationContext,
+    ) -> Self::Value {
+        ctx.global_data()
+            .get::<Computed>()
+            .unwrap()
+            .sums
+            .fetch_add(1, Ordering::SeqCst);
+        let ctx = &*ctx;
+        join_all((0..self.0).map(|i| ctx.compute(&Double(i))))
+            .await
+            .into_iter()
+            .map(|v| v.unwrap())
+            .sum()
+    }
+
+    fn equality(x: &Self::Value, y: &Self::Value) -> bool {
+        x == y
+    }
+}
+
+impl PersistentKey for SumOfDoubles {
+    const KIND: &'static str = "sum_of_doubles";
+
+    fn save(&self, value: &Self::Value) -> anyhow::Result<Option<Vec<u8>>> {
+        Ok(Some(bincode::serialize(&(self.0, *value))?))
+    }
+
+    fn load(data: &[u8], _global_data: &DiceData) -> anyhow::Result<(Self, Self::Value)> {
+        let (key, value) = bincode::deserialize(data)?;
+        Ok((SumOfDoubles(key), value))
+    }
+}
+
+fn new_dice(register_leaf: bool, factor: u32) -> Arc<DiceModern> {
+    let mut builder = DiceModern::builder();
+    builder.set(Computed::default());
+    builder.set(Factor(AtomicU32::new(factor)));
+    if register_leaf {
+        builder.register_persistent_key::<Leaf>();
+    }
+    builder.register_persistent_key::<Sum>();
+    builder.register_persistent_key::<SumOfDoubles>();
+    builder.build(DetectCycles::Disabled)
+}
+
+fn computed(dice: &DiceModern) -> (usize, usize) {
+    let computed = dice.global_data.get::<Computed>().unwrap();
+    (
+        computed.leaves.load(Ordering::SeqCst),
+        computed.sums.load(Ordering::SeqCst),
+    )
+}
+
+async fn with_environment(dice: &Arc<DiceModern>, environment: u32) -> BaseComputeCtx {
+    let mut updater = dice.updater();
+    updater
+        .changed_to(vec![(Environment, environment)])
+        .unwrap();
+    updater.commit().await
+}
+
+/// Computes `Sum(3)` with all the leaves, and saves the state.
+async fn saved_state(factor: u32) -> anyhow::Result<Vec<u8>> {
+    let dice = new_dice(true, factor);
+    let ctx = with_environment(&dice, 0).await;
+    assert_eq!(6 * factor, ctx.compute(&Sum(3)).await?);
+    assert_eq!((3, 1), computed(&dice));
+    dice.save_persistent_state(&ctx).await
+}
+
+#[tokio::test]
+async fn restored_values_are_reused() -> anyhow::Result<()> {
+    let saved = saved_state(2).await?;
+
+    let dice = new_dice(true, 2);
+    let ctx = with_environment(&dice, 0).await;
+    assert_eq!(
+        4,
+        dice.load_persistent_state(&saved, &ctx, &Environment)
+            .await?
+    );
+    drop(ctx);
+
+    let ctx = dice.updater().commit().await;
+    assert_eq!(12, ctx.compute(&Sum(3)).await?);
+    assert_eq!((0, 0), computed(&dice));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn values_that_are_not_up_to_date_are_recomputed() -> anyhow::Result<()> {
+    let saved = saved_state(2).await?;
+
+    let dice = new_dice(true, 3);
+    let ctx = with_environment(&dice, 0).await;
+    assert_eq!(
+        4,
+        dice.load_persistent_state(&saved, &ctx, &Environment)
+            .await?
+    );
+    drop(ctx);
+
+    let ctx = dice.updater().commit().await;
+    assert_eq!(18, ctx.compute(&Sum(3)).await?);
+    // `Leaf(0)` is still up to date.
+    assert_eq!((2, 1), computed(&dice));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn restored_values_depend_on_saved_values_through_keys_that_are_not_saved()
+-> anyhow::Result<()> {
+    let dice = new_dice(true, 2);
+    let ctx = with_environment(&dice, 0).await;
+    assert_eq!(12, ctx.compute(&SumOfDoubles(3)).await?);
+    let saved = dice.save_persistent_state(&ctx).await?;
+    drop(ctx);
+
+    let dice = new_dice(true, 3);
+    let ctx = with_environment(&dice, 0).await;
+    // `Double`s are not restored.
+    assert_eq!(
+        4,
+        dice.load_persistent_state(&saved, &ctx, &Environment)
+            .await?
+    );
+    drop(ctx);
+
+    let ctx = dice.updater().commit().await;
+    assert_eq!(18, ctx.compute(&SumOfDoubles(3)).await?);
+    // `Leaf(0)` is still up to date.
+    assert_eq!((2, 1), computed(&dice));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn restored_values_depend_on_the_environment() -> anyhow::Result<()> {
+    let saved = saved_state(2).await?;
+
+    let dice = new_dice(true, 2);
+    let ctx = with_environment(&dice, 0).await;
+    dice.load_persistent_state(&saved, &ctx, &Environment)
+        .await?;
+    drop(ctx);
+
+    let ctx = with_environment(&dice, 0).await;
+    assert_eq!(12, ctx.compute(&Sum(3)).await?);
+    assert_eq!((0, 0), computed(&dice));
+    drop(ctx);
+
+    let ctx = with_environment(&dice, 1).await;
+    assert_eq!(12, ctx.compute(&Sum(3)).await?);
+    assert_eq!((3, 1), computed(&dice));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn values_depending_on_unregistered_keys_are_not_restored() -> anyhow::Result<()> {
+    let saved = saved_state(2).await?;
+
+    let dice = new_dice(false, 2);
+    let ctx = with_environment(&dice, 0).await;
+    assert_eq!(
+        0,
+        dice.load_persistent_state(&saved, &ctx, &Environment)
+            .await?
+    );
+    drop(ctx);
+
+    let ctx = dice.updater().commit().await;
+    assert_eq!(12, ctx.compute(&Sum(3)).await?);
+    assert_eq!((3, 1), computed(&dice));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn existing_values_are_not_overwritten() -> anyhow::Result<()> {
+    let saved = saved_state(2).await?;
+
+    let dice = new_dice(true, 2);
+    let ctx = with_environment(&dice, 0).await;
+    ctx.compute(&Leaf(0)).await?;
+    // `Sum(3)` depends on the leaf that already exists.
+    assert_eq!(
+        2,
+        dice.load_persistent_state(&saved, &ctx, &Environment)
+            .await?
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn nothing_is_restored_at_an_old_version() -> anyhow::Result<()> {
+    let saved = saved_state(2).await?;
+
+    let dice = new_dice(true, 2);
+    let ctx = with_environment(&dice, 0).await;
+    let _newer = with_environment(&dice, 1).await;
+    assert_eq!(
+        0,
+        dice.load_persistent_state(&saved, &ctx, &Environment)
+            .await?
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn legacy_dice_does_not_persist() -> anyhow::Result<()> {
+    let dice = Dice::builder().build(DetectCycles::Disabled);
+    let ctx = dice.updater().commit().await;
+    assert!(dice.save_persistent_state(&ctx).await.is_err());
+    assert!(
+        dice.load_persistent_state(&[], &ctx, &Environment)
+            .await
+            .is_err()
+    );
+    Ok(())
+}
@@ -12,6 +12,7 @@ use std::fmt::Debug;
 use std::fmt::Formatter;
 
 use allocative::Allocative;
+use allocative::FlameGraphBuilder;
 use dupe::Dupe;
 
 use crate::arc::Arc;
@@ -31,7 +32,6 @@ impl Debug for DiceValidValue {
 }
 
 impl DiceValidValue {
-    #[cfg(test)]
     pub(crate) fn downcast_ref<V: Any>(&self) -> Option<&V> {
         self.0.downcast_ref()
     }
@@ -40,6 +40,13 @@ impl DiceValidValue {
     pub(crate) fn equality(&self, other: &DiceValidValue) -> bool {
         self.0.equality(&*other.0)
     }
+
+    /// Approximate memory retained by this value. Data shared with other values is included, so
+    /// this can overestimate what dropping the value frees. This walks the whole value, so it is
+    /// expensive and must not be called on the state thread.
+    pub(crate) fn allocated_size(&self) -> usize {
+        self.0.allocated_size()
+    }
 }
 
 /// Type erased value that may be transient, or whose dependencies are transient
@@ -150,6 +157,7 @@ pub(crate) trait DiceValueDyn: Allocative + Any + Send + Sync + 'static {
     /// Panics if called with incompatible values.
     fn equality(&self, other: &dyn DiceValueDyn) -> bool;
     fn validity(&self) -> bool;
+    fn allocated_size(&self) -> usize;
 }
 
 impl dyn DiceValueDyn {
@@ -158,6 +166,12 @@ impl dyn DiceValueDyn {
     }
 }
 
+fn allocated_size(value: &dyn Allocative) -> usize {
+    let mut builder = FlameGraphBuilder::default();
+    builder.visit_root(value);
+    builder.finish().flamegraph().total_size()
+}
+
 #[derive(Allocative)]
 pub(crate) struct DiceKeyValue<K: Key> {
     value: K::Value,
@@ -187,6 +201,10 @@ where
     fn validity(&self) -> bool {
         K::validity(&self.value)
     }
+
+    fn allocated_size(&self) -> usize {
+        allocated_size(self)
+    }
 }
 
 #[derive(Allocative)]
@@ -218,6 +236,10 @@ where
     fn validity(&self) -> bool {
         K::validity(&self.value)
     }
+
+    fn allocated_size(&self) -> usize {
+        allocated_size(self)
+    }
 }
 
 #[cfg(test)]
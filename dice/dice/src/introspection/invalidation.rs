@@ -0,0 +1,92 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//!
+//! Explains why keys were invalidated by the most recent change to DICE, by following the
+//! dependencies that were invalidated back to the keys that were changed directly.
+//!
+
+use thiserror::Error;
+
+use crate::introspection::graph::AnyKey;
+use crate::introspection::graph::VersionNumber;
+use crate::HashMap;
+
+#[derive(Debug, Error)]
+pub(crate) enum InvalidationCausesError {
+    #[error("Explaining invalidations is only supported by the modern DICE implementation")]
+    LegacyDice,
+}
+
+pub(crate) enum InvalidationCause {
+    /// The key itself was changed, via `changed` or `changed_to`.
+    Changed,
+    /// The key was invalidated because this dependency was.
+    Dep(AnyKey),
+}
+
+/// A key in an invalidation chain.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvalidatedKey {
+    pub key: String,
+    pub type_name: &'static str,
+}
+
+impl InvalidatedKey {
+    fn new(key: &AnyKey) -> Self {
+        Self {
+            key: key.to_string(),
+            type_name: key.short_type_name(),
+        }
+    }
+}
+
+/// Why each key was invalidated at the most recent version with changes.
+pub struct InvalidationCauses {
+    /// The version the invalidations happened at, or `None` if nothing was ever changed.
+    pub version: Option<VersionNumber>,
+    pub(crate) causes: HashMap<AnyKey, InvalidationCause>,
+}
+
+impl InvalidationCauses {
+    /// The number of keys that were invalidated.
+    pub fn len(&self) -> usize {
+        self.causes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.causes.is_empty()
+    }
+
+    /// For every invalidated key whose description contains `query`, returns the chain of keys
+    /// whose invalidation caused it. Each chain starts with the matching key and ends with the key
+    /// that was changed directly. Chains are sorted by the description of the matching key.
+    pub fn explain(&self, query: &str) -> Vec<Vec<InvalidatedKey>> {
+        let mut chains: Vec<_> = self
+            .causes
+            .keys()
+            .filter(|k| k.to_string().contains(query))
+            .map(|k| self.chain(k))
+            .collect();
+        chains.sort_by(|x, y| x[0].key.cmp(&y[0].key));
+        chains
+    }
+
+    fn chain(&self, key: &AnyKey) -> Vec<InvalidatedKey> {
+        let mut chain = vec![InvalidatedKey::new(key)];
+        let mut current = key;
+        // Only the first cause of each key is recorded and that cause was invalidated before the
+        // key was, so this always terminates at a changed key.
+        while let Some(InvalidationCause::Dep(dep)) = self.causes.get(current) {
+            chain.push(InvalidatedKey::new(dep));
+            current = dep;
+        }
+        chain
+    }
+}
@@ -214,6 +214,9 @@ impl DiceLegacy {
             active_transaction_count: self
                 .active_transaction_count
                 .load(std::sync::atomic::Ordering::SeqCst),
+            // legacy dice does not support eviction
+            evicted_key_count: 0,
+            evicted_bytes: 0,
         }
     }
 
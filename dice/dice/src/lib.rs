@@ -232,6 +232,7 @@ use legacy::dice_futures::future_handle::WeakDiceFutureHandle;
 use legacy::incremental::graph::GraphNode;
 use legacy::incremental::transaction_ctx::TransactionCtx;
 use legacy::key::StoragePropertiesForKey;
+use metrics::EvictionStats;
 use metrics::Metrics;
 pub use more_futures::cancellation::future::CancellationHandle; // expose cancellation handle as api
 pub use more_futures::cancellation::CancellationContext; // expose cancellation context as api
@@ -255,6 +256,7 @@ pub use crate::api::events::DiceEventListener;
 pub use crate::api::injected::InjectedKey;
 pub use crate::api::key::Key;
 pub use crate::api::opaque::OpaqueValue;
+pub use crate::api::persistence::PersistentKey;
 pub use crate::api::projection::DiceProjectionComputations;
 pub use crate::api::projection::ProjectionKey;
 pub use crate::api::transaction::DiceEquality;
@@ -264,13 +266,18 @@ pub use crate::api::user_data::UserComputationData;
 pub use crate::api::user_data::UserCycleDetector;
 pub use crate::api::user_data::UserCycleDetectorGuard;
 pub use crate::api::which::WhichDice;
+use crate::impls::core::eviction::EvictionError;
 use crate::impls::dice::DiceModern;
 use crate::impls::dice::DiceModernDataBuilder;
+use crate::impls::persistence::PersistenceError;
 use crate::introspection::graph::GraphIntrospectable;
+use crate::introspection::invalidation::InvalidationCauses;
+use crate::introspection::invalidation::InvalidationCausesError;
 use crate::introspection::serialize_dense_graph;
 use crate::introspection::serialize_graph;
 use crate::legacy::DiceLegacy;
 use crate::legacy::DiceLegacyDataBuilder;
+use crate::transaction::DiceTransactionImpl;
 use crate::transaction_update::DiceTransactionUpdaterImpl;
 
 #[derive(Allocative, Debug)]
@@ -355,6 +362,43 @@ impl DiceImplementation {
             DiceImplementation::Modern(dice) => dice.is_idle().await,
         }
     }
+
+    pub async fn save_persistent_state(&self, ctx: &DiceTransaction) -> anyhow::Result<Vec<u8>> {
+        match (self, &ctx.0) {
+            (DiceImplementation::Modern(dice), DiceTransactionImpl::Modern(ctx)) => {
+                dice.save_persistent_state(ctx).await
+            }
+            _ => Err(PersistenceError::LegacyDice.into()),
+        }
+    }
+
+    pub async fn load_persistent_state<E: Key>(
+        &self,
+        data: &[u8],
+        ctx: &DiceTransaction,
+        environment: &E,
+    ) -> anyhow::Result<usize> {
+        match (self, &ctx.0) {
+            (DiceImplementation::Modern(dice), DiceTransactionImpl::Modern(ctx)) => {
+                dice.load_persistent_state(data, ctx, environment).await
+            }
+            _ => Err(PersistenceError::LegacyDice.into()),
+        }
+    }
+
+    pub async fn evict(&self, bytes: u64) -> anyhow::Result<Option<EvictionStats>> {
+        match self {
+            DiceImplementation::Legacy(_) => Err(EvictionError::LegacyDice.into()),
+            DiceImplementation::Modern(dice) => Ok(dice.evict(bytes).await),
+        }
+    }
+
+    pub async fn invalidation_causes(&self) -> anyhow::Result<InvalidationCauses> {
+        match self {
+            DiceImplementation::Legacy(_) => Err(InvalidationCausesError::LegacyDice.into()),
+            DiceImplementation::Modern(dice) => Ok(dice.invalidation_causes().await),
+        }
+    }
 }
 
 pub(crate) enum DiceDataBuilderImpl {
@@ -378,6 +422,14 @@ impl DiceDataBuilderImpl {
         }
     }
 
+    pub fn register_persistent_key<K: PersistentKey>(&mut self) {
+        match self {
+            // legacy dice rejects saving and loading state instead
+            DiceDataBuilderImpl::Legacy(_) => {}
+            DiceDataBuilderImpl::Modern(d) => d.register_persistent_key::<K>(),
+        }
+    }
+
     pub fn build(self, detect_cycles: DetectCycles) -> Arc<Dice> {
         Dice::new(match self {
             DiceDataBuilderImpl::Legacy(d) => DiceImplementation::Legacy(d.build(detect_cycles)),
@@ -14,4 +14,15 @@ pub struct Metrics {
     /// The number of keys currently active in the per transaction cache
     pub currently_active_key_count: usize,
     pub active_transaction_count: u32,
+    /// The number of computed values dropped under memory pressure, over the lifetime of Dice
+    pub evicted_key_count: usize,
+    /// The memory retained by the values counted in `evicted_key_count`
+    pub evicted_bytes: u64,
+}
+
+/// The values dropped by a single call to `Dice::evict`.
+#[derive(Debug, Default)]
+pub struct EvictionStats {
+    pub evicted_key_count: usize,
+    pub evicted_bytes: u64,
 }
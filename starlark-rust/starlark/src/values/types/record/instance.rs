@@ -63,6 +63,46 @@ impl<'v, V: ValueLike<'v>> Display for RecordGen<V> {
 
 starlark_complex_value!(pub Record);
 
+#[derive(Debug, thiserror::Error)]
+enum RecordError {
+    #[error("Expected a record type, got `{0}`")]
+    NotRecordType(String),
+    #[error("Record type `{0}` has not been assigned a name")]
+    RecordTypeNotNamed(String),
+    #[error("Record type `{0}` has {1} fields, but {2} values were given")]
+    WrongNumberOfValues(String, usize, usize),
+}
+
+impl<'v> Record<'v> {
+    /// Creates a record of type `typ`, given the values of all its fields in order.
+    pub fn new(typ: Value<'v>, values: Vec<Value<'v>>) -> anyhow::Result<Self> {
+        let record_type =
+            RecordType::from_value(typ).ok_or_else(|| RecordError::NotRecordType(typ.to_repr()))?;
+        if record_type.either(
+            |x| x.ty_record_data().is_none(),
+            |x| x.ty_record_data().is_none(),
+        ) {
+            return Err(RecordError::RecordTypeNotNamed(typ.to_repr()).into());
+        }
+        let fields = record_fields(record_type);
+        if fields.len() != values.len() {
+            return Err(RecordError::WrongNumberOfValues(
+                typ.to_repr(),
+                fields.len(),
+                values.len(),
+            )
+            .into());
+        }
+        for ((name, field), value) in fields.iter().zip(&values) {
+            field.typ.check_type(*value, Some(name))?;
+        }
+        Ok(Record {
+            typ,
+            values: values.into_boxed_slice(),
+        })
+    }
+}
+
 impl<'v, V: ValueLike<'v>> RecordGen<V> {
     /// `type(x)` for records.
     pub const TYPE: &'static str = "record";
@@ -49,3 +49,5 @@ pub(crate) mod record_type;
 pub(crate) mod ty_record_type;
 
 pub use crate::values::record::instance::Record;
+pub use crate::values::record::record_type::FrozenRecordType;
+pub use crate::values::record::record_type::RecordType;
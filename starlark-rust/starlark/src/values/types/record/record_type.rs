@@ -57,10 +57,12 @@ use crate::values::record::matcher::RecordTypeMatcher;
 use crate::values::record::ty_record_type::TyRecordData;
 use crate::values::record::Record;
 use crate::values::types::type_instance_id::TypeInstanceId;
+use crate::values::typing::type_compiled::compiled::TypeCompiled;
 use crate::values::typing::type_compiled::type_matcher_factory::TypeMatcherFactory;
 use crate::values::Freeze;
 use crate::values::Freezer;
 use crate::values::FrozenValue;
+use crate::values::Heap;
 use crate::values::StarlarkValue;
 use crate::values::Trace;
 use crate::values::Value;
@@ -170,6 +172,22 @@ impl<'v> RecordType<'v> {
         }
     }
 
+    /// Creates a record type with fields of the given types, named as if it had been assigned to
+    /// a global variable called `name`, so that instances can be created with [`Record::new`].
+    pub fn new_named(
+        name: &str,
+        fields: impl IntoIterator<Item = (String, Ty)>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<Self> {
+        let fields = fields
+            .into_iter()
+            .map(|(name, ty)| (name, FieldGen::new(TypeCompiled::from_ty(&ty, heap), None)))
+            .collect();
+        let res = Self::new(fields);
+        res.init_ty_record_data(name)?;
+        Ok(res)
+    }
+
     fn make_parameter_spec(
         fields: &SmallMap<String, FieldGen<Value<'v>>>,
     ) -> ParametersSpec<FrozenValue> {
@@ -207,12 +225,60 @@ where
         V::get_ty(&self.ty_record_data)
     }
 
-    pub(crate) fn instance_ty(&self) -> Ty {
+    /// The type of the instances of this record type.
+    ///
+    /// Panics if the record type has not been assigned a name.
+    pub fn instance_ty(&self) -> Ty {
         self.ty_record_data()
             .expect("Instances can only be created if named are assigned")
             .ty_record
             .dupe()
     }
+
+    fn init_ty_record_data(&self, variable_name: &str) -> anyhow::Result<()> {
+        V::get_or_init_ty(&self.ty_record_data, || {
+            let fields: SortedMap<String, Ty> = self
+                .fields
+                .iter()
+                .map(|(name, field)| (name.clone(), field.ty()))
+                .collect();
+
+            let ty_record = Ty::custom(TyUser::new(
+                variable_name.to_owned(),
+                TyStarlarkValue::new::<Record>(),
+                self.id,
+                TyUserParams {
+                    matcher: Some(TypeMatcherFactory::new(RecordTypeMatcher { id: self.id })),
+                    fields: TyUserFields {
+                        known: fields,
+                        unknown: false,
+                    },
+                    ..TyUserParams::default()
+                },
+            )?);
+
+            let ty_record_type = Ty::custom(TyUser::new(
+                format!("record[{}]", variable_name),
+                TyStarlarkValue::new::<RecordType>(),
+                TypeInstanceId::gen(),
+                TyUserParams {
+                    callable: Some(TyFunction::new(
+                        // TODO(nga): more precise parameter types.
+                        vec![Param::kwargs(Ty::any())],
+                        ty_record.dupe(),
+                    )),
+                    ..TyUserParams::default()
+                },
+            )?);
+
+            Ok(Arc::new(TyRecordData {
+                name: variable_name.to_owned(),
+                id: self.id,
+                ty_record,
+                ty_record_type,
+            }))
+        })
+    }
 }
 
 #[starlark_value(type = FUNCTION_TYPE)]
@@ -292,48 +358,7 @@ where
     }
 
     fn export_as(&self, variable_name: &str, _eval: &mut Evaluator<'v, '_>) -> anyhow::Result<()> {
-        V::get_or_init_ty(&self.ty_record_data, || {
-            let fields: SortedMap<String, Ty> = self
-                .fields
-                .iter()
-                .map(|(name, field)| (name.clone(), field.ty()))
-                .collect();
-
-            let ty_record = Ty::custom(TyUser::new(
-                variable_name.to_owned(),
-                TyStarlarkValue::new::<Record>(),
-                self.id,
-                TyUserParams {
-                    matcher: Some(TypeMatcherFactory::new(RecordTypeMatcher { id: self.id })),
-                    fields: TyUserFields {
-                        known: fields,
-                        unknown: false,
-                    },
-                    ..TyUserParams::default()
-                },
-            )?);
-
-            let ty_record_type = Ty::custom(TyUser::new(
-                format!("record[{}]", variable_name),
-                TyStarlarkValue::new::<RecordType>(),
-                TypeInstanceId::gen(),
-                TyUserParams {
-                    callable: Some(TyFunction::new(
-                        // TODO(nga): more precise parameter types.
-                        vec![Param::kwargs(Ty::any())],
-                        ty_record.dupe(),
-                    )),
-                    ..TyUserParams::default()
-                },
-            )?);
-
-            Ok(Arc::new(TyRecordData {
-                name: variable_name.to_owned(),
-                id: self.id,
-                ty_record,
-                ty_record_type,
-            }))
-        })
+        self.init_ty_record_data(variable_name)
     }
 }
 
@@ -352,6 +377,38 @@ fn record_type_methods(methods: &mut MethodsBuilder) {
 #[cfg(test)]
 mod tests {
     use crate::assert;
+    use crate::typing::Ty;
+    use crate::values::record::Record;
+    use crate::values::record::RecordType;
+    use crate::values::Heap;
+
+    #[test]
+    fn test_record_type_new_named() {
+        let heap = Heap::new();
+        let typ = heap.alloc(
+            RecordType::new_named(
+                "Rec",
+                [("a".to_owned(), Ty::int()), ("b".to_owned(), Ty::any())],
+                &heap,
+            )
+            .unwrap(),
+        );
+        let rec = heap.alloc(Record::new(typ, vec![heap.alloc(1), heap.alloc(2)]).unwrap());
+        assert_eq!("record[Rec](a=1, b=2)", rec.to_repr());
+        let other = heap.alloc(Record::new(typ, vec![heap.alloc(1), heap.alloc(2)]).unwrap());
+        assert!(rec.equals(other).unwrap());
+        assert_eq!(
+            2,
+            rec.get_attr("b", &heap)
+                .unwrap()
+                .unwrap()
+                .unpack_i32()
+                .unwrap()
+        );
+        assert!(Record::new(typ, vec![heap.alloc(1)]).is_err());
+        assert!(Record::new(typ, vec![heap.alloc("x"), heap.alloc(2)]).is_err());
+        assert!(Record::new(heap.alloc(1), Vec::new()).is_err());
+    }
 
     #[test]
     fn test_record_type_as_type_pass() {
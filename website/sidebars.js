@@ -108,6 +108,10 @@ const manualSidebar = [
           'users/advanced/deferred_materialization',
           'users/advanced/restarter',
           'users/advanced/in_memory_cache',
+          'users/advanced/persistent_dice_state',
+          'users/advanced/dice_memory_budget',
+          'users/advanced/content_based_output_paths',
+          'users/advanced/external_cells',
           isInternal() ? 'users/advanced/offline_build_archives' : [],
           isInternal() ? 'users/advanced/vpnless' : [],
         ],